@@ -0,0 +1,351 @@
+use std::ffi::OsStr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use futures_core::Stream;
+use serial::{Error as SerialError, ErrorKind as SerialErrorKind};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio_serial::{DataBits, FlowControl, Parity, SerialStream, StopBits};
+
+use super::port::{is_disconnected, open_error};
+use super::prelude::*;
+use super::scan::ScanAssembler;
+use super::status::StatsAccumulator;
+
+/// Number of bytes requested from the source per read.
+const READ_CHUNK_SIZE: usize = 256;
+
+/// Baud rate of the XV-11.
+const BAUD_RATE: u32 = 115200;
+
+/// Capacity of the command channel of a `LidarStream`.
+const COMMAND_CAPACITY: usize = 8;
+
+/// ## Summary
+///
+/// Begin reading XV-11 data from a serial port on the Tokio runtime.
+///
+/// ## Parameters
+///
+/// port_name: Name of the serial port, e.g. `/dev/serial0` or `COM3`.
+///
+/// tx: Sends decoded LIDAR messages or error encountered.
+///
+/// rx: Receives commands from the calling program.
+///
+/// ## Remarks
+///
+/// The async counterpart of `run`: no thread is dedicated to the driver, commands are
+/// handled as soon as they are received and a full message channel holds the driver
+/// back instead of dropping messages. Returns after `LidarDriverMessage::Shutdown` is
+/// sent, on `LidarDriverCommand::Stop`, when either channel is closed or when the
+/// port is disconnected.
+///
+/// ## Example
+///
+/// ```no_run
+/// use neato_xv11::prelude::*;
+/// use tokio::sync::mpsc::channel;
+///
+/// # async fn example() {
+/// let (message_tx, mut message_rx) = channel(64);
+/// let (command_tx, command_rx) = channel(8);
+///
+/// tokio::spawn(neato_xv11::run_async("/dev/serial0", message_tx, command_rx));
+///
+/// while let Some(message) = message_rx.recv().await {
+///     if let Ok(LidarDriverMessage::Packet(packet)) = message {
+///         println!("{} RPM", packet.speed);
+///     }
+/// }
+/// # }
+/// ```
+pub async fn run_async<T: AsRef<str>>(port_name: T, tx: Sender<Result<LidarDriverMessage, LidarDriverError>>, rx: Receiver<LidarDriverCommand>) {
+    let port_name = port_name.as_ref();
+    let builder = tokio_serial::new(port_name, BAUD_RATE)
+        .data_bits(DataBits::Eight)
+        .parity(Parity::None)
+        .stop_bits(StopBits::One)
+        .flow_control(FlowControl::None);
+
+    match SerialStream::open(&builder) {
+        Ok(port) => run_async_from_source(port, tx, rx).await,
+        Err(err) => {
+            let _ = tx.send(Err(open_error(OsStr::new(port_name), serial_error(err)))).await;
+            let _ = tx.send(Ok(LidarDriverMessage::Shutdown)).await;
+        },
+    }
+}
+
+/// ## Summary
+///
+/// Begin reading XV-11 data from any async source, such as a TCP stream or a file.
+///
+/// ## Remarks
+///
+/// Read errors are reported like serial errors. The driver shuts down when the source
+/// reaches its end or reports a disconnection.
+///
+pub async fn run_async_from_source<R: AsyncRead + Unpin>(mut source: R, tx: Sender<Result<LidarDriverMessage, LidarDriverError>>, mut rx: Receiver<LidarDriverCommand>) {
+    let mut parser = Parser::new();
+    let mut buffer = [0; READ_CHUNK_SIZE];
+    let mut is_paused = false;
+    let mut echo_latency = None;
+    let mut target_rpm = None;
+    let mut stats = StatsAccumulator::default();
+    let mut stats_since = Instant::now();
+
+    'driver: loop {
+        tokio::select! {
+            // Commands first, a busy port must not delay `Stop`.
+            biased;
+
+            command = rx.recv() => {
+                let command = match command {
+                    Some(command) => command,
+                    // The calling program is gone.
+                    None => break,
+                };
+
+                let reply = match command {
+                    LidarDriverCommand::Run => {
+                        if is_paused {
+                            // Resync on the bytes read from now on.
+                            parser.reset();
+                            is_paused = false;
+                        }
+
+                        None
+                    },
+                    LidarDriverCommand::Echo => Some(LidarDriverMessage::Echo(Instant::now())),
+                    LidarDriverCommand::EchoReceived(sent) => {
+                        echo_latency = Some(sent.elapsed());
+                        None
+                    },
+                    // Frames are not captured by the async driver.
+                    LidarDriverCommand::Hexdump(_) => None,
+                    LidarDriverCommand::Pause => {
+                        is_paused = true;
+                        None
+                    },
+                    LidarDriverCommand::Stop => break,
+                    LidarDriverCommand::SetTargetRpm(rpm) => {
+                        target_rpm = Some(rpm);
+                        None
+                    },
+                    LidarDriverCommand::Acknowledge(reply) => {
+                        // The caller may have stopped waiting.
+                        let _ = reply.send(DriverStatus { is_paused, echo_latency, target_rpm, ..DriverStatus::default() });
+                        None
+                    },
+                    LidarDriverCommand::QueryStats => {
+                        let window = stats_since.elapsed();
+                        stats_since = Instant::now();
+                        Some(LidarDriverMessage::Stats(stats.take(window)))
+                    },
+                    LidarDriverCommand::QueryStatus => Some(LidarDriverMessage::Status(DriverStatus { is_paused, echo_latency, target_rpm, ..DriverStatus::default() })),
+                };
+
+                if let Some(reply) = reply {
+                    if tx.send(Ok(reply)).await.is_err() {
+                        break;
+                    }
+                }
+            },
+            read = source.read(&mut buffer) => {
+                let count = match read {
+                    // End of the source.
+                    Ok(0) => break,
+                    Ok(count) => count,
+                    Err(err) if is_disconnected(&err) => {
+                        let _ = tx.send(Err(LidarDriverError::DeviceDisconnected(err))).await;
+                        break;
+                    },
+                    Err(err) => {
+                        if tx.send(Err(LidarDriverError::SerialRead(err))).await.is_err() {
+                            break;
+                        }
+
+                        continue;
+                    },
+                };
+
+                // Keep reading while paused, so no stale bytes are parsed on resume.
+                if is_paused {
+                    continue;
+                }
+
+                for result in parser.packets(&buffer[..count]) {
+                    stats.push(&result);
+
+                    if tx.send(result.map(LidarDriverMessage::Packet)).await.is_err() {
+                        break 'driver;
+                    }
+                }
+            },
+        }
+    }
+
+    let _ = tx.send(Ok(LidarDriverMessage::Shutdown)).await;
+}
+
+/// ## Summary
+///
+/// The messages of an async driver as a `Stream`, e.g. to use `StreamExt` combinators
+/// or `select!` without a task bridging the channels.
+///
+/// ## Remarks
+///
+/// The driver runs on its own Tokio task. Messages are buffered up to the capacity
+/// given, then the driver waits for the stream to be polled, so a slow consumer holds
+/// the driver back instead of losing messages. The stream ends after
+/// `LidarDriverMessage::Shutdown`. Dropping it stops the driver.
+///
+/// ## Example
+///
+/// ```no_run
+/// use futures_util::future::ready;
+/// use futures_util::StreamExt;
+/// use neato_xv11::prelude::*;
+/// use neato_xv11::LidarStream;
+///
+/// # async fn example() {
+/// let mut packets = LidarStream::spawn("/dev/serial0", 64).filter_map(|message| {
+///     ready(match message {
+///         Ok(LidarDriverMessage::Packet(packet)) => Some(packet),
+///         _ => None,
+///     })
+/// });
+///
+/// while let Some(packet) = packets.next().await {
+///     println!("{} RPM", packet.speed);
+/// }
+/// # }
+/// ```
+pub struct LidarStream {
+    messages: Receiver<Result<LidarDriverMessage, LidarDriverError>>,
+    commands: Sender<LidarDriverCommand>,
+}
+
+impl LidarStream {
+    /// ## Summary
+    ///
+    /// Spawn `run_async` on the current Tokio runtime and stream its messages.
+    ///
+    /// ## Parameters
+    ///
+    /// port_name: Name of the serial port, e.g. `/dev/serial0` or `COM3`.
+    ///
+    /// capacity: Number of messages buffered before the driver waits for the stream.
+    ///
+    /// ## Remarks
+    ///
+    /// Panics if called outside of a Tokio runtime, like `tokio::spawn`.
+    ///
+    pub fn spawn<T: AsRef<str>>(port_name: T, capacity: usize) -> Self {
+        let port_name = port_name.as_ref().to_owned();
+        let (message_tx, message_rx) = channel(capacity);
+        let (command_tx, command_rx) = channel(COMMAND_CAPACITY);
+
+        tokio::spawn(async move { run_async(port_name, message_tx, command_rx).await });
+
+        LidarStream { messages: message_rx, commands: command_tx }
+    }
+
+    /// ## Summary
+    ///
+    /// Spawn `run_async_from_source` on the current Tokio runtime and stream its messages.
+    ///
+    /// ## Remarks
+    ///
+    /// Panics if called outside of a Tokio runtime, like `tokio::spawn`.
+    ///
+    pub fn from_source<R: AsyncRead + Unpin + Send + 'static>(source: R, capacity: usize) -> Self {
+        let (message_tx, message_rx) = channel(capacity);
+        let (command_tx, command_rx) = channel(COMMAND_CAPACITY);
+
+        tokio::spawn(run_async_from_source(source, message_tx, command_rx));
+
+        LidarStream { messages: message_rx, commands: command_tx }
+    }
+
+    /// ## Summary
+    ///
+    /// Sends commands to the driver, e.g. `LidarDriverCommand::Pause`.
+    ///
+    pub fn commands(&self) -> Sender<LidarDriverCommand> {
+        self.commands.clone()
+    }
+
+    /// ## Summary
+    ///
+    /// Assemble the packets into scans. Errors are passed through, the other messages
+    /// are dropped.
+    ///
+    pub fn scans(self, assembler: ScanAssembler) -> ScanStream {
+        ScanStream { inner: self, assembler }
+    }
+}
+
+impl Stream for LidarStream {
+    type Item = Result<LidarDriverMessage, LidarDriverError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.messages.poll_recv(cx)
+    }
+}
+
+/// ## Summary
+///
+/// The scans assembled from a `LidarStream`, see `LidarStream::scans`.
+///
+pub struct ScanStream {
+    inner: LidarStream,
+    assembler: ScanAssembler,
+}
+
+impl ScanStream {
+    /// ## Summary
+    ///
+    /// Sends commands to the driver, e.g. `LidarDriverCommand::Pause`.
+    ///
+    pub fn commands(&self) -> Sender<LidarDriverCommand> {
+        self.inner.commands()
+    }
+}
+
+impl Stream for ScanStream {
+    type Item = Result<LidarScan, LidarDriverError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        loop {
+            let scan = match this.inner.messages.poll_recv(cx) {
+                Poll::Ready(Some(Ok(LidarDriverMessage::Packet(packet)))) => this.assembler.push(packet),
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                Poll::Ready(Some(Ok(_))) => this.assembler.poll(),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            if let Some(scan) = scan {
+                return Poll::Ready(Some(Ok(scan)));
+            }
+        }
+    }
+}
+
+/// Convert a `tokio-serial` error to the errors of the `serial` crate reported by the driver.
+fn serial_error(err: tokio_serial::Error) -> SerialError {
+    let kind = match err.kind {
+        tokio_serial::ErrorKind::NoDevice => SerialErrorKind::NoDevice,
+        tokio_serial::ErrorKind::InvalidInput => SerialErrorKind::InvalidInput,
+        tokio_serial::ErrorKind::Io(kind) => SerialErrorKind::Io(kind),
+        tokio_serial::ErrorKind::Unknown => SerialErrorKind::Io(std::io::ErrorKind::Other),
+    };
+
+    SerialError::new(kind, err.description)
+}
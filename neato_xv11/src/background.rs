@@ -0,0 +1,321 @@
+#[cfg(feature = "std")]
+use std::sync::Mutex;
+#[cfg(feature = "std")]
+use std::sync::mpsc::SendError;
+
+use super::data::{Float, LidarScan, SCAN_SIZE};
+use super::filter::Accumulator;
+#[cfg(feature = "std")]
+use super::prelude::*;
+#[cfg(feature = "std")]
+use super::scan::ScanAssembler;
+
+/// Default number of foreground readings for a sector to count as changed.
+const DEFAULT_MIN_READINGS: usize = 3;
+
+/// ## Summary
+///
+/// Learns the static background of a room and separates the foreground from it.
+///
+/// ## Remarks
+///
+/// The background is the average of the first revolutions pushed. Afterwards, each
+/// scan is reduced to its foreground: readings deviating from the background by more
+/// than the threshold, and valid readings at angles where the background had none.
+/// This is the core of using the LIDAR as a presence or intrusion sensor.
+///
+/// ## Example
+///
+/// ```no_run
+/// # fn watch(scans: Vec<neato_xv11::data::LidarScan>) {
+/// use neato_xv11::background::BackgroundModel;
+///
+/// // Learn for 50 revolutions (10 seconds at 300 RPM), then report deviations above 150 mm.
+/// let mut background = BackgroundModel::new(50, 150);
+///
+/// for scan in &scans {
+///     if let Some(foreground) = background.push(scan) {
+///         if foreground.valid_readings().count() > 5 {
+///             println!("Someone is in the room");
+///         }
+///     }
+/// }
+/// # }
+/// ```
+pub struct BackgroundModel {
+    // Number of revolutions used to learn the background.
+    learning_revolutions: usize,
+    // Deviation in millimeters above which a reading is foreground.
+    threshold: i32,
+    // Averages the revolutions while learning.
+    learner: Accumulator,
+    // The learned background, once learning is complete.
+    background: Option<LidarScan>,
+}
+
+impl BackgroundModel {
+    /// ## Summary
+    ///
+    /// Initialize a new background model.
+    ///
+    /// ## Parameters
+    ///
+    /// learning_revolutions: Number of revolutions used to learn the background.
+    ///
+    /// threshold: Deviation in millimeters above which a reading is foreground.
+    ///
+    pub fn new(learning_revolutions: usize, threshold: i32) -> Self {
+        BackgroundModel {
+            learning_revolutions,
+            threshold,
+            learner: Accumulator::mean(learning_revolutions),
+            background: None,
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Whether the background is still being learned.
+    ///
+    pub fn is_learning(&self) -> bool {
+        self.background.is_none()
+    }
+
+    /// ## Summary
+    ///
+    /// The learned background, if learning is complete.
+    ///
+    pub fn background(&self) -> Option<&LidarScan> {
+        self.background.as_ref()
+    }
+
+    /// ## Summary
+    ///
+    /// Discard the background and learn it again from the next revolutions.
+    ///
+    pub fn relearn(&mut self) {
+        self.learner = Accumulator::mean(self.learning_revolutions);
+        self.background = None;
+    }
+
+    /// ## Summary
+    ///
+    /// Add a revolution. Returns `None` while learning, the foreground of the scan afterwards.
+    ///
+    pub fn push(&mut self, scan: &LidarScan) -> Option<LidarScan> {
+        let background = match &self.background {
+            Some(background) => background,
+            None => {
+                self.background = self.learner.push(scan);
+                return None;
+            },
+        };
+
+        let mut foreground = scan.clone();
+
+        for (index, slot) in foreground.readings.iter_mut().enumerate().take(SCAN_SIZE) {
+            let keep = match (slot.as_ref().filter(|reading| reading.is_valid()), background.get(index)) {
+                (Some(reading), Some(learned)) => (reading.distance - learned.distance).abs() > self.threshold,
+                (Some(_), None) => true,
+                (None, _) => false,
+            };
+
+            if !keep {
+                *slot = None;
+            }
+        }
+
+        Some(foreground)
+    }
+
+    /// ## Summary
+    ///
+    /// Deviation of a reading from the background in millimeters (negative when closer),
+    /// if both are known.
+    ///
+    pub fn deviation(&self, index: usize, distance: i32) -> Option<Float> {
+        self.background
+            .as_ref()
+            .and_then(|background| background.get(index))
+            .map(|learned| (distance - learned.distance) as Float)
+    }
+}
+
+/// ## Summary
+///
+/// Reports sectors of the room whose foreground persists, on top of a `BackgroundModel`.
+///
+/// ## Remarks
+///
+/// The scan is split in equal sectors. A sector is changed when it holds at least
+/// `min_readings` foreground readings. A change is reported once it persisted for
+/// `debounce` consecutive revolutions, and reported again only after the sector
+/// cleared. The magnitude is the mean absolute deviation from the background of the
+/// sector's foreground readings, in millimeters.
+///
+pub struct ChangeDetector {
+    // Learns the background and extracts the foreground.
+    model: BackgroundModel,
+    // Number of sectors.
+    sectors: usize,
+    // Number of consecutive changed revolutions before a change is reported.
+    debounce: u32,
+    // Number of foreground readings for a sector to count as changed.
+    min_readings: usize,
+    // Number of consecutive changed revolutions of each sector.
+    streaks: [u32; SCAN_SIZE],
+    // Magnitude of the change of each sector in the last revolution.
+    magnitudes: [Float; SCAN_SIZE],
+}
+
+impl ChangeDetector {
+    /// ## Summary
+    ///
+    /// Initialize a new change detector.
+    ///
+    /// ## Parameters
+    ///
+    /// model: The background model, usually still learning.
+    ///
+    /// sectors: Number of equal sectors the scan is split in (1 to 360).
+    ///
+    /// debounce: Number of consecutive changed revolutions before a change is reported.
+    ///
+    pub fn new(model: BackgroundModel, sectors: usize, debounce: u32) -> Self {
+        ChangeDetector {
+            model,
+            sectors: sectors.clamp(1, SCAN_SIZE),
+            debounce: debounce.max(1),
+            min_readings: DEFAULT_MIN_READINGS,
+            streaks: [0; SCAN_SIZE],
+            magnitudes: [0.0; SCAN_SIZE],
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Set the number of foreground readings for a sector to count as changed. Defaults to 3.
+    ///
+    pub fn set_min_readings(&mut self, min_readings: usize) {
+        self.min_readings = min_readings.max(1);
+    }
+
+    /// ## Summary
+    ///
+    /// The background model.
+    ///
+    pub fn model(&self) -> &BackgroundModel {
+        &self.model
+    }
+
+    /// ## Summary
+    ///
+    /// Add a revolution. Returns the `(sector, magnitude)` of every newly reported change.
+    ///
+    pub fn push(&mut self, scan: &LidarScan) -> impl Iterator<Item = (usize, Float)> + '_ {
+        let foreground = self.model.push(scan);
+        let mut counts = [0usize; SCAN_SIZE];
+        let mut sums = [0.0; SCAN_SIZE];
+
+        if let Some(foreground) = &foreground {
+            for reading in foreground.valid_readings().filter(|reading| reading.index < SCAN_SIZE) {
+                let sector = reading.index * self.sectors / SCAN_SIZE;
+                counts[sector] += 1;
+                // Readings without background are as far as the LIDAR can see.
+                sums[sector] += self.model.deviation(reading.index, reading.distance).map_or(reading.distance as Float, Float::abs);
+            }
+        }
+
+        let mut reported = [false; SCAN_SIZE];
+
+        for sector in 0..self.sectors {
+            if counts[sector] >= self.min_readings {
+                self.streaks[sector] = self.streaks[sector].saturating_add(1);
+                self.magnitudes[sector] = sums[sector] / counts[sector] as Float;
+                reported[sector] = self.streaks[sector] == self.debounce;
+            } else {
+                self.streaks[sector] = 0;
+            }
+        }
+
+        let magnitudes = &self.magnitudes;
+        (0..self.sectors).filter(move |&sector| reported[sector]).map(move |sector| (sector, magnitudes[sector]))
+    }
+}
+
+/// ## Summary
+///
+/// A message sink that assembles scans, runs a `ChangeDetector` and sends a
+/// `LidarDriverMessage::ChangeDetected` for every reported change. Every message
+/// is forwarded to the inner sink.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::thread;
+/// # use std::sync::mpsc::channel;
+/// use neato_xv11::background::{BackgroundModel, ChangeDetectionSink, ChangeDetector};
+/// use neato_xv11::prelude::*;
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// // 8 sectors of 45 degrees, reported after 3 revolutions.
+/// let detector = ChangeDetector::new(BackgroundModel::new(50, 150), 8, 3);
+/// let message_tx = ChangeDetectionSink::new(message_tx, detector);
+///
+/// thread::spawn(move || {
+///     neato_xv11::run("/dev/serial0", message_tx, command_rx);
+/// });
+///
+/// for message in message_rx.iter() {
+///     if let Ok(LidarDriverMessage::ChangeDetected { sector, magnitude }) = message {
+///         println!("Change in sector {} ({} mm)", sector, magnitude);
+///     }
+/// }
+/// ```
+#[cfg(feature = "std")]
+pub struct ChangeDetectionSink<S> {
+    inner: S,
+    detector: Mutex<(ScanAssembler, ChangeDetector)>,
+}
+
+#[cfg(feature = "std")]
+impl<S: MessageSink> ChangeDetectionSink<S> {
+    /// ## Summary
+    ///
+    /// Wrap a sink with a change detector.
+    ///
+    pub fn new(inner: S, detector: ChangeDetector) -> Self {
+        ChangeDetectionSink {
+            inner,
+            detector: Mutex::new((ScanAssembler::new(), detector)),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: MessageSink> MessageSink for ChangeDetectionSink<S> {
+    fn send(&self, message: Result<LidarDriverMessage, LidarDriverError>) -> Result<(), SendError<Result<LidarDriverMessage, LidarDriverError>>> {
+        if let Ok(LidarDriverMessage::Packet(packet)) = &message {
+            let mut guard = self.detector.lock().unwrap_or_else(|e| e.into_inner());
+            let (assembler, detector) = &mut *guard;
+
+            if let Some(scan) = assembler.push(packet.clone()) {
+                for (sector, magnitude) in detector.push(&scan) {
+                    self.inner.send(Ok(LidarDriverMessage::ChangeDetected { sector, magnitude }))?;
+                }
+            }
+        }
+
+        self.inner.send(message)
+    }
+
+    fn dropped(&self) -> u64 {
+        self.inner.dropped()
+    }
+
+    fn backlog(&self) -> Option<Backlog> {
+        self.inner.backlog()
+    }
+}
@@ -0,0 +1,168 @@
+use std::ffi::OsStr;
+use std::io::{ErrorKind, Read};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "log")]
+use log::info;
+
+use super::builder::PortConfig;
+use super::driver::open_port;
+use super::info::{DetectedModel, LidarInfo};
+use super::model::BOTVAC_MIN_SPEED;
+use super::prelude::*;
+use super::protocol::LidarProtocol;
+
+/// Baud rates used by the Neato LIDAR family and compatible modules, most common first.
+pub const BAUD_RATES: &[u32] = &[115200, 230400];
+
+/// Number of consecutive valid packets required to accept a baud rate.
+const SYNC_PACKETS: usize = 2;
+
+/// LIDARs tried by `detect_lidar`, in order, with the baud rate they talk at.
+const CANDIDATES: &[(DetectedModel, u32)] = &[
+    (DetectedModel::Neato(Model::Xv11), 115200),
+    #[cfg(feature = "ldlidar")]
+    (DetectedModel::LdLidar, 230400),
+    (DetectedModel::Neato(Model::Xv11), 230400),
+];
+
+/// ## Summary
+///
+/// Find the baud rate the LIDAR is talking at.
+///
+/// ## Parameters
+///
+/// port_name: The port name to open.
+///
+/// candidates: The baud rates to try, in order. See `BAUD_RATES`.
+///
+/// timeout: How long to listen at each baud rate.
+///
+/// ## Remarks
+///
+/// The port is reopened at each candidate until consecutive packets with a valid
+/// checksum are received. Returns `Ok(None)` if no candidate succeeds. Candidates
+/// the platform does not support are skipped. Errors that are not about the baud
+/// rate (e.g. `PortNotFound`) are returned immediately.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use neato_xv11::{detect_baud_rate, BAUD_RATES};
+///
+/// let baud_rate = detect_baud_rate("/dev/ttyUSB0", BAUD_RATES, Duration::from_secs(1)).unwrap();
+/// ```
+pub fn detect_baud_rate<T: AsRef<OsStr> + ?Sized>(port_name: &T, candidates: &[u32], timeout: Duration) -> Result<Option<u32>, LidarDriverError> {
+    for &baud_rate in candidates {
+        let config = PortConfig {
+            baud_rate,
+            ..PortConfig::default()
+        };
+
+        let mut port = match open_port(port_name.as_ref(), &config) {
+            Ok(port) => port,
+            // The platform does not support this baud rate.
+            Err(LidarDriverError::Configure(_)) => continue,
+            Err(err) => return Err(err),
+        };
+
+        if sniff(&mut port, Model::Xv11, timeout).is_some() {
+            #[cfg(feature = "log")]
+            info!("Detected baud rate {}", baud_rate);
+
+            return Ok(Some(baud_rate));
+        }
+    }
+
+    Ok(None)
+}
+
+/// ## Summary
+///
+/// Find which supported LIDAR is connected, and the baud rate it is talking at.
+///
+/// ## Parameters
+///
+/// port_name: The port name to open.
+///
+/// timeout: How long to listen for each candidate.
+///
+/// ## Remarks
+///
+/// The port is reopened for each candidate protocol and baud rate until consecutive
+/// valid packets are received. Neato models share the same framing and are told
+/// apart by the reported speed. Returns `Ok(None)` if nothing matches.
+///
+pub fn detect_lidar<T: AsRef<OsStr> + ?Sized>(port_name: &T, timeout: Duration) -> Result<Option<LidarInfo>, LidarDriverError> {
+    for &(model, baud_rate) in CANDIDATES {
+        let config = PortConfig {
+            baud_rate,
+            ..PortConfig::default()
+        };
+
+        let mut port = match open_port(port_name.as_ref(), &config) {
+            Ok(port) => port,
+            // The platform does not support this baud rate.
+            Err(LidarDriverError::Configure(_)) => continue,
+            Err(err) => return Err(err),
+        };
+
+        let packet = match sniff(&mut port, model.protocol(), timeout) {
+            Some(packet) => packet,
+            None => continue,
+        };
+
+        let model = match model {
+            DetectedModel::Neato(_) if packet.speed > BOTVAC_MIN_SPEED => DetectedModel::Neato(Model::Botvac),
+            model => model,
+        };
+
+        #[cfg(feature = "log")]
+        info!("Detected {:?} at {} baud", model, baud_rate);
+
+        return Ok(Some(LidarInfo { model, baud_rate }));
+    }
+
+    Ok(None)
+}
+
+/// ## Summary
+///
+/// Read from the port until consecutive valid packets are received or the timeout expires.
+/// Returns the last packet received.
+///
+pub(crate) fn sniff<R: Read, P: LidarProtocol>(port: &mut R, protocol: P, timeout: Duration) -> Option<LidarPacket> {
+    let start = Instant::now();
+    let mut parser = Parser::with_protocol(protocol);
+    let mut chunk = [0; 256];
+    let mut valid_packets = 0;
+
+    while start.elapsed() < timeout {
+        let count = match port.read(&mut chunk) {
+            Ok(0) => return None,
+            Ok(count) => count,
+            Err(err) if err.kind() == ErrorKind::TimedOut || err.kind() == ErrorKind::Interrupted => continue,
+            Err(_) => return None,
+        };
+
+        for &byte in &chunk[..count] {
+            let packet = match parser.push(byte) {
+                Some(Ok(packet)) => packet,
+                Some(Err(_)) => {
+                    valid_packets = 0;
+                    continue;
+                },
+                None => continue,
+            };
+
+            valid_packets += 1;
+
+            if valid_packets >= SYNC_PACKETS {
+                return Some(packet);
+            }
+        }
+    }
+
+    None
+}
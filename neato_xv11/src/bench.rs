@@ -0,0 +1,207 @@
+use std::io::{Error as IoError, Read};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::channel;
+use std::time::{Duration, Instant};
+
+use super::driver::{run_source, RunOptions};
+use super::prelude::*;
+use super::protocol::LidarProtocol;
+
+/// ## Summary
+///
+/// Result of `parse_throughput`.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Throughput {
+    // Number of bytes parsed.
+    pub bytes: usize,
+    // Number of packets decoded.
+    pub packets: usize,
+    // Number of errors reported (checksum and resync).
+    pub errors: usize,
+    // Time spent parsing.
+    pub elapsed: Duration,
+}
+
+impl Throughput {
+    /// ## Summary
+    ///
+    /// Bytes parsed per second.
+    ///
+    pub fn bytes_per_second(&self) -> f64 {
+        self.bytes as f64 / self.elapsed.as_secs_f64().max(f64::MIN_POSITIVE)
+    }
+
+    /// ## Summary
+    ///
+    /// Packets decoded per second.
+    ///
+    pub fn packets_per_second(&self) -> f64 {
+        self.packets as f64 / self.elapsed.as_secs_f64().max(f64::MIN_POSITIVE)
+    }
+}
+
+/// ## Summary
+///
+/// Result of `end_to_end_latency`.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LatencyReport {
+    // Number of packets measured.
+    pub samples: usize,
+    // Lowest latency.
+    pub min: Duration,
+    // Average latency.
+    pub mean: Duration,
+    // 99th percentile latency.
+    pub p99: Duration,
+    // Highest latency.
+    pub max: Duration,
+}
+
+/// ## Summary
+///
+/// Measure how fast a parser decodes a replayed capture, without a serial port.
+///
+/// ## Parameters
+///
+/// parser: The parser, e.g. `Parser::new()`.
+///
+/// capture: Bytes recorded from the LIDAR.
+///
+/// iterations: Number of times the capture is parsed.
+///
+/// ## Remarks
+///
+/// `Parser::push` and `Parser::feed` only take bytes, so criterion benchmarks can
+/// exercise them the same way, e.g. `b.iter(|| capture.iter().filter_map(|&byte| parser.push(byte)).count())`.
+///
+/// ## Example
+///
+/// ```no_run
+/// use neato_xv11::bench::parse_throughput;
+/// use neato_xv11::parser::Parser;
+///
+/// let capture = std::fs::read("capture.bin").unwrap();
+/// let throughput = parse_throughput(&mut Parser::new(), &capture, 100);
+///
+/// println!("{:.0} packets/s", throughput.packets_per_second());
+/// ```
+pub fn parse_throughput<P: LidarProtocol>(parser: &mut Parser<P>, capture: &[u8], iterations: usize) -> Throughput {
+    let (mut packets, mut errors) = (0, 0);
+    let start = Instant::now();
+
+    for _ in 0..iterations {
+        for &byte in capture {
+            match parser.push(byte) {
+                Some(Ok(_)) => packets += 1,
+                Some(Err(_)) => errors += 1,
+                None => {},
+            }
+        }
+    }
+
+    Throughput {
+        bytes: capture.len() * iterations,
+        packets,
+        errors,
+        elapsed: start.elapsed(),
+    }
+}
+
+/// ## Summary
+///
+/// Measure the latency between a packet's last byte being read and the packet being
+/// delivered by the driver, replaying a capture through the full driver pipeline
+/// (reader thread, ring buffer, parser and message channel).
+///
+/// ## Parameters
+///
+/// capture: Bytes recorded from an XV-11.
+///
+/// ## Remarks
+///
+/// The capture is read as fast as the driver consumes it, one packet per read.
+/// Returns an empty report if the capture holds no valid packet.
+///
+pub fn end_to_end_latency(capture: &[u8]) -> LatencyReport {
+    // Split the capture after every valid packet.
+    let mut parser = Parser::new();
+    let mut ends = Vec::new();
+
+    for (offset, &byte) in capture.iter().enumerate() {
+        if let Some(Ok(_)) = parser.push(byte) {
+            ends.push(offset + 1);
+        }
+    }
+
+    let read_at = Arc::new(Mutex::new(Vec::with_capacity(ends.len())));
+    let reader = ReplayReader {
+        capture: capture.to_vec(),
+        ends,
+        position: 0,
+        read_at: read_at.clone(),
+    };
+
+    let (message_tx, message_rx) = channel();
+    let (_command_tx, command_rx) = channel();
+
+    let driver = std::thread::spawn(move || {
+        run_source(move || Ok(reader), Parser::new(), message_tx, command_rx, RunOptions::default());
+    });
+
+    let mut received = Vec::new();
+
+    for message in message_rx.iter() {
+        match message {
+            Ok(LidarDriverMessage::Packet(_)) => received.push(Instant::now()),
+            Ok(LidarDriverMessage::Shutdown) => break,
+            _ => {},
+        }
+    }
+
+    let _ = driver.join();
+
+    let read_at = read_at.lock().unwrap_or_else(|e| e.into_inner());
+    let mut latencies: Vec<Duration> = read_at.iter().zip(&received).map(|(read, received)| received.saturating_duration_since(*read)).collect();
+
+    if latencies.is_empty() {
+        return LatencyReport::default();
+    }
+
+    latencies.sort_unstable();
+    let samples = latencies.len();
+
+    LatencyReport {
+        samples,
+        min: latencies[0],
+        mean: latencies.iter().sum::<Duration>() / samples as u32,
+        p99: latencies[(samples - 1) * 99 / 100],
+        max: latencies[samples - 1],
+    }
+}
+
+/// Hands out a capture one packet per read, recording when each valid packet was read.
+struct ReplayReader {
+    capture: Vec<u8>,
+    // Offsets right after each valid packet.
+    ends: Vec<usize>,
+    position: usize,
+    read_at: Arc<Mutex<Vec<Instant>>>,
+}
+
+impl Read for ReplayReader {
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, IoError> {
+        let next_end = self.ends.iter().copied().find(|&end| end > self.position).unwrap_or(self.capture.len());
+        let count = (next_end - self.position).min(buffer.len());
+
+        buffer[..count].copy_from_slice(&self.capture[self.position..self.position + count]);
+        self.position += count;
+
+        if count > 0 && self.ends.binary_search(&self.position).is_ok() {
+            self.read_at.lock().unwrap_or_else(|e| e.into_inner()).push(Instant::now());
+        }
+
+        Ok(count)
+    }
+}
@@ -0,0 +1,1176 @@
+use std::ffi::{OsStr, OsString};
+use std::io::{Error as IoError, Read, Write};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+use super::background::{ChangeDetectionSink, ChangeDetector};
+use super::baud::detect_lidar;
+use super::calibration::DistanceCorrection;
+use super::clock::Clock;
+#[cfg(feature = "serde")]
+use super::config::DriverConfig;
+use super::console::{run_console, RobotConsole};
+use super::control::StopToken;
+use super::device_info::{query_device_info, DeviceInfoSink};
+use super::error_policy::ErrorPolicy;
+use super::driver::{open_port, run_source, send_message, LidarSource, LogContext, RunOptions, Timing, POLL_INTERVAL, READ_TIMEOUT, SILENCE_TIMEOUT};
+use super::model::AutoModel;
+use super::motor::{MotorController, MotorRegulator};
+use super::prelude::*;
+use super::protocol::LidarProtocol;
+use super::parser::SyncStrategy;
+use super::quirks::{Firmware, Quirks};
+use super::reconnect::{Reconnect, ReconnectPolicy};
+use super::rpm::{RpmMonitor, RpmMonitorSink};
+use super::scan::{ScanAssembler, ScanSink};
+use super::sync::SyncConfig;
+
+/// ## Summary
+///
+/// Settings applied to the driver threads.
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize), serde(default, deny_unknown_fields))]
+pub struct ThreadConfig {
+    // Name of the driver thread. The reader thread is named `<name>-reader`.
+    pub name: Option<String>,
+    // SCHED_FIFO priority (1-99) requested for both threads. Linux only.
+    pub realtime_priority: Option<i32>,
+    // Cores both threads are pinned to. Linux only.
+    pub affinity: Option<Vec<usize>>,
+}
+
+/// How long protocol detection listens for each candidate.
+const DETECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// ## Summary
+///
+/// Settings applied to the serial port when it is opened.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct PortConfig {
+    // Baud rate of the port. Non-standard rates are only accepted where the platform supports them.
+    pub baud_rate: u32,
+    // Set the USB-serial latency timer to its minimum (ASYNC_LOW_LATENCY). Linux only.
+    pub low_latency: bool,
+    // Lock the port so no other process can read from it (TIOCEXCL and flock on Unix).
+    pub exclusive: bool,
+    // Discard incoming bytes until the port stays silent this long (at most 1 s) before reading.
+    pub quiet_gap: Option<Duration>,
+    // Target of the log records of this driver. Defaults to `neato_xv11`.
+    pub log_target: Option<String>,
+    // How many times opening a missing or busy port is attempted. At least one attempt is made.
+    pub open_attempts: u32,
+    // Delay between two attempts to open the port.
+    pub open_retry_delay: Duration,
+    // Timeout of a single read. Bounds how long stopping the driver takes.
+    pub read_timeout: Duration,
+    // How long the port may stay silent before `LidarDriverError::ReadTimeout` is reported.
+    // Longer for adapters or firmwares pausing between bursts.
+    pub silence_timeout: Duration,
+    // Longest time the driver waits for data before checking for commands. Packets and read
+    // errors wake it immediately, longer intervals only delay the commands of an idle driver.
+    pub poll_interval: Duration,
+}
+
+impl Default for PortConfig {
+    fn default() -> Self {
+        PortConfig {
+            baud_rate: 115200,
+            low_latency: false,
+            exclusive: false,
+            quiet_gap: None,
+            log_target: None,
+            open_attempts: 1,
+            open_retry_delay: Duration::from_secs(1),
+            read_timeout: READ_TIMEOUT,
+            silence_timeout: SILENCE_TIMEOUT,
+            poll_interval: POLL_INTERVAL,
+        }
+    }
+}
+
+impl PortConfig {
+    /// Timeouts of the driver threads.
+    pub(crate) fn timing(&self) -> Timing {
+        Timing {
+            silence_timeout: self.silence_timeout,
+            poll_interval: self.poll_interval,
+        }
+    }
+}
+
+/// ## Summary
+///
+/// Configures and spawns the LIDAR driver on its own thread.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::sync::mpsc::channel;
+/// use neato_xv11::LidarDriverBuilder;
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// let handle = LidarDriverBuilder::new("/dev/ttyUSB0")
+///     .low_latency(true)
+///     .thread_name("lidar")
+///     .realtime_priority(50)
+///     .affinity(&[3])
+///     .spawn(message_tx, command_rx)
+///     .unwrap();
+/// ```
+pub struct LidarDriverBuilder {
+    // The port name to open.
+    port_name: OsString,
+    // Framing and decoding of the packets. Detected when the driver starts if not set.
+    protocol: Option<Box<dyn LidarProtocol + Send>>,
+    // Firmware deviations to compensate for. Selected from the reported firmware if not set.
+    quirks: Option<Quirks>,
+    // Correction applied to every valid distance.
+    correction: Option<DistanceCorrection>,
+    // Query the LIDAR for its identification before reading.
+    device_info: bool,
+    // Sends `LidarDriverMessage::ChangeDetected` events.
+    change_detector: Option<ChangeDetector>,
+    // Sends `LidarDriverMessage::Warning` when the speed leaves or returns to a band.
+    rpm_monitor: Option<RpmMonitor>,
+    // Sends `LidarDriverMessage::Scan` once per revolution.
+    scan_assembler: Option<ScanAssembler>,
+    // Regulates the motor from the reported spin speed.
+    motor: Option<MotorRegulator<Box<dyn MotorController + Send>>>,
+    // Wrap the errors in `LidarDriverError::Context`.
+    error_context: bool,
+    // Send `LidarDriverMessage::Resumed` when the driver runs again after a pause.
+    report_resume: bool,
+    // Shuts the driver down on too many errors, or downgrades them to warnings.
+    error_policy: ErrorPolicy,
+    // Time of the watchdog, sync timeout, silence timeout and reconnection backoff.
+    clock: Option<Arc<dyn Clock>>,
+    // Report `LidarDriverError::NoData` or `LidarDriverError::MotorStalled` when no valid packet is parsed for this long.
+    watchdog: Option<Duration>,
+    // Receives a copy of every byte read from the port.
+    raw_tap: Option<Box<dyn Write + Send>>,
+    // Receives every read as a timestamped log record.
+    log_tap: Option<Box<dyn Write + Send>>,
+    // Receives every packet as a pcap frame.
+    pcap_tap: Option<Box<dyn Write + Send>>,
+    // Stops the driver from code without the command sender.
+    stop: Option<StopToken>,
+    // Reopens the port after a disconnection.
+    reconnect: Option<ReconnectPolicy>,
+    // How the packet boundaries are found and the search reported.
+    sync: SyncConfig,
+    // Settings applied to the serial port.
+    port: PortConfig,
+    // Settings applied to the driver threads.
+    thread: ThreadConfig,
+}
+
+impl LidarDriverBuilder {
+    /// ## Summary
+    ///
+    /// Initialize a new builder.
+    ///
+    /// ## Parameters
+    ///
+    /// port_name: The port name to open.
+    ///
+    pub fn new<T: AsRef<OsStr> + ?Sized>(port_name: &T) -> Self {
+        LidarDriverBuilder {
+            port_name: port_name.as_ref().to_os_string(),
+            protocol: None,
+            quirks: None,
+            correction: None,
+            device_info: false,
+            change_detector: None,
+            rpm_monitor: None,
+            scan_assembler: None,
+            motor: None,
+            error_context: false,
+            report_resume: false,
+            error_policy: ErrorPolicy::default(),
+            clock: None,
+            watchdog: None,
+            raw_tap: None,
+            log_tap: None,
+            pcap_tap: None,
+            stop: None,
+            reconnect: None,
+            sync: SyncConfig::default(),
+            port: PortConfig::default(),
+            thread: ThreadConfig::default(),
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Initialize a builder from a configuration.
+    ///
+    #[cfg(feature = "serde")]
+    pub fn with_config(config: DriverConfig) -> Self {
+        let filters = config.filter_chain();
+        let mut builder = LidarDriverBuilder::new(&config.port);
+        builder.protocol = config.model.map(|model| model.protocol());
+        builder.quirks = config.quirks.or(config.firmware.map(Firmware::quirks));
+        builder.correction = config.distance_correction;
+        builder.device_info = config.device_info;
+        builder.error_policy = config.error_policy;
+        builder.change_detector = config.change_detection.map(|change_detection| change_detection.detector());
+        builder.scan_assembler = match (config.calibration, filters) {
+            (None, None) => None,
+            (calibration, filters) => {
+                let mut assembler = ScanAssembler::new();
+
+                if let Some(calibration) = calibration {
+                    assembler.set_robot_calibration(calibration);
+                }
+
+                if let Some(filters) = filters {
+                    assembler.set_filter(filters);
+                }
+
+                Some(assembler)
+            },
+        };
+        builder.port.low_latency = config.low_latency;
+        builder.port.exclusive = config.exclusive;
+        builder.thread = config.thread;
+
+        if let Some(baud_rate) = config.baud_rate {
+            builder.port.baud_rate = baud_rate;
+        }
+
+        if let Some(read_timeout_ms) = config.read_timeout_ms {
+            builder.port.read_timeout = Duration::from_millis(read_timeout_ms);
+        }
+
+        if let Some(silence_timeout_ms) = config.silence_timeout_ms {
+            builder.port.silence_timeout = Duration::from_millis(silence_timeout_ms);
+        }
+
+        if let Some(poll_interval_us) = config.poll_interval_us {
+            builder.port.poll_interval = Duration::from_micros(poll_interval_us);
+        }
+
+        builder
+    }
+
+    /// ## Summary
+    ///
+    /// Initialize a builder from a configuration file. See `DriverConfig::load`.
+    ///
+    #[cfg(any(feature = "json", feature = "toml", feature = "yaml"))]
+    pub fn from_config<P: AsRef<std::path::Path>>(path: P) -> Result<Self, IoError> {
+        Ok(LidarDriverBuilder::with_config(DriverConfig::load(path)?))
+    }
+
+    /// ## Summary
+    ///
+    /// Select the LIDAR model.
+    ///
+    /// ## Remarks
+    ///
+    /// If neither a model nor a protocol is set, the driver detects the LIDAR and its
+    /// baud rate when it starts and sends a `LidarDriverMessage::Info` before any packet,
+    /// or `LidarDriverError::ProtocolNotDetected` if nothing matches.
+    ///
+    pub fn model(mut self, model: Model) -> Self {
+        self.protocol = Some(Box::new(model));
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Read another LIDAR through its protocol implementation.
+    ///
+    pub fn protocol<P: LidarProtocol + Send + 'static>(mut self, protocol: P) -> Self {
+        self.protocol = Some(Box::new(protocol));
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Compensate for the known deviations of a firmware version.
+    ///
+    pub fn firmware(mut self, firmware: Firmware) -> Self {
+        self.quirks = Some(firmware.quirks());
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Compensate for the given firmware deviations.
+    ///
+    pub fn quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = Some(quirks);
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Correct every valid distance with `corrected = scale * distance + offset`
+    /// (offset in millimeters), before any other processing.
+    ///
+    pub fn distance_correction(mut self, scale: Float, offset: Float) -> Self {
+        self.correction = Some(DistanceCorrection { scale, offset });
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Send a `LidarDriverMessage::DeviceInfo` identifying the LIDAR.
+    ///
+    /// ## Remarks
+    ///
+    /// The LIDAR is asked for its firmware version and serial number when the driver
+    /// starts. The quirks of the reported firmware are applied unless set explicitly.
+    /// LIDARs that do not answer are identified by the speed range and packet rate
+    /// observed over the first revolutions instead.
+    ///
+    pub fn device_info(mut self, enabled: bool) -> Self {
+        self.device_info = enabled;
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Send a `LidarDriverMessage::ChangeDetected` for every change reported by the detector.
+    ///
+    pub fn change_detection(mut self, detector: ChangeDetector) -> Self {
+        self.change_detector = Some(detector);
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Send a `LidarDriverMessage::Warning` when the speed leaves the band of the monitor,
+    /// and another one when it returns.
+    ///
+    pub fn rpm_monitor(mut self, monitor: RpmMonitor) -> Self {
+        self.rpm_monitor = Some(monitor);
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Send a `LidarDriverMessage::Scan` once per revolution, assembled by the assembler,
+    /// in addition to the packets.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use std::sync::mpsc::channel;
+    /// use neato_xv11::scan::{ScanAssembler, ScanPolicy};
+    /// use neato_xv11::LidarDriverBuilder;
+    ///
+    /// let (message_tx, message_rx) = channel();
+    /// let (command_tx, command_rx) = channel();
+    ///
+    /// let mut assembler = ScanAssembler::new();
+    /// assembler.set_policy(ScanPolicy::CompleteOnly);
+    ///
+    /// let handle = LidarDriverBuilder::new("/dev/ttyUSB0")
+    ///     .assemble_scans(assembler)
+    ///     .spawn(message_tx, command_rx)
+    ///     .unwrap();
+    /// ```
+    pub fn assemble_scans(mut self, assembler: ScanAssembler) -> Self {
+        self.scan_assembler = Some(assembler);
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Drive the motor and regulate its speed from the speed reported in the packets.
+    ///
+    /// ## Remarks
+    ///
+    /// The motor is started once the port is open and stopped when the driver shuts down.
+    /// `LidarDriverCommand::SetTargetRpm` changes the regulated speed.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use std::sync::mpsc::channel;
+    /// use neato_xv11::motor::{self, MotorRegulator, PidController};
+    /// use neato_xv11::LidarDriverBuilder;
+    ///
+    /// let (message_tx, message_rx) = channel();
+    /// let (command_tx, command_rx) = channel();
+    ///
+    /// // E.g. write the duty cycle to a sysfs PWM channel.
+    /// let motor = motor::from_fn(|duty| println!("Duty cycle {:.2}", duty));
+    ///
+    /// let handle = LidarDriverBuilder::new("/dev/ttyUSB0")
+    ///     .motor(MotorRegulator::new(motor, PidController::default()))
+    ///     .spawn(message_tx, command_rx)
+    ///     .unwrap();
+    /// ```
+    pub fn motor<M: MotorController + Send + 'static>(mut self, regulator: MotorRegulator<M>) -> Self {
+        self.motor = Some(regulator.boxed());
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Send every error of the running driver as a `LidarDriverError::Context`, with the
+    /// port, the number of bytes parsed and the index of the last valid packet.
+    ///
+    /// ## Remarks
+    ///
+    /// Use `LidarDriverError::root` to match on the error itself.
+    ///
+    pub fn error_context(mut self, enabled: bool) -> Self {
+        self.error_context = enabled;
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Send `LidarDriverMessage::Resumed` when the driver runs again after
+    /// `LidarDriverCommand::Pause`.
+    ///
+    /// ## Remarks
+    ///
+    /// On resume, the data received while paused is flushed and the parser resyncs, so
+    /// every packet after the message was read after resuming.
+    ///
+    pub fn report_resume(mut self, enabled: bool) -> Self {
+        self.report_resume = enabled;
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Shut the driver down when the link is hopeless, or downgrade recoverable errors to
+    /// warnings. See `error_policy::ErrorPolicy`.
+    ///
+    pub fn error_policy(mut self, policy: ErrorPolicy) -> Self {
+        self.error_policy = policy;
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Time the watchdog, the sync timeout, the silence timeout and the reconnection
+    /// backoff on the given clock instead of the system clock.
+    ///
+    /// ## Remarks
+    ///
+    /// With a `clock::VirtualClock`, a test advances the time instead of waiting for it:
+    /// `NoData` is reported as soon as the clock is moved past the watchdog timeout,
+    /// and reconnection delays are skipped while advancing the clock.
+    ///
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Send `LidarDriverError::NoData` when no valid packet is parsed for `timeout`
+    /// while the driver is running, then again after every further `timeout`. Once
+    /// packets were flowing, `LidarDriverError::MotorStalled` is sent instead.
+    ///
+    /// ## Remarks
+    ///
+    /// Catches a motor that is not spinning, which otherwise goes unnoticed when the
+    /// LIDAR keeps sending invalid data. The watchdog starts over, with `NoData`, when
+    /// the driver resumes or reconnects. Combine with `rpm_monitor` to be warned when
+    /// the motor slows down before it stalls.
+    ///
+    pub fn watchdog(mut self, timeout: Duration) -> Self {
+        self.watchdog = Some(timeout);
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Set how the parser finds the packet boundaries, and whether the search is reported.
+    /// See `sync::SyncConfig`.
+    ///
+    /// ## Remarks
+    ///
+    /// With a timeout, `LidarDriverError::SyncTimeout` is sent while the parser searches
+    /// for longer, e.g. when the motor is off. The time spent paused or reconnecting
+    /// does not count.
+    ///
+    pub fn sync(mut self, config: SyncConfig) -> Self {
+        self.sync = config;
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Write a copy of every byte read from the port to `tap`, while the packets are
+    /// parsed as usual. Use it to record captures for offline analysis.
+    ///
+    /// ## Remarks
+    ///
+    /// The tap is written from the reader thread, including while the driver is paused.
+    /// A slow tap delays reads, wrap files in a `BufWriter`. The tap is dropped after
+    /// its first write error, without affecting the driver.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use std::sync::mpsc::channel;
+    /// use std::fs::File;
+    /// use std::io::BufWriter;
+    /// use neato_xv11::LidarDriverBuilder;
+    ///
+    /// let (message_tx, message_rx) = channel();
+    /// let (command_tx, command_rx) = channel();
+    ///
+    /// let capture = BufWriter::new(File::create("capture.bin").unwrap());
+    ///
+    /// let handle = LidarDriverBuilder::new("/dev/ttyUSB0")
+    ///     .raw_tap(capture)
+    ///     .spawn(message_tx, command_rx)
+    ///     .unwrap();
+    /// ```
+    pub fn raw_tap<W: Write + Send + 'static>(mut self, tap: W) -> Self {
+        self.raw_tap = Some(Box::new(tap));
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Write every read from the port to `tap` as a timestamped log record, see
+    /// `recording::LogWriter`. Unlike `raw_tap`, the capture keeps the timing of the
+    /// data, so `recording::LogReader` can replay it in real time.
+    ///
+    /// ## Remarks
+    ///
+    /// The tap is written from the reader thread, including while the driver is paused.
+    /// Wrap files in a `BufWriter`. The tap is dropped after its first write error,
+    /// without affecting the driver.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use std::sync::mpsc::channel;
+    /// use std::fs::File;
+    /// use std::io::BufWriter;
+    /// use neato_xv11::LidarDriverBuilder;
+    ///
+    /// let (message_tx, message_rx) = channel();
+    /// let (command_tx, command_rx) = channel();
+    ///
+    /// let capture = BufWriter::new(File::create("capture.xvlog").unwrap());
+    ///
+    /// let handle = LidarDriverBuilder::new("/dev/ttyUSB0")
+    ///     .log_tap(capture)
+    ///     .spawn(message_tx, command_rx)
+    ///     .unwrap();
+    /// ```
+    pub fn log_tap<W: Write + Send + 'static>(mut self, tap: W) -> Self {
+        self.log_tap = Some(Box::new(tap));
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Reopen and reconfigure the port when it is disconnected, e.g. when the USB adapter
+    /// glitches, instead of shutting down.
+    ///
+    /// ## Remarks
+    ///
+    /// Each disconnection is reported as `LidarDriverError::DeviceDisconnected` followed by
+    /// a `LidarDriverMessage::Reconnecting` per attempt, then `LidarDriverMessage::Reconnected`
+    /// once the port is open again. The parser resyncs on the new data. When the policy
+    /// gives up, the driver shuts down as without it. Only applies to `spawn`.
+    ///
+    pub fn reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect = Some(policy);
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Write every valid packet to `tap` as a pcap capture, one timestamped frame per
+    /// packet, for inspection with Wireshark or tshark. See `pcap::LINK_TYPE`.
+    ///
+    /// ## Remarks
+    ///
+    /// Packets are written from the parser thread as they are decoded, wrap files in a
+    /// `BufWriter`. The tap is dropped after its first write error, without affecting
+    /// the driver.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use std::sync::mpsc::channel;
+    /// use std::fs::File;
+    /// use std::io::BufWriter;
+    /// use neato_xv11::LidarDriverBuilder;
+    ///
+    /// let (message_tx, message_rx) = channel();
+    /// let (command_tx, command_rx) = channel();
+    ///
+    /// let capture = BufWriter::new(File::create("lidar.pcap").unwrap());
+    ///
+    /// let handle = LidarDriverBuilder::new("/dev/ttyUSB0")
+    ///     .pcap_tap(capture)
+    ///     .spawn(message_tx, command_rx)
+    ///     .unwrap();
+    /// ```
+    pub fn pcap_tap<W: Write + Send + 'static>(mut self, tap: W) -> Self {
+        self.pcap_tap = Some(Box::new(tap));
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Stop the driver when `token` is stopped, as if it had received `LidarDriverCommand::Stop`.
+    ///
+    pub fn stop_token(mut self, token: StopToken) -> Self {
+        self.stop = Some(token);
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Set the baud rate. Defaults to 115200, the rate of the XV-11.
+    ///
+    /// ## Remarks
+    ///
+    /// Some compatible modules and Botvac LDS units use 230400 or other rates.
+    /// Use `detect_baud_rate` if the rate is unknown.
+    ///
+    pub fn baud_rate(mut self, baud_rate: u32) -> Self {
+        self.port.baud_rate = baud_rate;
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Request the lowest latency from USB-serial adapters (FTDI, CH340, CP210x).
+    ///
+    /// ## Remarks
+    ///
+    /// FTDI adapters buffer data for up to 16 ms by default, which delays packets and
+    /// delivers them in bunches. Setting `ASYNC_LOW_LATENCY` lowers the latency timer to 1 ms.
+    /// Linux only. If the setting cannot be applied, opening the port fails with
+    /// `LidarDriverError::Configure`.
+    ///
+    pub fn low_latency(mut self, enabled: bool) -> Self {
+        self.port.low_latency = enabled;
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Lock the port so two drivers cannot silently interleave reads from the same device.
+    ///
+    /// ## Remarks
+    ///
+    /// If another process holds the lock, opening the port fails with `LidarDriverError::PortBusy`.
+    /// Unix only, Windows always opens serial ports exclusively.
+    ///
+    pub fn exclusive(mut self, enabled: bool) -> Self {
+        self.port.exclusive = enabled;
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Discard incoming bytes until the port stays silent for `gap` before the first
+    /// synchronization. Gives up after 1 s.
+    ///
+    /// ## Remarks
+    ///
+    /// The bytes buffered by the OS are always discarded when the port is opened, but
+    /// USB-serial adapters may still hold older data. Useful when the motor is started
+    /// by the calling program after the port is opened.
+    ///
+    pub fn quiet_gap(mut self, gap: Duration) -> Self {
+        self.port.quiet_gap = Some(gap);
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Retry opening the port when it does not exist yet or is busy.
+    ///
+    /// ## Parameters
+    ///
+    /// attempts: How many times opening the port is attempted.
+    ///
+    /// delay: Delay between two attempts.
+    ///
+    /// ## Remarks
+    ///
+    /// USB-serial adapters often enumerate a second or two after services started at boot.
+    /// Other errors, such as `LidarDriverError::PermissionDenied`, are reported immediately.
+    ///
+    pub fn open_retry(mut self, attempts: u32, delay: Duration) -> Self {
+        self.port.open_attempts = attempts;
+        self.port.open_retry_delay = delay;
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Set the read timeouts of the port.
+    ///
+    /// ## Parameters
+    ///
+    /// read: Timeout of a single read. Bounds how long stopping the driver takes.
+    /// Defaults to 100 ms.
+    ///
+    /// silence: How long the port may stay silent before `LidarDriverError::ReadTimeout`
+    /// is reported. Defaults to 1 s.
+    ///
+    pub fn timeouts(mut self, read: Duration, silence: Duration) -> Self {
+        self.port.read_timeout = read;
+        self.port.silence_timeout = silence;
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Set how long the driver waits for data before checking for commands. Defaults
+    /// to 10 ms.
+    ///
+    /// ## Remarks
+    ///
+    /// Packets and read errors wake the driver as soon as they are read, the interval
+    /// only bounds how long a command waits while the port is silent or the driver is
+    /// paused. Longer intervals wake the driver less often when idle.
+    ///
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.port.poll_interval = interval;
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Replace every port setting at once.
+    ///
+    pub fn port_config(mut self, config: PortConfig) -> Self {
+        self.port = config;
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Set the target of the log records of this driver (`log` feature).
+    ///
+    /// ## Remarks
+    ///
+    /// Records carry the `port` key, and packet records the `packet_index`, `revolution`
+    /// and `error_kind` keys. With several LIDARs, a target per driver lets the logger
+    /// filter or route the records of each one.
+    ///
+    pub fn log_target<T: Into<String>>(mut self, target: T) -> Self {
+        self.port.log_target = Some(target.into());
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Name the driver thread.
+    ///
+    pub fn thread_name<T: Into<String>>(mut self, name: T) -> Self {
+        self.thread.name = Some(name.into());
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Request the SCHED_FIFO realtime policy with the given priority (1-99).
+    ///
+    /// ## Remarks
+    ///
+    /// Usually requires root or `CAP_SYS_NICE`. If the priority cannot be applied, the driver
+    /// sends `LidarDriverError::ThreadSettings` and keeps running with the default policy.
+    ///
+    pub fn realtime_priority(mut self, priority: i32) -> Self {
+        self.thread.realtime_priority = Some(priority);
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Pin the driver threads to the given cores.
+    ///
+    pub fn affinity(mut self, cores: &[usize]) -> Self {
+        self.thread.affinity = Some(cores.to_vec());
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Replace every thread setting at once.
+    ///
+    pub fn thread_config(mut self, config: ThreadConfig) -> Self {
+        self.thread = config;
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Spawn the driver thread.
+    ///
+    /// ## Parameters
+    ///
+    /// tx: Sends decoded LIDAR messages or error encountered.
+    ///
+    /// rx: Receives commands from the calling program.
+    ///
+    /// ## Remarks
+    ///
+    /// Returns an error if the thread could not be spawned.
+    ///
+    pub fn spawn<S: MessageSink + Send + 'static, C: CommandSource + Send + 'static>(self, tx: S, rx: C) -> Result<JoinHandle<()>, IoError> {
+        let mut builder = thread::Builder::new();
+
+        if let Some(name) = &self.thread.name {
+            builder = builder.name(name.clone());
+        }
+
+        let port_name = self.port_name;
+        let protocol = self.protocol;
+        let quirks = self.quirks;
+        let device_info = self.device_info;
+        let correction = self.correction;
+        let change_detector = self.change_detector;
+        let rpm_monitor = self.rpm_monitor;
+        let scan_assembler = self.scan_assembler;
+        let motor = self.motor;
+        let watchdog = self.watchdog;
+        let error_context = self.error_context;
+        let report_resume = self.report_resume;
+        let error_policy = self.error_policy;
+        let clock = self.clock;
+        let raw_tap = self.raw_tap;
+        let log_tap = self.log_tap;
+        let pcap_tap = self.pcap_tap;
+        let stop = self.stop;
+        let reconnect = self.reconnect;
+        let sync = self.sync;
+        let port_config = self.port;
+        let thread_config = self.thread;
+
+        builder.spawn(move || {
+            let tx = wrap_sink(tx, change_detector, rpm_monitor, scan_assembler);
+
+            // Detection opens the port once per candidate without retrying, wait for it first.
+            if protocol.is_none() && port_config.open_attempts > 1 {
+                if let Err(err) = open_port(&port_name, &port_config) {
+                    let _ = send_message(&tx, Err(err));
+                    return;
+                }
+            }
+
+            let (protocol, port_config) = match protocol {
+                Some(protocol) => (protocol, port_config),
+                None => match detect_lidar(&port_name, DETECT_TIMEOUT) {
+                    Ok(Some(info)) => {
+                        let protocol = info.model.protocol();
+                        let port_config = PortConfig {
+                            baud_rate: info.baud_rate,
+                            ..port_config
+                        };
+
+                        if send_message(&tx, Ok(LidarDriverMessage::Info(info))).is_err() {
+                            return;
+                        }
+
+                        (protocol, port_config)
+                    },
+                    Ok(None) => {
+                        let _ = send_message(&tx, Err(LidarDriverError::ProtocolNotDetected));
+                        return;
+                    },
+                    Err(err) => {
+                        let _ = send_message(&tx, Err(err));
+                        return;
+                    },
+                },
+            };
+
+            let reconnect = reconnect.map(|policy| {
+                let port_name = port_name.clone();
+                // The policy retries, each reopening is a single attempt.
+                let port_config = PortConfig { open_attempts: 1, ..port_config.clone() };
+
+                Reconnect {
+                    policy,
+                    reopen: Box::new(move || open_port(&port_name, &port_config).map(|port| Box::new(port) as Box<dyn Read + Send>)),
+                }
+            });
+
+            let options = RunOptions {
+                log: LogContext::new(&port_name, port_config.log_target.as_deref()),
+                thread: thread_config,
+                watchdog,
+                error_context,
+                raw_tap,
+                log_tap,
+                pcap_tap,
+                stop,
+                timing: port_config.timing(),
+                motor,
+                reconnect,
+                sync,
+                report_resume,
+                error_policy,
+                clock,
+            };
+
+            let mut parser = parser(protocol, quirks, correction, sync.strategy);
+
+            if !device_info {
+                run_source(move || open_port(&port_name, &port_config), parser, tx, rx, options);
+                return;
+            }
+
+            match query_device_info(&port_name, &port_config, DETECT_TIMEOUT) {
+                Ok(Some(info)) => {
+                    let firmware = info.firmware.as_deref().and_then(Firmware::from_version);
+
+                    if let (None, Some(firmware)) = (quirks, firmware) {
+                        parser.set_quirks(firmware.quirks());
+                    }
+
+                    if send_message(&tx, Ok(LidarDriverMessage::DeviceInfo(info))).is_err() {
+                        return;
+                    }
+
+                    run_source(move || open_port(&port_name, &port_config), parser, tx, rx, options);
+                },
+                Ok(None) => {
+                    let tx = DeviceInfoSink::new(tx);
+                    run_source(move || open_port(&port_name, &port_config), parser, tx, rx, options);
+                },
+                Err(err) => {
+                    let _ = send_message(&tx, Err(err));
+                },
+            }
+        })
+    }
+
+    /// ## Summary
+    ///
+    /// Spawn a driver reading the LDS through the USB console of a Neato robot, for an
+    /// LDS still attached to the robot. See `console::RobotConsole`.
+    ///
+    /// ## Parameters
+    ///
+    /// tx: Sends decoded LIDAR messages or error encountered.
+    ///
+    /// rx: Receives commands from the calling program.
+    ///
+    /// ## Remarks
+    ///
+    /// The port settings, thread name and sinks of the builder apply. The protocol,
+    /// taps, motor, watchdog and reconnection settings do not, as the robot drives the
+    /// LDS itself. Returns an error if the thread could not be spawned.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use std::sync::mpsc::channel;
+    /// use neato_xv11::LidarDriverBuilder;
+    ///
+    /// let (message_tx, message_rx) = channel();
+    /// let (command_tx, command_rx) = channel();
+    ///
+    /// let handle = LidarDriverBuilder::new("/dev/ttyACM0")
+    ///     .assemble_scans(Default::default())
+    ///     .spawn_console(message_tx, command_rx)
+    ///     .unwrap();
+    /// ```
+    pub fn spawn_console<S: MessageSink + Send + 'static, C: CommandSource + Send + 'static>(self, tx: S, rx: C) -> Result<JoinHandle<()>, IoError> {
+        let mut builder = thread::Builder::new();
+
+        if let Some(name) = &self.thread.name {
+            builder = builder.name(name.clone());
+        }
+
+        builder.spawn(move || {
+            let tx = wrap_sink(tx, self.change_detector, self.rpm_monitor, self.scan_assembler);
+
+            match open_port(&self.port_name, &self.port) {
+                Ok(port) => run_console(RobotConsole::new(port), tx, rx),
+                Err(err) => {
+                    let _ = send_message(&tx, Err(err));
+                },
+            }
+        })
+    }
+
+    /// ## Summary
+    ///
+    /// Replay a recorded byte stream, e.g. captured with `raw_tap`, through the driver
+    /// configured by the builder. Returns once the source ends or the driver is stopped.
+    ///
+    /// ## Parameters
+    ///
+    /// source: The recorded bytes. Read on a dedicated reader thread.
+    ///
+    /// tx: Sends decoded LIDAR messages or error encountered.
+    ///
+    /// rx: Receives commands from the calling program.
+    ///
+    /// ## Remarks
+    ///
+    /// The bytes go through the same synchronization, parsing and sinks as the bytes
+    /// read from the port, so the messages are the ones sent while recording. The port
+    /// is not opened: unless set, the Neato model is told from the first valid packet
+    /// (see `model::AutoModel`), the device is not queried and no motor is driven. Wrap
+    /// the source in a `clock::PacedSource` to replay at the pace of the serial link.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use std::sync::mpsc::channel;
+    /// use std::fs::File;
+    /// use std::io::BufReader;
+    /// use neato_xv11::prelude::*;
+    /// use neato_xv11::LidarDriverBuilder;
+    ///
+    /// let (message_tx, message_rx) = channel();
+    /// let (command_tx, command_rx) = channel();
+    ///
+    /// let capture = BufReader::new(File::open("capture.bin").unwrap());
+    ///
+    /// LidarDriverBuilder::new("capture.bin")
+    ///     .model(Model::Xv11)
+    ///     .replay(capture, message_tx, command_rx);
+    ///
+    /// for message in message_rx.try_iter() {
+    ///     println!("{:?}", message);
+    /// }
+    /// ```
+    pub fn replay<R: LidarSource + 'static, S: MessageSink + Send + 'static, C: CommandSource>(self, source: R, tx: S, rx: C) {
+        self.run_source(move || Ok(source), tx, rx);
+    }
+
+    /// ## Summary
+    ///
+    /// Spawn the driver thread on another source than the serial port, such as a
+    /// TCP-to-serial bridge, a pseudo-terminal or a test double.
+    ///
+    /// ## Parameters
+    ///
+    /// open: Opens the source. Called on the reader thread, errors are reported as
+    /// `LidarDriverError::OpenSerialPort`.
+    ///
+    /// tx: Sends decoded LIDAR messages or error encountered.
+    ///
+    /// rx: Receives commands from the calling program.
+    ///
+    /// ## Remarks
+    ///
+    /// Like `replay`, the Neato model is told from the first valid packet unless set, the
+    /// device is not queried and no motor is driven. See `LidarSource` for read timeouts.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use std::sync::mpsc::channel;
+    /// use std::net::TcpStream;
+    /// use std::time::Duration;
+    /// use neato_xv11::LidarDriverBuilder;
+    ///
+    /// let (message_tx, message_rx) = channel();
+    /// let (command_tx, command_rx) = channel();
+    ///
+    /// let handle = LidarDriverBuilder::new("bridge.local:2000")
+    ///     .spawn_source(|| {
+    ///         let stream = TcpStream::connect("bridge.local:2000")?;
+    ///         stream.set_read_timeout(Some(Duration::from_millis(100)))?;
+    ///         Ok(stream)
+    ///     }, message_tx, command_rx)
+    ///     .unwrap();
+    /// ```
+    pub fn spawn_source<R, F, S, C>(self, open: F, tx: S, rx: C) -> Result<JoinHandle<()>, IoError>
+    where
+        R: LidarSource + 'static,
+        F: FnOnce() -> Result<R, IoError> + Send + 'static,
+        S: MessageSink + Send + 'static,
+        C: CommandSource + Send + 'static,
+    {
+        let mut builder = thread::Builder::new();
+
+        if let Some(name) = &self.thread.name {
+            builder = builder.name(name.clone());
+        }
+
+        builder.spawn(move || self.run_source(open, tx, rx))
+    }
+
+    /// Run the driver configured by the builder on a source, on the calling thread.
+    fn run_source<R, F, S, C>(self, open: F, tx: S, rx: C)
+    where
+        R: LidarSource + 'static,
+        F: FnOnce() -> Result<R, IoError> + Send + 'static,
+        S: MessageSink + Send + 'static,
+        C: CommandSource,
+    {
+        let tx = wrap_sink(tx, self.change_detector, self.rpm_monitor, self.scan_assembler);
+        let protocol = self.protocol.unwrap_or_else(|| Box::new(AutoModel::new()));
+        let parser = parser(protocol, self.quirks, self.correction, self.sync.strategy);
+
+        let options = RunOptions {
+            log: LogContext::new(&self.port_name, self.port.log_target.as_deref()),
+            thread: self.thread,
+            watchdog: self.watchdog,
+            error_context: self.error_context,
+            raw_tap: self.raw_tap,
+            log_tap: self.log_tap,
+            pcap_tap: self.pcap_tap,
+            stop: self.stop,
+            timing: self.port.timing(),
+            motor: None,
+            reconnect: None,
+            sync: self.sync,
+            report_resume: self.report_resume,
+            error_policy: self.error_policy,
+            clock: self.clock,
+        };
+
+        run_source(move || open().map_err(|err| LidarDriverError::OpenSerialPort(err.into())), parser, tx, rx, options);
+    }
+}
+
+/// Wrap the sink of the calling program in the sinks enabled on a builder.
+fn wrap_sink<S: MessageSink + Send + 'static>(tx: S, change_detector: Option<ChangeDetector>, rpm_monitor: Option<RpmMonitor>, scan_assembler: Option<ScanAssembler>) -> Box<dyn MessageSink + Send> {
+    let mut tx: Box<dyn MessageSink + Send> = Box::new(tx);
+
+    if let Some(detector) = change_detector {
+        tx = Box::new(ChangeDetectionSink::new(tx, detector));
+    }
+
+    if let Some(monitor) = rpm_monitor {
+        tx = Box::new(RpmMonitorSink::new(tx, monitor));
+    }
+
+    if let Some(assembler) = scan_assembler {
+        tx = Box::new(ScanSink::with_assembler(tx, assembler));
+    }
+
+    tx
+}
+
+/// The parser of a protocol, with the quirks, distance correction and sync strategy of a builder.
+fn parser(protocol: Box<dyn LidarProtocol + Send>, quirks: Option<Quirks>, correction: Option<DistanceCorrection>, strategy: SyncStrategy) -> Parser<Box<dyn LidarProtocol + Send>> {
+    let mut parser = Parser::with_protocol(protocol);
+    parser.set_quirks(quirks.unwrap_or_default());
+    parser.set_sync_strategy(strategy);
+
+    if let Some(correction) = correction {
+        parser.set_distance_correction(correction);
+    }
+
+    parser
+}
@@ -0,0 +1,430 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+use super::data::{Float, LidarScan, SCAN_SIZE};
+#[cfg(feature = "std")]
+use super::data::RotationDirection;
+#[cfg(feature = "std")]
+use super::mounting::MountingConfig;
+
+/// ## Summary
+///
+/// A linear distance correction: `corrected = scale * distance + offset`.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct DistanceCorrection {
+    // Multiplier applied to the distance.
+    pub scale: Float,
+    // Offset added after scaling, in millimeters.
+    pub offset: Float,
+}
+
+impl DistanceCorrection {
+    /// ## Summary
+    ///
+    /// The correction that leaves distances unchanged.
+    ///
+    pub const IDENTITY: DistanceCorrection = DistanceCorrection { scale: 1.0, offset: 0.0 };
+
+    /// ## Summary
+    ///
+    /// Apply the correction to a distance in millimeters.
+    ///
+    pub fn apply(&self, distance: i32) -> i32 {
+        let corrected = self.scale * distance as Float + self.offset;
+        // Round to the nearest millimeter.
+        (corrected + if corrected < 0.0 { -0.5 } else { 0.5 }) as i32
+    }
+}
+
+impl Default for DistanceCorrection {
+    fn default() -> Self {
+        DistanceCorrection::IDENTITY
+    }
+}
+
+/// ## Summary
+///
+/// The corrections of a table, one per degree.
+///
+#[cfg(not(feature = "alloc"))]
+pub type Corrections = heapless::Vec<DistanceCorrection, SCAN_SIZE>;
+
+/// ## Summary
+///
+/// The corrections of a table, one per degree.
+///
+#[cfg(feature = "alloc")]
+pub type Corrections = Vec<DistanceCorrection>;
+
+/// ## Summary
+///
+/// A per-angle distance correction table, applied by `ScanAssembler` to correct the
+/// systematic bias of a unit (common on XV-11 units with a rewound motor or a
+/// replaced lens).
+///
+/// ## Remarks
+///
+/// Invalid readings are left untouched. With the `json` feature the table can be
+/// saved to and loaded from a JSON file.
+///
+/// ## Example
+///
+/// ```no_run
+/// use neato_xv11::calibration::CalibrationTable;
+/// use neato_xv11::scan::ScanAssembler;
+///
+/// let mut assembler = ScanAssembler::new();
+/// # #[cfg(feature = "json")]
+/// assembler.set_calibration(CalibrationTable::load("calibration.json").unwrap());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct CalibrationTable {
+    // One correction per degree, 360 entries.
+    pub corrections: Corrections,
+}
+
+impl CalibrationTable {
+    /// ## Summary
+    ///
+    /// Initialize a table that leaves distances unchanged.
+    ///
+    pub fn identity() -> Self {
+        CalibrationTable {
+            corrections: (0..SCAN_SIZE).map(|_| DistanceCorrection::IDENTITY).collect(),
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// The correction of the given degree.
+    ///
+    /// ## Remarks
+    ///
+    /// Tables with fewer than 360 entries (e.g. a truncated file) leave the missing
+    /// angles uncorrected.
+    ///
+    pub fn get(&self, index: usize) -> DistanceCorrection {
+        self.corrections.get(index % SCAN_SIZE).copied().unwrap_or_default()
+    }
+
+    /// ## Summary
+    ///
+    /// Set the correction of the given degree.
+    ///
+    pub fn set(&mut self, index: usize, correction: DistanceCorrection) {
+        if let Some(entry) = self.corrections.get_mut(index % SCAN_SIZE) {
+            *entry = correction;
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Correct the valid readings of a scan in place.
+    ///
+    pub fn apply(&self, scan: &mut LidarScan) {
+        for (index, reading) in scan.readings.iter_mut().enumerate() {
+            if let Some(reading) = reading.as_mut().filter(|reading| reading.is_valid()) {
+                reading.distance = self.get(index).apply(reading.distance);
+            }
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Load a table from a JSON file.
+    ///
+    #[cfg(feature = "json")]
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Self, std::io::Error> {
+        let file = std::fs::File::open(path)?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+
+    /// ## Summary
+    ///
+    /// Save the table to a JSON file.
+    ///
+    #[cfg(feature = "json")]
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), std::io::Error> {
+        let file = std::fs::File::create(path)?;
+        Ok(serde_json::to_writer_pretty(std::io::BufWriter::new(file), self)?)
+    }
+}
+
+impl Default for CalibrationTable {
+    fn default() -> Self {
+        CalibrationTable::identity()
+    }
+}
+
+/// ## Summary
+///
+/// The corrections of an installed unit, applied by `ScanAssembler` so scans come out
+/// corrected and in the robot frame.
+///
+/// ## Remarks
+///
+/// Applied after the calibration table, in this order: the distance bias is added to
+/// every valid distance, the indices of a `RotationDirection::Clockwise` sensor are
+/// mirrored into the native indexing of the crate, then the scan is expressed in the robot frame with
+/// `MountingConfig::to_base_scan`. The positions given by `LidarReading::to_point`
+/// are then robot-frame positions.
+///
+/// ## Example
+///
+/// ```no_run
+/// use neato_xv11::calibration::Calibration;
+/// use neato_xv11::data::{Angle, RotationDirection};
+/// use neato_xv11::mounting::MountingConfig;
+/// use neato_xv11::scan::ScanAssembler;
+///
+/// // Mounted upside down, 80 mm behind the wheel axis, reading 15 mm long.
+/// let calibration = Calibration {
+///     direction: RotationDirection::Clockwise,
+///     distance_bias: -15,
+///     mounting: MountingConfig { angle_offset: Angle::from_degrees(180.0), x: -80.0, y: 0.0 },
+/// };
+///
+/// let mut assembler = ScanAssembler::new();
+/// assembler.set_robot_calibration(calibration);
+/// ```
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Calibration {
+    // The direction the reading indices increase in, relative to the native indexing.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub direction: RotationDirection,
+    // Added to every valid distance, in millimeters.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub distance_bias: i32,
+    // Angular offset of the sensor and its position on the robot.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub mounting: MountingConfig,
+}
+
+#[cfg(feature = "std")]
+impl Calibration {
+    /// ## Summary
+    ///
+    /// Correct a scan and express it in the robot frame.
+    ///
+    pub fn apply(&self, scan: &mut LidarScan) {
+        if self.distance_bias != 0 {
+            for reading in scan.readings.iter_mut().flatten().filter(|reading| reading.is_valid()) {
+                reading.distance = (reading.distance + self.distance_bias).max(0);
+            }
+        }
+
+        if self.direction == RotationDirection::Clockwise {
+            // Index i moves to (360 - i) % 360.
+            scan.readings.reverse();
+            scan.readings.rotate_right(1);
+
+            for (index, reading) in scan.readings.iter_mut().enumerate() {
+                if let Some(reading) = reading {
+                    reading.index = index;
+                }
+            }
+        }
+
+        if self.mounting != MountingConfig::default() {
+            *scan = self.mounting.to_base_scan(scan);
+        }
+    }
+}
+
+/// ## Summary
+///
+/// Result of `fit_enclosure`.
+///
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct EnclosureFit {
+    // Distance correction to apply to the readings.
+    pub correction: DistanceCorrection,
+    // Sensor angle (degrees) of the enclosure's width axis.
+    pub angle_offset: Float,
+    // Position of the sensor along the width axis, from the corner, in millimeters.
+    pub x: Float,
+    // Position of the sensor along the depth axis, from the corner, in millimeters.
+    pub y: Float,
+    // Root mean square of the residuals, in millimeters.
+    pub rms_residual: Float,
+    // Largest absolute residual, in millimeters.
+    pub max_residual: Float,
+    // Number of readings the fit used.
+    pub readings: usize,
+}
+
+/// Smallest number of readings `fit_enclosure` accepts.
+#[cfg(feature = "std")]
+const MIN_ENCLOSURE_READINGS: usize = 90;
+
+/// ## Summary
+///
+/// Estimate the distance scale and offset, and the angular misalignment, of a sensor
+/// standing inside a rectangular enclosure of known size.
+///
+/// ## Parameters
+///
+/// scans: Scans taken without moving the sensor.
+///
+/// width: Inner size of the enclosure along its first axis, in millimeters.
+///
+/// depth: Inner size of the enclosure along its second axis, in millimeters.
+///
+/// ## Remarks
+///
+/// The sensor position is estimated along with the calibration, so it can stand
+/// anywhere in the enclosure. The enclosure must be empty and its walls must be
+/// in range. A rectangle looks the same rotated by 180 degrees, so `angle_offset`
+/// may be off by 180 (or 90 for a square enclosure). Returns `None` if fewer than
+/// 90 valid readings are available.
+///
+/// ## Example
+///
+/// ```no_run
+/// # fn calibrate(scans: &[neato_xv11::data::LidarScan]) {
+/// use neato_xv11::calibration::fit_enclosure;
+///
+/// let fit = fit_enclosure(scans, 2400.0, 1800.0).unwrap();
+/// println!("scale {:.3}, offset {:.1} mm, rms {:.1} mm",
+///          fit.correction.scale, fit.correction.offset, fit.rms_residual);
+/// # }
+/// ```
+#[cfg(feature = "std")]
+pub fn fit_enclosure(scans: &[LidarScan], width: Float, depth: Float) -> Option<EnclosureFit> {
+    // (sensor angle in radians, measured distance) of every valid reading.
+    let readings: Vec<(Float, Float)> = scans.iter()
+        .flat_map(|scan| scan.valid_readings())
+        .filter(|reading| reading.distance > 0)
+        .map(|reading| ((reading.index as Float).to_radians(), reading.distance as Float))
+        .collect();
+
+    if readings.len() < MIN_ENCLOSURE_READINGS {
+        return None;
+    }
+
+    let enclosure = Enclosure { readings: &readings, width, depth };
+
+    // Coarse search of the rotation from the center, then refine every parameter.
+    let mut best = (0..72)
+        .map(|step| [(step as Float * 5.0).to_radians(), width / 2.0, depth / 2.0])
+        .filter_map(|params| enclosure.cost(params).map(|cost| (params, cost)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    let mut steps = [(5.0 as Float).to_radians(), width / 8.0, depth / 8.0];
+
+    while steps[1] > 0.1 {
+        let mut improved = false;
+
+        for i in 0..3 {
+            for sign in [-1.0, 1.0] {
+                let mut params = best.0;
+                params[i] += sign * steps[i];
+
+                if let Some(cost) = enclosure.cost(params) {
+                    if cost < best.1 {
+                        best = (params, cost);
+                        improved = true;
+                    }
+                }
+            }
+        }
+
+        if !improved {
+            steps.iter_mut().for_each(|step| *step /= 2.0);
+        }
+    }
+
+    let [rotation, x, y] = best.0;
+    let (correction, _) = enclosure.solve(best.0)?;
+
+    let residuals: Vec<Float> = readings.iter()
+        .map(|&(angle, distance)| {
+            let expected = enclosure.range(angle + rotation, x, y);
+            correction.scale * distance + correction.offset - expected
+        })
+        .collect();
+
+    let rms_residual = (residuals.iter().map(|r| r * r).sum::<Float>() / residuals.len() as Float).sqrt();
+    let max_residual = residuals.iter().fold(0.0, |max: Float, r| max.max(r.abs()));
+
+    Some(EnclosureFit {
+        correction,
+        angle_offset: (-rotation.to_degrees()).rem_euclid(360.0),
+        x,
+        y,
+        rms_residual,
+        max_residual,
+        readings: readings.len(),
+    })
+}
+
+/// Readings taken inside a rectangular enclosure.
+#[cfg(feature = "std")]
+struct Enclosure<'a> {
+    readings: &'a [(Float, Float)],
+    width: Float,
+    depth: Float,
+}
+
+#[cfg(feature = "std")]
+impl Enclosure<'_> {
+    /// Distance from (x, y) to the wall in the given direction.
+    fn range(&self, angle: Float, x: Float, y: Float) -> Float {
+        let (sin, cos) = angle.sin_cos();
+        let along_x = if cos > 0.0 { (self.width - x) / cos } else if cos < 0.0 { -x / cos } else { Float::MAX };
+        let along_y = if sin > 0.0 { (self.depth - y) / sin } else if sin < 0.0 { -y / sin } else { Float::MAX };
+        along_x.min(along_y)
+    }
+
+    /// Best distance correction for a rotation and position, with its sum of squared residuals.
+    fn solve(&self, [rotation, x, y]: [Float; 3]) -> Option<(DistanceCorrection, Float)> {
+        if x <= 0.0 || x >= self.width || y <= 0.0 || y >= self.depth {
+            return None;
+        }
+
+        let count = self.readings.len() as Float;
+        let (mut sum_d, mut sum_r, mut sum_dd, mut sum_dr) = (0.0, 0.0, 0.0, 0.0);
+
+        for &(angle, distance) in self.readings {
+            let expected = self.range(angle + rotation, x, y);
+            sum_d += distance;
+            sum_r += expected;
+            sum_dd += distance * distance;
+            sum_dr += distance * expected;
+        }
+
+        // Linear regression of the expected ranges on the measured distances.
+        let denominator = count * sum_dd - sum_d * sum_d;
+
+        if denominator.abs() < Float::EPSILON {
+            return None;
+        }
+
+        let scale = (count * sum_dr - sum_d * sum_r) / denominator;
+        let offset = (sum_r - scale * sum_d) / count;
+
+        let cost = self.readings.iter()
+            .map(|&(angle, distance)| {
+                let residual = scale * distance + offset - self.range(angle + rotation, x, y);
+                residual * residual
+            })
+            .sum();
+
+        Some((DistanceCorrection { scale, offset }, cost))
+    }
+
+    /// Sum of squared residuals of the best distance correction.
+    fn cost(&self, params: [Float; 3]) -> Option<Float> {
+        self.solve(params).map(|(_, cost)| cost)
+    }
+}
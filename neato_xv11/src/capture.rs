@@ -0,0 +1,406 @@
+use std::io::{BufReader, Error as IoError, ErrorKind, Read, Seek, SeekFrom, Write};
+
+use zstd::stream::read::Decoder;
+
+use super::parser::{is_valid_index, PACKET_HEADER, PACKET_SIZE};
+
+/// Number of revolutions per compressed frame by default, about 10 seconds at 300 RPM.
+pub const DEFAULT_FRAME_REVOLUTIONS: u64 = 50;
+
+/// Magic number of the skippable frame holding the index.
+const INDEX_FRAME_MAGIC: u32 = 0x184D_2A5E;
+/// Marks the end of an indexed capture.
+const FOOTER_MAGIC: [u8; 4] = *b"XVIX";
+/// Size of the footer: number of frames, number of revolutions and magic.
+const FOOTER_SIZE: u64 = 16;
+/// Size of an index entry: offset and first revolution of a frame.
+const ENTRY_SIZE: u64 = 16;
+
+/// Finds the start of revolutions in a raw XV-11 stream.
+#[derive(Default)]
+struct RevolutionScanner {
+    // Position in the current packet, `None` while looking for a packet.
+    position: Option<usize>,
+}
+
+impl RevolutionScanner {
+    /// Consume a byte, true if it is the index byte of the first packet of a revolution.
+    fn push(&mut self, byte: u8) -> bool {
+        match self.position {
+            None | Some(0) if byte == PACKET_HEADER => {
+                self.position = Some(1);
+                false
+            },
+            None | Some(0) => {
+                self.position = None;
+                false
+            },
+            Some(1) if is_valid_index(byte) => {
+                self.position = Some(2);
+                byte == 0xA0
+            },
+            Some(1) => {
+                self.position = None;
+                false
+            },
+            Some(position) => {
+                self.position = Some((position + 1) % PACKET_SIZE);
+                false
+            },
+        }
+    }
+}
+
+/// ## Summary
+///
+/// Writes a raw XV-11 stream as a zstd compressed capture that can be replayed from
+/// any revolution.
+///
+/// ## Remarks
+///
+/// The stream is split into independent zstd frames of `frame_revolutions`
+/// revolutions, each starting with the first packet of a revolution, followed by an
+/// index of the frames in a skippable frame. The file remains a regular `.zst` file:
+/// `zstd -d` restores the raw capture.
+///
+/// A frame is only written once complete, so the last few seconds are lost if the
+/// process is killed. The capture is finished when the writer is dropped, call
+/// `finish` to handle errors.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::sync::mpsc::channel;
+/// use std::fs::File;
+/// use neato_xv11::capture::CaptureWriter;
+/// use neato_xv11::LidarDriverBuilder;
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// let capture = CaptureWriter::new(File::create("capture.bin.zst").unwrap());
+///
+/// let handle = LidarDriverBuilder::new("/dev/ttyUSB0")
+///     .raw_tap(capture)
+///     .spawn(message_tx, command_rx)
+///     .unwrap();
+/// ```
+pub struct CaptureWriter<W: Write> {
+    // Receives the capture, `None` once finished.
+    writer: Option<W>,
+    // zstd compression level.
+    level: i32,
+    // Number of revolutions per frame.
+    frame_revolutions: u64,
+    // Raw bytes of the frame being filled.
+    segment: Vec<u8>,
+    // Number of revolutions started in the segment.
+    segment_revolutions: u64,
+    scanner: RevolutionScanner,
+    // Number of revolutions started.
+    revolutions: u64,
+    // Number of compressed bytes written.
+    offset: u64,
+    // Offset and first revolution of every frame.
+    index: Vec<(u64, u64)>,
+}
+
+impl<W: Write> CaptureWriter<W> {
+    /// ## Summary
+    ///
+    /// Start a capture with the default compression level and frame length.
+    ///
+    pub fn new(writer: W) -> Self {
+        CaptureWriter::with_options(writer, zstd::DEFAULT_COMPRESSION_LEVEL, DEFAULT_FRAME_REVOLUTIONS)
+    }
+
+    /// ## Summary
+    ///
+    /// Start a capture.
+    ///
+    /// ## Parameters
+    ///
+    /// writer: Receives the capture.
+    ///
+    /// level: zstd compression level, 1 to 22.
+    ///
+    /// frame_revolutions: Number of revolutions per frame. Shorter frames seek faster
+    /// but compress less.
+    ///
+    pub fn with_options(writer: W, level: i32, frame_revolutions: u64) -> Self {
+        CaptureWriter {
+            writer: Some(writer),
+            level,
+            frame_revolutions: frame_revolutions.max(1),
+            segment: Vec::new(),
+            segment_revolutions: 0,
+            scanner: RevolutionScanner::default(),
+            revolutions: 0,
+            offset: 0,
+            index: vec![(0, 0)],
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Write the last frame and the index, and take the writer back.
+    ///
+    pub fn finish(mut self) -> Result<W, IoError> {
+        self.write_index()?;
+        // Finished, nothing left for `drop`.
+        Ok(self.writer.take().expect("the writer is only taken when finishing"))
+    }
+
+    /// Compress raw bytes as a frame.
+    fn write_frame(&mut self, end: usize) -> Result<(), IoError> {
+        let frame = zstd::bulk::compress(&self.segment[..end], self.level)?;
+
+        if let Some(writer) = self.writer.as_mut() {
+            writer.write_all(&frame)?;
+        }
+
+        self.segment.drain(..end);
+        self.offset += frame.len() as u64;
+        Ok(())
+    }
+
+    /// Write the last frame and the index.
+    fn write_index(&mut self) -> Result<(), IoError> {
+        self.write_frame(self.segment.len())?;
+
+        let mut index = Vec::with_capacity(8 + self.index.len() * ENTRY_SIZE as usize + FOOTER_SIZE as usize);
+        index.extend_from_slice(&INDEX_FRAME_MAGIC.to_le_bytes());
+        index.extend_from_slice(&((self.index.len() as u64 * ENTRY_SIZE + FOOTER_SIZE) as u32).to_le_bytes());
+
+        for (offset, revolution) in self.index.iter() {
+            index.extend_from_slice(&offset.to_le_bytes());
+            index.extend_from_slice(&revolution.to_le_bytes());
+        }
+
+        index.extend_from_slice(&(self.index.len() as u32).to_le_bytes());
+        index.extend_from_slice(&self.revolutions.to_le_bytes());
+        index.extend_from_slice(&FOOTER_MAGIC);
+
+        match self.writer.as_mut() {
+            Some(writer) => {
+                writer.write_all(&index)?;
+                writer.flush()
+            },
+            None => Ok(()),
+        }
+    }
+}
+
+impl<W: Write> Write for CaptureWriter<W> {
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, IoError> {
+        for &byte in buffer {
+            self.segment.push(byte);
+
+            if self.scanner.push(byte) {
+                // The frame starts at the packet header, one byte before the index.
+                let start = self.segment.len() - 2;
+
+                if self.segment_revolutions >= self.frame_revolutions {
+                    self.write_frame(start)?;
+                    self.index.push((self.offset, self.revolutions));
+                    self.segment_revolutions = 0;
+                }
+
+                self.revolutions += 1;
+                self.segment_revolutions += 1;
+            }
+        }
+
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> Result<(), IoError> {
+        match self.writer.as_mut() {
+            Some(writer) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<W: Write> Drop for CaptureWriter<W> {
+    fn drop(&mut self) {
+        if self.writer.is_some() {
+            let _ = self.write_index();
+        }
+    }
+}
+
+/// ## Summary
+///
+/// Reads a capture written by `CaptureWriter`, decompressing it on the fly.
+///
+/// ## Remarks
+///
+/// The reader yields the raw stream, so it can be replayed like an uncompressed
+/// capture, e.g. through `clock::PacedSource` and `run_from_source`.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::thread;
+/// # use std::sync::mpsc::channel;
+/// use std::fs::File;
+/// use neato_xv11::capture::CaptureReader;
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// let mut capture = CaptureReader::open(File::open("capture.bin.zst").unwrap()).unwrap();
+/// // Skip the first minute at 300 RPM.
+/// capture.seek_revolution(300).unwrap();
+///
+/// thread::spawn(move || {
+///     neato_xv11::run_from_source(capture, message_tx, command_rx);
+/// });
+/// ```
+pub struct CaptureReader<R: Read + Seek> {
+    // Decompresses the capture, `None` only while seeking.
+    decoder: Option<Decoder<'static, BufReader<R>>>,
+    // Offset and first revolution of every frame.
+    index: Vec<(u64, u64)>,
+    // Number of revolutions in the capture.
+    revolutions: u64,
+    // Decompressed bytes read while seeking, returned first.
+    pending: Vec<u8>,
+}
+
+impl<R: Read + Seek> CaptureReader<R> {
+    /// ## Summary
+    ///
+    /// Open a capture, reading its index.
+    ///
+    pub fn open(mut reader: R) -> Result<Self, IoError> {
+        let end = reader.seek(SeekFrom::End(0))?;
+
+        if end < FOOTER_SIZE {
+            return Err(IoError::new(ErrorKind::InvalidData, "not an indexed capture"));
+        }
+
+        let mut footer = [0; FOOTER_SIZE as usize];
+        reader.seek(SeekFrom::Start(end - FOOTER_SIZE))?;
+        reader.read_exact(&mut footer)?;
+
+        if footer[12..] != FOOTER_MAGIC {
+            return Err(IoError::new(ErrorKind::InvalidData, "not an indexed capture"));
+        }
+
+        let frames = u64::from(u32::from_le_bytes([footer[0], footer[1], footer[2], footer[3]]));
+        let mut revolutions = [0; 8];
+        revolutions.copy_from_slice(&footer[4..12]);
+        let size = frames * ENTRY_SIZE;
+
+        if end < FOOTER_SIZE + size {
+            return Err(IoError::new(ErrorKind::InvalidData, "truncated capture index"));
+        }
+
+        let mut entries = vec![0; size as usize];
+        reader.seek(SeekFrom::Start(end - FOOTER_SIZE - size))?;
+        reader.read_exact(&mut entries)?;
+
+        let index = entries
+            .chunks_exact(ENTRY_SIZE as usize)
+            .map(|entry| {
+                let mut offset = [0; 8];
+                let mut revolution = [0; 8];
+                offset.copy_from_slice(&entry[..8]);
+                revolution.copy_from_slice(&entry[8..]);
+                (u64::from_le_bytes(offset), u64::from_le_bytes(revolution))
+            })
+            .collect();
+
+        reader.seek(SeekFrom::Start(0))?;
+
+        Ok(CaptureReader {
+            decoder: Some(Decoder::new(reader)?),
+            index,
+            revolutions: u64::from_le_bytes(revolutions),
+            pending: Vec::new(),
+        })
+    }
+
+    /// ## Summary
+    ///
+    /// Number of revolutions in the capture.
+    ///
+    pub fn revolutions(&self) -> u64 {
+        self.revolutions
+    }
+
+    /// ## Summary
+    ///
+    /// Continue reading from the first packet of a revolution, counted from 0.
+    ///
+    /// ## Remarks
+    ///
+    /// Only the frame holding the revolution is decompressed.
+    ///
+    pub fn seek_revolution(&mut self, revolution: u64) -> Result<(), IoError> {
+        if revolution >= self.revolutions {
+            return Err(IoError::new(ErrorKind::InvalidInput, "revolution out of range"));
+        }
+
+        let &(offset, first) = self
+            .index
+            .iter()
+            .rev()
+            .find(|(_, first)| *first <= revolution)
+            .ok_or_else(|| IoError::new(ErrorKind::InvalidData, "corrupted capture index"))?;
+
+        let mut reader = self.decoder.take().expect("the decoder is only taken while seeking").finish().into_inner();
+        reader.seek(SeekFrom::Start(offset))?;
+        let mut decoder = Decoder::new(reader)?;
+
+        // Skip to the header of the revolution's first packet.
+        let mut scanner = RevolutionScanner::default();
+        let mut skipped = 0;
+        let mut buffer = [0; 4096];
+
+        self.pending.clear();
+
+        loop {
+            let count = decoder.read(&mut buffer)?;
+
+            if count == 0 {
+                self.decoder = Some(decoder);
+                return Err(IoError::new(ErrorKind::UnexpectedEof, "revolution not found"));
+            }
+
+            if let Some(position) = buffer[..count].iter().position(|&byte| scanner.push(byte) && {
+                skipped += 1;
+                skipped > revolution - first
+            }) {
+                // The packet header may have been in the previous read.
+                if position == 0 {
+                    self.pending.push(PACKET_HEADER);
+                    self.pending.extend_from_slice(&buffer[..count]);
+                } else {
+                    self.pending.extend_from_slice(&buffer[position - 1..count]);
+                }
+
+                self.decoder = Some(decoder);
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl<R: Read + Seek> Read for CaptureReader<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, IoError> {
+        if !self.pending.is_empty() {
+            let count = buffer.len().min(self.pending.len());
+            buffer[..count].copy_from_slice(&self.pending[..count]);
+            self.pending.drain(..count);
+            return Ok(count);
+        }
+
+        match self.decoder.as_mut() {
+            Some(decoder) => decoder.read(buffer),
+            None => Ok(0),
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use std::io::{Error as IoError, Read};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// ## Summary
+///
+/// A source of time for the timing-dependent parts of the crate.
+///
+pub trait Clock: Send + Sync {
+    /// ## Summary
+    ///
+    /// The current time.
+    ///
+    fn now(&self) -> Instant;
+
+    /// ## Summary
+    ///
+    /// Block for the given duration.
+    ///
+    fn sleep(&self, duration: Duration);
+}
+
+/// ## Summary
+///
+/// The real time of the system.
+///
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// ## Summary
+///
+/// A deterministic virtual time, only advanced by `sleep` and `advance`.
+///
+/// ## Remarks
+///
+/// Sleeping returns immediately after advancing the time, so simulations of
+/// timing-dependent behavior run instantly and reproducibly. Clones share the same time.
+/// Pass a clone to `LidarDriverBuilder::clock` to run the watchdog, the sync timeout and
+/// the reconnection backoff of the driver on it.
+///
+/// ## Example
+///
+/// ```
+/// use std::time::Duration;
+/// use neato_xv11::clock::{Clock, VirtualClock};
+///
+/// let clock = VirtualClock::new();
+/// let start = clock.now();
+///
+/// clock.sleep(Duration::from_secs(60));
+///
+/// assert_eq!(clock.now() - start, Duration::from_secs(60));
+/// ```
+#[derive(Clone, Debug)]
+pub struct VirtualClock {
+    // Real time the virtual time started at.
+    base: Instant,
+    // Nanoseconds elapsed in virtual time.
+    elapsed: Arc<AtomicU64>,
+}
+
+impl VirtualClock {
+    /// ## Summary
+    ///
+    /// Initialize a virtual clock at the current time.
+    ///
+    pub fn new() -> Self {
+        VirtualClock {
+            base: Instant::now(),
+            elapsed: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Move the time forward.
+    ///
+    pub fn advance(&self, duration: Duration) {
+        self.elapsed.fetch_add(duration.as_nanos() as u64, Ordering::AcqRel);
+    }
+
+    /// ## Summary
+    ///
+    /// Virtual time elapsed since the clock was created.
+    ///
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.elapsed.load(Ordering::Acquire))
+    }
+}
+
+impl Default for VirtualClock {
+    fn default() -> Self {
+        VirtualClock::new()
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Instant {
+        self.base + self.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+/// ## Summary
+///
+/// A source replaying recorded bytes at the pace of a serial link.
+///
+/// ## Remarks
+///
+/// Each read sleeps on the clock for the time its bytes take on the wire (10 bits per
+/// byte for 8N1). With a `VirtualClock`, the replay runs as fast as possible while the
+/// virtual time advances as if the LIDAR were attached.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::thread;
+/// # use std::sync::mpsc::channel;
+/// use neato_xv11::clock::{PacedSource, VirtualClock};
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// let clock = VirtualClock::new();
+/// let capture = std::fs::File::open("capture.bin").unwrap();
+/// let source = PacedSource::new(capture, 115200, clock.clone());
+///
+/// thread::spawn(move || {
+///     neato_xv11::run_from_source(source, message_tx, command_rx);
+/// });
+/// ```
+pub struct PacedSource<R> {
+    inner: R,
+    // Time a byte takes on the wire.
+    byte_time: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl<R: Read> PacedSource<R> {
+    /// ## Summary
+    ///
+    /// Wrap a source.
+    ///
+    /// ## Parameters
+    ///
+    /// inner: The recorded bytes.
+    ///
+    /// baud_rate: Baud rate of the simulated link.
+    ///
+    /// clock: Clock slept on.
+    ///
+    pub fn new<C: Clock + 'static>(inner: R, baud_rate: u32, clock: C) -> Self {
+        PacedSource {
+            inner,
+            byte_time: Duration::from_secs(10) / baud_rate.max(1),
+            clock: Arc::new(clock),
+        }
+    }
+}
+
+impl<R: Read> Read for PacedSource<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, IoError> {
+        let count = self.inner.read(buffer)?;
+        self.clock.sleep(self.byte_time * count as u32);
+        Ok(count)
+    }
+}
+
+/// Number of readings of the external clock per `ClockMapper::sync`, the tightest one is kept.
+const SYNC_ATTEMPTS: usize = 5;
+
+/// ## Summary
+///
+/// Maps the monotonic `Instant`s of the driver onto an external clock, such as a
+/// PTP-disciplined clock or the clock of a camera or IMU driver, so scans can be
+/// aligned with data captured by other processes.
+///
+/// ## Remarks
+///
+/// Each `sync` reads the external clock around an `Instant` and anchors the mapping
+/// there. From the second `sync` on, the drift between the two clocks is estimated
+/// from the last two anchors. Call `sync` periodically (e.g. every few seconds) to
+/// follow the external clock.
+///
+/// ## Example
+///
+/// ```
+/// use std::time::{Instant, SystemTime, UNIX_EPOCH};
+/// use neato_xv11::clock::ClockMapper;
+///
+/// // Nanoseconds since the Unix epoch.
+/// let mut mapper = ClockMapper::new(|| SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64);
+/// mapper.sync();
+///
+/// let stamp = mapper.to_external(Instant::now());
+/// ```
+pub struct ClockMapper<F> {
+    // Reads the external clock, in nanoseconds.
+    external: F,
+    // The last anchor: an instant and the external time at that instant.
+    anchor: Option<(Instant, u64)>,
+    // External nanoseconds per monotonic nanosecond.
+    rate: f64,
+}
+
+impl<F: Fn() -> u64> ClockMapper<F> {
+    /// ## Summary
+    ///
+    /// Initialize a mapper onto the external clock read by `external`, in nanoseconds.
+    ///
+    pub fn new(external: F) -> Self {
+        ClockMapper {
+            external,
+            anchor: None,
+            rate: 1.0,
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Anchor the mapping at the current time. Returns the uncertainty of the anchor,
+    /// half the time the tightest reading of the external clock took.
+    ///
+    pub fn sync(&mut self) -> Duration {
+        let mut best: Option<(Instant, u64, u64)> = None;
+
+        for _ in 0..SYNC_ATTEMPTS {
+            let before = (self.external)();
+            let instant = Instant::now();
+            let after = (self.external)();
+            let width = after.saturating_sub(before);
+
+            if best.is_none_or(|(_, _, best_width)| width < best_width) {
+                best = Some((instant, before + width / 2, width));
+            }
+        }
+
+        // SYNC_ATTEMPTS is not zero.
+        let (instant, external, width) = best.unwrap_or((Instant::now(), (self.external)(), 0));
+
+        if let Some((last_instant, last_external)) = self.anchor {
+            let elapsed = instant.duration_since(last_instant).as_nanos() as f64;
+
+            if elapsed > 0.0 && external > last_external {
+                self.rate = (external - last_external) as f64 / elapsed;
+            }
+        }
+
+        self.anchor = Some((instant, external));
+        Duration::from_nanos(width / 2)
+    }
+
+    /// ## Summary
+    ///
+    /// The external time at `instant`, in nanoseconds. Syncs first if `sync` was never called.
+    ///
+    pub fn to_external(&mut self, instant: Instant) -> u64 {
+        if self.anchor.is_none() {
+            self.sync();
+        }
+
+        let (anchor_instant, anchor_external) = self.anchor.unwrap_or((instant, 0));
+
+        let offset = match instant.checked_duration_since(anchor_instant) {
+            Some(after) => after.as_nanos() as f64 * self.rate,
+            None => -(anchor_instant.duration_since(instant).as_nanos() as f64 * self.rate),
+        };
+
+        (anchor_external as f64 + offset).max(0.0) as u64
+    }
+
+    /// ## Summary
+    ///
+    /// External nanoseconds per monotonic nanosecond, 1 until the second `sync`.
+    ///
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+}
@@ -0,0 +1,338 @@
+#[cfg(any(feature = "json", feature = "toml", feature = "yaml"))]
+use std::io::{Error as IoError, ErrorKind};
+#[cfg(any(feature = "json", feature = "toml", feature = "yaml"))]
+use std::path::Path;
+
+use serde::{Serialize, Deserialize};
+
+use super::background::{BackgroundModel, ChangeDetector};
+use super::builder::ThreadConfig;
+use super::calibration::{Calibration, DistanceCorrection};
+use super::data::Float;
+use super::error_policy::ErrorPolicy;
+use super::filter::{AngleMask, FilterChain, KalmanFilter, MedianFilter, OutlierFilter, QualityFilter, RangeFilter, ScanFilter};
+use super::info::DetectedModel;
+use super::quirks::{Firmware, Quirks};
+
+/// ## Summary
+///
+/// Change detection settings of a `DriverConfig`. See `background::ChangeDetector`.
+///
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ChangeDetectionConfig {
+    // Number of revolutions used to learn the background.
+    pub learning_revolutions: usize,
+    // Deviation in millimeters above which a reading is foreground.
+    pub threshold: i32,
+    // Number of equal sectors the scan is split in.
+    pub sectors: usize,
+    // Number of consecutive changed revolutions before a change is reported.
+    pub debounce: u32,
+    // Number of foreground readings for a sector to count as changed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_readings: Option<usize>,
+}
+
+impl ChangeDetectionConfig {
+    /// ## Summary
+    ///
+    /// Build the change detector.
+    ///
+    pub fn detector(&self) -> ChangeDetector {
+        let model = BackgroundModel::new(self.learning_revolutions, self.threshold);
+        let mut detector = ChangeDetector::new(model, self.sectors, self.debounce);
+
+        if let Some(min_readings) = self.min_readings {
+            detector.set_min_readings(min_readings);
+        }
+
+        detector
+    }
+}
+
+/// ## Summary
+///
+/// A filter stage of a `DriverConfig`, see the `filter` module.
+///
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum FilterConfig {
+    // `filter::QualityFilter`.
+    Quality {
+        min_quality: i32,
+        #[serde(default)]
+        keep_weak_signal: bool,
+    },
+    // `filter::RangeFilter`.
+    Range {
+        min_distance: i32,
+        max_distance: i32,
+    },
+    // `filter::MedianFilter`.
+    Median {
+        revolutions: usize,
+    },
+    // `filter::OutlierFilter`.
+    Outlier {
+        max_deviation: i32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        neighbours: Option<usize>,
+    },
+    // `filter::KalmanFilter`.
+    Kalman {
+        process_noise: Float,
+        measurement_noise: Float,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reset_threshold: Option<Float>,
+    },
+}
+
+impl FilterConfig {
+    /// ## Summary
+    ///
+    /// Build the filter.
+    ///
+    pub fn filter(&self) -> Box<dyn ScanFilter + Send> {
+        match *self {
+            FilterConfig::Quality { min_quality, keep_weak_signal } => {
+                let mut filter = QualityFilter::new(min_quality);
+                filter.set_keep_weak_signal(keep_weak_signal);
+                Box::new(filter)
+            },
+            FilterConfig::Range { min_distance, max_distance } => Box::new(RangeFilter::new(min_distance, max_distance)),
+            FilterConfig::Median { revolutions } => Box::new(MedianFilter::new(revolutions)),
+            FilterConfig::Outlier { max_deviation, neighbours } => {
+                let mut filter = OutlierFilter::new(max_deviation);
+
+                if let Some(neighbours) = neighbours {
+                    filter.set_neighbours(neighbours);
+                }
+
+                Box::new(filter)
+            },
+            FilterConfig::Kalman { process_noise, measurement_noise, reset_threshold } => {
+                let mut filter = KalmanFilter::new(process_noise, measurement_noise);
+
+                if let Some(reset_threshold) = reset_threshold {
+                    filter.set_reset_threshold(reset_threshold);
+                }
+
+                Box::new(filter)
+            },
+        }
+    }
+}
+
+/// ## Summary
+///
+/// A sector of a `DriverConfig` whose readings are removed, see `filter::AngleMask`.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MaskSector {
+    // First masked degree.
+    pub start: usize,
+    // Last masked degree, wrapping around 360 if smaller than `start`.
+    pub end: usize,
+}
+
+/// ## Summary
+///
+/// A complete driver setup, loaded from a configuration file so deployments can be
+/// tuned without recompiling. Every setting but the port is optional.
+///
+/// ## Remarks
+///
+/// Masks and filters are applied to the assembled scans, masks first then the filters
+/// in order. The message sinks are not part of the configuration: they are given to
+/// `LidarDriverBuilder::spawn`.
+///
+/// Unknown keys are rejected rather than ignored, so a misspelled or misplaced setting
+/// (e.g. `masks` written under `[thread]` in TOML) fails to load.
+///
+/// ## Example
+///
+/// A TOML configuration (`toml` feature):
+///
+/// ```toml
+/// port = "/dev/ttyUSB0"
+/// model = { Neato = "Xv11" }
+/// low_latency = true
+/// silence_timeout_ms = 3000
+/// device_info = true
+/// distance_correction = { scale = 1.01, offset = -12.0 }
+///
+/// masks = [{ start = 170, end = 190 }]
+/// filters = [
+///     { Quality = { min_quality = 20 } },
+///     { Range = { min_distance = 150, max_distance = 5000 } },
+///     { Median = { revolutions = 3 } },
+/// ]
+///
+/// [thread]
+/// name = "lidar"
+/// realtime_priority = 50
+///
+/// [change_detection]
+/// learning_revolutions = 50
+/// threshold = 150
+/// sectors = 8
+/// debounce = 3
+/// ```
+///
+/// The same configuration in YAML (`yaml` feature), where enums are tags:
+///
+/// ```yaml
+/// port: /dev/ttyUSB0
+/// model: !Neato Xv11
+/// low_latency: true
+/// silence_timeout_ms: 3000
+/// device_info: true
+/// distance_correction: { scale: 1.01, offset: -12.0 }
+/// masks:
+///   - { start: 170, end: 190 }
+/// filters:
+///   - !Quality { min_quality: 20 }
+///   - !Range { min_distance: 150, max_distance: 5000 }
+///   - !Median { revolutions: 3 }
+/// thread:
+///   name: lidar
+///   realtime_priority: 50
+/// change_detection:
+///   learning_revolutions: 50
+///   threshold: 150
+///   sectors: 8
+///   debounce: 3
+/// ```
+///
+/// ```no_run
+/// # use std::sync::mpsc::channel;
+/// use neato_xv11::LidarDriverBuilder;
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// let handle = LidarDriverBuilder::from_config("lidar.toml")
+///     .unwrap()
+///     .spawn(message_tx, command_rx)
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DriverConfig {
+    // The port name to open.
+    pub port: String,
+    // The LIDAR. Detected when the driver starts if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<DetectedModel>,
+    // Baud rate of the port. Defaults to 115200, or the detected rate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baud_rate: Option<u32>,
+    // Set the USB-serial latency timer to its minimum. Linux only.
+    pub low_latency: bool,
+    // Lock the port so no other process can read from it.
+    pub exclusive: bool,
+    // Timeout of a single read in milliseconds. Defaults to 100.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_timeout_ms: Option<u64>,
+    // Silence in milliseconds before `LidarDriverError::ReadTimeout` is reported. Defaults to 1000.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub silence_timeout_ms: Option<u64>,
+    // Wait for data in microseconds before checking for commands. Defaults to 10000.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub poll_interval_us: Option<u64>,
+    // Firmware whose deviations are compensated for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub firmware: Option<Firmware>,
+    // Firmware deviations to compensate for. Takes precedence over `firmware`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quirks: Option<Quirks>,
+    // Correction applied to every valid distance.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub distance_correction: Option<DistanceCorrection>,
+    // Corrections of the installed unit and transform to the robot frame. Enables
+    // `LidarDriverMessage::Scan`, assembled with the default settings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub calibration: Option<Calibration>,
+    // Sectors whose readings are removed from the scans. Enables `LidarDriverMessage::Scan`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub masks: Vec<MaskSector>,
+    // Filters applied in turn to the scans. Enables `LidarDriverMessage::Scan`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub filters: Vec<FilterConfig>,
+    // Query the LIDAR for its identification before reading.
+    pub device_info: bool,
+    // Settings applied to the driver threads.
+    pub thread: ThreadConfig,
+    // When errors shut the driver down or are downgraded to warnings.
+    pub error_policy: ErrorPolicy,
+    // Send `LidarDriverMessage::ChangeDetected` events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub change_detection: Option<ChangeDetectionConfig>,
+}
+
+impl DriverConfig {
+    /// ## Summary
+    ///
+    /// Load a configuration file. The format is chosen by the extension: `.toml`
+    /// (`toml` feature), `.yaml` or `.yml` (`yaml` feature) or `.json` (`json` feature).
+    ///
+    /// ## Remarks
+    ///
+    /// Returns an `ErrorKind::InvalidData` error if the file cannot be parsed or its
+    /// format is not supported.
+    ///
+    #[cfg(any(feature = "json", feature = "toml", feature = "yaml"))]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, IoError> {
+        let path = path.as_ref();
+        let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+
+        match extension {
+            #[cfg(feature = "json")]
+            "json" => {
+                let file = std::fs::File::open(path)?;
+                Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+            },
+            #[cfg(feature = "toml")]
+            "toml" => DriverConfig::from_toml(&std::fs::read_to_string(path)?),
+            #[cfg(feature = "yaml")]
+            "yaml" | "yml" => DriverConfig::from_yaml(&std::fs::read_to_string(path)?),
+            _ => Err(IoError::new(ErrorKind::InvalidData, format!("Unsupported configuration format \"{}\"", extension))),
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Parse a TOML configuration.
+    ///
+    #[cfg(feature = "toml")]
+    pub fn from_toml(text: &str) -> Result<Self, IoError> {
+        toml::from_str(text).map_err(|err| IoError::new(ErrorKind::InvalidData, err))
+    }
+
+    /// ## Summary
+    ///
+    /// Parse a YAML configuration.
+    ///
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(text: &str) -> Result<Self, IoError> {
+        serde_yaml::from_str(text).map_err(|err| IoError::new(ErrorKind::InvalidData, err))
+    }
+
+    /// ## Summary
+    ///
+    /// The masks then the filters, `None` if there are neither.
+    ///
+    pub fn filter_chain(&self) -> Option<FilterChain> {
+        if self.masks.is_empty() && self.filters.is_empty() {
+            return None;
+        }
+
+        let mut chain = FilterChain::new();
+
+        if !self.masks.is_empty() {
+            let mask = self.masks.iter().fold(AngleMask::new(), |mask, sector| mask.with_sector(sector.start, sector.end));
+            chain = chain.with(mask);
+        }
+
+        Some(self.filters.iter().fold(chain, |chain, filter| chain.with(filter.filter())))
+    }
+}
@@ -0,0 +1,332 @@
+use std::io::{ErrorKind, Read, Write};
+use std::sync::mpsc::TryRecvError;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "log")]
+use log::{info, warn};
+
+use super::control::CommandSource;
+use super::data::{Readings, SCAN_SIZE};
+use super::driver::send_message;
+use super::prelude::*;
+
+/// Number of readings per packet built from a console scan, as in the XV-11 stream.
+const READINGS_PER_PACKET: usize = 4;
+
+/// Default time `GetLDSScan` may take to answer.
+const DEFAULT_RESPONSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Largest number of bytes read while waiting for an answer.
+const MAX_RESPONSE_SIZE: usize = 65536;
+
+/// How long `run_console` waits between commands while paused.
+const PAUSED_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// ## Summary
+///
+/// The USB console of a Neato robot, to read the LDS while it is still attached to the
+/// robot.
+///
+/// ## Remarks
+///
+/// Instead of the raw packet stream, the robot answers text commands: `TestMode On`
+/// and `SetLDSRotation On` start the LDS, then each `GetLDSScan` returns the last
+/// revolution as a table of `AngleInDegrees,DistInMM,Intensity,ErrorCodeHEX` lines
+/// followed by `ROTATION_SPEED,<Hz>`. Each scan is converted to 90 `LidarPacket`s of
+/// four readings, so the rest of the crate (e.g. `scan::ScanAssembler`) applies
+/// unchanged. See `LidarDriverBuilder::spawn_console` to run it as a driver.
+///
+/// ## Example
+///
+/// ```no_run
+/// # fn read<P: std::io::Read + std::io::Write>(port: P) {
+/// use neato_xv11::console::RobotConsole;
+///
+/// let mut console = RobotConsole::new(port);
+///
+/// console.start().unwrap();
+/// let packets = console.get_scan().unwrap();
+/// console.stop().unwrap();
+/// # }
+/// ```
+pub struct RobotConsole<P> {
+    port: P,
+    // How long `GetLDSScan` may take to answer.
+    response_timeout: Duration,
+}
+
+impl<P: Read + Write> RobotConsole<P> {
+    /// ## Summary
+    ///
+    /// Wrap the console port. It should have a short read timeout.
+    ///
+    pub fn new(port: P) -> Self {
+        RobotConsole { port, response_timeout: DEFAULT_RESPONSE_TIMEOUT }
+    }
+
+    /// ## Summary
+    ///
+    /// Set how long `GetLDSScan` may take to answer. Defaults to 2 seconds.
+    ///
+    pub fn set_response_timeout(&mut self, timeout: Duration) {
+        self.response_timeout = timeout;
+    }
+
+    /// ## Summary
+    ///
+    /// Enter the test mode and spin the LDS up.
+    ///
+    /// ## Remarks
+    ///
+    /// The first scans after spinning up may be incomplete while the motor reaches its speed.
+    ///
+    pub fn start(&mut self) -> Result<(), LidarDriverError> {
+        self.command("TestMode On")?;
+        self.command("SetLDSRotation On")
+    }
+
+    /// ## Summary
+    ///
+    /// Spin the LDS down and leave the test mode.
+    ///
+    pub fn stop(&mut self) -> Result<(), LidarDriverError> {
+        self.command("SetLDSRotation Off")?;
+        self.command("TestMode Off")
+    }
+
+    /// ## Summary
+    ///
+    /// Request the last revolution.
+    ///
+    /// ## Remarks
+    ///
+    /// Fails with `LidarDriverError::ReadTimeout` if the answer does not end within the
+    /// response timeout, e.g. when the robot is not in test mode.
+    ///
+    pub fn get_scan(&mut self) -> Result<Vec<LidarPacket>, LidarDriverError> {
+        // Drop what is left of previous answers.
+        self.drain()?;
+        self.command("GetLDSScan")?;
+
+        let start = Instant::now();
+        let mut response = Vec::new();
+        let mut chunk = [0; 1024];
+
+        while start.elapsed() < self.response_timeout && response.len() < MAX_RESPONSE_SIZE {
+            match self.port.read(&mut chunk) {
+                Ok(0) => return Err(LidarDriverError::SerialRead(ErrorKind::UnexpectedEof.into())),
+                Ok(count) => response.extend_from_slice(&chunk[..count]),
+                Err(err) if err.kind() == ErrorKind::TimedOut || err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(LidarDriverError::SerialRead(err)),
+            }
+
+            if let Some(packets) = parse_scan(&String::from_utf8_lossy(&response)) {
+                return Ok(packets);
+            }
+        }
+
+        Err(LidarDriverError::ReadTimeout)
+    }
+
+    /// ## Summary
+    ///
+    /// Take the port back.
+    ///
+    pub fn into_inner(self) -> P {
+        self.port
+    }
+
+    /// Send a command line.
+    fn command(&mut self, command: &str) -> Result<(), LidarDriverError> {
+        self.port.write_all(command.as_bytes())
+            .and_then(|_| self.port.write_all(b"\n"))
+            .and_then(|_| self.port.flush())
+            .map_err(LidarDriverError::SerialWrite)
+    }
+
+    /// Read and drop the bytes already received, until the port times out.
+    fn drain(&mut self) -> Result<(), LidarDriverError> {
+        let mut chunk = [0; 1024];
+
+        loop {
+            match self.port.read(&mut chunk) {
+                Ok(0) => return Ok(()),
+                Ok(_) => continue,
+                Err(err) if err.kind() == ErrorKind::TimedOut || err.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(LidarDriverError::SerialRead(err)),
+            }
+        }
+    }
+}
+
+/// ## Summary
+///
+/// Convert a `GetLDSScan` answer into packets of four readings, `None` until the
+/// `ROTATION_SPEED` line ending the answer is received.
+///
+/// ## Remarks
+///
+/// Lines that are not readings, such as the echoed command and the table header, are
+/// ignored. A non-zero error code is reported as `LidarReadingError::InvalidDataError`.
+/// Angles missing from the answer are missing from the packets.
+///
+/// ## Example
+///
+/// ```
+/// use neato_xv11::console::parse_scan;
+///
+/// let answer = "GetLDSScan\r\nAngleInDegrees,DistInMM,Intensity,ErrorCodeHEX\r\n0,1200,180,0\r\n1,0,0,8035\r\nROTATION_SPEED,5.00\r\n";
+/// let packets = parse_scan(answer).unwrap();
+///
+/// assert_eq!(packets[0].speed, 300.0);
+/// assert_eq!(packets[0].readings[0].distance, 1200);
+/// assert!(!packets[0].readings[1].is_valid());
+/// ```
+pub fn parse_scan(response: &str) -> Option<Vec<LidarPacket>> {
+    let mut readings: Vec<Option<LidarReading>> = vec![None; SCAN_SIZE];
+    let mut speed = None;
+
+    // Only complete lines, the last one may still be received.
+    for line in response.split_inclusive('\n').filter(|line| line.ends_with('\n')) {
+        let fields: Vec<&str> = line.trim().split(',').map(str::trim).collect();
+
+        if let ["ROTATION_SPEED", hz, ..] = fields.as_slice() {
+            speed = hz.parse::<Float>().ok();
+            break;
+        }
+
+        let reading = match fields.as_slice() {
+            [angle, distance, intensity, error, ..] => parse_reading(angle, distance, intensity, error),
+            _ => None,
+        };
+
+        if let Some(reading) = reading {
+            let index = reading.index;
+            readings[index] = Some(reading);
+        }
+    }
+
+    // Revolutions per second to RPM.
+    let speed = speed? * 60.0;
+
+    let packets = readings.chunks(READINGS_PER_PACKET)
+        .map(|chunk| LidarPacket::new(chunk.iter().flatten().cloned().collect::<Readings>(), speed))
+        .filter(|packet| !packet.readings.is_empty())
+        .collect();
+
+    Some(packets)
+}
+
+/// Parse a reading line of a `GetLDSScan` answer.
+fn parse_reading(angle: &str, distance: &str, intensity: &str, error: &str) -> Option<LidarReading> {
+    let index = angle.parse::<usize>().ok().filter(|&index| index < SCAN_SIZE)?;
+    let distance = distance.parse().ok()?;
+    let quality = intensity.parse().ok()?;
+    let error = match i32::from_str_radix(error, 16).ok()? {
+        0 => None,
+        code => Some(LidarReadingError::InvalidDataError(code)),
+    };
+
+    Some(LidarReading::new(index, distance, quality, error))
+}
+
+/// ## Summary
+///
+/// Read the LDS through the robot console, sending every reading as
+/// `LidarDriverMessage::Packet`s, until stopped. Runs on the calling thread.
+///
+/// ## Parameters
+///
+/// console: The robot console. Started and stopped by the driver.
+///
+/// tx: Sends decoded LIDAR messages or error encountered.
+///
+/// rx: Receives commands from the calling program. `Pause` spins the LDS down and
+/// `Run` spins it up again, `Stop` stops the driver.
+///
+/// ## Remarks
+///
+/// Scans are requested back to back, so the message rate follows the console's answer
+/// time rather than the LDS speed. Read timeouts are reported and retried, other
+/// errors stop the driver. `LidarDriverMessage::Shutdown` is sent last.
+///
+pub fn run_console<P: Read + Write, S: MessageSink, C: CommandSource>(mut console: RobotConsole<P>, tx: S, mut rx: C) {
+    let mut is_paused = false;
+
+    #[cfg(feature = "log")]
+    info!("Starting the LDS through the robot console.");
+
+    if let Err(err) = console.start() {
+        let _ = send_message(&tx, Err(err));
+        let _ = send_message(&tx, Ok(LidarDriverMessage::Shutdown));
+        return;
+    }
+
+    'driver: loop {
+        loop {
+            let command = match rx.try_recv() {
+                Ok(command) => command,
+                Err(TryRecvError::Empty) => break,
+                // The calling program is gone.
+                Err(TryRecvError::Disconnected) => break 'driver,
+            };
+
+            let result = match command {
+                LidarDriverCommand::Pause if !is_paused => {
+                    is_paused = true;
+                    console.command("SetLDSRotation Off")
+                },
+                LidarDriverCommand::Run if is_paused => {
+                    is_paused = false;
+                    console.command("SetLDSRotation On")
+                },
+                LidarDriverCommand::Stop => break 'driver,
+                // The other commands do not apply to the console.
+                _ => Ok(()),
+            };
+
+            if let Err(err) = result {
+                let _ = send_message(&tx, Err(err));
+                break 'driver;
+            }
+        }
+
+        if is_paused {
+            thread::sleep(PAUSED_POLL_INTERVAL);
+            continue;
+        }
+
+        match console.get_scan() {
+            Ok(packets) => {
+                for packet in packets {
+                    if send_message(&tx, Ok(LidarDriverMessage::Packet(packet))).is_err() {
+                        break 'driver;
+                    }
+                }
+            },
+            Err(LidarDriverError::ReadTimeout) => {
+                #[cfg(feature = "log")]
+                warn!("The robot console did not answer GetLDSScan.");
+
+                if send_message(&tx, Err(LidarDriverError::ReadTimeout)).is_err() {
+                    break;
+                }
+            },
+            Err(err) => {
+                let _ = send_message(&tx, Err(err));
+                break;
+            },
+        }
+    }
+
+    #[cfg(feature = "log")]
+    info!("Stopping the LDS through the robot console.");
+
+    if let Err(err) = console.stop() {
+        let _ = send_message(&tx, Err(err));
+    }
+
+    let _ = send_message(&tx, Ok(LidarDriverMessage::Shutdown));
+}
@@ -0,0 +1,270 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::time::Duration;
+
+use super::data::Float;
+use super::error::ControlError;
+use super::message::LidarDriverCommand;
+use super::status::DriverStatus;
+
+/// Default time to wait for the driver to acknowledge a command.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// ## Summary
+///
+/// Where the LIDAR driver receives its commands from, polled between reads.
+///
+/// ## Remarks
+///
+/// Implemented for the receivers of std mpsc channels, of crossbeam channels with the
+/// `crossbeam` feature and of Tokio mpsc channels with the `async` feature, so the
+/// driver takes commands from the channels the calling program already uses.
+///
+pub trait CommandSource {
+    /// ## Summary
+    ///
+    /// Take the next command without blocking. Returns `TryRecvError::Disconnected` once
+    /// the sending end is gone, in which case the driver shuts down.
+    ///
+    fn try_recv(&mut self) -> Result<LidarDriverCommand, TryRecvError>;
+}
+
+impl CommandSource for Receiver<LidarDriverCommand> {
+    fn try_recv(&mut self) -> Result<LidarDriverCommand, TryRecvError> {
+        Receiver::try_recv(self)
+    }
+}
+
+#[cfg(feature = "crossbeam")]
+impl CommandSource for crossbeam_channel::Receiver<LidarDriverCommand> {
+    fn try_recv(&mut self) -> Result<LidarDriverCommand, TryRecvError> {
+        crossbeam_channel::Receiver::try_recv(self).map_err(|err| match err {
+            crossbeam_channel::TryRecvError::Empty => TryRecvError::Empty,
+            crossbeam_channel::TryRecvError::Disconnected => TryRecvError::Disconnected,
+        })
+    }
+}
+
+#[cfg(feature = "async")]
+impl CommandSource for tokio::sync::mpsc::Receiver<LidarDriverCommand> {
+    fn try_recv(&mut self) -> Result<LidarDriverCommand, TryRecvError> {
+        tokio::sync::mpsc::Receiver::try_recv(self).map_err(tokio_try_recv_error)
+    }
+}
+
+#[cfg(feature = "async")]
+impl CommandSource for tokio::sync::mpsc::UnboundedReceiver<LidarDriverCommand> {
+    fn try_recv(&mut self) -> Result<LidarDriverCommand, TryRecvError> {
+        tokio::sync::mpsc::UnboundedReceiver::try_recv(self).map_err(tokio_try_recv_error)
+    }
+}
+
+/// Convert the error of a Tokio receiver to the error of a std receiver.
+#[cfg(feature = "async")]
+fn tokio_try_recv_error(err: tokio::sync::mpsc::error::TryRecvError) -> TryRecvError {
+    match err {
+        tokio::sync::mpsc::error::TryRecvError::Empty => TryRecvError::Empty,
+        tokio::sync::mpsc::error::TryRecvError::Disconnected => TryRecvError::Disconnected,
+    }
+}
+
+/// ## Summary
+///
+/// Controls a running driver. Wraps the command channel and waits for the driver
+/// to acknowledge every command.
+///
+/// ## Remarks
+///
+/// Commands are acknowledged once the driver has processed them, with a snapshot
+/// of its status sent directly to the caller instead of through the message sink.
+/// Returns `ControlError::Disconnected` once the driver has stopped.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::thread;
+/// # use std::sync::mpsc::channel;
+/// use neato_xv11::control::DriverControl;
+///
+/// let (message_tx, message_rx) = channel();
+/// let (control, command_rx) = DriverControl::channel();
+///
+/// thread::spawn(move || {
+///     neato_xv11::run("/dev/serial0", message_tx, command_rx);
+/// });
+///
+/// control.pause().unwrap();
+/// assert!(control.query_status().unwrap().is_paused);
+/// control.stop().unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct DriverControl {
+    // Sends commands to the driver.
+    tx: Sender<LidarDriverCommand>,
+    // How long to wait for an acknowledgement.
+    timeout: Duration,
+}
+
+impl DriverControl {
+    /// ## Summary
+    ///
+    /// Wrap the sending half of a command channel.
+    ///
+    pub fn new(tx: Sender<LidarDriverCommand>) -> Self {
+        DriverControl {
+            tx,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Create a command channel. Pass the receiver to the driver.
+    ///
+    pub fn channel() -> (Self, Receiver<LidarDriverCommand>) {
+        let (tx, rx) = channel();
+        (DriverControl::new(tx), rx)
+    }
+
+    /// ## Summary
+    ///
+    /// Set how long to wait for the driver to acknowledge a command. Defaults to 1 second.
+    ///
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// ## Summary
+    ///
+    /// Pause reading. Data received while paused is discarded.
+    ///
+    pub fn pause(&self) -> Result<(), ControlError> {
+        self.send(LidarDriverCommand::Pause).map(|_| ())
+    }
+
+    /// ## Summary
+    ///
+    /// Resume reading after `pause`. The parser resyncs on fresh data.
+    ///
+    pub fn resume(&self) -> Result<(), ControlError> {
+        self.send(LidarDriverCommand::Run).map(|_| ())
+    }
+
+    /// ## Summary
+    ///
+    /// Stop the driver and wait until it has released the port.
+    ///
+    pub fn stop(&self) -> Result<(), ControlError> {
+        match self.send(LidarDriverCommand::Stop) {
+            // The driver drops the pending acknowledgement when it exits.
+            Err(ControlError::Disconnected) => Ok(()),
+            Err(err) => Err(err),
+            Ok(_) => Err(ControlError::Disconnected),
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Set the spin speed the LIDAR should run at (RPM), reported in `DriverStatus::target_rpm`.
+    ///
+    /// ## Remarks
+    ///
+    /// The XV-11 motor is powered separately from the serial link, the driver does
+    /// not regulate it.
+    ///
+    pub fn set_target_rpm(&self, rpm: Float) -> Result<(), ControlError> {
+        self.send(LidarDriverCommand::SetTargetRpm(rpm)).map(|_| ())
+    }
+
+    /// ## Summary
+    ///
+    /// The current status of the driver.
+    ///
+    pub fn query_status(&self) -> Result<DriverStatus, ControlError> {
+        let (reply_tx, reply_rx) = channel();
+
+        self.tx.send(LidarDriverCommand::Acknowledge(reply_tx)).map_err(|_| ControlError::Disconnected)?;
+        self.wait(&reply_rx)
+    }
+
+    /// Send a command and wait for the driver to acknowledge it.
+    fn send(&self, command: LidarDriverCommand) -> Result<DriverStatus, ControlError> {
+        let (reply_tx, reply_rx) = channel();
+
+        self.tx.send(command).map_err(|_| ControlError::Disconnected)?;
+        // Commands are processed in order, the acknowledgement follows the command.
+        self.tx.send(LidarDriverCommand::Acknowledge(reply_tx)).map_err(|_| ControlError::Disconnected)?;
+        self.wait(&reply_rx)
+    }
+
+    /// Wait for an acknowledgement.
+    fn wait(&self, reply_rx: &Receiver<DriverStatus>) -> Result<DriverStatus, ControlError> {
+        reply_rx.recv_timeout(self.timeout).map_err(|err| match err {
+            RecvTimeoutError::Timeout => ControlError::Timeout,
+            RecvTimeoutError::Disconnected => ControlError::Disconnected,
+        })
+    }
+}
+
+/// ## Summary
+///
+/// Stops a driver from code that does not hold its command sender, such as a global
+/// shutdown sequence. Clones share the same state.
+///
+/// ## Remarks
+///
+/// Given to the driver with `LidarDriverBuilder::stop_token`. The driver checks the
+/// token between reads and shuts down as if it had received `LidarDriverCommand::Stop`.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::sync::mpsc::channel;
+/// use neato_xv11::LidarDriverBuilder;
+/// use neato_xv11::control::StopToken;
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+/// let token = StopToken::new();
+///
+/// let handle = LidarDriverBuilder::new("/dev/ttyUSB0")
+///     .stop_token(token.clone())
+///     .spawn(message_tx, command_rx)
+///     .unwrap();
+///
+/// // Elsewhere, during shutdown.
+/// token.stop();
+/// handle.join().unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct StopToken {
+    // Set once stopping is requested.
+    is_stopped: Arc<AtomicBool>,
+}
+
+impl StopToken {
+    /// ## Summary
+    ///
+    /// Initialize a new token.
+    ///
+    pub fn new() -> Self {
+        StopToken::default()
+    }
+
+    /// ## Summary
+    ///
+    /// Request every driver holding the token to stop.
+    ///
+    pub fn stop(&self) {
+        self.is_stopped.store(true, Ordering::Release);
+    }
+
+    /// ## Summary
+    ///
+    /// Whether stopping was requested.
+    ///
+    pub fn is_stopped(&self) -> bool {
+        self.is_stopped.load(Ordering::Acquire)
+    }
+}
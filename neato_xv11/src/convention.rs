@@ -0,0 +1,98 @@
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+#[cfg(feature = "std")]
+use super::data::{LidarScan, Point2D, PolarConfig};
+use super::data::{Float, LidarReading, RotationDirection, SCAN_SIZE};
+
+/// ## Summary
+///
+/// The angle and axis convention of converted readings.
+///
+/// ## Remarks
+///
+/// The XV-11 numbers its readings by degree in the direction it spins, clockwise seen
+/// from above. `Native` keeps that indexing: angles in degrees equal to the index,
+/// positions in millimeters with the x axis at index 0 and the y axis at index 90.
+/// `Rep103` follows ROS REP-103: x forward (index 0), y left, counterclockwise
+/// angles in radians between -π and π, positions in meters. `data::PolarConfig`
+/// adds a zero angle offset to a convention.
+///
+/// ## Example
+///
+/// ```no_run
+/// # fn publish(scan: &neato_xv11::data::LidarScan) {
+/// use neato_xv11::convention::Convention;
+///
+/// let convention = Convention::Rep103;
+/// let angles: Vec<f64> = (0..360).map(|index| convention.angle(index) as f64).collect();
+/// let points = convention.point_cloud(scan);
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum Convention {
+    // Sensor indexing: degrees and millimeters.
+    #[default]
+    Native,
+    // ROS REP-103: radians and meters, counterclockwise, y left.
+    Rep103,
+}
+
+impl Convention {
+    /// ## Summary
+    ///
+    /// Direction the angles increase in, relative to the native indexing.
+    ///
+    pub fn direction(&self) -> RotationDirection {
+        match self {
+            Convention::Native => RotationDirection::Counterclockwise,
+            Convention::Rep103 => RotationDirection::Clockwise,
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Angle of the reading at an index.
+    ///
+    pub fn angle(&self, index: usize) -> Float {
+        match self {
+            Convention::Native => (index % SCAN_SIZE) as Float,
+            Convention::Rep103 => {
+                // Clockwise indices, wrapped to -180..180 degrees.
+                let degrees = (SCAN_SIZE - index % SCAN_SIZE) % SCAN_SIZE;
+                let degrees = if degrees >= SCAN_SIZE / 2 { degrees as Float - SCAN_SIZE as Float } else { degrees as Float };
+                degrees.to_radians()
+            },
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Distance of a reading, in millimeters (`Native`) or meters (`Rep103`).
+    ///
+    pub fn range(&self, reading: &LidarReading) -> Float {
+        match self {
+            Convention::Native => reading.distance as Float,
+            Convention::Rep103 => reading.distance as Float / 1000.0,
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Position of a reading in the LIDAR frame.
+    ///
+    #[cfg(feature = "std")]
+    pub fn point(&self, reading: &LidarReading) -> Point2D {
+        PolarConfig::from(*self).to_point(reading)
+    }
+
+    /// ## Summary
+    ///
+    /// Positions of the valid readings of a scan in the LIDAR frame, in index order.
+    ///
+    #[cfg(feature = "std")]
+    pub fn point_cloud(&self, scan: &LidarScan) -> Vec<Point2D> {
+        scan.valid_readings().map(|reading| self.point(reading)).collect()
+    }
+}
@@ -1,13 +1,35 @@
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use super::convention::Convention;
 use super::error::LidarReadingError;
 
 #[cfg(feature = "serde")]
 use serde::{Serialize, Deserialize};
 
+/// ## Summary
+/// 
+/// Floating point type used for speeds and derived values.
+/// `f32` when the `f32` feature is enabled, `f64` otherwise.
+/// 
+#[cfg(feature = "f32")]
+pub type Float = f32;
+
+/// ## Summary
+/// 
+/// Floating point type used for speeds and derived values.
+/// `f32` when the `f32` feature is enabled, `f64` otherwise.
+/// 
+#[cfg(not(feature = "f32"))]
+pub type Float = f64;
+
 /// ## Summary
 /// 
 /// A LIDAR distance reading.
 /// 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct LidarReading {
     // Index of the reading.
@@ -19,6 +41,9 @@ pub struct LidarReading {
     // Error reported in reading.
     #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub error: Option<LidarReadingError>,
+    // Estimated standard deviation of the distance in millimeters, set by `noise::NoiseModel::annotate`.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub std_dev: Option<Float>,
 }
 
 impl LidarReading {
@@ -45,21 +70,375 @@ impl LidarReading {
             distance,
             quality,
             error,
+            std_dev: None,
+        }
+    }
+
+    /// ## Summary
+    /// 
+    /// Whether the distance can be used. Readings with a signal strength warning are
+    /// valid, readings flagged as invalid data are not.
+    /// 
+    pub fn is_valid(&self) -> bool {
+        !matches!(self.error, Some(LidarReadingError::InvalidDataError(_)))
+    }
+
+    /// ## Summary
+    /// 
+    /// Direction of the reading in the LIDAR frame.
+    /// 
+    pub fn angle(&self) -> Angle {
+        Angle::from_degrees((self.index % SCAN_SIZE) as Float)
+    }
+
+    /// ## Summary
+    /// 
+    /// Direction of the reading in the LIDAR frame, in degrees.
+    /// 
+    pub fn angle_degrees(&self) -> Float {
+        self.angle().degrees()
+    }
+
+    /// ## Summary
+    /// 
+    /// Direction of the reading in the LIDAR frame, in radians.
+    /// 
+    pub fn angle_radians(&self) -> Float {
+        self.angle().radians()
+    }
+
+    /// ## Summary
+    /// 
+    /// Position of the reading in the LIDAR frame.
+    /// 
+    #[cfg(feature = "std")]
+    pub fn to_point(&self) -> Point2D {
+        Point2D::from_polar(self.distance as Float, self.angle())
+    }
+}
+
+/// ## Summary
+/// 
+/// A position in the LIDAR frame, in millimeters. The x axis points at index 0 and
+/// the y axis at index 90.
+/// 
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Point2D {
+    // Coordinate along the x axis in millimeters.
+    pub x: Float,
+    // Coordinate along the y axis in millimeters.
+    pub y: Float,
+}
+
+impl Point2D {
+    /// ## Summary
+    /// 
+    /// The point at a distance (millimeters) and angle from the origin.
+    /// 
+    #[cfg(feature = "std")]
+    pub fn from_polar(distance: Float, angle: Angle) -> Self {
+        let (sin, cos) = angle.radians().sin_cos();
+
+        Point2D {
+            x: distance * cos,
+            y: distance * sin,
+        }
+    }
+
+    /// ## Summary
+    /// 
+    /// Direction of the point from the origin.
+    /// 
+    #[cfg(feature = "std")]
+    pub fn angle(&self) -> Angle {
+        Angle::from_radians(self.y.atan2(self.x))
+    }
+
+    /// ## Summary
+    /// 
+    /// Euclidean distance to another point in millimeters.
+    /// 
+    #[cfg(feature = "std")]
+    pub fn distance(&self, other: &Point2D) -> Float {
+        (self.x - other.x).hypot(self.y - other.y)
+    }
+}
+
+/// ## Summary
+/// 
+/// An angle, counterclockwise in the native indexing (index 90 is at 90 degrees).
+/// 
+/// ## Remarks
+/// 
+/// Constructed from an explicit unit with `from_degrees` or `from_radians`, so the
+/// geometry API never has to guess which one a bare number is in.
+/// 
+/// ## Example
+/// 
+/// ```
+/// use neato_xv11::data::Angle;
+/// 
+/// let angle = Angle::from_degrees(-90.0);
+/// assert_eq!(angle.normalized().degrees(), 270.0);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize), serde(transparent))]
+pub struct Angle {
+    // The angle in degrees. Serialized as a number of degrees.
+    degrees: Float,
+}
+
+impl Angle {
+    /// ## Summary
+    /// 
+    /// An angle in degrees.
+    /// 
+    pub fn from_degrees(degrees: Float) -> Self {
+        Angle { degrees }
+    }
+
+    /// ## Summary
+    /// 
+    /// An angle in radians.
+    /// 
+    pub fn from_radians(radians: Float) -> Self {
+        Angle { degrees: radians.to_degrees() }
+    }
+
+    /// ## Summary
+    /// 
+    /// The angle in degrees.
+    /// 
+    pub fn degrees(&self) -> Float {
+        self.degrees
+    }
+
+    /// ## Summary
+    /// 
+    /// The angle in radians.
+    /// 
+    pub fn radians(&self) -> Float {
+        self.degrees.to_radians()
+    }
+
+    /// ## Summary
+    /// 
+    /// The same direction between 0 (included) and 360 (excluded) degrees.
+    /// 
+    pub fn normalized(&self) -> Self {
+        let degrees = self.degrees % 360.0;
+        Angle { degrees: if degrees < 0.0 { degrees + 360.0 } else { degrees } }
+    }
+
+    /// ## Summary
+    /// 
+    /// The index of the nearest reading in a scan.
+    /// 
+    pub fn index(&self) -> usize {
+        // Rounding may reach 360.
+        (self.normalized().degrees + 0.5) as usize % SCAN_SIZE
+    }
+}
+
+impl core::ops::Add for Angle {
+    type Output = Angle;
+
+    fn add(self, other: Angle) -> Angle {
+        Angle { degrees: self.degrees + other.degrees }
+    }
+}
+
+impl core::ops::Sub for Angle {
+    type Output = Angle;
+
+    fn sub(self, other: Angle) -> Angle {
+        Angle { degrees: self.degrees - other.degrees }
+    }
+}
+
+impl core::ops::Neg for Angle {
+    type Output = Angle;
+
+    fn neg(self) -> Angle {
+        Angle { degrees: -self.degrees }
+    }
+}
+
+/// ## Summary
+/// 
+/// Direction in which angles increase, relative to the native indexing.
+/// 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum RotationDirection {
+    // Angles increase with the index, as in the native frame.
+    #[default]
+    Counterclockwise,
+    // Angles decrease as the index increases.
+    Clockwise,
+}
+
+/// ## Summary
+/// 
+/// How readings are converted to angles and points: a `Convention` and the native
+/// angle that becomes 0.
+/// 
+/// ## Remarks
+/// 
+/// A reading at native angle `a` is converted to `a - zero_offset`, negated if the
+/// convention is clockwise (`Convention::direction`), then normalized between 0 and
+/// 360 degrees. Distances are in the units of the convention. The default keeps the
+/// native frame.
+/// 
+/// ## Example
+/// 
+/// ```
+/// use neato_xv11::convention::Convention;
+/// use neato_xv11::data::{Angle, PolarConfig};
+/// 
+/// // Index 90 is straight ahead, angles increase clockwise.
+/// let config = PolarConfig { convention: Convention::Rep103, zero_offset: Angle::from_degrees(90.0) };
+/// assert_eq!(config.angle(Angle::from_degrees(80.0)).degrees(), 10.0);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize), serde(default))]
+pub struct PolarConfig {
+    // Units and direction of the converted angles and points.
+    pub convention: Convention,
+    // Native angle converted to 0.
+    pub zero_offset: Angle,
+}
+
+impl PolarConfig {
+    /// ## Summary
+    /// 
+    /// Convert a native angle.
+    /// 
+    pub fn angle(&self, native: Angle) -> Angle {
+        let angle = native - self.zero_offset;
+
+        match self.convention.direction() {
+            RotationDirection::Counterclockwise => angle.normalized(),
+            RotationDirection::Clockwise => (-angle).normalized(),
         }
     }
+
+    /// ## Summary
+    /// 
+    /// Position of a reading, in the distance unit of the convention.
+    /// 
+    #[cfg(feature = "std")]
+    pub fn to_point(&self, reading: &LidarReading) -> Point2D {
+        Point2D::from_polar(self.convention.range(reading), self.angle(reading.angle()))
+    }
+}
+
+impl From<Convention> for PolarConfig {
+    fn from(convention: Convention) -> Self {
+        PolarConfig { convention, zero_offset: Angle::default() }
+    }
+}
+
+/// ## Summary
+/// 
+/// A rotation speed, with accessors in every common unit.
+/// 
+/// ## Example
+/// 
+/// ```
+/// use neato_xv11::data::RotationSpeed;
+/// 
+/// let speed = RotationSpeed::from_hz(5.0);
+/// assert_eq!(speed.rpm(), 300.0);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct RotationSpeed {
+    // Revolutions per minute.
+    rpm: Float,
+}
+
+impl RotationSpeed {
+    /// ## Summary
+    /// 
+    /// A speed in revolutions per minute.
+    /// 
+    pub fn from_rpm(rpm: Float) -> Self {
+        RotationSpeed { rpm }
+    }
+
+    /// ## Summary
+    /// 
+    /// A speed in revolutions per second.
+    /// 
+    pub fn from_hz(hz: Float) -> Self {
+        RotationSpeed { rpm: hz * 60.0 }
+    }
+
+    /// ## Summary
+    /// 
+    /// A speed in radians per second.
+    /// 
+    pub fn from_rad_per_sec(rad_per_sec: Float) -> Self {
+        RotationSpeed { rpm: rad_per_sec.to_degrees() / 6.0 }
+    }
+
+    /// ## Summary
+    /// 
+    /// Revolutions per minute.
+    /// 
+    pub fn rpm(&self) -> Float {
+        self.rpm
+    }
+
+    /// ## Summary
+    /// 
+    /// Revolutions per second.
+    /// 
+    pub fn hz(&self) -> Float {
+        self.rpm / 60.0
+    }
+
+    /// ## Summary
+    /// 
+    /// Radians per second.
+    /// 
+    pub fn rad_per_sec(&self) -> Float {
+        // One revolution per minute is 6 degrees per second.
+        (self.rpm * 6.0).to_radians()
+    }
 }
 
+/// Largest number of readings in a packet of any supported protocol.
+pub const MAX_READINGS: usize = 12;
+
 /// ## Summary
 /// 
-/// A decoded LIDAR packet containing four distance readings.
+/// The readings of a packet, a fixed capacity `heapless::Vec` stored inline so
+/// decoding a packet never allocates. Serialized as a sequence, like a `Vec`.
 /// 
-#[derive(Debug)]
+pub type Readings = heapless::Vec<LidarReading, MAX_READINGS>;
+
+/// ## Summary
+/// 
+/// A decoded LIDAR packet containing four distance readings (up to `MAX_READINGS`
+/// for other protocols).
+/// 
+#[derive(Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct LidarPacket {
     // Collection of four readings.
-    pub readings: Vec<LidarReading>,
+    pub readings: Readings,
     // LIDAR spin speed (RPM).
-    pub speed: f64,
+    pub speed: Float,
+    // Number of packets decoded by the parser before this one. Keeps increasing across resyncs.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub sequence: u64,
+    // Number of packets missing from the index sequence right before this one (lost or corrupted).
+    // Always 0 for the first packet after a reset and for protocols without a packet index.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub missed: usize,
 }
 
 impl LidarPacket {
@@ -73,10 +452,365 @@ impl LidarPacket {
     /// 
     /// speed: LIDAR spin speed (RPM).
     /// 
-    pub(crate) fn new(readings: Vec<LidarReading>, speed: f64) -> Self {
+    pub(crate) fn new(readings: Readings, speed: Float) -> Self {
         LidarPacket {
             readings,
             speed,
+            sequence: 0,
+            missed: 0,
+        }
+    }
+
+    /// ## Summary
+    /// 
+    /// LIDAR spin speed, `speed` with its unit.
+    /// 
+    pub fn rotation_speed(&self) -> RotationSpeed {
+        RotationSpeed::from_rpm(self.speed)
+    }
+
+    /// ## Summary
+    /// 
+    /// Positions of the valid readings in the LIDAR frame.
+    /// 
+    #[cfg(feature = "std")]
+    pub fn to_points(&self) -> Vec<Point2D> {
+        self.to_points_with(&PolarConfig::default())
+    }
+
+    /// ## Summary
+    /// 
+    /// Positions of the valid readings, converted with `config`.
+    /// 
+    #[cfg(feature = "std")]
+    pub fn to_points_with(&self, config: &PolarConfig) -> Vec<Point2D> {
+        self.readings.iter().filter(|reading| reading.is_valid()).map(|reading| config.to_point(reading)).collect()
+    }
+}
+
+/// Number of readings in a full revolution, one per degree.
+pub const SCAN_SIZE: usize = 360;
+
+/// Largest angle in degrees between the two beams `LidarScan::distance_at` interpolates between.
+pub const DEFAULT_MAX_GAP: Float = 5.0;
+
+/// ## Summary
+/// 
+/// The readings of a scan, indexed by degree. A fixed capacity `heapless::Vec` without
+/// the `alloc` feature.
+/// 
+#[cfg(not(feature = "alloc"))]
+pub type ScanReadings = heapless::Vec<Option<LidarReading>, SCAN_SIZE>;
+
+/// ## Summary
+/// 
+/// The readings of a scan, indexed by degree. A heap allocated `Vec` with the `alloc`
+/// feature.
+/// 
+#[cfg(feature = "alloc")]
+pub type ScanReadings = Vec<Option<LidarReading>>;
+
+/// ## Summary
+/// 
+/// Whether `LidarScan::iter` yields the readings flagged as invalid data.
+/// 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InvalidReadings {
+    // Only yield readings with a usable distance.
+    #[default]
+    Skip,
+    // Yield every received reading.
+    Include,
+}
+
+/// ## Summary
+/// 
+/// A full 360 degree revolution assembled from packets by `scan::ScanAssembler`.
+/// 
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct LidarScan {
+    // One slot per degree. `None` where the packet was missing or corrupted.
+    pub readings: ScanReadings,
+    // Average LIDAR spin speed over the revolution (RPM).
+    pub speed: Float,
+    // Name of the sensor's coordinate frame, set with `ScanAssembler::set_frame_id`. Empty by default.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub frame_id: String,
+    // Number of scans completed by the assembler before this one.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub revolution: u64,
+}
+
+impl LidarScan {
+    /// ## Summary
+    /// 
+    /// Initialize a scan with every slot missing.
+    /// 
+    pub(crate) fn empty() -> Self {
+        LidarScan {
+            readings: (0..SCAN_SIZE).map(|_| None).collect(),
+            speed: 0.0,
+            #[cfg(feature = "alloc")]
+            frame_id: String::new(),
+            revolution: 0,
+        }
+    }
+
+    /// ## Summary
+    /// 
+    /// Average LIDAR spin speed over the revolution, `speed` with its unit.
+    /// 
+    pub fn rotation_speed(&self) -> RotationSpeed {
+        RotationSpeed::from_rpm(self.speed)
+    }
+
+    /// ## Summary
+    /// 
+    /// The reading at the given degree, if it was received.
+    /// 
+    pub fn get(&self, index: usize) -> Option<&LidarReading> {
+        self.readings.get(index % SCAN_SIZE).and_then(Option::as_ref)
+    }
+
+    /// ## Summary
+    /// 
+    /// Number of slots with no reading.
+    /// 
+    pub fn missing(&self) -> usize {
+        self.readings.iter().filter(|reading| reading.is_none()).count()
+    }
+
+    /// ## Summary
+    /// 
+    /// Iterate over the received readings by increasing angle, one per degree.
+    /// 
+    /// ## Example
+    /// 
+    /// ```no_run
+    /// # fn print(scan: &neato_xv11::data::LidarScan) {
+    /// use neato_xv11::data::InvalidReadings;
+    /// 
+    /// for reading in scan.iter(InvalidReadings::Include) {
+    ///     println!("{} {} {}", reading.index, reading.distance, reading.is_valid());
+    /// }
+    /// # }
+    /// ```
+    pub fn iter(&self, invalid: InvalidReadings) -> impl Iterator<Item = &LidarReading> {
+        // Slots are indexed by degree, duplicates were already replaced by the latest packet.
+        self.readings.iter().flatten().filter(move |reading| invalid == InvalidReadings::Include || reading.is_valid())
+    }
+
+    /// ## Summary
+    /// 
+    /// Iterate over the valid readings from `start` to `end`, both included, counterclockwise.
+    /// 
+    /// ## Remarks
+    /// 
+    /// Angles are rounded to the nearest degree. The range wraps around 0 when `end` is
+    /// before `start`, e.g. 350 to 10 degrees covers 21 degrees ahead of the sensor.
+    /// Equal angles cover a single degree. Reverse the iterator to go clockwise.
+    /// 
+    /// ## Example
+    /// 
+    /// ```no_run
+    /// # fn closest_ahead(scan: &neato_xv11::data::LidarScan) {
+    /// use neato_xv11::data::Angle;
+    /// 
+    /// let ahead = scan.range_iter(Angle::from_degrees(-15.0), Angle::from_degrees(15.0));
+    /// let closest = ahead.map(|reading| reading.distance).min();
+    /// 
+    /// // Clockwise, from 15 down to -15 degrees.
+    /// let sweep = scan.range_iter(Angle::from_degrees(-15.0), Angle::from_degrees(15.0)).rev();
+    /// # }
+    /// ```
+    pub fn range_iter(&self, start: Angle, end: Angle) -> impl DoubleEndedIterator<Item = &LidarReading> {
+        let start = start.index();
+        let span = (end.index() + SCAN_SIZE - start) % SCAN_SIZE;
+
+        (0..=span)
+            .filter_map(move |offset| self.get(start + offset))
+            .filter(|reading| reading.is_valid())
+    }
+
+    /// ## Summary
+    /// 
+    /// Distance in millimeters at an arbitrary angle, linearly interpolated between
+    /// the two nearest valid beams on either side. See `distance_at_within`.
+    /// 
+    /// ## Example
+    /// 
+    /// ```no_run
+    /// # fn brake(scan: &neato_xv11::data::LidarScan) -> bool {
+    /// use neato_xv11::data::Angle;
+    /// 
+    /// scan.distance_at(Angle::from_degrees(0.0)).is_some_and(|distance| distance < 300.0)
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn distance_at(&self, angle: Angle) -> Option<Float> {
+        self.distance_at_within(angle, Angle::from_degrees(DEFAULT_MAX_GAP))
+    }
+
+    /// ## Summary
+    /// 
+    /// Distance in millimeters at an arbitrary angle, linearly interpolated between
+    /// the two nearest valid beams on either side.
+    /// 
+    /// ## Parameters
+    /// 
+    /// angle: Where to measure the distance.
+    /// 
+    /// max_gap: Largest angle between the two beams. Returns `None` when the beams are
+    /// further apart, e.g. around a dark object returning no valid reading.
+    /// 
+    #[cfg(feature = "std")]
+    pub fn distance_at_within(&self, angle: Angle, max_gap: Angle) -> Option<Float> {
+        let degrees = angle.normalized().degrees();
+        let max_gap = max_gap.degrees();
+        let below = degrees.floor();
+        let usable = |index: usize| self.get(index).filter(|reading| reading.is_valid() && reading.distance > 0);
+
+        // Nearest usable beam at or before the angle, then at or after it.
+        let before = (0..=max_gap as usize)
+            .find_map(|offset| usable((below as usize + SCAN_SIZE - offset % SCAN_SIZE) % SCAN_SIZE).map(|reading| (below - offset as Float, reading)))?;
+
+        if before.0 == degrees {
+            return Some(before.1.distance as Float);
         }
+
+        let after = (1..=max_gap as usize)
+            .find_map(|offset| usable((below as usize + offset) % SCAN_SIZE).map(|reading| (below + offset as Float, reading)))?;
+
+        let gap = after.0 - before.0;
+
+        if gap > max_gap {
+            return None;
+        }
+
+        let fraction = (degrees - before.0) / gap;
+        Some(before.1.distance as Float + (after.1.distance - before.1.distance) as Float * fraction)
+    }
+
+    /// ## Summary
+    /// 
+    /// Distances in millimeters of `beams` uniformly spaced beams, beam `i` at
+    /// `i * 360 / beams` degrees. `None` where no valid reading supports the beam.
+    /// 
+    /// ## Remarks
+    /// 
+    /// With 360 beams or more, distances are interpolated with `distance_at`. With fewer,
+    /// each beam keeps the closest valid reading of the sector it covers, so no obstacle
+    /// is lost to decimation.
+    /// 
+    /// ## Example
+    /// 
+    /// ```no_run
+    /// # fn convert(scan: &neato_xv11::data::LidarScan) {
+    /// let ranges = scan.resample(720);
+    /// assert_eq!(ranges.len(), 720);
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn resample(&self, beams: usize) -> Vec<Option<Float>> {
+        let step = SCAN_SIZE as Float / beams as Float;
+
+        (0..beams)
+            .map(|beam| {
+                let angle = Angle::from_degrees(beam as Float * step);
+
+                if beams >= SCAN_SIZE {
+                    return self.distance_at(angle);
+                }
+
+                let start = angle - Angle::from_degrees(step / 2.0);
+                let end = angle + Angle::from_degrees(step / 2.0 - 1.0);
+
+                self.range_iter(start, end)
+                    .filter(|reading| reading.distance > 0)
+                    .map(|reading| reading.distance as Float)
+                    .min_by(Float::total_cmp)
+            })
+            .collect()
+    }
+
+    /// ## Summary
+    /// 
+    /// Iterate over the received readings with a usable distance.
+    /// 
+    pub fn valid_readings(&self) -> impl Iterator<Item = &LidarReading> {
+        self.readings.iter().flatten().filter(|reading| reading.is_valid())
+    }
+
+    /// ## Summary
+    /// 
+    /// Count the valid distances falling in each bin.
+    /// 
+    /// ## Parameters
+    /// 
+    /// bin_width_mm: Width of a bin in millimeters. Bin `i` holds the distances in
+    /// `[i * bin_width_mm, (i + 1) * bin_width_mm)`.
+    /// 
+    /// ## Remarks
+    /// 
+    /// The number of bins is set by the farthest distance. Returns no bins if the scan
+    /// has no valid reading. Panics if `bin_width_mm` is 0.
+    /// 
+    /// ## Example
+    /// 
+    /// ```no_run
+    /// # fn classify(scan: &neato_xv11::data::LidarScan) {
+    /// let histogram = scan.histogram(250);
+    /// let close: usize = histogram.iter().take(4).sum();
+    /// let is_cluttered = close > 180;
+    /// # }
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn histogram(&self, bin_width_mm: u32) -> Vec<usize> {
+        assert!(bin_width_mm > 0, "bin width must be positive");
+
+        let mut bins = Vec::new();
+
+        for reading in self.valid_readings() {
+            let bin = reading.distance.max(0) as usize / bin_width_mm as usize;
+
+            if bin >= bins.len() {
+                bins.resize(bin + 1, 0);
+            }
+
+            bins[bin] += 1;
+        }
+
+        bins
+    }
+
+    /// ## Summary
+    /// 
+    /// Positions of the valid readings in the LIDAR frame, in index order.
+    /// 
+    #[cfg(feature = "std")]
+    pub fn to_point_cloud(&self) -> Vec<Point2D> {
+        self.valid_readings().map(LidarReading::to_point).collect()
+    }
+
+    /// ## Summary
+    /// 
+    /// Positions of the valid readings converted with `config`, in index order.
+    /// 
+    /// ## Example
+    /// 
+    /// ```no_run
+    /// # fn convert(scan: &neato_xv11::data::LidarScan) {
+    /// use neato_xv11::convention::Convention;
+    /// use neato_xv11::data::PolarConfig;
+    /// 
+    /// let config = PolarConfig::from(Convention::Rep103);
+    /// let points = scan.to_point_cloud_with(&config);
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn to_point_cloud_with(&self, config: &PolarConfig) -> Vec<Point2D> {
+        self.valid_readings().map(|reading| config.to_point(reading)).collect()
     }
 }
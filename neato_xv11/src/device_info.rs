@@ -0,0 +1,196 @@
+use std::ffi::OsStr;
+use std::io::{ErrorKind, Read, Write};
+use std::sync::Mutex;
+use std::sync::mpsc::SendError;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "log")]
+use log::{info, warn};
+
+use super::builder::PortConfig;
+use super::driver::open_port;
+use super::info::DeviceInfo;
+use super::prelude::*;
+
+/// Query understood by Neato LDS units and robots.
+const QUERY: &[u8] = b"GetVersion\r\n";
+
+/// Largest number of bytes read while waiting for the answer.
+const MAX_RESPONSE_SIZE: usize = 4096;
+
+/// Number of packets observed before the device info is synthesized (four revolutions).
+const OBSERVED_PACKETS: u32 = 360;
+
+/// ## Summary
+///
+/// Ask the LIDAR for its firmware version and serial number.
+///
+/// ## Parameters
+///
+/// port_name: The port name to open.
+///
+/// config: Settings applied to the serial port.
+///
+/// timeout: How long to wait for the answer.
+///
+/// ## Remarks
+///
+/// Sends `GetVersion` and looks for the `LDS Software` and `LDS Serial` lines of the
+/// answer. Returns `Ok(None)` if the LIDAR does not answer, which is the case of a bare
+/// XV-11 LDS that only streams packets.
+///
+pub fn query_device_info<T: AsRef<OsStr> + ?Sized>(port_name: &T, config: &PortConfig, timeout: Duration) -> Result<Option<DeviceInfo>, LidarDriverError> {
+    let mut port = open_port(port_name.as_ref(), config)?;
+
+    if let Err(err) = port.write_all(QUERY) {
+        #[cfg(feature = "log")]
+        warn!("Unable to send the info query. {}", err);
+
+        return Err(LidarDriverError::SerialWrite(err));
+    }
+
+    let start = Instant::now();
+    let mut response = Vec::new();
+    let mut chunk = [0; 256];
+
+    while start.elapsed() < timeout && response.len() < MAX_RESPONSE_SIZE {
+        match port.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(count) => response.extend_from_slice(&chunk[..count]),
+            Err(err) if err.kind() == ErrorKind::TimedOut || err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(LidarDriverError::SerialRead(err)),
+        }
+
+        let info = parse_response(&response);
+
+        if info.firmware.is_some() && info.serial.is_some() {
+            return Ok(Some(info));
+        }
+    }
+
+    let info = parse_response(&response);
+
+    if info.firmware.is_some() || info.serial.is_some() {
+        Ok(Some(info))
+    } else {
+        Ok(None)
+    }
+}
+
+/// ## Summary
+///
+/// Extract the firmware version and serial number from a `GetVersion` answer.
+///
+/// ## Remarks
+///
+/// The answer is a comma separated table, e.g. `LDS Software,V2.6.15295,` and
+/// `LDS Serial,KSH13315AA-0000153,`. Binary packet data around it is ignored.
+///
+pub(crate) fn parse_response(response: &[u8]) -> DeviceInfo {
+    let response = String::from_utf8_lossy(response);
+    let mut info = DeviceInfo::default();
+
+    for line in response.split(['\r', '\n']) {
+        let mut fields = line.split(',').map(str::trim);
+        let name = match fields.next() {
+            Some(name) => name,
+            None => continue,
+        };
+
+        let value = fields.next().filter(|value| !value.is_empty()).map(String::from);
+
+        if name.ends_with("LDS Software") {
+            info.firmware = value;
+        } else if name.ends_with("LDS Serial") {
+            info.serial = value;
+        }
+    }
+
+    info
+}
+
+/// ## Summary
+///
+/// A message sink that synthesizes a `LidarDriverMessage::DeviceInfo` from the observed
+/// speed range and packet rate, for LIDARs that do not answer the info query.
+/// Every message is forwarded to the inner sink.
+///
+pub struct DeviceInfoSink<S> {
+    inner: S,
+    observer: Mutex<Observer>,
+}
+
+/// Speeds and packet count observed so far.
+struct Observer {
+    // When the first packet was received.
+    start: Option<Instant>,
+    // Number of packets received.
+    packets: u32,
+    // Slowest speed received.
+    min_speed: Float,
+    // Fastest speed received.
+    max_speed: Float,
+    // Whether the device info was sent.
+    is_done: bool,
+}
+
+impl<S: MessageSink> DeviceInfoSink<S> {
+    /// ## Summary
+    ///
+    /// Wrap a sink.
+    ///
+    pub fn new(inner: S) -> Self {
+        DeviceInfoSink {
+            inner,
+            observer: Mutex::new(Observer {
+                start: None,
+                packets: 0,
+                min_speed: Float::MAX,
+                max_speed: 0.0,
+                is_done: false,
+            }),
+        }
+    }
+}
+
+impl<S: MessageSink> MessageSink for DeviceInfoSink<S> {
+    fn send(&self, message: Result<LidarDriverMessage, LidarDriverError>) -> Result<(), SendError<Result<LidarDriverMessage, LidarDriverError>>> {
+        if let Ok(LidarDriverMessage::Packet(packet)) = &message {
+            let mut observer = self.observer.lock().unwrap_or_else(|e| e.into_inner());
+
+            if !observer.is_done {
+                let start = *observer.start.get_or_insert_with(Instant::now);
+                observer.packets += 1;
+                observer.min_speed = observer.min_speed.min(packet.speed);
+                observer.max_speed = observer.max_speed.max(packet.speed);
+
+                if observer.packets >= OBSERVED_PACKETS {
+                    observer.is_done = true;
+
+                    let elapsed = start.elapsed().as_secs_f64() as Float;
+                    let info = DeviceInfo {
+                        min_speed: Some(observer.min_speed),
+                        max_speed: Some(observer.max_speed),
+                        packet_rate: (elapsed > 0.0).then(|| (observer.packets - 1) as Float / elapsed),
+                        ..DeviceInfo::default()
+                    };
+
+                    #[cfg(feature = "log")]
+                    info!("Synthesized device info {:?}", info);
+
+                    self.inner.send(Ok(LidarDriverMessage::DeviceInfo(info)))?;
+                }
+            }
+        }
+
+        self.inner.send(message)
+    }
+
+    fn dropped(&self) -> u64 {
+        self.inner.dropped()
+    }
+
+    fn backlog(&self) -> Option<Backlog> {
+        self.inner.backlog()
+    }
+}
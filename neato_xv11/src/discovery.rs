@@ -0,0 +1,122 @@
+use std::path::PathBuf;
+
+/// Device name prefixes of serial ports under `/dev`.
+#[cfg(target_os = "linux")]
+const PREFIXES: &[&str] = &["ttyUSB", "ttyACM", "ttyAMA", "ttyS", "serial"];
+
+/// Device name prefixes of serial ports under `/dev`.
+/// The callout (`cu.`) devices are used since opening them does not wait for carrier detect.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+const PREFIXES: &[&str] = &["cu."];
+
+/// Device name prefixes of serial ports under `/dev`.
+/// `cuaU` are USB adapters, `cuau` are on-board UARTs. Both are callout devices.
+#[cfg(any(target_os = "freebsd", target_os = "dragonfly", target_os = "openbsd"))]
+const PREFIXES: &[&str] = &["cuaU", "cuau", "cua0", "cua1"];
+
+/// Device name prefixes of serial ports under `/dev`.
+#[cfg(target_os = "netbsd")]
+const PREFIXES: &[&str] = &["dtyU", "dty0"];
+
+/// Device name prefixes of serial ports under `/dev`.
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "macos", target_os = "ios", target_os = "freebsd",
+                        target_os = "dragonfly", target_os = "openbsd", target_os = "netbsd"))))]
+const PREFIXES: &[&str] = &["tty"];
+
+/// ## Summary
+///
+/// List the serial ports a LIDAR may be connected to.
+///
+/// ## Remarks
+///
+/// On Unix the device nodes under `/dev` are matched against the names used by the
+/// platform (`ttyUSB*`/`ttyACM*`/`serial*` on Linux, `cu.*` on macOS, `cuaU*`/`cuau*`
+/// on FreeBSD and OpenBSD, `dtyU*` on NetBSD). On Windows `COM1` to `COM256` are probed,
+/// including the ports currently in use by another process.
+///
+/// The returned names can be passed to `run` as is. They are sorted by name.
+///
+/// ## Example
+///
+/// ```no_run
+/// for port in neato_xv11::discovery::available_ports() {
+///     println!("{}", port.display());
+/// }
+/// ```
+#[cfg(unix)]
+pub fn available_ports() -> Vec<PathBuf> {
+    use std::os::unix::fs::FileTypeExt;
+
+    let entries = match std::fs::read_dir("/dev") {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let paths = entries.filter_map(|entry| entry.ok()).map(|entry| entry.path());
+
+    // Skip directories such as /dev/serial/by-id. Follow symlinks such as /dev/serial0.
+    matching_ports(paths, |path| std::fs::metadata(path).map(|m| m.file_type().is_char_device()).unwrap_or(false))
+}
+
+/// ## Summary
+///
+/// Keep the character devices named like a serial port of the platform, sorted by name.
+///
+/// ## Parameters
+///
+/// paths: The device nodes, e.g. the entries of `/dev`.
+///
+/// is_char_device: Whether a path is a character device. Only called for matching names.
+///
+#[cfg(unix)]
+pub(crate) fn matching_ports<I, F>(paths: I, is_char_device: F) -> Vec<PathBuf>
+where
+    I: IntoIterator<Item = PathBuf>,
+    F: Fn(&std::path::Path) -> bool,
+{
+    let mut ports: Vec<PathBuf> = paths.into_iter()
+        .filter(|path| {
+            let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+            PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+        })
+        .filter(|path| is_char_device(path))
+        .collect();
+
+    ports.sort();
+    ports
+}
+
+/// ## Summary
+///
+/// List the serial ports a LIDAR may be connected to.
+///
+/// ## Remarks
+///
+/// `COM1` to `COM256` are probed, including the ports currently in use by another process.
+///
+#[cfg(windows)]
+pub fn available_ports() -> Vec<PathBuf> {
+    use std::fs::OpenOptions;
+
+    probed_ports(|name| {
+        OpenOptions::new().read(true).write(true).open(format!(r"\\.\{}", name)).map(|_| ()).map_err(|e| e.kind())
+    })
+}
+
+/// ## Summary
+///
+/// Keep the ports from `COM1` to `COM256` that `probe` opens, or that are in use by
+/// another process (`ErrorKind::PermissionDenied`).
+///
+#[cfg(windows)]
+pub(crate) fn probed_ports<F: FnMut(&str) -> Result<(), std::io::ErrorKind>>(mut probe: F) -> Vec<PathBuf> {
+    (1..=256)
+        .map(|number| format!("COM{}", number))
+        .filter(|name| match probe(name) {
+            Ok(()) => true,
+            // The port exists but is in use.
+            Err(kind) => kind == std::io::ErrorKind::PermissionDenied,
+        })
+        .map(PathBuf::from)
+        .collect()
+}
@@ -1,184 +1,606 @@
+use std::any::Any;
+use std::collections::VecDeque;
 use std::ffi::OsStr;
-use std::sync::mpsc::{Sender, Receiver, TryRecvError};
-use std::time::Duration;
-use std::vec::Vec;
+use std::io::{Error as IoError, ErrorKind, Read, Write};
+use std::ops::ControlFlow;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Receiver, SendError, Sender, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
 #[cfg(feature = "log")]
-use log::{info, warn, error};
+use log::{debug, error, info, trace, warn};
 
+#[cfg(not(windows))]
 use serial::prelude::*;
 
+use super::builder::{PortConfig, ThreadConfig};
+use super::clock::{Clock, SystemClock};
+use super::control::{CommandSource, StopToken};
+use super::error::ErrorContext;
+use super::error_policy::{ErrorPolicy, ErrorTracker, Verdict};
+use super::message::Hexdump;
+use super::motor::{MotorController, MotorRegulator};
+use super::pcap::PcapWriter;
+use super::port;
 use super::prelude::*;
+use super::protocol::LidarProtocol;
+use super::reconnect::{Backoff, Reconnect};
+use super::recording::LogWriter;
+use super::ring::{self, Consumer, Producer};
+use super::sched;
+use super::sink::CallbackSink;
+use super::status::StatsAccumulator;
+use super::sync::{SyncConfig, SyncMonitor};
 
 
-/// Default Neato XV-11 LIDAR settings.
-const SETTINGS: serial::PortSettings = serial::PortSettings {
-    baud_rate: serial::Baud115200,
-    char_size: serial::CharSize::Bits8,
-    parity: serial::Parity::ParityNone,
-    stop_bits: serial::StopBits::Stop1,
-    flow_control: serial::FlowControl::FlowNone,
-};
+/// Neato XV-11 LIDAR settings at the given baud rate.
+fn settings(baud_rate: u32) -> serial::PortSettings {
+    serial::PortSettings {
+        baud_rate: serial::BaudRate::from_speed(baud_rate as usize),
+        char_size: serial::CharSize::Bits8,
+        parity: serial::Parity::ParityNone,
+        stop_bits: serial::StopBits::Stop1,
+        flow_control: serial::FlowControl::FlowNone,
+    }
+}
+
+/// Size of the ring buffer between the reader and parser threads (about 1.4 s of data).
+const RING_CAPACITY: usize = 16384;
+
+/// Maximum number of bytes read from the serial port at once.
+const READ_CHUNK_SIZE: usize = 256;
+
+/// Default timeout of a single read. Bounds how long stopping the reader thread takes.
+pub(crate) const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Default time the port may stay silent before `LidarDriverError::ReadTimeout` is reported.
+pub(crate) const SILENCE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Longest wait for `PortConfig::quiet_gap`. The port is used anyway afterwards.
+const QUIET_GAP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Default longest time the parser thread waits for data or reader events before checking
+/// for commands. Data and events wake it immediately.
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Longest sleep of the reader thread while waiting to reconnect. Bounds how long stopping takes.
+const RECONNECT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Minimum time between two `LidarDriverMessage::Hexdump` messages.
+const HEXDUMP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Events sent from the reader thread to the parser thread.
+enum ReaderEvent {
+    // The port was opened, bytes will be written to the ring buffer.
+    Opened,
+    // The port could not be opened. The reader thread has exited.
+    Failed(LidarDriverError),
+    // A non-fatal error occured (e.g. a read failed). The reader thread keeps running.
+    Error(LidarDriverError),
+    // The port was disconnected and is reopened after the delay.
+    Reconnecting { attempt: u32, delay: Duration },
+    // The port was reopened after a disconnection.
+    Reconnected { attempts: u32 },
+}
+
+/// Target of the log records when `PortConfig::log_target` is not set.
+#[cfg(feature = "log")]
+const DEFAULT_LOG_TARGET: &str = "neato_xv11";
 
 /// ## Summary
 /// 
-/// Calculate the checksum using the first 20 bytes of the packet.
+/// Identifies a driver instance in its log records and error contexts, so the
+/// records of several LIDARs can be told apart.
 /// 
-/// ## Remarks
-/// 
-/// The slice must be 20 bytes in size.
-/// 
-pub(crate) fn calc_checksum(data : &[u8]) -> u32 {
-    let mut chk32 : u32 = 0;
+#[derive(Clone, Debug)]
+pub(crate) struct LogContext {
+    // Target of the log records.
+    #[cfg(feature = "log")]
+    target: String,
+    // Name of the port, added to every record as the `port` key.
+    port: String,
+}
 
-    for i in 0..10 {
-        // Group the data by word, little-endian
-        let lsb = data[2 * i] as u32;
-        let msb = data[2 * i + 1] as u32;
-        let val = (msb << 8) | lsb;
-        // compute the checksum on 32 bits
-        chk32 = (chk32 << 1) + val;
+impl LogContext {
+    /// ## Summary
+    /// 
+    /// Initialize a context for the given port, logging to `target` or the crate name.
+    /// 
+    #[cfg_attr(not(feature = "log"), allow(unused_variables))]
+    pub(crate) fn new(port: &OsStr, target: Option<&str>) -> Self {
+        LogContext {
+            #[cfg(feature = "log")]
+            target: target.unwrap_or(DEFAULT_LOG_TARGET).to_string(),
+            port: port.to_string_lossy().into_owned(),
+        }
     }
+}
 
-    // Wrap around to fit into 15 bits
-    let mut check_sum = (chk32 & 0x7FFF) + (chk32 >> 15);
-    // Truncate to 15 bits
-    check_sum &= 0x7FFF;
-    // Return the checksum
-    check_sum
+impl Default for LogContext {
+    fn default() -> Self {
+        LogContext::new(OsStr::new(""), None)
+    }
 }
 
 /// ## Summary
 /// 
-/// Parse encoded LIDAR packet.
+/// Settings of `run_source`.
 /// 
-pub(crate) fn parse_packet(buffer: &[u8; 22]) -> Result<LidarDriverMessage, LidarDriverError> {
-    let mut readings = Vec::with_capacity(4);
+#[derive(Default)]
+pub(crate) struct RunOptions {
+    // Settings applied to the calling thread and the reader thread.
+    pub(crate) thread: ThreadConfig,
+    // Report `LidarDriverError::NoData` or `LidarDriverError::MotorStalled` when no valid packet is parsed for this long.
+    pub(crate) watchdog: Option<Duration>,
+    // Wrap the errors in `LidarDriverError::Context`.
+    pub(crate) error_context: bool,
+    // Identifies the driver in its log records and error contexts.
+    pub(crate) log: LogContext,
+    // Receives a copy of every byte read from the port.
+    pub(crate) raw_tap: Option<Box<dyn Write + Send>>,
+    // Receives every read as a timestamped log record.
+    pub(crate) log_tap: Option<Box<dyn Write + Send>>,
+    // Receives every valid packet as a pcap frame.
+    pub(crate) pcap_tap: Option<Box<dyn Write + Send>>,
+    // Stops the driver like `LidarDriverCommand::Stop`.
+    pub(crate) stop: Option<StopToken>,
+    // Timeouts of the driver threads.
+    pub(crate) timing: Timing,
+    // Regulates the motor from the reported spin speed.
+    pub(crate) motor: Option<MotorRegulator<Box<dyn MotorController + Send>>>,
+    // Reopens the port after a disconnection.
+    pub(crate) reconnect: Option<Reconnect>,
+    // Reports the search for the packet boundaries.
+    pub(crate) sync: SyncConfig,
+    // Send `LidarDriverMessage::Resumed` when the driver runs again after a pause.
+    pub(crate) report_resume: bool,
+    // Shuts the driver down on too many errors, or downgrades them to warnings.
+    pub(crate) error_policy: ErrorPolicy,
+    // Time of the watchdog, sync timeout, silence timeout and reconnection backoff.
+    // `None` for the system clock.
+    pub(crate) clock: Option<Arc<dyn Clock>>,
+}
 
-    // Packet index | Range = [0,89].
-    let index = buffer[1] as usize;
-    let index = index - 0xA0;
+/// ## Summary
+/// 
+/// Timeouts of the driver threads, set with `PortConfig`.
+/// 
+#[derive(Clone, Copy)]
+pub(crate) struct Timing {
+    // How long the port may stay silent before `LidarDriverError::ReadTimeout` is reported.
+    pub(crate) silence_timeout: Duration,
+    // Longest time the parser thread waits for data or reader events before checking for commands.
+    pub(crate) poll_interval: Duration,
+}
 
-    // Lidar Speed.
-    let msb = buffer[3] as u32;
-    let lsb = buffer[2] as u32;
-    let speed = ((msb << 8) | lsb) as f64 / 64.0;
+impl Default for Timing {
+    fn default() -> Self {
+        Timing {
+            silence_timeout: SILENCE_TIMEOUT,
+            poll_interval: POLL_INTERVAL,
+        }
+    }
+}
 
-    // Verify the packet's integrity.
-    let msb = buffer[21] as u32;
-    let lsb = buffer[20] as u32;
+/// Flags and clock shared between the parser thread and the reader thread.
+struct ReaderControl {
+    // Discard bytes instead of writing them to the ring buffer.
+    is_paused: AtomicBool,
+    // Exit the reader thread.
+    is_stopped: AtomicBool,
+    // Time of both threads.
+    clock: Arc<dyn Clock>,
+    // Reference for `last_read`.
+    epoch: Instant,
+    // When bytes were last read, in nanoseconds since `epoch`.
+    last_read: AtomicU64,
+}
 
-    // Generate the expected checksum.
-    let expected_checksum = (msb << 8) | lsb;
-    let calc_checksum = calc_checksum(&buffer[0..20]);
+impl ReaderControl {
+    fn new(clock: Arc<dyn Clock>) -> Self {
+        ReaderControl {
+            is_paused: AtomicBool::new(false),
+            is_stopped: AtomicBool::new(false),
+            epoch: clock.now(),
+            clock,
+            last_read: AtomicU64::new(0),
+        }
+    }
 
-    if calc_checksum != expected_checksum {
-        #[cfg(feature = "log")]
-        error!("A checksum error occured. The data is corrupted");
+    /// Record that bytes were just read.
+    fn mark_read(&self) {
+        let now = self.clock.now();
+        self.last_read.store(now.saturating_duration_since(self.epoch).as_nanos() as u64, Ordering::Release);
+    }
 
-        // Checksum error occured. The data is corrupted.
-        return Err(LidarDriverError::Checksum(index));
+    /// When bytes were last read.
+    fn last_read(&self) -> Instant {
+        self.epoch + Duration::from_nanos(self.last_read.load(Ordering::Acquire))
     }
+}
+
+/// Time from reading packets to sending them, reported in `DriverStatus`.
+#[derive(Default)]
+struct SendLatency {
+    // Sum of the latencies.
+    total: Duration,
+    // Longest latency.
+    max: Duration,
+    // Number of packets sent.
+    count: u32,
+}
+
+impl SendLatency {
+    fn push(&mut self, latency: Duration) {
+        self.total += latency;
+        self.max = self.max.max(latency);
+        self.count += 1;
+    }
+
+    /// Mean and longest latency, if any packet was sent.
+    fn take(&mut self) -> (Option<Duration>, Option<Duration>) {
+        let latency = core::mem::take(self);
 
-    for i in 1..5 {
-        let byte_index = 4 * i;
-        let reading_index = 4 * index + i - 1;
-
-        // The first 2 bytes are two flags + distance.
-        let msb = buffer[byte_index + 1] as i32;
-        let lsb = buffer[byte_index] as i32;
-        let distance = (msb << 8) | lsb;
-
-        // The next 2 bytes are the reliability (higher # = more reliable reading).
-        let msb = buffer[byte_index + 3] as i32;
-        let lsb = buffer[byte_index + 2] as i32;
-        let quality = (msb << 8) | lsb;
-
-        if distance & 0x8000 > 0 {
-            // Invalid data flag triggered. LSB contains error code.
-            let error_code = distance & 0x00FF;
-            readings.push(LidarReading::new(reading_index, distance, quality, Some(LidarReadingError::InvalidDataError(error_code))));
-        } else if distance & 0x4000 > 0 {
-            // Signal strength warning flag triggered. Remove flag before recording.
-            let distance = distance & 0x3FFF;
-            readings.push(LidarReading::new(reading_index, distance, quality, Some(LidarReadingError::SignalStrengthWarning)));
-        } else {
-            // No flag triggered. Write distance to readings.
-            readings.push(LidarReading::new(reading_index, distance, quality, None));
+        match latency.count {
+            0 => (None, None),
+            count => (Some(latency.total / count), Some(latency.max)),
         }
     }
-    
-    Ok(LidarDriverMessage::Packet(LidarPacket::new(readings, speed)))
 }
 
 /// ## Summary
 /// 
-/// Read from the serial port. Send read errors to the async channel.
-/// 
-/// ## Parameters
+/// Open and configure the serial port.
 /// 
-/// port: The port to read from.
-/// 
-/// buffer: The buffer to read to. The size of the slice will be the read size.
+/// ## Remarks
 /// 
-/// tx: Send channel to write to in the event of a read error.
+/// Opening a missing or busy port is attempted up to `config.open_attempts` times.
 /// 
-fn read<T: SerialPort>(port: &mut T, mut buffer: &mut [u8], tx: &Sender<Result<LidarDriverMessage, LidarDriverError>>) -> Result<(), ()> {
-    port.read_exact(&mut buffer).map_err(|e| {
+pub(crate) fn open_port(port_name: &OsStr, config: &PortConfig) -> Result<port::SystemPort, LidarDriverError> {
+    let port_name = port::normalize_port_name(port_name);
+    #[cfg(feature = "log")]
+    let log = LogContext::new(&port_name, config.log_target.as_deref());
+    let mut attempt = 1;
+
+    // Open the serial port.
+    let mut port = loop {
+        let err = match port::open(&port_name) {
+            Ok(port) => break port,
+            Err(err) => port::open_error(&port_name, err),
+        };
+
+        if attempt >= config.open_attempts || !port::is_retryable(&err) {
+            #[cfg(feature = "log")]
+            error!(target: &log.target, port = log.port.as_str(); "Unable to open serial port. {}", err);
+
+            return Err(err);
+        }
+
+        #[cfg(feature = "log")]
+        warn!(target: &log.target, port = log.port.as_str(); "Unable to open serial port, retrying ({}/{}). {}", attempt, config.open_attempts, err);
+
+        attempt += 1;
+        thread::sleep(config.open_retry_delay);
+    };
+
+    #[cfg(feature = "log")]
+    info!(target: &log.target, port = log.port.as_str(); "Successfully opened serial port");
+
+    if config.exclusive {
+        port::lock_exclusive(&port).map_err(|err| {
+            #[cfg(feature = "log")]
+            error!(target: &log.target, port = log.port.as_str(); "Unable to lock serial port. {}", err);
+
+            match err.kind() {
+                ErrorKind::WouldBlock => LidarDriverError::PortBusy,
+                _ => LidarDriverError::Configure(serial::Error::new(serial::ErrorKind::Io(err.kind()), err.to_string())),
+            }
+        })?;
+
+        #[cfg(feature = "log")]
+        info!(target: &log.target, port = log.port.as_str(); "Successfully locked serial port");
+    }
+
+    // Set a short timeout so the reader thread notices a stop request quickly.
+    port.set_timeout(config.read_timeout).map_err(|err| {
+        #[cfg(feature = "log")]
+        error!(target: &log.target, port = log.port.as_str(); "Unable to set timeout. {}", err);
+
+        LidarDriverError::SetTimeout(err)
+    })?;
+
+    #[cfg(feature = "log")]
+    info!(target: &log.target, port = log.port.as_str(); "Successfully set the timeout");
+
+    // Configure the serial port.
+    port.configure(&settings(config.baud_rate)).map_err(|err| {
+        #[cfg(feature = "log")]
+        error!(target: &log.target, port = log.port.as_str(); "Unable to configure serial port. {}", err);
+
+        LidarDriverError::Configure(err)
+    })?;
+
+    #[cfg(feature = "log")]
+    info!(target: &log.target, port = log.port.as_str(); "Successfully configured the serial port");
+
+    if config.low_latency {
+        port::set_low_latency(&port).map_err(|err| {
+            #[cfg(feature = "log")]
+            error!(target: &log.target, port = log.port.as_str(); "Unable to set low latency. {}", err);
+
+            LidarDriverError::Configure(serial::Error::new(serial::ErrorKind::Io(err.kind()), err.to_string()))
+        })?;
+
         #[cfg(feature = "log")]
-        error!("Unable to read from serial port. {}", e);
+        info!(target: &log.target, port = log.port.as_str(); "Successfully set low latency");
+    }
 
-        // Consume error into wrapper.
-        let serial_error = LidarDriverError::SerialRead(e);
-        
-        // Report error to the calling program.
-        // We don't care about the result since being unable to read is a fatal error.
-        let _ = send_message(&tx, Err(serial_error));
-    })
+    // The serial backend discards the bytes buffered by the OS when the port is configured.
+    // Bytes still held by the USB-serial adapter are discarded here.
+    if let Some(gap) = config.quiet_gap {
+        port::wait_quiet(&mut port, gap, QUIET_GAP_TIMEOUT).map_err(|err| {
+            #[cfg(feature = "log")]
+            error!(target: &log.target, port = log.port.as_str(); "Unable to read from serial port. {}", err);
+
+            LidarDriverError::SerialRead(err)
+        })?;
+    }
+
+    Ok(port)
 }
 
 /// ## Summary
 /// 
-/// Synchronizes by finding the header of a NeatoXV-11 LIDAR data packet.
+/// Read from the port into the ring buffer until stopped. Runs on the reader thread.
+/// Returns whether the port was disconnected.
 /// 
 /// ## Parameters
 /// 
 /// port: The port to read from.
 /// 
-/// buffer: The buffer to read to.
+/// producer: The ring buffer to write to.
 /// 
-/// tx: Send channel to write to in the event of a read error.
+/// events: Send channel to write to in the event of a read error.
 /// 
-fn sync<T: SerialPort>(mut port: &mut T, buffer: &mut [u8; 22], tx: &Sender<Result<LidarDriverMessage, LidarDriverError>>) -> Result<(), ()> {
-    loop {
-        // Sleep for 1 millisecond.
-        std::thread::sleep(Duration::from_micros(100));
-        
-        // Read 1 byte until '0xFA' is found.
-        if let Err(_) = read::<T>(&mut port, &mut buffer[0..1], &tx) {
-            return Err(());
+/// control: Flags set by the parser thread.
+/// 
+/// log: Identifies the driver in its log records.
+/// 
+/// taps: Receive a copy of every byte read. Dropped after their first write error.
+/// 
+/// timing: Timeouts of the driver threads.
+/// 
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+fn read_loop<R: Read>(mut port: R, producer: &Producer, events: &Sender<ReaderEvent>, control: &ReaderControl, log: &LogContext, taps: &mut ReaderTaps, timing: Timing) -> bool {
+    let mut chunk = [0; READ_CHUNK_SIZE];
+    let clock = &*control.clock;
+    // When data was last received, or a timeout last reported.
+    let mut last_activity = clock.now();
+
+    while !control.is_stopped.load(Ordering::Acquire) {
+        // The bytes of a read started while paused are stale, even if the driver resumed since.
+        let was_paused = control.is_paused.load(Ordering::Acquire);
+
+        let error = match port.read(&mut chunk) {
+            Ok(0) => IoError::from(ErrorKind::UnexpectedEof),
+            Ok(count) => {
+                last_activity = clock.now();
+                control.mark_read();
+
+                if !was_paused && !control.is_paused.load(Ordering::Acquire) {
+                    producer.push(&chunk[..count]);
+                }
+
+                taps.write(&chunk[..count], log);
+                continue;
+            },
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) if is_timeout(&err) && clock.now().saturating_duration_since(last_activity) < timing.silence_timeout => continue,
+            Err(err) => err,
+        };
+
+        last_activity = clock.now();
+
+        #[cfg(feature = "log")]
+        error!(target: &log.target, port = log.port.as_str(), error_kind:? = error.kind(); "Unable to read from serial port. {}", error);
+
+        let is_disconnected = port::is_disconnected(&error);
+        let is_end = error.kind() == ErrorKind::UnexpectedEof || is_disconnected;
+        let error = match error.kind() {
+            // The LIDAR is silent, which is usually recoverable (e.g. the motor is off).
+            _ if is_timeout(&error) => LidarDriverError::ReadTimeout,
+            // Reading again would fail forever.
+            _ if is_disconnected => LidarDriverError::DeviceDisconnected(error),
+            _ => LidarDriverError::SerialRead(error),
+        };
+
+        if send_event(events, producer, ReaderEvent::Error(error)).is_err() {
+            // The parser thread is gone.
+            return false;
         }
 
-        if buffer[0] != 0xFA {
-            continue;
+        if is_end {
+            // The port has no more data or was disconnected.
+            return is_disconnected;
         }
 
-        // Read the remaining 21 bytes.
-        if let Err(_) = read::<T>(&mut port, &mut buffer[1..], &tx) {
-            return Err(());
+        // Avoid spinning on a persistent error. A real sleep, a virtual clock would not throttle.
+        thread::sleep(timing.poll_interval);
+    }
+
+    false
+}
+
+/// ## Summary
+/// 
+/// Reopen the port after a disconnection and read from it, again after every further
+/// disconnection, until the policy gives up or the driver stops. Runs on the reader thread.
+/// 
+#[allow(clippy::too_many_arguments)]
+fn reconnect_loop(mut reconnect: Reconnect, producer: &Producer, events: &Sender<ReaderEvent>, control: &ReaderControl, log: &LogContext, taps: &mut ReaderTaps, timing: Timing) {
+    let mut backoff = Backoff::new(reconnect.policy);
+
+    while let Some(delay) = backoff.next_delay() {
+        let attempt = backoff.attempts();
+
+        #[cfg(feature = "log")]
+        warn!(target: &log.target, port = log.port.as_str(), attempt; "Reconnecting in {:?}", delay);
+
+        if send_event(events, producer, ReaderEvent::Reconnecting { attempt, delay }).is_err() {
+            return;
         }
 
-        // Ensure that the next byte is a valid index.
-        if buffer[1] < 0xA0 || buffer[1] > 0xF9 {
-            continue;
+        // Wait in short steps, so stopping the driver is not delayed.
+        let clock = &*control.clock;
+        let deadline = clock.now() + delay;
+
+        while clock.now() < deadline {
+            if control.is_stopped.load(Ordering::Acquire) {
+                return;
+            }
+
+            clock.sleep(deadline.saturating_duration_since(clock.now()).min(RECONNECT_POLL_INTERVAL));
+        }
+
+        let port = match (reconnect.reopen)() {
+            Ok(port) => port,
+            Err(err) => {
+                if send_event(events, producer, ReaderEvent::Error(err)).is_err() {
+                    return;
+                }
+
+                continue;
+            },
+        };
+
+        #[cfg(feature = "log")]
+        info!(target: &log.target, port = log.port.as_str(), attempt; "Reconnected");
+
+        backoff.reset();
+
+        if send_event(events, producer, ReaderEvent::Reconnected { attempts: attempt }).is_err() || !read_loop(port, producer, events, control, log, taps, timing) {
+            return;
         }
-        
-        // In sync, break out of loop.
-        return Ok(());
     }
 }
 
-fn send_message(tx: &Sender<Result<LidarDriverMessage, LidarDriverError>>, result: Result<LidarDriverMessage, LidarDriverError>) -> Result<(), ()> {
+/// Copies of the bytes read, written from the reader thread.
+pub(crate) struct ReaderTaps {
+    // Receives a copy of every byte read.
+    raw: Option<Box<dyn Write + Send>>,
+    // Receives every read as a timestamped log record.
+    log: Option<LogWriter<Box<dyn Write + Send>>>,
+}
+
+impl ReaderTaps {
+    /// Start the taps, dropping the log tap if its header cannot be written.
+    #[cfg_attr(not(feature = "log"), allow(unused_variables))]
+    fn new(raw: Option<Box<dyn Write + Send>>, log_tap: Option<Box<dyn Write + Send>>, log: &LogContext) -> Self {
+        let log_tap = log_tap.map(LogWriter::new).transpose().unwrap_or_else(|err| {
+            #[cfg(feature = "log")]
+            warn!(target: &log.target, port = log.port.as_str(), error_kind:? = err.kind(); "Unable to write to log tap, disabling it. {}", err);
+
+            None
+        });
+
+        ReaderTaps { raw, log: log_tap }
+    }
+
+    /// Write the bytes read to the taps. The captures are best effort, a failing tap is dropped.
+    #[cfg_attr(not(feature = "log"), allow(unused_variables))]
+    fn write(&mut self, chunk: &[u8], log: &LogContext) {
+        if let Some(writer) = &mut self.raw {
+            if let Err(err) = writer.write_all(chunk) {
+                #[cfg(feature = "log")]
+                warn!(target: &log.target, port = log.port.as_str(), error_kind:? = err.kind(); "Unable to write to raw tap, disabling it. {}", err);
+
+                self.raw = None;
+            }
+        }
+
+        if let Some(writer) = &mut self.log {
+            if let Err(err) = writer.write_record(chunk, SystemTime::now()) {
+                #[cfg(feature = "log")]
+                warn!(target: &log.target, port = log.port.as_str(), error_kind:? = err.kind(); "Unable to write to log tap, disabling it. {}", err);
+
+                self.log = None;
+            }
+        }
+    }
+}
+
+/// Send an event to the parser thread and wake it, so the event is not held until the next poll.
+fn send_event(events: &Sender<ReaderEvent>, producer: &Producer, event: ReaderEvent) -> Result<(), SendError<ReaderEvent>> {
+    let result = events.send(event);
+    producer.wake();
+    result
+}
+
+/// ## Summary
+/// 
+/// Forward the errors and reconnections reported by the reader thread, up to the reopening
+/// of the port, as the bytes read after it have not been parsed yet. Returns whether the
+/// port was reopened.
+/// 
+fn forward_reader_events<S: MessageSink, W: Fn(LidarDriverError) -> LidarDriverError>(events: &Receiver<ReaderEvent>, tx: &S, wrap: W, errors: &mut ErrorTracker, is_reconnecting: &mut bool) -> Result<bool, ()> {
+    while let Ok(event) = events.try_recv() {
+        match event {
+            ReaderEvent::Error(err) => send_checked(tx, errors, Err(wrap(err)))?,
+            ReaderEvent::Reconnecting { attempt, delay } => {
+                *is_reconnecting = true;
+                send_message(tx, Ok(LidarDriverMessage::Reconnecting { attempt, delay }))?;
+            },
+            ReaderEvent::Reconnected { attempts } => {
+                *is_reconnecting = false;
+                send_message(tx, Ok(LidarDriverMessage::Reconnected { attempts }))?;
+                return Ok(true);
+            },
+            ReaderEvent::Opened | ReaderEvent::Failed(_) => {},
+        }
+    }
+    Ok(false)
+}
+
+/// ## Summary
+/// 
+/// Send a message through the error policy. Fails if sending failed or the policy shuts
+/// the driver down, after sending `LidarDriverError::TooManyErrors`.
+/// 
+fn send_checked<S: MessageSink>(tx: &S, errors: &mut ErrorTracker, result: Result<LidarDriverMessage, LidarDriverError>) -> Result<(), ()> {
+    match errors.check(result) {
+        Verdict::Send(result) => send_message(tx, result),
+        Verdict::Stop(error) => {
+            #[cfg(feature = "log")]
+            error!("{}, shutting down.", error);
+
+            let _ = send_message(tx, Err(error));
+            Err(())
+        },
+    }
+}
+
+/// ## Summary
+/// 
+/// Wrap an error with the port and the position in the stream, if a port is given.
+/// 
+fn with_context(error: LidarDriverError, port: Option<&str>, offset: u64, last_packet_index: Option<usize>) -> LidarDriverError {
+    match port {
+        Some(port) => LidarDriverError::Context {
+            context: ErrorContext {
+                port: port.to_string(),
+                offset,
+                last_packet_index,
+            },
+            source: Box::new(error),
+        },
+        None => error,
+    }
+}
+
+pub(crate) fn send_message<S: MessageSink>(tx: &S, result: Result<LidarDriverMessage, LidarDriverError>) -> Result<(), ()> {
     #[cfg(feature = "log")]
     return tx.send(result).map_err(|e| {
         error!("Unable to send message. {}", e);
@@ -196,7 +618,8 @@ fn send_message(tx: &Sender<Result<LidarDriverMessage, LidarDriverError>>, resul
 /// 
 /// port_name: The port name to open.
 /// 
-/// tx: Sends decoded LIDAR messages or error encountered.
+/// tx: Sends decoded LIDAR messages or error encountered. Either a `std::sync::mpsc::Sender`
+/// or a `queue::BoundedSender` for a bounded queue with an overflow policy.
 /// 
 /// rx: Receives commands from the calling program.
 /// 
@@ -222,75 +645,450 @@ fn send_message(tx: &Sender<Result<LidarDriverMessage, LidarDriverError>>, resul
 ///     neato_xv11::run("/dev/serial0", message_tx, command_rx);
 /// });
 /// ```
-pub fn run<T: AsRef<OsStr> + ?Sized> (port_name: &T, tx: Sender<Result<LidarDriverMessage, LidarDriverError>>, rx: Receiver<LidarDriverCommand>) {
-    let mut port;
-    
-    // Open the serial port.
-    match serial::open(port_name) {
-        Ok(p) => {
-            #[cfg(feature = "log")]
-            info!("Successfully opened serial port");
+pub fn run<T: AsRef<OsStr> + ?Sized, S: MessageSink, C: CommandSource> (port_name: &T, tx: S, rx: C) {
+    let port_name = port_name.as_ref().to_os_string();
 
-            // Initialize the serial port.
-            port = p
-        },
-        Err(err) => {
+    let options = RunOptions { log: LogContext::new(&port_name, None), ..RunOptions::default() };
+
+    run_source(move || open_port(&port_name, &PortConfig::default()), Parser::new(), tx, rx, options);
+}
+
+/// ## Summary
+/// 
+/// Begin reading LIDAR data with the given port settings.
+/// 
+/// ## Parameters
+/// 
+/// port_name: The port name to open.
+/// 
+/// config: Settings applied to the serial port.
+/// 
+/// tx: Sends decoded LIDAR messages or error encountered.
+/// 
+/// rx: Receives commands from the calling program.
+/// 
+/// ## Example
+/// 
+/// ```no_run
+/// # use std::sync::mpsc::channel;
+/// # use std::time::Duration;
+/// use neato_xv11::PortConfig;
+/// 
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+/// 
+/// // Wait up to 10 s for the USB adapter to enumerate.
+/// let config = PortConfig {
+///     open_attempts: 10,
+///     open_retry_delay: Duration::from_secs(1),
+///     ..PortConfig::default()
+/// };
+/// 
+/// neato_xv11::run_with_config("/dev/ttyUSB0", &config, message_tx, command_rx);
+/// ```
+pub fn run_with_config<T: AsRef<OsStr> + ?Sized, S: MessageSink, C: CommandSource>(port_name: &T, config: &PortConfig, tx: S, rx: C) {
+    let port_name = port_name.as_ref().to_os_string();
+    let config = config.clone();
+
+    let options = RunOptions {
+        log: LogContext::new(&port_name, config.log_target.as_deref()),
+        timing: config.timing(),
+        ..RunOptions::default()
+    };
+
+    run_source(move || open_port(&port_name, &config), Parser::new(), tx, rx, options);
+}
+
+/// ## Summary
+/// 
+/// Begin reading LIDAR data, handing every message to a closure on the calling thread
+/// instead of a channel.
+/// 
+/// ## Parameters
+/// 
+/// port_name: The port name to open.
+/// 
+/// config: Settings applied to the serial port.
+/// 
+/// callback: Receives decoded LIDAR messages or error encountered. Return
+/// `ControlFlow::Break` to stop the driver.
+/// 
+/// ## Remarks
+/// 
+/// The driver takes no commands. Returns once the callback breaks, the port cannot be
+/// opened or is disconnected. The callback is not called again after it breaks, so it
+/// receives `LidarDriverMessage::Shutdown` only when the driver stops on its own.
+/// 
+/// ## Example
+/// 
+/// ```no_run
+/// use std::ops::ControlFlow;
+/// use neato_xv11::prelude::*;
+/// use neato_xv11::PortConfig;
+/// 
+/// let mut revolutions = 0;
+/// 
+/// neato_xv11::run_with_callback("/dev/ttyUSB0", &PortConfig::default(), |message| {
+///     if let Ok(LidarDriverMessage::Packet(packet)) = message {
+///         if packet.readings[0].index == 0 {
+///             revolutions += 1;
+///         }
+///     }
+/// 
+///     if revolutions < 10 { ControlFlow::Continue(()) } else { ControlFlow::Break(()) }
+/// });
+/// ```
+pub fn run_with_callback<T, F>(port_name: &T, config: &PortConfig, callback: F)
+where
+    T: AsRef<OsStr> + ?Sized,
+    F: FnMut(Result<LidarDriverMessage, LidarDriverError>) -> ControlFlow<()>,
+{
+    let port_name = port_name.as_ref().to_os_string();
+    let config = config.clone();
+    let stop = StopToken::new();
+    let tx = CallbackSink::new(callback, stop.clone());
+    // Nothing sends commands, but a closed channel would stop the driver.
+    let (_command_tx, command_rx) = channel();
+
+    let options = RunOptions {
+        log: LogContext::new(&port_name, config.log_target.as_deref()),
+        timing: config.timing(),
+        stop: Some(stop),
+        ..RunOptions::default()
+    };
+
+    run_source(move || open_port(&port_name, &config), Parser::new(), tx, command_rx, options);
+}
+
+/// Whether a read timed out. Sockets report their read timeout as `WouldBlock` on Unix.
+fn is_timeout(err: &IoError) -> bool {
+    matches!(err.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock)
+}
+
+/// ## Summary
+/// 
+/// A source of LIDAR bytes, such as a serial port, a file, a socket or a test double.
+/// Implemented for every `Read + Send` type.
+/// 
+/// ## Remarks
+/// 
+/// Stopping the driver waits for the read in progress, give sockets and pipes a read
+/// timeout (e.g. `TcpStream::set_read_timeout`). Timeouts are reported like those of
+/// the serial port, as `LidarDriverError::ReadTimeout` once the source stays silent.
+/// 
+pub trait LidarSource: Read + Send {}
+
+impl<T: Read + Send> LidarSource for T {}
+
+/// ## Summary
+/// 
+/// Begin reading XV-11 data from any source instead of a serial port.
+/// 
+/// ## Parameters
+/// 
+/// source: The source of bytes. Read on a dedicated reader thread.
+/// 
+/// tx: Sends decoded LIDAR messages or error encountered.
+/// 
+/// rx: Receives commands from the calling program.
+/// 
+/// ## Remarks
+/// 
+/// Read errors are reported like serial errors. The driver shuts down when the source
+/// reaches its end or reports a disconnection (e.g. `ErrorKind::BrokenPipe`).
+/// 
+pub fn run_from_source<R: LidarSource + 'static, S: MessageSink, C: CommandSource>(source: R, tx: S, rx: C) {
+    run_source(move || Ok(source), Parser::new(), tx, rx, RunOptions::default());
+}
+
+/// ## Summary
+/// 
+/// Read LIDAR data from a source opened on a dedicated reader thread.
+/// The calling thread parses the data and processes commands.
+/// 
+/// ## Parameters
+/// 
+/// open: Opens the source. Called on the reader thread.
+/// 
+/// parser: Decodes the packets read from the source.
+/// 
+/// tx: Sends decoded LIDAR messages or error encountered.
+/// 
+/// rx: Receives commands from the calling program.
+/// 
+/// options: Thread settings, watchdog and log context.
+/// 
+/// ## Remarks
+/// 
+/// The reader thread only copies bytes into a lock-free ring buffer, so slow
+/// parsing or a slow consumer never causes serial overruns. Bytes that do not
+/// fit in the ring buffer are counted in `DriverStatus::overrun_bytes`.
+/// 
+/// A panic on either thread is reported as `LidarDriverError::DriverPanicked`,
+/// followed by `LidarDriverMessage::Shutdown`.
+/// 
+pub(crate) fn run_source<R, F, P, S, C>(open: F, parser: Parser<P>, tx: S, rx: C, options: RunOptions)
+where
+    R: Read,
+    F: FnOnce() -> Result<R, LidarDriverError> + Send + 'static,
+    P: LidarProtocol,
+    S: MessageSink,
+    C: CommandSource,
+{
+    // The calling program must not wait forever on a driver killed by a bug (e.g. in a sink).
+    // The parser is not used after a panic, only the error and shutdown are sent.
+    if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| drive(open, parser, &tx, rx, options))) {
+        let message = panic_message(payload);
+
+        #[cfg(feature = "log")]
+        error!("The driver panicked: {}", message);
+
+        // The sink may be what panicked. If it panics again, it is dropped without the
+        // shutdown message and the calling program sees its channel disconnect instead.
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let _ = send_message(&tx, Err(LidarDriverError::DriverPanicked(message)));
+            let _ = send_message(&tx, Ok(LidarDriverMessage::Shutdown));
+        }));
+
+        if result.is_err() {
             #[cfg(feature = "log")]
-            error!("Unable to open serial port. {}", err);
+            error!("The message sink panicked again while reporting the panic.");
+        }
+    }
+}
+
+/// The body of `run_source`, which catches its panics.
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+fn drive<R, F, P, S, C>(open: F, mut parser: Parser<P>, tx: &S, mut rx: C, options: RunOptions)
+where
+    R: Read,
+    F: FnOnce() -> Result<R, LidarDriverError> + Send + 'static,
+    P: LidarProtocol,
+    S: MessageSink,
+    C: CommandSource,
+{
+    let (producer, consumer) = ring::channel(RING_CAPACITY, thread::current());
+    let (event_tx, event_rx) = channel();
+    let RunOptions { thread: thread_config, watchdog, error_context, log, raw_tap, log_tap, pcap_tap, stop, timing, mut motor, reconnect, sync, report_resume, error_policy, clock } = options;
+    let clock = clock.unwrap_or_else(|| Arc::new(SystemClock));
+    let control = Arc::new(ReaderControl::new(clock.clone()));
+    // Stops the reader thread if the driver panics.
+    let _stop_reader = StopReader(&control);
+    // Port name attached to the errors, if enabled.
+    let context_port = if error_context { Some(log.port.clone()) } else { None };
 
-            // Unable to open the serial port.
-            let _ = send_message(&tx, Err(LidarDriverError::OpenSerialPort(err)));
+    // Apply the thread settings to the parser thread.
+    if let Err(err) = sched::apply(&thread_config) {
+        if send_message(tx, Err(LidarDriverError::ThreadSettings(err))).is_err() {
             return;
         }
     }
 
-    // Set the timeout.
-    if let Err(err) = port.set_timeout(Duration::from_secs(1)) {
-        #[cfg(feature = "log")]
-        error!("Unable to set timeout. {}", err);
-        
-        // Unable to set the timeout.
-        let _ = send_message(&tx, Err(LidarDriverError::SetTimeout(err)));
-        return;
+    let mut reader = thread::Builder::new();
+
+    if let Some(name) = &thread_config.name {
+        reader = reader.name(format!("{}-reader", name));
     }
 
-    #[cfg(feature = "log")]
-    info!("Successfully set the timeout");
+    let reader_control = control.clone();
+    let reader_log = log.clone();
+    let reader = reader.spawn(move || {
+        let mut taps = ReaderTaps::new(raw_tap, log_tap, &reader_log);
 
-    // Configure the serial port.
-    if let Err(err) = port.configure(&SETTINGS) {
-        #[cfg(feature = "log")]
-        error!("Unable to configure serial port. {}", err);
+        // Apply the thread settings to the reader thread.
+        if let Err(err) = sched::apply(&thread_config) {
+            let _ = event_tx.send(ReaderEvent::Error(LidarDriverError::ThreadSettings(err)));
+        }
+
+        match open() {
+            Ok(port) => {
+                if event_tx.send(ReaderEvent::Opened).is_err() {
+                    return;
+                }
+
+                let is_disconnected = read_loop(port, &producer, &event_tx, &reader_control, &reader_log, &mut taps, timing);
 
-        // Unable to configure the port.
-        let _ = send_message(&tx, Err(LidarDriverError::Configure(err)));
-        return;
+                if let (true, Some(reconnect)) = (is_disconnected, reconnect) {
+                    reconnect_loop(reconnect, &producer, &event_tx, &reader_control, &reader_log, &mut taps, timing);
+                }
+            },
+            Err(err) => {
+                let _ = event_tx.send(ReaderEvent::Failed(err));
+            },
+        }
+    });
+
+    let reader = match reader {
+        Ok(reader) => reader,
+        Err(err) => {
+            #[cfg(feature = "log")]
+            error!(target: &log.target, port = log.port.as_str(); "Unable to spawn reader thread. {}", err);
+
+            let _ = send_message(tx, Err(LidarDriverError::SpawnThread(err)));
+            return;
+        },
+    };
+
+    // Wait for the reader thread to open the port.
+    loop {
+        match event_rx.recv() {
+            Ok(ReaderEvent::Opened) => break,
+            Ok(ReaderEvent::Error(err)) => {
+                let _ = send_message(tx, Err(err));
+            },
+            // Only sent once the port was opened.
+            Ok(ReaderEvent::Reconnecting { .. } | ReaderEvent::Reconnected { .. }) => {},
+            Ok(ReaderEvent::Failed(err)) => {
+                // Unable to open the port.
+                let _ = send_message(tx, Err(err));
+                let _ = reader.join();
+                return;
+            },
+            Err(_) => {
+                #[cfg(feature = "log")]
+                error!(target: &log.target, port = log.port.as_str(); "Reader thread exited unexpectedly");
+
+                let _ = reader.join();
+                return;
+            },
+        }
     }
 
-    #[cfg(feature = "log")]
-    info!("Successfully configured the serial port");
-    
-    // Temporary buffer to hold packet data.
-    let mut buffer : [u8; 22] = [0; 22];
-    // Dictates if synchronization is required.
-    let mut needs_sync = true;
-    // Prevents the driver from reading from the serial port.
+    // Temporary buffer to hold bytes taken from the ring buffer.
+    let mut chunk = [0; READ_CHUNK_SIZE];
+    // Prevents the driver from parsing data.
     let mut is_paused = false;
+    // The port was disconnected and is not reopened yet.
+    let mut is_reconnecting = false;
+    // When a valid packet was last parsed, or the driver last started running.
+    let mut last_packet = clock.now();
+    // Speed reported by the last valid packet since the driver last started running, `None`
+    // until one is parsed. Tells the watchdog a stalled motor from a LIDAR never heard from.
+    let mut last_rpm = None;
+    // Number of bytes parsed and index of the last packet parsed, for the log records and error contexts.
+    let (mut offset, mut last_packet_index) = (0u64, None);
+    // Number of revolutions started, for the log records.
+    #[cfg(feature = "log")]
+    let mut revolution = 0;
+    // When the watchdog next reports the missing packets.
+    let mut next_report = last_packet;
+    // The last frame's worth of bytes and when the next hexdump is due, while hexdumps are enabled.
+    let mut frame = VecDeque::with_capacity(parser.protocol().frame_size());
+    let mut next_hexdump = None;
+    // Receives every valid packet, dropped after its first write error.
+    let mut pcap = pcap_tap.map(PcapWriter::new).transpose().unwrap_or_else(|err| {
+        #[cfg(feature = "log")]
+        warn!(target: &log.target, port = log.port.as_str(), error_kind:? = err.kind(); "Unable to write to pcap tap, disabling it. {}", err);
 
-    loop {
-        // Sleep for 1 millisecond.
-        std::thread::sleep(Duration::from_millis(1));
+        None
+    });
+    // Link quality since the statistics were last queried.
+    let mut stats = StatsAccumulator::default();
+    let mut stats_since = clock.now();
+    // Time from reading packets to sending them, and round trip of the last echo.
+    let mut send_latency = SendLatency::default();
+    let mut echo_latency = None;
+    // Spin speed set by the calling program, or regulated to.
+    let mut target_rpm = motor.as_ref().map(MotorRegulator::target_rpm);
+    // When the motor speed was last regulated.
+    let mut last_regulation = None;
+    // Applies the error policy to every message.
+    let mut errors = ErrorTracker::new(error_policy);
+    // The parser searches for the first packet. A failed send is detected by the next one.
+    let mut sync = SyncMonitor::new(sync, clock.now());
+
+    if let Some(message) = sync.start(clock.now()) {
+        let _ = send_message(tx, Ok(message));
+    }
+
+    if let Some(motor) = &mut motor {
+        motor.start();
+    }
+
+    'driver: loop {
+        if stop.as_ref().is_some_and(StopToken::is_stopped) {
+            #[cfg(feature = "log")]
+            info!(target: &log.target, port = log.port.as_str(); "Stop requested by token");
+            break;
+        }
 
         // Try to receive a command message from the main thread.
         match rx.try_recv() {
             Ok(cmd) => {
                 #[cfg(feature = "log")]
-                info!("Received command {}", cmd);
+                info!(target: &log.target, port = log.port.as_str(); "Received command {}", cmd);
 
                 match cmd {
-                    LidarDriverCommand::Run => is_paused = false,
-                    LidarDriverCommand::Pause => is_paused = true,
+                    LidarDriverCommand::Run => {
+                        if is_paused {
+                            // The reader thread drained the port while paused and drops the read in
+                            // progress. Discard what was buffered before the pause and resync.
+                            consumer.clear();
+                            parser.reset();
+                            last_packet_index = None;
+                            control.is_paused.store(false, Ordering::Release);
+                            is_paused = false;
+                            last_packet = clock.now();
+                            last_rpm = None;
+                            next_report = last_packet;
+
+                            if report_resume && send_message(tx, Ok(LidarDriverMessage::Resumed)).is_err() {
+                                // Sending a message to the calling program failed, shutdown the driver.
+                                break;
+                            }
+
+                            if let Some(message) = sync.start(last_packet) {
+                                if send_message(tx, Ok(message)).is_err() {
+                                    // Sending a message to the calling program failed, shutdown the driver.
+                                    break;
+                                }
+                            }
+                        }
+                    },
+                    LidarDriverCommand::Echo => {
+                        if send_message(tx, Ok(LidarDriverMessage::Echo(clock.now()))).is_err() {
+                            // Sending a message to the calling program failed, shutdown the driver.
+                            break;
+                        }
+                    },
+                    LidarDriverCommand::EchoReceived(sent) => echo_latency = Some(clock.now().saturating_duration_since(sent)),
+                    LidarDriverCommand::Hexdump(enabled) => {
+                        frame.clear();
+                        next_hexdump = if enabled { Some(clock.now()) } else { None };
+                    },
+                    LidarDriverCommand::Pause => {
+                        control.is_paused.store(true, Ordering::Release);
+                        is_paused = true;
+                    },
                     LidarDriverCommand::Stop => break,
+                    LidarDriverCommand::SetTargetRpm(rpm) => {
+                        target_rpm = Some(rpm);
+
+                        if let Some(motor) = &mut motor {
+                            motor.set_target_rpm(rpm);
+                        }
+                    },
+                    LidarDriverCommand::Acknowledge(reply) => {
+                        // The caller may have stopped waiting.
+                        let _ = reply.send(status(tx, &consumer, is_paused, &mut send_latency, echo_latency, target_rpm));
+                    },
+                    LidarDriverCommand::QueryStats => {
+                        let window = clock.now().saturating_duration_since(stats_since);
+                        stats_since = clock.now();
+
+                        if send_message(tx, Ok(LidarDriverMessage::Stats(stats.take(window)))).is_err() {
+                            // Sending a message to the calling program failed, shutdown the driver.
+                            break;
+                        }
+                    },
+                    LidarDriverCommand::QueryStatus => {
+                        let status = status(tx, &consumer, is_paused, &mut send_latency, echo_latency, target_rpm);
+
+                        if send_message(tx, Ok(LidarDriverMessage::Status(status))).is_err() {
+                            // Sending a message to the calling program failed, shutdown the driver.
+                            break;
+                        }
+                    },
                 }
             },
             Err(err) => {
@@ -298,67 +1096,272 @@ pub fn run<T: AsRef<OsStr> + ?Sized> (port_name: &T, tx: Sender<Result<LidarDriv
                     TryRecvError::Empty => {}
                     TryRecvError::Disconnected => {
                         #[cfg(feature = "log")]
-                        error!("Command channel disconnected");
+                        error!(target: &log.target, port = log.port.as_str(); "Command channel disconnected");
                         break;
                     },
                 }
             }
         }
 
-        if is_paused {
-            // Skip reading from serial.
-            continue;
-        }
+        if let (Some(timeout), false) = (watchdog, is_paused) {
+            let now = clock.now();
+
+            if now.duration_since(next_report.max(last_packet)) >= timeout {
+                let since = now.duration_since(last_packet);
+
+                let error = match last_rpm {
+                    Some(last_rpm) => {
+                        #[cfg(feature = "log")]
+                        warn!(target: &log.target, port = log.port.as_str(); "Valid packets stopped arriving, has the motor stalled?");
+
+                        LidarDriverError::MotorStalled { since, last_rpm }
+                    },
+                    None => {
+                        #[cfg(feature = "log")]
+                        warn!(target: &log.target, port = log.port.as_str(); "No valid packet received, is the motor spinning?");
+
+                        LidarDriverError::NoData { since }
+                    },
+                };
 
-        // Clear buffer
-        for element in buffer.iter_mut() {
-            *element = 0;
+                let error = with_context(error, context_port.as_deref(), offset, last_packet_index);
+
+                if send_checked(tx, &mut errors, Err(error)).is_err() {
+                    // Sending a message to the calling program failed, or too many errors, shutdown the driver.
+                    break;
+                }
+
+                // Report again after another timeout.
+                next_report = now;
+            }
         }
 
-        if needs_sync {
-            // Synchronize to ensure every 22 bytes is a valid packet.
-            if let Err(_) = sync(&mut port, &mut buffer, &tx) {
+        if !is_paused && !is_reconnecting {
+            if let Some(error) = sync.poll(clock.now()) {
                 #[cfg(feature = "log")]
-                error!("Unable to sync");
+                warn!(target: &log.target, port = log.port.as_str(); "Not synchronized with the LIDAR, is the motor spinning?");
 
-                // Error syncing.
-                continue;
+                if send_checked(tx, &mut errors, Err(with_context(error, context_port.as_deref(), offset, last_packet_index))).is_err() {
+                    // Sending a message to the calling program failed, or too many errors, shutdown the driver.
+                    break;
+                }
             }
-            needs_sync = false;
         }
-        else {
-            // Read 22 bytes from serial.
-            if let Err(_) = read(&mut port, &mut buffer, &tx) {
-                // Error reading from serial. Try again later.
+
+        let is_closed = consumer.is_closed();
+        // While reconnecting, the bytes of the new port wait for `LidarDriverMessage::Reconnected`.
+        let count = if is_paused || is_reconnecting { 0 } else { consumer.pop(&mut chunk) };
+        // The popped bytes were read then at the latest, the latencies are lower bounds.
+        let read_at = control.last_read();
+
+        if count == 0 {
+            // Every byte read before the errors has been parsed, report them now.
+            let wrap = |err| with_context(err, context_port.as_deref(), offset, last_packet_index);
+
+            let is_reopened = match forward_reader_events(&event_rx, tx, wrap, &mut errors, &mut is_reconnecting) {
+                Ok(is_reopened) => is_reopened,
+                Err(()) => {
+                    // Sending a message to the calling program failed, or too many errors, shutdown the driver.
+                    break;
+                },
+            };
+
+            if is_reopened {
+                // Every byte of the disconnected port has been parsed, resync on the new one.
+                parser.reset();
+                last_packet_index = None;
+                last_rpm = None;
+
+                if let Some(message) = sync.start(clock.now()) {
+                    if send_message(tx, Ok(message)).is_err() {
+                        // Sending a message to the calling program failed, shutdown the driver.
+                        break;
+                    }
+                }
+
                 continue;
             }
-            
-            if buffer[0] != 0xFA || buffer[1] < 0xA0 || buffer[1] > 0xF9 {
-                // The first byte is not '0xFA' or the second byte isn't a valid index.
-                // Resync required.
+
+            if is_closed {
+                // The reader thread has exited.
+                break;
+            }
+
+            // Wait for data or an event from the reader thread, which wake this thread, or
+            // for the next check of the commands.
+            thread::park_timeout(timing.poll_interval);
+            continue;
+        }
+
+        for &byte in &chunk[..count] {
+            offset += 1;
+
+            if next_hexdump.is_some() || pcap.is_some() {
+                if frame.len() == parser.protocol().frame_size() {
+                    frame.pop_front();
+                }
+
+                frame.push_back(byte);
+            }
+
+            let result = match parser.push(byte) {
+                Some(result) => result,
+                None => continue,
+            };
+
+            stats.push(&result);
+
+            if next_hexdump.is_some_and(|due| clock.now() >= due) {
+                let hexdump = Hexdump { offset: offset - frame.len() as u64, bytes: frame.iter().copied().collect() };
+
                 #[cfg(feature = "log")]
-                warn!("Corrupted data, resync required.");
+                debug!(target: &log.target, port = log.port.as_str(), offset; "{}", hexdump);
 
-                if let Err(_) = send_message(&tx, Err(LidarDriverError::ResyncRequired)) {
+                if send_message(tx, Ok(LidarDriverMessage::Hexdump(hexdump))).is_err() {
                     // Sending a message to the calling program failed, shutdown the driver.
-                    break;
-                } else {
-                    needs_sync = true;
-                    continue;
+                    break 'driver;
                 }
+
+                next_hexdump = Some(clock.now() + HEXDUMP_INTERVAL);
             }
-        }
 
-        let result = parse_packet(&buffer);
-        
-        if let Err(_) = send_message(&tx, result) {
-            // Sending a message to the calling program failed, shutdown the driver.
-            break;
+            if let (Ok(_), Some(writer)) = (&result, &mut pcap) {
+                if let Err(err) = writer.write_frame(frame.make_contiguous(), SystemTime::now()) {
+                    // The capture is best effort, keep reading without it.
+                    #[cfg(feature = "log")]
+                    warn!(target: &log.target, port = log.port.as_str(), error_kind:? = err.kind(); "Unable to write to pcap tap, disabling it. {}", err);
+
+                    pcap = None;
+                }
+            }
+
+            if let Ok(packet) = &result {
+                let index = packet.readings.first().map_or(0, |reading| reading.index / packet.readings.len());
+
+                let is_new_revolution = last_packet_index.is_some_and(|last| index < last);
+
+                #[cfg(feature = "log")]
+                if is_new_revolution {
+                    revolution += 1;
+                }
+
+                last_packet = clock.now();
+                last_rpm = Some(packet.speed);
+
+                // Regulate once per revolution, the speed is averaged over it.
+                if let (Some(motor), true) = (&mut motor, is_new_revolution) {
+                    let dt = last_regulation.map_or(0.0, |last: Instant| last_packet.duration_since(last).as_secs_f64() as Float);
+                    let duty = motor.update(packet.speed, dt);
+                    last_regulation = Some(last_packet);
+
+                    #[cfg(feature = "log")]
+                    trace!(target: &log.target, port = log.port.as_str(), revolution, rpm = packet.speed, duty; "Motor regulated");
+                }
+                last_packet_index = Some(index);
+            }
+
+            #[cfg(feature = "log")]
+            match &result {
+                Ok(packet) => {
+                    trace!(target: &log.target, port = log.port.as_str(), packet_index = last_packet_index, revolution, offset, sequence = packet.sequence; "Packet parsed");
+
+                    if packet.missed > 0 {
+                        warn!(target: &log.target, port = log.port.as_str(), packet_index = last_packet_index, revolution, offset, missed = packet.missed; "{} packets missing before this one.", packet.missed);
+                    }
+                },
+                Err(LidarDriverError::Checksum(index, details)) => {
+                    warn!(target: &log.target, port = log.port.as_str(), packet_index = index, revolution, offset, error_kind = "checksum", packet:? = details.map(|details| details.packet); "Checksum error.");
+                },
+                Err(LidarDriverError::ResyncRequired) => {
+                    warn!(target: &log.target, port = log.port.as_str(), packet_index = last_packet_index, revolution, offset, error_kind = "resync"; "Corrupted data, resync required.");
+                },
+                Err(_) => {},
+            }
+
+            // `Synced` is sent before the first packet, `Syncing` after the resync error.
+            let (synced, syncing) = match &result {
+                Ok(_) => (sync.synced(last_packet), None),
+                Err(LidarDriverError::ResyncRequired) => (None, sync.start(clock.now())),
+                Err(_) => (None, None),
+            };
+
+            if let Some(message) = synced {
+                if send_message(tx, Ok(message)).is_err() {
+                    // Sending a message to the calling program failed, shutdown the driver.
+                    break 'driver;
+                }
+            }
+
+            let result = result.map_err(|err| with_context(err, context_port.as_deref(), offset, last_packet_index));
+            let is_packet = result.is_ok();
+
+            if send_checked(tx, &mut errors, result.map(LidarDriverMessage::Packet)).is_err() {
+                // Sending a message to the calling program failed, or too many errors, shutdown the driver.
+                break 'driver;
+            }
+
+            if let Some(message) = syncing {
+                if send_message(tx, Ok(message)).is_err() {
+                    // Sending a message to the calling program failed, shutdown the driver.
+                    break 'driver;
+                }
+            }
+
+            if is_packet {
+                send_latency.push(clock.now().saturating_duration_since(read_at));
+            }
         }
     }
 
     #[cfg(feature = "log")]
-    info!("Shutting down lidar.");
+    info!(target: &log.target, port = log.port.as_str(); "Shutting down lidar.");
+
+    if let Some(motor) = &mut motor {
+        motor.stop();
+    }
+
+    // Stop the reader thread. It exits after its current read returns.
+    control.is_stopped.store(true, Ordering::Release);
+
+    if let Err(payload) = reader.join() {
+        let _ = send_message(tx, Err(LidarDriverError::DriverPanicked(panic_message(payload))));
+    }
 
-    let _ = send_message(&tx, Ok(LidarDriverMessage::Shutdown));
-}
\ No newline at end of file
+    let _ = send_message(tx, Ok(LidarDriverMessage::Shutdown));
+}
+
+/// Snapshot of the driver state. Starts measuring the send latency anew.
+fn status<S: MessageSink>(tx: &S, consumer: &Consumer, is_paused: bool, send_latency: &mut SendLatency, echo_latency: Option<Duration>, target_rpm: Option<Float>) -> DriverStatus {
+    let latency = send_latency.take();
+
+    DriverStatus {
+        is_paused,
+        dropped_messages: tx.dropped(),
+        overrun_bytes: consumer.overruns(),
+        send_latency: latency.0,
+        max_send_latency: latency.1,
+        echo_latency,
+        backlog: tx.backlog(),
+        target_rpm,
+    }
+}
+
+/// Stops the reader thread when dropped.
+struct StopReader<'a>(&'a ReaderControl);
+
+impl Drop for StopReader<'_> {
+    fn drop(&mut self) {
+        self.0.is_stopped.store(true, Ordering::Release);
+    }
+}
+
+/// The message of a panic payload, if it is a string.
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => String::from("unknown panic payload"),
+        },
+    }
+}
@@ -0,0 +1,177 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::channel::Sender;
+use embedded_io_async::Read;
+
+use super::data::{LidarPacket, LidarScan};
+use super::error::EmbeddedLidarError;
+use super::model::Model;
+use super::parser::Parser;
+use super::scan::ScanAssembler;
+
+/// Number of bytes requested from the UART per read.
+const READ_CHUNK_SIZE: usize = 32;
+
+/// ## Summary
+///
+/// A LIDAR connected to an async UART implementing `embedded_io_async::Read`.
+///
+/// ## Remarks
+///
+/// The UART must be configured for 115200 baud, 8 data bits, no parity and one stop bit.
+///
+/// ## Example
+///
+/// ```ignore
+/// static PACKETS: Channel<ThreadModeRawMutex, Result<LidarPacket, EmbeddedLidarError<Error>>, 4> = Channel::new();
+///
+/// #[embassy_executor::task]
+/// async fn lidar_task(uart_rx: BufferedUartRx<'static, UART0>) {
+///     AsyncLidar::new(uart_rx).run(PACKETS.sender()).await;
+/// }
+/// ```
+pub struct AsyncLidar<R> {
+    // The UART receiver.
+    serial: R,
+    // Packet parser.
+    parser: Parser,
+    // Bytes read from the UART but not parsed yet.
+    buffer: [u8; READ_CHUNK_SIZE],
+    // Position of the next unparsed byte in the buffer.
+    position: usize,
+    // Number of valid bytes in the buffer.
+    len: usize,
+}
+
+impl<R: Read> AsyncLidar<R> {
+    /// ## Summary
+    ///
+    /// Initialize a new LIDAR over an async UART receiver.
+    ///
+    pub fn new(serial: R) -> Self {
+        AsyncLidar {
+            serial,
+            parser: Parser::new(),
+            buffer: [0; READ_CHUNK_SIZE],
+            position: 0,
+            len: 0,
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Initialize a new LIDAR of the given model over an async UART receiver.
+    ///
+    pub fn with_model(serial: R, model: Model) -> Self {
+        let mut lidar = Self::new(serial);
+        lidar.parser = Parser::with_model(model);
+        lidar
+    }
+
+    /// ## Summary
+    ///
+    /// Wait for the next complete packet.
+    ///
+    pub async fn read_packet(&mut self) -> Result<LidarPacket, EmbeddedLidarError<R::Error>> {
+        loop {
+            while self.position < self.len {
+                let byte = self.buffer[self.position];
+                self.position += 1;
+
+                if let Some(result) = self.parser.push(byte) {
+                    return result.map_err(EmbeddedLidarError::Packet);
+                }
+            }
+
+            self.position = 0;
+            self.len = self.serial.read(&mut self.buffer).await.map_err(EmbeddedLidarError::Serial)?;
+
+            if self.len == 0 {
+                return Err(EmbeddedLidarError::EndOfStream);
+            }
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Wait for the next complete revolution, assembled from the packets by `assembler`.
+    ///
+    /// ## Remarks
+    ///
+    /// A scan is complete when the packet starting the next revolution arrives. Packet
+    /// errors are returned as they occur; the scan being assembled is kept in the
+    /// assembler, so calling again resumes it. The assembler is passed in rather than
+    /// kept by the LIDAR, since a scan takes several kilobytes without an allocator.
+    ///
+    /// ## Example
+    ///
+    /// ```ignore
+    /// #[embassy_executor::task]
+    /// async fn lidar_task(uart_rx: BufferedUartRx<'static, UART0>) {
+    ///     let mut lidar = AsyncLidar::new(uart_rx);
+    ///     let mut assembler = ScanAssembler::new();
+    ///
+    ///     loop {
+    ///         if let Ok(scan) = lidar.next_scan(&mut assembler).await {
+    ///             info!("{} readings missing", scan.missing());
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub async fn next_scan(&mut self, assembler: &mut ScanAssembler) -> Result<LidarScan, EmbeddedLidarError<R::Error>> {
+        loop {
+            let packet = self.read_packet().await?;
+
+            if let Some(scan) = assembler.push(packet) {
+                return Ok(scan);
+            }
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Read packets forever, sending every packet or error to an Embassy channel.
+    ///
+    /// ## Remarks
+    ///
+    /// Returns when the UART reaches the end of its stream.
+    ///
+    pub async fn run<M: RawMutex, const N: usize>(self, tx: Sender<'_, M, Result<LidarPacket, EmbeddedLidarError<R::Error>>, N>) {
+        self.run_until(tx, &AtomicBool::new(false)).await;
+    }
+
+    /// ## Summary
+    ///
+    /// Read packets until `stop` is set, sending every packet or error to an Embassy channel.
+    ///
+    /// ## Remarks
+    ///
+    /// `stop` is checked after every packet, so a shutdown sequence can stop the task
+    /// without holding its channel, e.g. through a `static STOP: AtomicBool`. Returns
+    /// the LIDAR so the UART can be reused. Dropping the future also stops reading,
+    /// but loses the bytes buffered in the LIDAR.
+    ///
+    pub async fn run_until<M: RawMutex, const N: usize>(mut self, tx: Sender<'_, M, Result<LidarPacket, EmbeddedLidarError<R::Error>>, N>, stop: &AtomicBool) -> Self {
+        while !stop.load(Ordering::Acquire) {
+            let result = self.read_packet().await;
+            let is_end = matches!(result, Err(EmbeddedLidarError::EndOfStream));
+
+            tx.send(result).await;
+
+            if is_end {
+                break;
+            }
+        }
+
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Release the UART receiver.
+    ///
+    pub fn free(self) -> R {
+        self.serial
+    }
+}
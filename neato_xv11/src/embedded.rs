@@ -0,0 +1,83 @@
+use embedded_hal::serial::Read;
+
+use super::data::LidarPacket;
+use super::error::EmbeddedLidarError;
+use super::model::Model;
+use super::parser::Parser;
+
+/// ## Summary
+///
+/// A LIDAR connected to a UART implementing `embedded_hal::serial::Read<u8>`.
+///
+/// ## Remarks
+///
+/// The UART must be configured for 115200 baud, 8 data bits, no parity and one stop bit.
+///
+/// ## Example
+///
+/// ```ignore
+/// let mut lidar = EmbeddedLidar::new(uart_rx);
+///
+/// loop {
+///     match lidar.read_packet() {
+///         Ok(packet) => handle(packet),
+///         Err(nb::Error::WouldBlock) => {}, // Not enough bytes yet.
+///         Err(nb::Error::Other(error)) => report(error),
+///     }
+/// }
+/// ```
+pub struct EmbeddedLidar<R> {
+    // The UART receiver.
+    serial: R,
+    // Packet parser.
+    parser: Parser,
+}
+
+impl<R: Read<u8>> EmbeddedLidar<R> {
+    /// ## Summary
+    ///
+    /// Initialize a new LIDAR over a UART receiver.
+    ///
+    pub fn new(serial: R) -> Self {
+        EmbeddedLidar {
+            serial,
+            parser: Parser::new(),
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Initialize a new LIDAR of the given model over an UART receiver.
+    ///
+    pub fn with_model(serial: R, model: Model) -> Self {
+        let mut lidar = Self::new(serial);
+        lidar.parser = Parser::with_model(model);
+        lidar
+    }
+
+    /// ## Summary
+    ///
+    /// Read the bytes available on the UART and return the next complete packet.
+    ///
+    /// ## Remarks
+    ///
+    /// Never blocks. Returns `nb::Error::WouldBlock` if a complete packet has not been received yet.
+    ///
+    pub fn read_packet(&mut self) -> nb::Result<LidarPacket, EmbeddedLidarError<R::Error>> {
+        loop {
+            let byte = self.serial.read().map_err(|e| e.map(EmbeddedLidarError::Serial))?;
+
+            if let Some(result) = self.parser.push(byte) {
+                return result.map_err(|e| nb::Error::Other(EmbeddedLidarError::Packet(e)));
+            }
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Release the UART receiver.
+    ///
+    pub fn free(self) -> R {
+        self.serial
+    }
+}
@@ -1,7 +1,12 @@
-use std::fmt::{Display, Formatter, Result};
-use std::error::Error;
+#[cfg(feature = "std")]
 use std::io::Error as IoError;
 
+use thiserror::Error;
+
+#[cfg(feature = "std")]
+use super::data::Float;
+
+#[cfg(feature = "std")]
 use serial::Error as SerialError;
 
 #[cfg(feature = "serde")]
@@ -12,52 +17,264 @@ use serde::{Serialize, Deserialize};
 /// A driver error.
 /// These errors are usually very serious.
 /// 
-#[derive(Debug)]
+/// ## Remarks
+/// 
+/// New variants may be added in minor releases, matches must have a wildcard arm.
+/// Two errors are equal if they are the same variant with the same packet index,
+/// duration or I/O error kind.
+/// 
+#[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum LidarDriverError {
-    // Checksum error occured. The associated value is the packet index.
-    Checksum(usize),
+    // Checksum error occured. The associated values are the packet index and, for
+    // XV-11 packets, the raw packet and both checksums.
+    #[error("A checksum error occured at packet index {0}")]
+    Checksum(usize, Option<ChecksumDetails>),
     // Unable to configure serial port.
-    Configure(SerialError),
+    #[cfg(feature = "std")]
+    #[error("Unable to configure serial port")]
+    Configure(#[source] SerialError),
+    // An error with the port and the position in the stream where it occured.
+    // Sent instead of the error itself when enabled in `LidarDriverBuilder::error_context`.
+    #[cfg(feature = "std")]
+    #[error("{source} ({context})")]
+    Context { context: ErrorContext, source: Box<LidarDriverError> },
+    // The device was disconnected (e.g. the USB adapter was unplugged). The driver shuts down.
+    #[cfg(feature = "std")]
+    #[error("The serial device was disconnected")]
+    DeviceDisconnected(#[source] IoError),
+    // The driver panicked (e.g. a bug in a sink or filter). The associated value is the panic
+    // message. The driver shuts down.
+    #[cfg(feature = "std")]
+    #[error("The driver panicked: {0}")]
+    DriverPanicked(String),
+    // No valid packet was parsed for a while, although the driver is running (e.g. the motor is not spinning).
+    #[cfg(feature = "std")]
+    #[error("No valid packet received for {} ms", since.as_millis())]
+    NoData { since: core::time::Duration },
     // Unable to open serial port.
-    OpenSerialPort(SerialError),
+    #[cfg(feature = "std")]
+    #[error("Unable to open serial port")]
+    OpenSerialPort(#[source] SerialError),
+    // Access to the serial port was denied (insufficient permissions or in use by another process).
+    #[cfg(feature = "std")]
+    #[error("Access to the serial port was denied")]
+    PermissionDenied(#[source] SerialError),
+    // The serial port is locked by another process.
+    #[cfg(feature = "std")]
+    #[error("The serial port is locked by another process")]
+    PortBusy,
+    // The serial port does not exist.
+    #[cfg(feature = "std")]
+    #[error("Serial port not found")]
+    PortNotFound(#[source] SerialError),
+    // No supported LIDAR protocol was detected on the serial port.
+    #[cfg(feature = "std")]
+    #[error("No supported LIDAR protocol detected")]
+    ProtocolNotDetected,
+    // No data was received from the serial port for a while (e.g. the motor is off).
+    #[cfg(feature = "std")]
+    #[error("No data received from the serial port")]
+    ReadTimeout,
     // A resync is required.
+    #[error("Resync required")]
     ResyncRequired,
     // Serial read error.
-    SerialRead(IoError),
+    #[cfg(feature = "std")]
+    #[error("Unable to read from serial port")]
+    SerialRead(#[source] IoError),
+    // Serial write error.
+    #[cfg(feature = "std")]
+    #[error("Unable to write to serial port")]
+    SerialWrite(#[source] IoError),
     // Unable to set timeout.
-    SetTimeout(SerialError),
+    #[cfg(feature = "std")]
+    #[error("Unable to set serial port timeout")]
+    SetTimeout(#[source] SerialError),
+    // Unable to spawn the reader thread. The driver shuts down.
+    #[cfg(feature = "std")]
+    #[error("Unable to spawn the reader thread")]
+    SpawnThread(#[source] IoError),
+    // The parser did not find the packet boundaries for a while, although data is read
+    // (e.g. the motor is off or the baud rate is wrong). Reported when enabled in
+    // `LidarDriverBuilder::sync`.
+    #[cfg(feature = "std")]
+    #[error("Not synchronized with the LIDAR for {} ms", since.as_millis())]
+    SyncTimeout { since: core::time::Duration },
+    // Valid packets stopped arriving for a while after flowing (e.g. the motor stalled or
+    // lost power), with the speed the last one reported. Reported instead of `NoData` by
+    // `LidarDriverBuilder::watchdog` once a packet was parsed.
+    #[cfg(feature = "std")]
+    #[error("Motor stalled at {last_rpm} RPM, no valid packet for {} ms", since.as_millis())]
+    MotorStalled { since: core::time::Duration, last_rpm: Float },
+    // Too many errors of a class without a valid packet between them, see
+    // `error_policy::ErrorPolicy`. The driver shuts down.
+    #[cfg(feature = "std")]
+    #[error("Stopped after {count} consecutive {class} errors")]
+    TooManyErrors { class: ErrorClass, count: u32 },
+    // Unable to apply the thread name, priority or affinity. The driver keeps running.
+    #[cfg(feature = "std")]
+    #[error("Unable to apply thread settings")]
+    ThreadSettings(#[source] IoError),
 }
 
-impl Display for LidarDriverError {
-    fn fmt(&self, f: &mut Formatter) -> Result {
+#[cfg(feature = "std")]
+impl LidarDriverError {
+    /// ## Summary
+    /// 
+    /// The error without its context.
+    /// 
+    /// ## Example
+    /// 
+    /// ```
+    /// use neato_xv11::error::{ErrorContext, LidarDriverError};
+    /// 
+    /// let error = LidarDriverError::Context {
+    ///     context: ErrorContext { port: "/dev/ttyUSB0".into(), offset: 4096, last_packet_index: Some(12) },
+    ///     source: Box::new(LidarDriverError::Checksum(13, None)),
+    /// };
+    /// 
+    /// assert_eq!(error.root(), &LidarDriverError::Checksum(13, None));
+    /// assert_eq!(error.to_string(), "A checksum error occured at packet index 13 (port /dev/ttyUSB0, byte 4096, last packet 12)");
+    /// ```
+    pub fn root(&self) -> &LidarDriverError {
         match self {
-            LidarDriverError::Checksum(index) => write!(f, "A checksum error occured at packet index {}", index),
-            LidarDriverError::Configure(_) => write!(f, "Unable to configure serial port"),
-            LidarDriverError::OpenSerialPort(_) => write!(f, "Unable to open serial port"),
-            LidarDriverError::ResyncRequired => write!(f, "Resync required"),
-            LidarDriverError::SerialRead(_) => write!(f, "Unable to read from serial port"),
-            LidarDriverError::SetTimeout(_) => write!(f, "Unable to set serial port timeout"),
+            LidarDriverError::Context { source, .. } => source.root(),
+            _ => self,
         }
     }
 }
 
-impl Error for LidarDriverError {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
-        match self {
-            LidarDriverError::Configure(e) => Some(e),
-            LidarDriverError::OpenSerialPort(e) => Some(e),
-            LidarDriverError::SerialRead(e) => Some(e),
-            LidarDriverError::SetTimeout(e) => Some(e),
+impl PartialEq for LidarDriverError {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (LidarDriverError::Checksum(first, first_details), LidarDriverError::Checksum(second, second_details)) => {
+                first == second && first_details == second_details
+            },
+            #[cfg(feature = "std")]
+            (LidarDriverError::Configure(first), LidarDriverError::Configure(second))
+            | (LidarDriverError::OpenSerialPort(first), LidarDriverError::OpenSerialPort(second))
+            | (LidarDriverError::PermissionDenied(first), LidarDriverError::PermissionDenied(second))
+            | (LidarDriverError::PortNotFound(first), LidarDriverError::PortNotFound(second))
+            | (LidarDriverError::SetTimeout(first), LidarDriverError::SetTimeout(second)) => first.kind() == second.kind(),
+            #[cfg(feature = "std")]
+            (LidarDriverError::DeviceDisconnected(first), LidarDriverError::DeviceDisconnected(second))
+            | (LidarDriverError::SerialRead(first), LidarDriverError::SerialRead(second))
+            | (LidarDriverError::SerialWrite(first), LidarDriverError::SerialWrite(second))
+            | (LidarDriverError::SpawnThread(first), LidarDriverError::SpawnThread(second))
+            | (LidarDriverError::ThreadSettings(first), LidarDriverError::ThreadSettings(second)) => first.kind() == second.kind(),
+            #[cfg(feature = "std")]
+            (LidarDriverError::Context { context: first, source: first_source }, LidarDriverError::Context { context: second, source: second_source }) => {
+                first == second && first_source == second_source
+            },
+            #[cfg(feature = "std")]
+            (LidarDriverError::DriverPanicked(first), LidarDriverError::DriverPanicked(second)) => first == second,
+            #[cfg(feature = "std")]
+            (LidarDriverError::NoData { since: first }, LidarDriverError::NoData { since: second })
+            | (LidarDriverError::SyncTimeout { since: first }, LidarDriverError::SyncTimeout { since: second }) => first == second,
+            #[cfg(feature = "std")]
+            (LidarDriverError::MotorStalled { since: first, last_rpm: first_rpm }, LidarDriverError::MotorStalled { since: second, last_rpm: second_rpm }) => {
+                first == second && first_rpm == second_rpm
+            },
+            #[cfg(feature = "std")]
+            (LidarDriverError::TooManyErrors { class: first, count: first_count }, LidarDriverError::TooManyErrors { class: second, count: second_count }) => {
+                first == second && first_count == second_count
+            },
+            #[cfg(feature = "std")]
+            (LidarDriverError::PortBusy, LidarDriverError::PortBusy)
+            | (LidarDriverError::ProtocolNotDetected, LidarDriverError::ProtocolNotDetected)
+            | (LidarDriverError::ReadTimeout, LidarDriverError::ReadTimeout) => true,
+            (LidarDriverError::ResyncRequired, LidarDriverError::ResyncRequired) => true,
+            _ => false
+        }
+    }
+}
+
+/// ## Summary
+/// 
+/// The recoverable errors an `error_policy::ErrorPolicy` acts on.
+/// 
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum ErrorClass {
+    // `LidarDriverError::Checksum`.
+    Checksum,
+    // `LidarDriverError::ResyncRequired`.
+    Resync,
+    // `LidarDriverError::ReadTimeout`, `LidarDriverError::NoData`, `LidarDriverError::SyncTimeout`
+    // and `LidarDriverError::MotorStalled`.
+    Timeout,
+}
+
+impl ErrorClass {
+    /// ## Summary
+    /// 
+    /// The class of an error, context removed. `None` for the other errors.
+    /// 
+    pub fn of(error: &LidarDriverError) -> Option<Self> {
+        match error {
+            LidarDriverError::Checksum(..) => Some(ErrorClass::Checksum),
+            LidarDriverError::ResyncRequired => Some(ErrorClass::Resync),
+            #[cfg(feature = "std")]
+            LidarDriverError::ReadTimeout | LidarDriverError::NoData { .. } | LidarDriverError::SyncTimeout { .. } | LidarDriverError::MotorStalled { .. } => {
+                Some(ErrorClass::Timeout)
+            },
+            #[cfg(feature = "std")]
+            LidarDriverError::Context { source, .. } => ErrorClass::of(source),
+            // Without `std`, every error has a class.
+            #[cfg(feature = "std")]
             _ => None,
         }
     }
 }
 
-impl PartialEq for LidarDriverError {
-    fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (LidarDriverError::Checksum(first), LidarDriverError::Checksum(second)) => first == second,
-            _ => false
+impl core::fmt::Display for ErrorClass {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            ErrorClass::Checksum => write!(f, "checksum"),
+            ErrorClass::Resync => write!(f, "resync"),
+            ErrorClass::Timeout => write!(f, "timeout"),
+        }
+    }
+}
+
+/// ## Summary
+/// 
+/// A corrupted XV-11 packet, to log and later analyze what corrupted frames look like.
+/// 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChecksumDetails {
+    // The raw packet, starting with 0xFA.
+    pub packet: [u8; 22],
+    // Checksum carried by the packet.
+    pub received: u16,
+    // Checksum calculated from the first 20 bytes.
+    pub calculated: u16,
+}
+
+/// ## Summary
+/// 
+/// Where an error occured, so a single log line is enough to find it in a long capture.
+/// 
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ErrorContext {
+    // The port name.
+    pub port: String,
+    // Number of bytes parsed since the driver started, including the byte that raised the error.
+    pub offset: u64,
+    // Index of the last valid packet, if any.
+    pub last_packet_index: Option<usize>,
+}
+
+#[cfg(feature = "std")]
+impl core::fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "port {}, byte {}", self.port, self.offset)?;
+
+        match self.last_packet_index {
+            Some(index) => write!(f, ", last packet {}", index),
+            None => write!(f, ", no packet yet"),
         }
     }
 }
@@ -68,11 +285,48 @@ impl PartialEq for LidarDriverError {
 /// This occurs when the LIDAR reports that the data is erroneous or unreliable, 
 /// which typically happens if the LIDAR is attempting to scan a far surface.
 /// 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, Error, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[non_exhaustive]
 pub enum LidarReadingError {
     // The Invalid Data Error flag was set. The associated value is the error code.
+    #[error("Invalid data (error code {0})")]
     InvalidDataError(i32),
     // The Signal Strength Warning flag was set.
+    #[error("Weak signal")]
     SignalStrengthWarning,
-}
\ No newline at end of file
+}
+
+/// ## Summary
+///
+/// An error reported by the embedded (`embedded` and `embassy` features) LIDAR backends.
+///
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum EmbeddedLidarError<E> {
+    // The UART reached the end of its stream.
+    #[error("The UART reached the end of its stream")]
+    EndOfStream,
+    // The packet could not be decoded.
+    #[error(transparent)]
+    Packet(LidarDriverError),
+    // The UART reported an error. The associated value is the HAL error.
+    #[error("Unable to read from UART: {0:?}")]
+    Serial(E),
+}
+
+/// ## Summary
+///
+/// An error returned by `control::DriverControl`.
+///
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, Error, PartialEq)]
+#[non_exhaustive]
+pub enum ControlError {
+    // The driver has stopped.
+    #[error("The driver has stopped")]
+    Disconnected,
+    // The driver did not acknowledge the command in time.
+    #[error("The driver did not acknowledge the command in time")]
+    Timeout,
+}
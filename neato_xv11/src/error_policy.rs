@@ -0,0 +1,129 @@
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+use super::error::ErrorClass;
+use super::message::Warning;
+use super::prelude::*;
+
+/// ## Summary
+///
+/// How the driver handles recoverable errors: when a hopeless link shuts it down, and
+/// which errors are only warnings.
+///
+/// ## Remarks
+///
+/// By default, recoverable errors are reported one by one and never stop the driver.
+/// A limit shuts the driver down with `LidarDriverError::TooManyErrors` once that many
+/// errors of its class are reported without a valid packet between them. Errors of a
+/// downgraded class are sent as `Warning::Downgraded` instead, they still count
+/// towards the limits. Unrecoverable errors, such as a disconnection, stop the driver
+/// whatever the policy.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::sync::mpsc::channel;
+/// use neato_xv11::error::ErrorClass;
+/// use neato_xv11::error_policy::ErrorPolicy;
+/// use neato_xv11::LidarDriverBuilder;
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// let policy = ErrorPolicy {
+///     max_consecutive_checksum_errors: Some(50),
+///     max_consecutive_resyncs: Some(20),
+///     downgrade: vec![ErrorClass::Timeout],
+/// };
+///
+/// let handle = LidarDriverBuilder::new("/dev/ttyUSB0")
+///     .error_policy(policy)
+///     .spawn(message_tx, command_rx)
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ErrorPolicy {
+    // Shut down after this many checksum errors without a valid packet between them.
+    pub max_consecutive_checksum_errors: Option<u32>,
+    // Shut down after this many resyncs without a valid packet between them.
+    pub max_consecutive_resyncs: Option<u32>,
+    // Classes of errors sent as `Warning::Downgraded` instead of errors.
+    pub downgrade: Vec<ErrorClass>,
+}
+
+/// Outcome of checking a message against an `ErrorPolicy`.
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum Verdict {
+    // Send the message, possibly downgraded.
+    Send(Result<LidarDriverMessage, LidarDriverError>),
+    // Send the error and shut down.
+    Stop(LidarDriverError),
+}
+
+/// ## Summary
+///
+/// Applies an `ErrorPolicy` to the messages of the driver.
+///
+pub(crate) struct ErrorTracker {
+    policy: ErrorPolicy,
+    // Checksum errors since the last valid packet.
+    checksum_errors: u32,
+    // Resyncs since the last valid packet.
+    resyncs: u32,
+}
+
+impl ErrorTracker {
+    pub(crate) fn new(policy: ErrorPolicy) -> Self {
+        ErrorTracker { policy, checksum_errors: 0, resyncs: 0 }
+    }
+
+    /// ## Summary
+    ///
+    /// Count a message about to be sent and decide what to send.
+    ///
+    pub(crate) fn check(&mut self, message: Result<LidarDriverMessage, LidarDriverError>) -> Verdict {
+        let error = match message {
+            Ok(LidarDriverMessage::Packet(packet)) => {
+                self.checksum_errors = 0;
+                self.resyncs = 0;
+                return Verdict::Send(Ok(LidarDriverMessage::Packet(packet)));
+            },
+            Ok(message) => return Verdict::Send(Ok(message)),
+            Err(error) => error,
+        };
+
+        let class = match ErrorClass::of(&error) {
+            Some(class) => class,
+            None => return Verdict::Send(Err(error)),
+        };
+
+        let (count, limit) = match class {
+            ErrorClass::Checksum => (&mut self.checksum_errors, self.policy.max_consecutive_checksum_errors),
+            ErrorClass::Resync => (&mut self.resyncs, self.policy.max_consecutive_resyncs),
+            ErrorClass::Timeout => return Verdict::Send(self.downgrade(class, error)),
+        };
+
+        *count = count.saturating_add(1);
+
+        match limit {
+            Some(limit) if *count >= limit => Verdict::Stop(LidarDriverError::TooManyErrors { class, count: *count }),
+            _ => Verdict::Send(self.downgrade(class, error)),
+        }
+    }
+
+    /// The error, or its warning if its class is downgraded.
+    fn downgrade(&self, class: ErrorClass, error: LidarDriverError) -> Result<LidarDriverMessage, LidarDriverError> {
+        if !self.policy.downgrade.contains(&class) {
+            return Err(error);
+        }
+
+        let packet_index = match error.root() {
+            LidarDriverError::Checksum(index, _) => Some(*index),
+            _ => None,
+        };
+
+        Ok(LidarDriverMessage::Warning(Warning::Downgraded { class, packet_index }))
+    }
+}
@@ -0,0 +1,475 @@
+use std::io::{Error as IoError, Write};
+#[cfg(feature = "hdf5")]
+use std::path::Path;
+#[cfg(feature = "parquet")]
+use std::sync::Arc;
+
+#[cfg(feature = "parquet")]
+use arrow_array::builder::{Float32Builder, Int32Builder, Int64Builder, StringBuilder, UInt64Builder};
+#[cfg(feature = "parquet")]
+use arrow_array::{ArrayRef, RecordBatch};
+#[cfg(feature = "parquet")]
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+#[cfg(feature = "parquet")]
+use parquet::arrow::ArrowWriter;
+#[cfg(feature = "json")]
+use serde::Serialize;
+
+#[cfg(feature = "hdf5")]
+use super::data::Float;
+use super::data::{InvalidReadings, LidarPacket, LidarReading, LidarScan, Point2D};
+
+/// Number of rows buffered before a record batch is written.
+#[cfg(feature = "parquet")]
+const BATCH_ROWS: usize = 64 * 1024;
+
+/// ## Summary
+///
+/// Writes the readings of scans to a Parquet file, one row per reading, so long
+/// captures can be analyzed with Polars, pandas or DuckDB without a custom loader.
+///
+/// ## Remarks
+///
+/// Columns: `timestamp` (nanoseconds since the Unix epoch), `revolution`, `angle`
+/// (degrees), `distance` (millimeters), `quality` and `error` (null for valid readings).
+/// Invalid readings are kept, missing ones are not. Rows are buffered and written in
+/// batches, call `finish` to write the footer.
+///
+/// ## Example
+///
+/// ```no_run
+/// # fn export(scans: impl Iterator<Item = (neato_xv11::data::LidarScan, u64)>) {
+/// use std::fs::File;
+/// use neato_xv11::export::ParquetWriter;
+///
+/// let mut writer = ParquetWriter::new(File::create("capture.parquet").unwrap()).unwrap();
+///
+/// for (scan, timestamp) in scans {
+///     writer.write_scan(&scan, timestamp).unwrap();
+/// }
+///
+/// writer.finish().unwrap();
+/// # }
+/// ```
+///
+/// ```python
+/// import polars as pl
+/// readings = pl.read_parquet("capture.parquet")
+/// ```
+#[cfg(feature = "parquet")]
+pub struct ParquetWriter<W: Write + Send> {
+    // Writes the record batches.
+    writer: ArrowWriter<W>,
+    // Columns of the file.
+    schema: SchemaRef,
+    // Rows not written yet.
+    timestamp: Int64Builder,
+    revolution: UInt64Builder,
+    angle: Float32Builder,
+    distance: Int32Builder,
+    quality: Int32Builder,
+    error: StringBuilder,
+    // Number of rows not written yet.
+    rows: usize,
+}
+
+#[cfg(feature = "parquet")]
+impl<W: Write + Send> ParquetWriter<W> {
+    /// ## Summary
+    ///
+    /// Start a Parquet file.
+    ///
+    pub fn new(writer: W) -> Result<Self, IoError> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("timestamp", DataType::Int64, false),
+            Field::new("revolution", DataType::UInt64, false),
+            Field::new("angle", DataType::Float32, false),
+            Field::new("distance", DataType::Int32, false),
+            Field::new("quality", DataType::Int32, false),
+            Field::new("error", DataType::Utf8, true),
+        ]));
+
+        Ok(ParquetWriter {
+            writer: ArrowWriter::try_new(writer, schema.clone(), None).map_err(IoError::other)?,
+            schema,
+            timestamp: Int64Builder::new(),
+            revolution: UInt64Builder::new(),
+            angle: Float32Builder::new(),
+            distance: Int32Builder::new(),
+            quality: Int32Builder::new(),
+            error: StringBuilder::new(),
+            rows: 0,
+        })
+    }
+
+    /// ## Summary
+    ///
+    /// Add the readings of a scan.
+    ///
+    /// ## Parameters
+    ///
+    /// scan: The scan.
+    ///
+    /// timestamp: When the scan completed, in nanoseconds since the Unix epoch.
+    ///
+    pub fn write_scan(&mut self, scan: &LidarScan, timestamp: u64) -> Result<(), IoError> {
+        for reading in scan.iter(InvalidReadings::Include) {
+            self.timestamp.append_value(timestamp as i64);
+            self.revolution.append_value(scan.revolution);
+            self.angle.append_value(reading.index as f32);
+            self.distance.append_value(reading.distance);
+            self.quality.append_value(reading.quality);
+            self.error.append_option(reading.error.map(|error| error.to_string()));
+            self.rows += 1;
+        }
+
+        if self.rows >= BATCH_ROWS {
+            self.flush_rows()?;
+        }
+
+        Ok(())
+    }
+
+    /// ## Summary
+    ///
+    /// Write the buffered rows and the footer, and close the file.
+    ///
+    pub fn finish(mut self) -> Result<W, IoError> {
+        self.flush_rows()?;
+        self.writer.into_inner().map_err(IoError::other)
+    }
+
+    /// Write the buffered rows as a record batch.
+    fn flush_rows(&mut self) -> Result<(), IoError> {
+        if self.rows == 0 {
+            return Ok(());
+        }
+
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(self.timestamp.finish()),
+            Arc::new(self.revolution.finish()),
+            Arc::new(self.angle.finish()),
+            Arc::new(self.distance.finish()),
+            Arc::new(self.quality.finish()),
+            Arc::new(self.error.finish()),
+        ];
+
+        let batch = RecordBatch::try_new(self.schema.clone(), columns).map_err(IoError::other)?;
+        self.rows = 0;
+        self.writer.write(&batch).map_err(IoError::other)
+    }
+}
+
+/// ## Summary
+///
+/// Writes scans to an HDF5 file, one group per scan, for analysis pipelines built
+/// around HDF5.
+///
+/// ## Remarks
+///
+/// Groups are named `scan_00000000`, `scan_00000001`, ... in the order the scans are
+/// written. Each holds three datasets of 360 values indexed by degree: `distance`
+/// (millimeters), `quality` and `valid` (1 for a valid reading, 0 for an invalid or
+/// missing one). Attributes: `timestamp` (nanoseconds since the Unix epoch),
+/// `revolution`, `rpm`, `missing` (number of slots without a reading), and
+/// `valid_readings`, `quality_mean` and `quality_median` over the valid readings.
+///
+/// ## Example
+///
+/// ```no_run
+/// # fn export(scans: impl Iterator<Item = (neato_xv11::data::LidarScan, u64)>) {
+/// use neato_xv11::export::Hdf5Writer;
+///
+/// let mut writer = Hdf5Writer::create("capture.h5").unwrap();
+///
+/// for (scan, timestamp) in scans {
+///     writer.write_scan(&scan, timestamp).unwrap();
+/// }
+/// # }
+/// ```
+///
+/// ```python
+/// import h5py
+/// with h5py.File("capture.h5") as f:
+///     rpm = [scan.attrs["rpm"] for scan in f.values()]
+/// ```
+#[cfg(feature = "hdf5")]
+pub struct Hdf5Writer {
+    // The file, closed when the writer is dropped.
+    file: hdf5::File,
+    // Number of scans written.
+    count: u64,
+}
+
+#[cfg(feature = "hdf5")]
+impl Hdf5Writer {
+    /// ## Summary
+    ///
+    /// Create an HDF5 file, replacing any existing one.
+    ///
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, IoError> {
+        Ok(Hdf5Writer {
+            file: hdf5::File::create(path).map_err(hdf5_error)?,
+            count: 0,
+        })
+    }
+
+    /// ## Summary
+    ///
+    /// Add a scan as a new group.
+    ///
+    /// ## Parameters
+    ///
+    /// scan: The scan.
+    ///
+    /// timestamp: When the scan completed, in nanoseconds since the Unix epoch.
+    ///
+    pub fn write_scan(&mut self, scan: &LidarScan, timestamp: u64) -> Result<(), IoError> {
+        let distance: Vec<i32> = scan.readings.iter().map(|slot| slot.as_ref().map_or(0, |reading| reading.distance)).collect();
+        let quality: Vec<i32> = scan.readings.iter().map(|slot| slot.as_ref().map_or(0, |reading| reading.quality)).collect();
+        let valid: Vec<u8> = scan.readings.iter().map(|slot| slot.as_ref().is_some_and(|reading| reading.is_valid()) as u8).collect();
+        let stats = scan.quality_stats(0);
+
+        let group = self.file.create_group(&format!("scan_{:08}", self.count)).map_err(hdf5_error)?;
+
+        group.new_dataset_builder().with_data(&distance[..]).create("distance").map_err(hdf5_error)?;
+        group.new_dataset_builder().with_data(&quality[..]).create("quality").map_err(hdf5_error)?;
+        group.new_dataset_builder().with_data(&valid[..]).create("valid").map_err(hdf5_error)?;
+
+        group.new_attr::<u64>().create("timestamp").and_then(|attr| attr.write_scalar(&timestamp)).map_err(hdf5_error)?;
+        group.new_attr::<u64>().create("revolution").and_then(|attr| attr.write_scalar(&scan.revolution)).map_err(hdf5_error)?;
+        group.new_attr::<Float>().create("rpm").and_then(|attr| attr.write_scalar(&scan.speed)).map_err(hdf5_error)?;
+        group.new_attr::<u64>().create("missing").and_then(|attr| attr.write_scalar(&(scan.missing() as u64))).map_err(hdf5_error)?;
+
+        let (count, mean, median) = stats.map_or((0, 0.0, 0), |stats| (stats.count, stats.mean, stats.p50));
+        group.new_attr::<u64>().create("valid_readings").and_then(|attr| attr.write_scalar(&(count as u64))).map_err(hdf5_error)?;
+        group.new_attr::<Float>().create("quality_mean").and_then(|attr| attr.write_scalar(&mean)).map_err(hdf5_error)?;
+        group.new_attr::<i32>().create("quality_median").and_then(|attr| attr.write_scalar(&median)).map_err(hdf5_error)?;
+
+        self.count += 1;
+        Ok(())
+    }
+
+    /// ## Summary
+    ///
+    /// Number of scans written.
+    ///
+    pub fn len(&self) -> u64 {
+        self.count
+    }
+
+    /// ## Summary
+    ///
+    /// Whether no scan was written.
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// ## Summary
+    ///
+    /// Flush the file to disk.
+    ///
+    pub fn flush(&self) -> Result<(), IoError> {
+        self.file.flush().map_err(hdf5_error)
+    }
+}
+
+/// Convert an HDF5 error, which holds a handle on the library's error stack.
+#[cfg(feature = "hdf5")]
+fn hdf5_error(err: hdf5::Error) -> IoError {
+    IoError::other(err.to_string())
+}
+
+/// ## Summary
+///
+/// Writes readings as CSV rows, for spreadsheets and quick scripts.
+///
+/// ## Remarks
+///
+/// Columns: `timestamp` (nanoseconds since the Unix epoch), `angle` (degrees),
+/// `distance` (millimeters), `quality` and `error` (empty for valid readings). Invalid
+/// readings are kept, missing ones are not. The header is written by `new`, wrap files
+/// in a `BufWriter`.
+///
+/// ## Example
+///
+/// ```no_run
+/// # fn export(scans: impl Iterator<Item = (neato_xv11::data::LidarScan, u64)>) {
+/// use std::fs::File;
+/// use std::io::BufWriter;
+/// use neato_xv11::export::CsvWriter;
+///
+/// let mut writer = CsvWriter::new(BufWriter::new(File::create("capture.csv").unwrap())).unwrap();
+///
+/// for (scan, timestamp) in scans {
+///     writer.write_scan(&scan, timestamp).unwrap();
+/// }
+/// # }
+/// ```
+pub struct CsvWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> CsvWriter<W> {
+    /// ## Summary
+    ///
+    /// Start a CSV file with its header.
+    ///
+    pub fn new(mut writer: W) -> Result<Self, IoError> {
+        writeln!(writer, "timestamp,angle,distance,quality,error")?;
+        Ok(CsvWriter { writer })
+    }
+
+    /// ## Summary
+    ///
+    /// Add the readings of a packet.
+    ///
+    /// ## Parameters
+    ///
+    /// packet: The packet.
+    ///
+    /// timestamp: When the packet was received, in nanoseconds since the Unix epoch.
+    ///
+    pub fn write_packet(&mut self, packet: &LidarPacket, timestamp: u64) -> Result<(), IoError> {
+        packet.readings.iter().try_for_each(|reading| self.write_reading(reading, timestamp))
+    }
+
+    /// ## Summary
+    ///
+    /// Add the readings of a scan.
+    ///
+    /// ## Parameters
+    ///
+    /// scan: The scan.
+    ///
+    /// timestamp: When the scan completed, in nanoseconds since the Unix epoch.
+    ///
+    pub fn write_scan(&mut self, scan: &LidarScan, timestamp: u64) -> Result<(), IoError> {
+        scan.iter(InvalidReadings::Include).try_for_each(|reading| self.write_reading(reading, timestamp))
+    }
+
+    /// ## Summary
+    ///
+    /// Flush the rows and return the inner writer.
+    ///
+    pub fn finish(mut self) -> Result<W, IoError> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    /// Write a reading as a row.
+    fn write_reading(&mut self, reading: &LidarReading, timestamp: u64) -> Result<(), IoError> {
+        write!(self.writer, "{},{},{},{},", timestamp, reading.angle_degrees(), reading.distance, reading.quality)?;
+
+        match reading.error {
+            // Error messages hold no comma or quote.
+            Some(error) => writeln!(self.writer, "{}", error),
+            None => writeln!(self.writer),
+        }
+    }
+}
+
+/// ## Summary
+///
+/// Writes values as JSON Lines, one JSON document per line, through their serde
+/// representation.
+///
+/// ## Example
+///
+/// ```no_run
+/// # fn export(scans: Vec<neato_xv11::data::LidarScan>) {
+/// use std::fs::File;
+/// use std::io::BufWriter;
+/// use neato_xv11::export::JsonLinesWriter;
+///
+/// let mut writer = JsonLinesWriter::new(BufWriter::new(File::create("capture.jsonl").unwrap()));
+///
+/// for scan in &scans {
+///     writer.write(scan).unwrap();
+/// }
+/// # }
+/// ```
+///
+/// ```python
+/// import pandas as pd
+/// scans = pd.read_json("capture.jsonl", lines=True)
+/// ```
+#[cfg(feature = "json")]
+pub struct JsonLinesWriter<W: Write> {
+    writer: W,
+}
+
+#[cfg(feature = "json")]
+impl<W: Write> JsonLinesWriter<W> {
+    /// ## Summary
+    ///
+    /// Start a JSON Lines file.
+    ///
+    pub fn new(writer: W) -> Self {
+        JsonLinesWriter { writer }
+    }
+
+    /// ## Summary
+    ///
+    /// Add a value, e.g. a `LidarPacket` or a `LidarScan`, as a line.
+    ///
+    pub fn write<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), IoError> {
+        serde_json::to_writer(&mut self.writer, value)?;
+        writeln!(self.writer)
+    }
+
+    /// ## Summary
+    ///
+    /// Flush the lines and return the inner writer.
+    ///
+    pub fn finish(mut self) -> Result<W, IoError> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// ## Summary
+///
+/// Write points as an ASCII PLY point cloud, e.g. for CloudCompare or MeshLab.
+///
+/// ## Remarks
+///
+/// Coordinates are in millimeters, with z at 0. Convert a scan with
+/// `LidarScan::to_point_cloud` or `LidarScan::to_point_cloud_with`.
+///
+/// ## Example
+///
+/// ```no_run
+/// # fn export(scan: &neato_xv11::data::LidarScan) {
+/// use std::fs::File;
+/// use neato_xv11::export::write_ply;
+///
+/// write_ply(File::create("scan.ply").unwrap(), &scan.to_point_cloud()).unwrap();
+/// # }
+/// ```
+pub fn write_ply<W: Write>(mut writer: W, points: &[Point2D]) -> Result<(), IoError> {
+    writeln!(writer, "ply")?;
+    writeln!(writer, "format ascii 1.0")?;
+    writeln!(writer, "comment units mm")?;
+    writeln!(writer, "element vertex {}", points.len())?;
+    writeln!(writer, "property float x")?;
+    writeln!(writer, "property float y")?;
+    writeln!(writer, "property float z")?;
+    writeln!(writer, "end_header")?;
+
+    write_xyz(writer, points)
+}
+
+/// ## Summary
+///
+/// Write points as an XYZ point cloud, one `x y z` line per point in millimeters, with
+/// z at 0.
+///
+pub fn write_xyz<W: Write>(mut writer: W, points: &[Point2D]) -> Result<(), IoError> {
+    for point in points {
+        writeln!(writer, "{} {} 0", point.x, point.y)?;
+    }
+
+    writer.flush()
+}
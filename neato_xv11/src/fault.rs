@@ -0,0 +1,184 @@
+use std::io::{Error as IoError, ErrorKind, Read};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::clock::{Clock, SystemClock};
+
+/// Start byte of an XV-11 packet.
+const PACKET_START: u8 = 0xFA;
+/// Size of an XV-11 packet in bytes.
+const PACKET_SIZE: usize = 22;
+
+/// ## Summary
+///
+/// Faults injected by a `FaultInjector`. Rates are probabilities from 0 to 1.
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FaultConfig {
+    // Probability of flipping one bit of a byte.
+    pub bit_flip_rate: f64,
+    // Probability of dropping a byte.
+    pub byte_drop_rate: f64,
+    // Probability of cutting a packet short after its start byte.
+    pub truncate_rate: f64,
+    // Probability of a read failing with `ErrorKind::TimedOut`.
+    pub timeout_rate: f64,
+    // Probability of a read failing with a non-recoverable I/O error.
+    pub error_rate: f64,
+    // Probability of a read stalling before returning.
+    pub stall_rate: f64,
+    // How long a stalled read blocks.
+    pub stall_duration: Duration,
+    // Seed of the random generator. The same seed and source reproduce the same faults.
+    pub seed: u64,
+}
+
+/// ## Summary
+///
+/// A `LidarSource` wrapper that injects faults into the bytes of an inner source:
+/// bit flips, truncated packets, dropped bytes, timeouts, read errors and stalls.
+///
+/// ## Remarks
+///
+/// Used to verify error handling against every failure the driver can report:
+/// corrupted bytes surface as `LidarDriverError::Checksum` or `ResyncRequired`,
+/// persistent timeouts as `ReadTimeout` and failed reads as `SerialRead`. Faults are pseudo-random but
+/// deterministic for a given seed.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::thread;
+/// # use std::sync::mpsc::channel;
+/// use neato_xv11::fault::{FaultConfig, FaultInjector};
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// let capture = std::fs::File::open("capture.bin").unwrap();
+/// let source = FaultInjector::new(capture, FaultConfig {
+///     bit_flip_rate: 0.001,
+///     truncate_rate: 0.01,
+///     seed: 42,
+///     ..FaultConfig::default()
+/// });
+///
+/// thread::spawn(move || {
+///     neato_xv11::run_from_source(source, message_tx, command_rx);
+/// });
+/// ```
+pub struct FaultInjector<R> {
+    inner: R,
+    config: FaultConfig,
+    // State of the xorshift random generator.
+    state: u64,
+    // Number of bytes left to drop from a truncated packet.
+    truncating: usize,
+    // Clock stalls sleep on.
+    clock: Arc<dyn Clock>,
+}
+
+impl<R: Read> FaultInjector<R> {
+    /// ## Summary
+    ///
+    /// Wrap a source.
+    ///
+    pub fn new(inner: R, config: FaultConfig) -> Self {
+        // xorshift must not start at 0.
+        let state = config.seed ^ 0x9E37_79B9_7F4A_7C15;
+
+        FaultInjector {
+            inner,
+            config,
+            state: if state == 0 { 1 } else { state },
+            truncating: 0,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Sleep on the given clock when stalling, e.g. a `clock::VirtualClock` so stalls
+    /// advance virtual time instead of blocking.
+    ///
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Unwrap the inner source.
+    ///
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Next pseudo-random number.
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// Draw an event of the given probability.
+    fn chance(&mut self, rate: f64) -> bool {
+        rate > 0.0 && ((self.next() >> 11) as f64 / (1u64 << 53) as f64) < rate
+    }
+}
+
+impl<R: Read> Read for FaultInjector<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, IoError> {
+        if self.chance(self.config.stall_rate) {
+            self.clock.sleep(self.config.stall_duration);
+        }
+
+        if self.chance(self.config.timeout_rate) {
+            return Err(IoError::new(ErrorKind::TimedOut, "Injected timeout"));
+        }
+
+        if self.chance(self.config.error_rate) {
+            return Err(IoError::other("Injected read error"));
+        }
+
+        loop {
+            let count = self.inner.read(buffer)?;
+
+            if count == 0 {
+                return Ok(0);
+            }
+
+            let mut kept = 0;
+
+            for index in 0..count {
+                let mut byte = buffer[index];
+
+                if self.truncating > 0 {
+                    self.truncating -= 1;
+                    continue;
+                }
+
+                if self.chance(self.config.byte_drop_rate) {
+                    continue;
+                }
+
+                if byte == PACKET_START && self.chance(self.config.truncate_rate) {
+                    self.truncating = 1 + (self.next() % (PACKET_SIZE as u64 - 2)) as usize;
+                }
+
+                if self.chance(self.config.bit_flip_rate) {
+                    byte ^= 1 << (self.next() % 8);
+                }
+
+                buffer[kept] = byte;
+                kept += 1;
+            }
+
+            // Every byte was dropped, read again rather than signal the end of the source.
+            if kept > 0 {
+                return Ok(kept);
+            }
+        }
+    }
+}
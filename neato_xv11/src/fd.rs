@@ -0,0 +1,150 @@
+use std::io::{Error as IoError, ErrorKind, Read};
+use std::os::unix::io::{AsRawFd, OwnedFd};
+use std::time::Duration;
+
+#[cfg(feature = "log")]
+use log::info;
+
+use super::driver::{run_source, RunOptions};
+use super::prelude::*;
+
+/// Timeout of a single read. Bounds how long stopping the reader thread takes.
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// ## Summary
+///
+/// A serial device opened by another component and handed over as a file descriptor.
+///
+struct FdPort {
+    fd: OwnedFd,
+}
+
+impl FdPort {
+    /// ## Summary
+    ///
+    /// Take ownership of the descriptor. Terminal devices are configured for the LIDAR
+    /// (115200 baud, 8N1, raw mode) and their stale input is discarded. Other descriptors (pipes, sockets) are used as is.
+    ///
+    fn new(fd: OwnedFd) -> Result<Self, LidarDriverError> {
+        let raw_fd = fd.as_raw_fd();
+
+        // Safety: raw_fd is a valid descriptor owned by fd.
+        if unsafe { libc::isatty(raw_fd) } == 1 {
+            configure(raw_fd).map_err(|err| {
+                LidarDriverError::Configure(serial::Error::new(serial::ErrorKind::Io(err.kind()), err.to_string()))
+            })?;
+
+            #[cfg(feature = "log")]
+            info!("Successfully configured the file descriptor");
+        }
+
+        Ok(FdPort { fd })
+    }
+}
+
+impl Read for FdPort {
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, IoError> {
+        let mut poll_fd = libc::pollfd {
+            fd: self.fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+
+        // Safety: poll_fd is a valid pollfd for the duration of the call.
+        let ready = unsafe { libc::poll(&mut poll_fd, 1, READ_TIMEOUT.as_millis() as libc::c_int) };
+
+        if ready < 0 {
+            return Err(IoError::last_os_error());
+        }
+
+        if ready == 0 {
+            return Err(IoError::new(ErrorKind::TimedOut, "Operation timed out"));
+        }
+
+        // Safety: buffer is valid for writes of buffer.len() bytes.
+        let count = unsafe { libc::read(poll_fd.fd, buffer.as_mut_ptr() as *mut libc::c_void, buffer.len()) };
+
+        if count < 0 {
+            Err(IoError::last_os_error())
+        } else {
+            Ok(count as usize)
+        }
+    }
+}
+
+/// Put a terminal device in raw mode at 115200 baud, 8 data bits, no parity, one stop bit,
+/// and discard its input buffer.
+fn configure(fd: libc::c_int) -> Result<(), IoError> {
+    // Safety: termios is plain data and is fully initialized by tcgetattr.
+    let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+
+    // Safety: fd is a valid terminal descriptor and termios is a valid termios.
+    unsafe {
+        if libc::tcgetattr(fd, &mut termios) != 0 {
+            return Err(IoError::last_os_error());
+        }
+
+        libc::cfmakeraw(&mut termios);
+        termios.c_cflag &= !(libc::CSIZE | libc::PARENB | libc::CSTOPB | libc::CRTSCTS);
+        termios.c_cflag |= libc::CS8 | libc::CLOCAL | libc::CREAD;
+        termios.c_iflag &= !(libc::IXON | libc::IXOFF | libc::IXANY);
+
+        if libc::cfsetispeed(&mut termios, libc::B115200) != 0 || libc::cfsetospeed(&mut termios, libc::B115200) != 0 {
+            return Err(IoError::last_os_error());
+        }
+
+        if libc::tcsetattr(fd, libc::TCSANOW, &termios) != 0 {
+            return Err(IoError::last_os_error());
+        }
+
+        // Discard the bytes received before the descriptor was handed over, which may be
+        // several revolutions old.
+        if libc::tcflush(fd, libc::TCIFLUSH) != 0 {
+            return Err(IoError::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+/// ## Summary
+///
+/// Begin reading LIDAR data from an already open file descriptor.
+///
+/// ## Parameters
+///
+/// fd: The open device. The driver takes ownership and closes it on shutdown.
+///
+/// tx: Sends decoded LIDAR messages or error encountered.
+///
+/// rx: Receives commands from the calling program.
+///
+/// ## Remarks
+///
+/// Intended for environments where the driver cannot open `/dev/tty*` itself, such as
+/// Android apps, where the device is opened through `UsbManager` or Termux and only
+/// the descriptor is handed to native code. Terminal devices are configured for the
+/// LIDAR. Any other readable descriptor (e.g. a pipe fed by a USB-serial library)
+/// is read as is.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::thread;
+/// # use std::sync::mpsc::channel;
+/// use std::os::unix::io::{FromRawFd, OwnedFd};
+///
+/// # let raw_fd = 3;
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// // The descriptor received from Java through JNI.
+/// let fd = unsafe { OwnedFd::from_raw_fd(raw_fd) };
+///
+/// thread::spawn(move || {
+///     neato_xv11::run_fd(fd, message_tx, command_rx);
+/// });
+/// ```
+pub fn run_fd<S: MessageSink, C: CommandSource>(fd: OwnedFd, tx: S, rx: C) {
+    run_source(move || FdPort::new(fd), Parser::new(), tx, rx, RunOptions::default());
+}
@@ -0,0 +1,327 @@
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::ptr;
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::thread::JoinHandle;
+
+use super::data::MAX_READINGS;
+use super::prelude::*;
+use super::LidarDriverBuilder;
+
+/// Largest number of readings of a `NeatoPacket`.
+pub const NEATO_XV11_MAX_READINGS: usize = MAX_READINGS;
+
+/// ## Summary
+///
+/// A running driver, owned by the C program between `neato_xv11_start` and `neato_xv11_stop`.
+///
+/// ## Remarks
+///
+/// Generate the header with `cbindgen --config cbindgen.toml --output neato_xv11.h` and
+/// build the library with `cargo rustc --release --features ffi --crate-type cdylib` (or
+/// `staticlib`).
+///
+/// ## Example
+///
+/// ```c
+/// NeatoXv11 *lidar = neato_xv11_start("/dev/ttyUSB0");
+/// NeatoPacket packet;
+/// NeatoError error;
+///
+/// while (running) {
+///     switch (neato_xv11_poll_packet(lidar, &packet, &error)) {
+///         case NEATO_STATUS_PACKET: handle_packet(&packet); break;
+///         case NEATO_STATUS_ERROR: handle_error(&error); break;
+///         case NEATO_STATUS_EMPTY: usleep(1000); break;
+///         default: running = 0; break;
+///     }
+/// }
+///
+/// neato_xv11_stop(lidar);
+/// ```
+pub struct NeatoXv11 {
+    messages: Receiver<Result<LidarDriverMessage, LidarDriverError>>,
+    commands: Sender<LidarDriverCommand>,
+    // The driver thread, `None` once joined.
+    thread: Option<JoinHandle<()>>,
+    // Set once the driver sent `LidarDriverMessage::Shutdown`.
+    is_stopped: bool,
+}
+
+/// ## Summary
+///
+/// Outcome of `neato_xv11_poll_packet`.
+///
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NeatoStatus {
+    // A packet was written.
+    Packet = 0,
+    // No packet is available yet, poll again later.
+    Empty = 1,
+    // The driver reported an error, which was written.
+    Error = 2,
+    // The driver stopped, no packet will follow.
+    Stopped = 3,
+    // A null pointer was passed.
+    InvalidArgument = 4,
+}
+
+/// ## Summary
+///
+/// Kind of a `NeatoError`.
+///
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NeatoErrorCode {
+    // A packet failed its checksum. `packet_index` is set.
+    Checksum = 0,
+    // The port was silent for too long.
+    ReadTimeout = 1,
+    // No valid packet was received for too long.
+    NoData = 2,
+    // The device was disconnected.
+    Disconnected = 3,
+    // The port does not exist.
+    PortNotFound = 4,
+    // Access to the port was denied.
+    PermissionDenied = 5,
+    // The port is locked by another process.
+    PortBusy = 6,
+    // The port could not be opened or configured.
+    OpenSerialPort = 7,
+    // Reading from or writing to the port failed.
+    SerialIo = 8,
+    // Any other error.
+    Other = 9,
+    // The driver did not find the packet boundaries for too long.
+    SyncTimeout = 10,
+    // Too many errors without a valid packet, the driver stopped.
+    TooManyErrors = 11,
+    // Valid packets stopped arriving after flowing, the motor may have stalled.
+    MotorStalled = 12,
+}
+
+/// ## Summary
+///
+/// An error reported by the driver.
+///
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NeatoError {
+    pub code: NeatoErrorCode,
+    // Index of the packet for `NeatoErrorCode::Checksum`, -1 otherwise.
+    pub packet_index: i64,
+}
+
+/// ## Summary
+///
+/// Validity of a `NeatoReading`.
+///
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NeatoReadingStatus {
+    Valid = 0,
+    // The invalid data flag was set, `error_code` is set.
+    InvalidData = 1,
+    // The signal strength warning flag was set. The distance is usable but unreliable.
+    WeakSignal = 2,
+}
+
+/// ## Summary
+///
+/// A distance reading.
+///
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NeatoReading {
+    // Angle of the reading in degrees (0-359).
+    pub index: u32,
+    // Distance in millimeters.
+    pub distance: i32,
+    // Signal strength.
+    pub quality: i32,
+    pub status: NeatoReadingStatus,
+    // Error code reported with `NeatoReadingStatus::InvalidData`, 0 otherwise.
+    pub error_code: i32,
+}
+
+/// ## Summary
+///
+/// A decoded packet.
+///
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NeatoPacket {
+    // The first `reading_count` entries are set.
+    pub readings: [NeatoReading; NEATO_XV11_MAX_READINGS],
+    pub reading_count: u32,
+    // Spin speed (RPM).
+    pub speed: f64,
+    // Number of packets decoded before this one.
+    pub sequence: u64,
+}
+
+impl From<&LidarReading> for NeatoReading {
+    fn from(reading: &LidarReading) -> Self {
+        let (status, error_code) = match reading.error {
+            Some(LidarReadingError::InvalidDataError(code)) => (NeatoReadingStatus::InvalidData, code),
+            Some(LidarReadingError::SignalStrengthWarning) => (NeatoReadingStatus::WeakSignal, 0),
+            None => (NeatoReadingStatus::Valid, 0),
+        };
+
+        NeatoReading {
+            index: reading.index as u32,
+            distance: reading.distance,
+            quality: reading.quality,
+            status,
+            error_code,
+        }
+    }
+}
+
+impl From<&LidarPacket> for NeatoPacket {
+    // `Float` is `f32` with the `f32` feature.
+    #[allow(clippy::unnecessary_cast)]
+    fn from(packet: &LidarPacket) -> Self {
+        let empty = NeatoReading { index: 0, distance: 0, quality: 0, status: NeatoReadingStatus::Valid, error_code: 0 };
+        let mut readings = [empty; NEATO_XV11_MAX_READINGS];
+
+        for (slot, reading) in readings.iter_mut().zip(packet.readings.iter()) {
+            *slot = reading.into();
+        }
+
+        NeatoPacket {
+            readings,
+            reading_count: packet.readings.len().min(NEATO_XV11_MAX_READINGS) as u32,
+            speed: packet.speed as f64,
+            sequence: packet.sequence,
+        }
+    }
+}
+
+impl From<&LidarDriverError> for NeatoError {
+    fn from(error: &LidarDriverError) -> Self {
+        let code = match error.root() {
+            LidarDriverError::Checksum(index, _) => {
+                return NeatoError { code: NeatoErrorCode::Checksum, packet_index: *index as i64 };
+            },
+            LidarDriverError::ReadTimeout => NeatoErrorCode::ReadTimeout,
+            LidarDriverError::NoData { .. } => NeatoErrorCode::NoData,
+            LidarDriverError::SyncTimeout { .. } => NeatoErrorCode::SyncTimeout,
+            LidarDriverError::TooManyErrors { .. } => NeatoErrorCode::TooManyErrors,
+            LidarDriverError::MotorStalled { .. } => NeatoErrorCode::MotorStalled,
+            LidarDriverError::DeviceDisconnected(_) => NeatoErrorCode::Disconnected,
+            LidarDriverError::PortNotFound(_) => NeatoErrorCode::PortNotFound,
+            LidarDriverError::PermissionDenied(_) => NeatoErrorCode::PermissionDenied,
+            LidarDriverError::PortBusy => NeatoErrorCode::PortBusy,
+            LidarDriverError::OpenSerialPort(_) | LidarDriverError::Configure(_) | LidarDriverError::SetTimeout(_) => NeatoErrorCode::OpenSerialPort,
+            LidarDriverError::SerialRead(_) | LidarDriverError::SerialWrite(_) => NeatoErrorCode::SerialIo,
+            _ => NeatoErrorCode::Other,
+        };
+
+        NeatoError { code, packet_index: -1 }
+    }
+}
+
+/// ## Summary
+///
+/// Start a driver on the serial port `port_name`, with the default settings.
+///
+/// ## Remarks
+///
+/// Returns null if `port_name` is null or not UTF-8, or if the driver thread could not
+/// be spawned. Errors opening the port are reported by `neato_xv11_poll_packet`.
+///
+/// ## Safety
+///
+/// `port_name` must be null or a valid NUL-terminated string.
+///
+#[no_mangle]
+pub unsafe extern "C" fn neato_xv11_start(port_name: *const c_char) -> *mut NeatoXv11 {
+    if port_name.is_null() {
+        return ptr::null_mut();
+    }
+
+    let port_name = match CStr::from_ptr(port_name).to_str() {
+        Ok(port_name) => port_name,
+        Err(_) => return ptr::null_mut(),
+    };
+
+    let (message_tx, message_rx) = channel();
+    let (command_tx, command_rx) = channel();
+
+    match LidarDriverBuilder::new(port_name).spawn(message_tx, command_rx) {
+        Ok(thread) => Box::into_raw(Box::new(NeatoXv11 {
+            messages: message_rx,
+            commands: command_tx,
+            thread: Some(thread),
+            is_stopped: false,
+        })),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// ## Summary
+///
+/// Take the next packet or error without blocking. Other driver messages are skipped.
+///
+/// ## Remarks
+///
+/// Writes `packet` on `NeatoStatus::Packet` and `error` on `NeatoStatus::Error`.
+///
+/// ## Safety
+///
+/// `driver` must be a pointer returned by `neato_xv11_start` and not yet stopped,
+/// `packet` and `error` valid pointers.
+///
+#[no_mangle]
+pub unsafe extern "C" fn neato_xv11_poll_packet(driver: *mut NeatoXv11, packet: *mut NeatoPacket, error: *mut NeatoError) -> NeatoStatus {
+    if driver.is_null() || packet.is_null() || error.is_null() {
+        return NeatoStatus::InvalidArgument;
+    }
+
+    let driver = &mut *driver;
+
+    loop {
+        if driver.is_stopped {
+            return NeatoStatus::Stopped;
+        }
+
+        match driver.messages.try_recv() {
+            Ok(Ok(LidarDriverMessage::Packet(message))) => {
+                *packet = NeatoPacket::from(&message);
+                return NeatoStatus::Packet;
+            },
+            Ok(Ok(LidarDriverMessage::Shutdown)) | Err(TryRecvError::Disconnected) => driver.is_stopped = true,
+            Ok(Ok(_)) => continue,
+            Ok(Err(message)) => {
+                *error = NeatoError::from(&message);
+                return NeatoStatus::Error;
+            },
+            Err(TryRecvError::Empty) => return NeatoStatus::Empty,
+        }
+    }
+}
+
+/// ## Summary
+///
+/// Stop the driver, wait for its thread and free it. Does nothing if `driver` is null.
+///
+/// ## Safety
+///
+/// `driver` must be null or a pointer returned by `neato_xv11_start`, and is invalid afterwards.
+///
+#[no_mangle]
+pub unsafe extern "C" fn neato_xv11_stop(driver: *mut NeatoXv11) {
+    if driver.is_null() {
+        return;
+    }
+
+    let mut driver = Box::from_raw(driver);
+    let _ = driver.commands.send(LidarDriverCommand::Stop);
+
+    if let Some(thread) = driver.thread.take() {
+        let _ = thread.join();
+    }
+}
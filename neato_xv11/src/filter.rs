@@ -0,0 +1,731 @@
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+#[cfg(feature = "alloc")]
+use alloc::collections::VecDeque;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use super::data::{Float, LidarReading, LidarScan, SCAN_SIZE};
+use super::error::LidarReadingError;
+
+/// ## Summary
+///
+/// A processing stage applied to assembled scans.
+///
+pub trait ScanFilter {
+    /// ## Summary
+    ///
+    /// Filter a scan in place.
+    ///
+    fn apply(&mut self, scan: &mut LidarScan);
+
+    /// ## Summary
+    ///
+    /// Return a filtered copy of a scan, leaving the original (e.g. for mapping) untouched.
+    ///
+    fn filtered(&mut self, scan: &LidarScan) -> LidarScan {
+        let mut scan = scan.clone();
+        self.apply(&mut scan);
+        scan
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<F: ScanFilter + ?Sized> ScanFilter for Box<F> {
+    fn apply(&mut self, scan: &mut LidarScan) {
+        (**self).apply(scan);
+    }
+}
+
+/// Estimate of the distance at one angle.
+#[derive(Clone, Copy)]
+struct Estimate {
+    // Estimated distance in millimeters.
+    distance: Float,
+    // Variance of the estimate in square millimeters.
+    variance: Float,
+}
+
+/// ## Summary
+///
+/// A per-angle Kalman filter that stabilizes distances across revolutions, for display
+/// and control.
+///
+/// ## Remarks
+///
+/// Each angle is filtered independently with a constant-distance model. Missing and
+/// invalid readings are left as is and do not update the estimate. When a reading
+/// deviates from the estimate by more than the reset threshold (an object moved), the
+/// estimate restarts from the reading instead of slowly converging.
+///
+/// ## Example
+///
+/// ```no_run
+/// # fn show(scan: &neato_xv11::data::LidarScan) {
+/// use neato_xv11::filter::{KalmanFilter, ScanFilter};
+///
+/// let mut smoother = KalmanFilter::new(4.0, 100.0);
+///
+/// // `scan` is left untouched for mapping.
+/// let stable = smoother.filtered(scan);
+/// # }
+/// ```
+pub struct KalmanFilter {
+    // Variance added to every estimate between revolutions (square millimeters).
+    process_noise: Float,
+    // Variance of a reading (square millimeters).
+    measurement_noise: Float,
+    // Deviation (millimeters) above which the estimate restarts from the reading.
+    reset_threshold: Option<Float>,
+    // Estimate of each angle.
+    estimates: [Option<Estimate>; SCAN_SIZE],
+}
+
+impl KalmanFilter {
+    /// ## Summary
+    ///
+    /// Initialize a new filter.
+    ///
+    /// ## Parameters
+    ///
+    /// process_noise: Variance added to every estimate between revolutions (square millimeters).
+    /// Higher values follow changes faster.
+    ///
+    /// measurement_noise: Variance of a reading (square millimeters). Higher values smooth more.
+    ///
+    pub fn new(process_noise: Float, measurement_noise: Float) -> Self {
+        KalmanFilter {
+            process_noise: process_noise.max(0.0),
+            measurement_noise: measurement_noise.max(Float::EPSILON),
+            reset_threshold: None,
+            estimates: [None; SCAN_SIZE],
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Restart the estimate of an angle when a reading deviates from it by more than
+    /// the threshold, in millimeters.
+    ///
+    pub fn set_reset_threshold(&mut self, threshold: Float) {
+        self.reset_threshold = Some(threshold);
+    }
+
+    /// ## Summary
+    ///
+    /// Forget every estimate.
+    ///
+    pub fn reset(&mut self) {
+        self.estimates = [None; SCAN_SIZE];
+    }
+}
+
+impl ScanFilter for KalmanFilter {
+    fn apply(&mut self, scan: &mut LidarScan) {
+        for (index, slot) in scan.readings.iter_mut().enumerate().take(SCAN_SIZE) {
+            let estimate = &mut self.estimates[index];
+
+            // Predict.
+            if let Some(estimate) = estimate {
+                estimate.variance += self.process_noise;
+            }
+
+            let reading = match slot.as_mut().filter(|reading| reading.is_valid()) {
+                Some(reading) => reading,
+                None => continue,
+            };
+
+            let measured = reading.distance as Float;
+
+            let updated = match *estimate {
+                Some(current) if self.reset_threshold.is_none_or(|t| (measured - current.distance).abs() <= t) => {
+                    // Update.
+                    let gain = current.variance / (current.variance + self.measurement_noise);
+                    Estimate {
+                        distance: current.distance + gain * (measured - current.distance),
+                        variance: (1.0 - gain) * current.variance,
+                    }
+                },
+                _ => Estimate {
+                    distance: measured,
+                    variance: self.measurement_noise,
+                },
+            };
+
+            *estimate = Some(updated);
+            reading.distance = (updated.distance + 0.5) as i32;
+        }
+    }
+}
+
+/// ## Summary
+///
+/// How the readings of an angle are combined across revolutions.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Accumulation {
+    // Largest distance (e.g. to detect intermittent reflections).
+    Max,
+    // Average distance and quality.
+    Mean,
+    // Smallest distance (e.g. for a conservative obstacle envelope).
+    Min,
+}
+
+/// ## Summary
+///
+/// Combines several consecutive revolutions into a single scan.
+///
+/// ## Remarks
+///
+/// Averaging is intended for stationary, survey-style measurements. Min-hold and
+/// max-hold keep the quality of the held reading. A duration is expressed as a number
+/// of revolutions (at 300 RPM, 5 seconds are 25 revolutions). Invalid readings are
+/// ignored; an angle without any valid reading over the revolutions is missing from
+/// the result.
+///
+/// ## Example
+///
+/// ```no_run
+/// # fn survey(scans: Vec<neato_xv11::data::LidarScan>) {
+/// use neato_xv11::filter::Accumulator;
+///
+/// let mut averager = Accumulator::mean(10);
+///
+/// for scan in &scans {
+///     if let Some(averaged) = averager.push(scan) {
+///         // One denoised scan every 10 revolutions.
+///     }
+/// }
+/// # }
+/// ```
+pub struct Accumulator {
+    // How readings are combined.
+    mode: Accumulation,
+    // Number of revolutions combined into one scan.
+    revolutions: usize,
+    // Number of revolutions pushed since the last emitted scan.
+    pushed: usize,
+    // Sum of the spin speeds of the pushed revolutions.
+    speed_sum: Float,
+    // Sum (or held value) of the valid distances of each angle.
+    distances: [Float; SCAN_SIZE],
+    // Sum (or quality of the held reading) of the qualities of each angle.
+    qualities: [Float; SCAN_SIZE],
+    // Number of valid readings of each angle.
+    counts: [u32; SCAN_SIZE],
+}
+
+impl Accumulator {
+    /// ## Summary
+    ///
+    /// Average the valid readings of each angle over a number of revolutions.
+    ///
+    /// ## Parameters
+    ///
+    /// revolutions: Number of revolutions combined into one scan (at least 1).
+    ///
+    pub fn mean(revolutions: usize) -> Self {
+        Accumulator::new(Accumulation::Mean, revolutions)
+    }
+
+    /// ## Summary
+    ///
+    /// Hold the smallest valid distance of each angle over a number of revolutions.
+    ///
+    /// ## Parameters
+    ///
+    /// revolutions: Number of revolutions combined into one scan (at least 1).
+    ///
+    pub fn min_hold(revolutions: usize) -> Self {
+        Accumulator::new(Accumulation::Min, revolutions)
+    }
+
+    /// ## Summary
+    ///
+    /// Hold the largest valid distance of each angle over a number of revolutions.
+    ///
+    /// ## Parameters
+    ///
+    /// revolutions: Number of revolutions combined into one scan (at least 1).
+    ///
+    pub fn max_hold(revolutions: usize) -> Self {
+        Accumulator::new(Accumulation::Max, revolutions)
+    }
+
+    /// ## Summary
+    ///
+    /// Combine the valid readings of each angle over a number of revolutions.
+    ///
+    /// ## Parameters
+    ///
+    /// mode: How readings are combined.
+    ///
+    /// revolutions: Number of revolutions combined into one scan (at least 1).
+    ///
+    pub fn new(mode: Accumulation, revolutions: usize) -> Self {
+        Accumulator {
+            mode,
+            revolutions: revolutions.max(1),
+            pushed: 0,
+            speed_sum: 0.0,
+            distances: [0.0; SCAN_SIZE],
+            qualities: [0.0; SCAN_SIZE],
+            counts: [0; SCAN_SIZE],
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// How readings are combined.
+    ///
+    pub fn mode(&self) -> Accumulation {
+        self.mode
+    }
+
+    /// ## Summary
+    ///
+    /// Add a revolution. Returns the combined scan once enough revolutions were pushed.
+    ///
+    pub fn push(&mut self, scan: &LidarScan) -> Option<LidarScan> {
+        for reading in scan.valid_readings().filter(|reading| reading.index < SCAN_SIZE) {
+            let index = reading.index;
+            let distance = reading.distance as Float;
+            let quality = reading.quality as Float;
+
+            let replace = match self.mode {
+                Accumulation::Max => self.counts[index] == 0 || distance > self.distances[index],
+                Accumulation::Mean => {
+                    self.distances[index] += distance;
+                    self.qualities[index] += quality;
+                    false
+                },
+                Accumulation::Min => self.counts[index] == 0 || distance < self.distances[index],
+            };
+
+            if replace {
+                self.distances[index] = distance;
+                self.qualities[index] = quality;
+            }
+
+            self.counts[index] += 1;
+        }
+
+        self.speed_sum += scan.speed;
+        self.pushed += 1;
+
+        if self.pushed < self.revolutions {
+            return None;
+        }
+
+        let mut result = LidarScan::empty();
+        result.speed = self.speed_sum / self.pushed as Float;
+
+        for (index, slot) in result.readings.iter_mut().enumerate() {
+            let count = self.counts[index];
+            if count > 0 {
+                let divisor = match self.mode {
+                    Accumulation::Mean => count as Float,
+                    _ => 1.0,
+                };
+                let distance = self.distances[index] / divisor;
+                let quality = self.qualities[index] / divisor;
+                *slot = Some(LidarReading::new(index, (distance + 0.5) as i32, (quality + 0.5) as i32, None));
+            }
+        }
+
+        self.reset();
+        Some(result)
+    }
+
+    /// ## Summary
+    ///
+    /// Discard the revolutions pushed since the last emitted scan.
+    ///
+    pub fn reset(&mut self) {
+        self.pushed = 0;
+        self.speed_sum = 0.0;
+        self.distances = [0.0; SCAN_SIZE];
+        self.qualities = [0.0; SCAN_SIZE];
+        self.counts = [0; SCAN_SIZE];
+    }
+}
+
+/// ## Summary
+///
+/// Removes the invalid, flagged and low-quality readings of a scan.
+///
+/// ## Remarks
+///
+/// Removed readings are left as `None`, like the readings of a missing packet. Readings
+/// flagged with `LidarReadingError::SignalStrengthWarning` are removed as well, unless
+/// kept with `set_keep_weak_signal`.
+///
+pub struct QualityFilter {
+    // Lowest quality kept.
+    min_quality: i32,
+    // Keep the readings flagged with a weak signal.
+    keep_weak_signal: bool,
+}
+
+impl QualityFilter {
+    /// ## Summary
+    ///
+    /// Initialize a new filter.
+    ///
+    /// ## Parameters
+    ///
+    /// min_quality: Lowest quality kept.
+    ///
+    pub fn new(min_quality: i32) -> Self {
+        QualityFilter {
+            min_quality,
+            keep_weak_signal: false,
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Keep the readings flagged with `LidarReadingError::SignalStrengthWarning` if their
+    /// quality is high enough.
+    ///
+    pub fn set_keep_weak_signal(&mut self, keep: bool) {
+        self.keep_weak_signal = keep;
+    }
+}
+
+impl ScanFilter for QualityFilter {
+    fn apply(&mut self, scan: &mut LidarScan) {
+        for slot in scan.readings.iter_mut() {
+            let keep = slot.as_ref().is_some_and(|reading| match reading.error {
+                None => reading.quality >= self.min_quality,
+                Some(LidarReadingError::SignalStrengthWarning) => self.keep_weak_signal && reading.quality >= self.min_quality,
+                Some(LidarReadingError::InvalidDataError(_)) => false,
+            });
+
+            if !keep {
+                *slot = None;
+            }
+        }
+    }
+}
+
+/// ## Summary
+///
+/// Removes the valid readings closer or farther than the range of the sensor, e.g. hits
+/// on the robot's own body or unreliable far returns.
+///
+pub struct RangeFilter {
+    // Shortest distance kept, in millimeters.
+    min_distance: i32,
+    // Longest distance kept, in millimeters.
+    max_distance: i32,
+}
+
+impl RangeFilter {
+    /// ## Summary
+    ///
+    /// Initialize a new filter.
+    ///
+    /// ## Parameters
+    ///
+    /// min_distance: Shortest distance kept, in millimeters.
+    ///
+    /// max_distance: Longest distance kept, in millimeters.
+    ///
+    pub fn new(min_distance: i32, max_distance: i32) -> Self {
+        RangeFilter { min_distance, max_distance }
+    }
+}
+
+impl ScanFilter for RangeFilter {
+    fn apply(&mut self, scan: &mut LidarScan) {
+        for slot in scan.readings.iter_mut() {
+            if slot.as_ref().is_some_and(|reading| reading.is_valid() && !(self.min_distance..=self.max_distance).contains(&reading.distance)) {
+                *slot = None;
+            }
+        }
+    }
+}
+
+/// ## Summary
+///
+/// Replaces each valid distance with the median of the valid distances of its angle over
+/// the last revolutions.
+///
+/// ## Remarks
+///
+/// Unlike `KalmanFilter`, a median removes single-revolution spikes without blurring the
+/// edges of moving objects for long. Angles without a valid reading in the current scan
+/// are left as is.
+///
+#[cfg(feature = "alloc")]
+pub struct MedianFilter {
+    // Number of revolutions the median is taken over.
+    revolutions: usize,
+    // Valid distances of the last revolutions, newest first.
+    history: VecDeque<[Option<i32>; SCAN_SIZE]>,
+    // Distances of one angle, reused between angles.
+    window: Vec<i32>,
+}
+
+#[cfg(feature = "alloc")]
+impl MedianFilter {
+    /// ## Summary
+    ///
+    /// Initialize a new filter.
+    ///
+    /// ## Parameters
+    ///
+    /// revolutions: Number of revolutions the median is taken over, including the current
+    /// one (at least 1).
+    ///
+    pub fn new(revolutions: usize) -> Self {
+        let revolutions = revolutions.max(1);
+
+        MedianFilter {
+            revolutions,
+            history: VecDeque::with_capacity(revolutions),
+            window: Vec::with_capacity(revolutions),
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Forget the previous revolutions.
+    ///
+    pub fn reset(&mut self) {
+        self.history.clear();
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl ScanFilter for MedianFilter {
+    fn apply(&mut self, scan: &mut LidarScan) {
+        if self.history.len() == self.revolutions {
+            self.history.pop_back();
+        }
+
+        self.history.push_front(valid_distances(scan));
+
+        for (index, slot) in scan.readings.iter_mut().enumerate().take(SCAN_SIZE) {
+            let reading = match slot.as_mut().filter(|reading| reading.is_valid()) {
+                Some(reading) => reading,
+                None => continue,
+            };
+
+            self.window.clear();
+            self.window.extend(self.history.iter().filter_map(|distances| distances[index]));
+            self.window.sort_unstable();
+
+            let middle = self.window.len() / 2;
+
+            reading.distance = if self.window.len() % 2 == 1 {
+                self.window[middle]
+            } else {
+                (self.window[middle - 1] + self.window[middle] + 1) / 2
+            };
+        }
+    }
+}
+
+/// ## Summary
+///
+/// Removes isolated readings, which no neighbouring angle confirms. These are usually
+/// mixed pixels on the edge of an object or stray reflections.
+///
+/// ## Remarks
+///
+/// A valid reading is kept if a valid reading within `neighbours` degrees on either side
+/// (1 by default) is at most `max_deviation` millimeters nearer or farther.
+///
+pub struct OutlierFilter {
+    // Largest difference with a neighbour confirming a reading, in millimeters.
+    max_deviation: i32,
+    // Number of angles checked on each side.
+    neighbours: usize,
+}
+
+impl OutlierFilter {
+    /// ## Summary
+    ///
+    /// Initialize a new filter.
+    ///
+    /// ## Parameters
+    ///
+    /// max_deviation: Largest difference with a neighbour confirming a reading, in millimeters.
+    ///
+    pub fn new(max_deviation: i32) -> Self {
+        OutlierFilter {
+            max_deviation,
+            neighbours: 1,
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Set the number of angles checked on each side of a reading (at least 1).
+    ///
+    pub fn set_neighbours(&mut self, neighbours: usize) {
+        self.neighbours = neighbours.clamp(1, SCAN_SIZE / 2);
+    }
+}
+
+impl ScanFilter for OutlierFilter {
+    fn apply(&mut self, scan: &mut LidarScan) {
+        // Compare with the readings before filtering, so removals do not cascade.
+        let distances = valid_distances(scan);
+
+        for (index, slot) in scan.readings.iter_mut().enumerate().take(SCAN_SIZE) {
+            let distance = match distances[index] {
+                Some(distance) => distance,
+                None => continue,
+            };
+
+            let is_confirmed = (1..=self.neighbours)
+                .flat_map(|offset| [(index + offset) % SCAN_SIZE, (index + SCAN_SIZE - offset) % SCAN_SIZE])
+                .filter_map(|neighbour| distances[neighbour])
+                .any(|neighbour| (neighbour - distance).abs() <= self.max_deviation);
+
+            if !is_confirmed {
+                *slot = None;
+            }
+        }
+    }
+}
+
+/// ## Summary
+///
+/// Removes every reading of the masked sectors, e.g. the angles blocked by the robot's
+/// own frame or a mast.
+///
+/// ## Example
+///
+/// ```no_run
+/// # fn show(scan: &neato_xv11::data::LidarScan) {
+/// use neato_xv11::filter::{AngleMask, ScanFilter};
+///
+/// // A mast behind the sensor, and the sector wrapping around 0.
+/// let mut mask = AngleMask::new().with_sector(170, 190).with_sector(355, 5);
+///
+/// let masked = mask.filtered(scan);
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct AngleMask {
+    // Whether the readings of each degree are removed.
+    masked: [bool; SCAN_SIZE],
+}
+
+impl AngleMask {
+    /// ## Summary
+    ///
+    /// Initialize a mask without any sector, which leaves scans untouched.
+    ///
+    pub fn new() -> Self {
+        AngleMask { masked: [false; SCAN_SIZE] }
+    }
+
+    /// ## Summary
+    ///
+    /// Mask the degrees from `start` to `end` included, wrapping around 360 if `end` is
+    /// smaller than `start`.
+    ///
+    pub fn with_sector(mut self, start: usize, end: usize) -> Self {
+        let (start, end) = (start % SCAN_SIZE, end % SCAN_SIZE);
+        let length = (end + SCAN_SIZE - start) % SCAN_SIZE + 1;
+
+        for offset in 0..length {
+            self.masked[(start + offset) % SCAN_SIZE] = true;
+        }
+
+        self
+    }
+}
+
+impl Default for AngleMask {
+    fn default() -> Self {
+        AngleMask::new()
+    }
+}
+
+impl ScanFilter for AngleMask {
+    fn apply(&mut self, scan: &mut LidarScan) {
+        for (slot, masked) in scan.readings.iter_mut().zip(self.masked.iter()) {
+            if *masked {
+                *slot = None;
+            }
+        }
+    }
+}
+
+/// ## Summary
+///
+/// Stages applied in turn to each scan.
+///
+/// ## Remarks
+///
+/// Apply a chain to scans after the fact with `ScanFilter::apply`, or to every scan
+/// assembled by the driver with `scan::ScanAssembler::set_filter`.
+///
+/// ## Example
+///
+/// ```no_run
+/// # fn show(scan: &neato_xv11::data::LidarScan) {
+/// use neato_xv11::filter::{FilterChain, MedianFilter, OutlierFilter, QualityFilter, RangeFilter, ScanFilter};
+///
+/// let mut filters = FilterChain::new()
+///     .with(QualityFilter::new(20))
+///     .with(RangeFilter::new(150, 5000))
+///     .with(OutlierFilter::new(100))
+///     .with(MedianFilter::new(3));
+///
+/// let filtered = filters.filtered(scan);
+/// # }
+/// ```
+#[cfg(feature = "alloc")]
+#[derive(Default)]
+pub struct FilterChain {
+    // The stages, in order.
+    stages: Vec<Box<dyn ScanFilter + Send>>,
+}
+
+#[cfg(feature = "alloc")]
+impl FilterChain {
+    /// ## Summary
+    ///
+    /// Initialize an empty chain, which leaves scans untouched.
+    ///
+    pub fn new() -> Self {
+        FilterChain::default()
+    }
+
+    /// ## Summary
+    ///
+    /// Append a stage to the chain.
+    ///
+    pub fn with<F: ScanFilter + Send + 'static>(mut self, stage: F) -> Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl ScanFilter for FilterChain {
+    fn apply(&mut self, scan: &mut LidarScan) {
+        for stage in &mut self.stages {
+            stage.apply(scan);
+        }
+    }
+}
+
+/// The valid distances of a scan, by angle.
+fn valid_distances(scan: &LidarScan) -> [Option<i32>; SCAN_SIZE] {
+    let mut distances = [None; SCAN_SIZE];
+
+    for (distance, slot) in distances.iter_mut().zip(scan.readings.iter()) {
+        *distance = slot.as_ref().filter(|reading| reading.is_valid()).map(|reading| reading.distance);
+    }
+
+    distances
+}
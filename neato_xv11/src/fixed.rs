@@ -0,0 +1,143 @@
+use super::data::{Float, LidarPacket, LidarReading};
+use super::error::LidarReadingError;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+/// ## Summary
+///
+/// LIDAR spin speed in revolutions per minute, as the unsigned Q10.6 fixed-point
+/// value reported by the LIDAR (10 integer bits, 6 fractional bits).
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Rpm(pub u16);
+
+impl Rpm {
+    /// Number of fractional bits.
+    pub const FRACTIONAL_BITS: u32 = 6;
+
+    /// ## Summary
+    ///
+    /// Initialize a speed from the raw Q10.6 value.
+    ///
+    pub fn from_raw(raw: u16) -> Self {
+        Rpm(raw)
+    }
+
+    /// ## Summary
+    ///
+    /// The raw Q10.6 value.
+    ///
+    pub fn raw(self) -> u16 {
+        self.0
+    }
+
+    /// ## Summary
+    ///
+    /// The integer part of the speed (truncated).
+    ///
+    pub fn whole(self) -> u16 {
+        self.0 >> Self::FRACTIONAL_BITS
+    }
+
+    /// ## Summary
+    ///
+    /// The speed in RPM as a floating point value. The conversion is exact.
+    ///
+    pub fn to_float(self) -> Float {
+        self.0 as Float / (1 << Self::FRACTIONAL_BITS) as Float
+    }
+}
+
+/// ## Summary
+///
+/// A LIDAR distance reading using integer values only.
+///
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct FixedReading {
+    // Index of the reading.
+    pub index: u16,
+    // Distance in millimeters.
+    pub distance: u16,
+    // Quality of the reading.
+    pub quality: u16,
+    // Error reported in reading.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub error: Option<LidarReadingError>,
+}
+
+impl FixedReading {
+    /// ## Summary
+    ///
+    /// Initialize a new fixed-point LIDAR reading.
+    ///
+    /// ## Parameters
+    ///
+    /// index: Index of the reading.
+    ///
+    /// distance: Distance in millimeters.
+    ///
+    /// quality: Quality of the reading.
+    ///
+    /// error: Error reported in reading.
+    ///
+    pub(crate) fn new(index: u16,
+                      distance: u16,
+                      quality: u16,
+                      error: Option<LidarReadingError>) -> Self {
+        FixedReading {
+            index,
+            distance,
+            quality,
+            error,
+        }
+    }
+}
+
+impl From<FixedReading> for LidarReading {
+    fn from(reading: FixedReading) -> Self {
+        LidarReading::new(reading.index as usize, reading.distance as i32, reading.quality as i32, reading.error)
+    }
+}
+
+/// ## Summary
+///
+/// A decoded LIDAR packet using integer values only, for targets without an FPU.
+/// Converts losslessly into `LidarPacket`.
+///
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct FixedPacket {
+    // Collection of four readings.
+    pub readings: [FixedReading; 4],
+    // LIDAR spin speed.
+    pub speed: Rpm,
+}
+
+impl FixedPacket {
+    /// ## Summary
+    ///
+    /// Initialize a new fixed-point LIDAR packet.
+    ///
+    /// ## Parameters
+    ///
+    /// readings: Collection of four readings.
+    ///
+    /// speed: LIDAR spin speed.
+    ///
+    pub(crate) fn new(readings: [FixedReading; 4], speed: Rpm) -> Self {
+        FixedPacket {
+            readings,
+            speed,
+        }
+    }
+}
+
+impl From<FixedPacket> for LidarPacket {
+    fn from(packet: FixedPacket) -> Self {
+        let readings = IntoIterator::into_iter(packet.readings).map(LidarReading::from).collect();
+        LidarPacket::new(readings, packet.speed.to_float())
+    }
+}
@@ -0,0 +1,433 @@
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+use super::data::{Float, LidarScan, Point2D, SCAN_SIZE};
+
+/// Smallest number of readings in a wall.
+const MIN_WALL_READINGS: usize = 8;
+/// Largest distance in millimeters between a reading and the line of its wall.
+const WALL_TOLERANCE: Float = 30.0;
+/// Largest gap in millimeters between neighbouring readings of a wall.
+const WALL_GAP: Float = 200.0;
+
+/// ## Summary
+///
+/// A line fitted to points by total least squares.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Line {
+    // Centroid of the points, on the line.
+    pub(crate) centroid: Point2D,
+    // Direction of the line in radians.
+    pub(crate) direction: Float,
+}
+
+impl Line {
+    /// ## Summary
+    ///
+    /// Fit a line to points. Returns `None` for fewer than 2 points.
+    ///
+    pub(crate) fn fit(points: &[Point2D]) -> Option<Self> {
+        if points.len() < 2 {
+            return None;
+        }
+
+        let count = points.len() as Float;
+        let centroid = Point2D {
+            x: points.iter().map(|p| p.x).sum::<Float>() / count,
+            y: points.iter().map(|p| p.y).sum::<Float>() / count,
+        };
+
+        let (mut sxx, mut syy, mut sxy) = (0.0, 0.0, 0.0);
+
+        for point in points {
+            let (dx, dy) = (point.x - centroid.x, point.y - centroid.y);
+            sxx += dx * dx;
+            syy += dy * dy;
+            sxy += dx * dy;
+        }
+
+        Some(Line {
+            centroid,
+            direction: 0.5 * (2.0 * sxy).atan2(sxx - syy),
+        })
+    }
+
+    /// ## Summary
+    ///
+    /// Perpendicular distance from a point to the line.
+    ///
+    pub(crate) fn distance(&self, point: &Point2D) -> Float {
+        let (sin, cos) = self.direction.sin_cos();
+        ((point.x - self.centroid.x) * sin - (point.y - self.centroid.y) * cos).abs()
+    }
+
+    /// ## Summary
+    ///
+    /// Projection of a point on the line.
+    ///
+    pub(crate) fn project(&self, point: &Point2D) -> Point2D {
+        let (sin, cos) = self.direction.sin_cos();
+        let along = (point.x - self.centroid.x) * cos + (point.y - self.centroid.y) * sin;
+
+        Point2D {
+            x: self.centroid.x + along * cos,
+            y: self.centroid.y + along * sin,
+        }
+    }
+}
+
+/// ## Summary
+///
+/// A closed polygon in the LIDAR frame.
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Polygon {
+    // Vertices in order. The last vertex connects back to the first.
+    pub vertices: Vec<Point2D>,
+}
+
+impl Polygon {
+    /// ## Summary
+    ///
+    /// Whether a point lies inside the polygon.
+    ///
+    pub fn contains(&self, point: &Point2D) -> bool {
+        let mut inside = false;
+        let count = self.vertices.len();
+
+        for i in 0..count {
+            let a = self.vertices[i];
+            let b = self.vertices[(i + count - 1) % count];
+
+            if (a.y > point.y) != (b.y > point.y) && point.x < (b.x - a.x) * (point.y - a.y) / (b.y - a.y) + a.x {
+                inside = !inside;
+            }
+        }
+
+        inside
+    }
+
+    /// ## Summary
+    ///
+    /// Area of the polygon in square millimeters.
+    ///
+    pub fn area(&self) -> Float {
+        let count = self.vertices.len();
+        let twice: Float = (0..count)
+            .map(|i| {
+                let (a, b) = (self.vertices[i], self.vertices[(i + 1) % count]);
+                a.x * b.y - b.x * a.y
+            })
+            .sum();
+
+        twice.abs() / 2.0
+    }
+}
+
+/// ## Summary
+///
+/// An axis-aligned rectangle in the LIDAR frame, in millimeters.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Extents {
+    // Smallest x coordinate.
+    pub min_x: Float,
+    // Smallest y coordinate.
+    pub min_y: Float,
+    // Largest x coordinate.
+    pub max_x: Float,
+    // Largest y coordinate.
+    pub max_y: Float,
+}
+
+impl Extents {
+    /// ## Summary
+    ///
+    /// Smallest rectangle containing every point. Returns `None` if there is no point.
+    ///
+    pub fn of(points: &[Point2D]) -> Option<Self> {
+        let first = points.first()?;
+        let initial = Extents {
+            min_x: first.x,
+            min_y: first.y,
+            max_x: first.x,
+            max_y: first.y,
+        };
+
+        Some(points.iter().fold(initial, |extents, point| Extents {
+            min_x: extents.min_x.min(point.x),
+            min_y: extents.min_y.min(point.y),
+            max_x: extents.max_x.max(point.x),
+            max_y: extents.max_y.max(point.y),
+        }))
+    }
+
+    /// ## Summary
+    ///
+    /// Whether a point lies inside the rectangle, borders included.
+    ///
+    pub fn contains(&self, point: &Point2D) -> bool {
+        (self.min_x..=self.max_x).contains(&point.x) && (self.min_y..=self.max_y).contains(&point.y)
+    }
+
+    /// ## Summary
+    ///
+    /// Width (along x) of the rectangle.
+    ///
+    pub fn width(&self) -> Float {
+        self.max_x - self.min_x
+    }
+
+    /// ## Summary
+    ///
+    /// Depth (along y) of the rectangle.
+    ///
+    pub fn depth(&self) -> Float {
+        self.max_y - self.min_y
+    }
+}
+
+/// ## Summary
+///
+/// A straight wall segment found in a scan.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Wall {
+    // First end of the segment (lowest index) in the LIDAR frame.
+    pub start: Point2D,
+    // Last end of the segment (highest index) in the LIDAR frame.
+    pub end: Point2D,
+    // Perpendicular distance from the sensor to the wall's line in millimeters.
+    pub distance: Float,
+    // Direction of the perpendicular from the sensor to the wall in degrees (-180 to 180),
+    // e.g. 90 for a wall on the left of a robot facing index 0.
+    pub bearing: Float,
+    // Angle between index 0 and the wall in degrees (-90 to 90). 0 when driving parallel to it.
+    pub heading: Float,
+    // Number of readings fitted.
+    pub readings: usize,
+}
+
+impl LidarScan {
+    /// ## Summary
+    ///
+    /// The free space around the sensor: the boundary traced by the valid readings.
+    ///
+    /// ## Parameters
+    ///
+    /// max_range: Distance in millimeters used for missing and invalid readings, and to
+    /// clip farther readings. Gaps are usually open space beyond the LIDAR's range.
+    ///
+    /// ## Remarks
+    ///
+    /// The polygon has one vertex per degree. Useful to fill the free space when
+    /// rendering and for quick collision checks with `Polygon::contains`.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # fn check(scan: &neato_xv11::data::LidarScan) {
+    /// use neato_xv11::data::Point2D;
+    ///
+    /// let free = scan.free_space(4000.0);
+    /// let is_reachable = free.contains(&Point2D { x: 500.0, y: 0.0 });
+    /// # }
+    /// ```
+    pub fn free_space(&self, max_range: Float) -> Polygon {
+        let vertices = (0..SCAN_SIZE)
+            .map(|index| {
+                let distance = self
+                    .get(index)
+                    .filter(|r| r.is_valid() && r.distance > 0)
+                    .map_or(max_range, |r| (r.distance as Float).min(max_range));
+                let (sin, cos) = (index as Float).to_radians().sin_cos();
+
+                Point2D {
+                    x: distance * cos,
+                    y: distance * sin,
+                }
+            })
+            .collect();
+
+        Polygon { vertices }
+    }
+
+    /// ## Summary
+    ///
+    /// Convex hull of the valid readings, counterclockwise. Empty if the scan has no valid reading.
+    ///
+    pub fn convex_hull(&self) -> Polygon {
+        let mut points = self.to_point_cloud();
+        points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+        points.dedup();
+
+        if points.len() < 3 {
+            return Polygon { vertices: points };
+        }
+
+        let cross = |o: &Point2D, a: &Point2D, b: &Point2D| (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x);
+        let mut hull: Vec<Point2D> = Vec::with_capacity(points.len() * 2);
+
+        // Andrew's monotone chain: lower hull, then upper hull.
+        for point in &points {
+            while hull.len() >= 2 && cross(&hull[hull.len() - 2], &hull[hull.len() - 1], point) <= 0.0 {
+                hull.pop();
+            }
+
+            hull.push(*point);
+        }
+
+        let lower = hull.len() + 1;
+
+        for point in points.iter().rev().skip(1) {
+            while hull.len() >= lower && cross(&hull[hull.len() - 2], &hull[hull.len() - 1], point) <= 0.0 {
+                hull.pop();
+            }
+
+            hull.push(*point);
+        }
+
+        hull.pop();
+        Polygon { vertices: hull }
+    }
+
+    /// ## Summary
+    ///
+    /// Axis-aligned extents of the valid readings. Returns `None` if the scan has no valid reading.
+    ///
+    pub fn extents(&self) -> Option<Extents> {
+        Extents::of(&self.to_point_cloud())
+    }
+
+    /// ## Summary
+    ///
+    /// Number of valid readings inside a rectangle, e.g. the footprint of a robot.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # fn check(scan: &neato_xv11::data::LidarScan) {
+    /// use neato_xv11::geometry::Extents;
+    ///
+    /// // 600 mm ahead of the sensor, 400 mm wide.
+    /// let ahead = Extents { min_x: 0.0, min_y: -200.0, max_x: 600.0, max_y: 200.0 };
+    ///
+    /// if scan.count_within(&ahead) > 2 {
+    ///     println!("Obstacle ahead");
+    /// }
+    /// # }
+    /// ```
+    pub fn count_within(&self, extents: &Extents) -> usize {
+        self.valid_readings().filter(|r| extents.contains(&r.to_point())).count()
+    }
+
+    /// ## Summary
+    ///
+    /// Find the closest straight wall segment, the input of a wall-following controller.
+    ///
+    /// ## Remarks
+    ///
+    /// Starting from the closest reading, neighbouring readings are added while they lie
+    /// within 30 mm of the fitted line. A wall needs at least 8 readings, otherwise the
+    /// next closest reading is tried. Returns `None` if no wall is found.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # fn steer(scan: &neato_xv11::data::LidarScan) {
+    /// if let Some(wall) = scan.nearest_wall() {
+    ///     // Keep 300 mm from the wall while staying parallel to it.
+    ///     let error = (wall.distance - 300.0) * 0.01 - wall.heading;
+    /// }
+    /// # }
+    /// ```
+    pub fn nearest_wall(&self) -> Option<Wall> {
+        let points: Vec<Option<Point2D>> = (0..SCAN_SIZE)
+            .map(|index| self.get(index).filter(|r| r.is_valid() && r.distance > 0).map(|r| r.to_point()))
+            .collect();
+
+        let mut seeds: Vec<usize> = (0..SCAN_SIZE).filter(|&index| points[index].is_some()).collect();
+        seeds.sort_by_key(|&index| self.get(index).map(|r| r.distance));
+
+        let mut visited = [false; SCAN_SIZE];
+
+        for seed in seeds {
+            if visited[seed] {
+                continue;
+            }
+
+            let (first, last, line) = grow_wall(&points, seed);
+            let count = (last + SCAN_SIZE - first) % SCAN_SIZE + 1;
+
+            for offset in 0..count {
+                visited[(first + offset) % SCAN_SIZE] = true;
+            }
+
+            if count < MIN_WALL_READINGS {
+                continue;
+            }
+
+            let line = line?;
+            let origin = line.project(&Point2D::default());
+            let heading = line.direction.to_degrees();
+
+            return Some(Wall {
+                start: line.project(&points[first]?),
+                end: line.project(&points[last]?),
+                distance: origin.x.hypot(origin.y),
+                bearing: origin.y.atan2(origin.x).to_degrees(),
+                heading: if heading > 90.0 { heading - 180.0 } else if heading < -90.0 { heading + 180.0 } else { heading },
+                readings: count,
+            });
+        }
+
+        None
+    }
+}
+
+/// Grow a wall from a seed index in both directions. Returns the first and last index
+/// and the fitted line.
+fn grow_wall(points: &[Option<Point2D>], seed: usize) -> (usize, usize, Option<Line>) {
+    let mut wall = vec![points[seed].unwrap_or_default()];
+    let (mut first, mut last) = (seed, seed);
+    let mut line = None;
+
+    for step in [1, SCAN_SIZE - 1] {
+        loop {
+            let (edge, next) = if step == 1 {
+                (last, (last + 1) % SCAN_SIZE)
+            } else {
+                (first, (first + SCAN_SIZE - 1) % SCAN_SIZE)
+            };
+
+            if wall.len() >= SCAN_SIZE || next == seed {
+                break;
+            }
+
+            let (Some(previous), Some(point)) = (points[edge], points[next]) else {
+                break;
+            };
+
+            if point.distance(&previous) > WALL_GAP || line.is_some_and(|line: Line| line.distance(&point) > WALL_TOLERANCE) {
+                break;
+            }
+
+            wall.push(point);
+
+            if wall.len() >= 3 {
+                line = Line::fit(&wall);
+            }
+
+            if step == 1 {
+                last = next;
+            } else {
+                first = next;
+            }
+        }
+    }
+
+    (first, last, line.or_else(|| Line::fit(&wall)))
+}
@@ -0,0 +1,232 @@
+use std::ffi::OsStr;
+use std::io::Error as IoError;
+use std::sync::mpsc::{channel, Receiver};
+use std::thread::{self, JoinHandle};
+
+use super::builder::LidarDriverBuilder;
+use super::control::DriverControl;
+use super::error::{ControlError, LidarDriverError};
+use super::data::{LidarPacket, LidarScan};
+use super::driver::LidarSource;
+use super::message::LidarDriverMessage;
+use super::scan::ScanAssembler;
+
+/// ## Summary
+///
+/// A driver running on its own thread, owning the thread and both channels.
+///
+/// ## Remarks
+///
+/// Commands wait for the driver to acknowledge them, see `control::DriverControl`.
+/// Dropping the handle stops the driver and waits for it to release the port. For
+/// custom message sinks or command channels, use `LidarDriverBuilder::spawn` or `run`.
+///
+/// ## Example
+///
+/// ```no_run
+/// use neato_xv11::prelude::*;
+/// use neato_xv11::LidarDriver;
+///
+/// let driver = LidarDriver::spawn("/dev/serial0").unwrap();
+///
+/// for message in driver.messages().iter().take(1000) {
+///     if let Ok(LidarDriverMessage::Packet(packet)) = message {
+///         println!("{} RPM", packet.speed);
+///     }
+/// }
+///
+/// driver.stop().unwrap();
+/// driver.join().unwrap();
+/// ```
+pub struct LidarDriver {
+    // Sends commands and waits for their acknowledgement.
+    control: DriverControl,
+    // Receives the messages of the driver.
+    messages: Receiver<Result<LidarDriverMessage, LidarDriverError>>,
+    // The driver thread, `None` once joined.
+    thread: Option<JoinHandle<()>>,
+}
+
+impl LidarDriver {
+    /// ## Summary
+    ///
+    /// Start a driver on a serial port with the default settings.
+    ///
+    pub fn spawn<T: AsRef<OsStr> + ?Sized>(port_name: &T) -> Result<Self, IoError> {
+        LidarDriver::spawn_with(LidarDriverBuilder::new(port_name))
+    }
+
+    /// ## Summary
+    ///
+    /// Start a driver configured by a builder.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use neato_xv11::{LidarDriver, LidarDriverBuilder};
+    ///
+    /// let driver = LidarDriver::spawn_with(LidarDriverBuilder::new("/dev/ttyUSB0").watchdog(Duration::from_secs(2))).unwrap();
+    /// ```
+    pub fn spawn_with(builder: LidarDriverBuilder) -> Result<Self, IoError> {
+        let (message_tx, messages) = channel();
+        let (control, command_rx) = DriverControl::channel();
+        let thread = builder.spawn(message_tx, command_rx)?;
+
+        Ok(LidarDriver {
+            control,
+            messages,
+            thread: Some(thread),
+        })
+    }
+
+    /// ## Summary
+    ///
+    /// Start a driver configured by a builder on another source than the serial port.
+    /// See `LidarDriverBuilder::spawn_source`.
+    ///
+    pub fn spawn_source<R, F>(builder: LidarDriverBuilder, open: F) -> Result<Self, IoError>
+    where
+        R: LidarSource + 'static,
+        F: FnOnce() -> Result<R, IoError> + Send + 'static,
+    {
+        let (message_tx, messages) = channel();
+        let (control, command_rx) = DriverControl::channel();
+        let thread = builder.spawn_source(open, message_tx, command_rx)?;
+
+        Ok(LidarDriver {
+            control,
+            messages,
+            thread: Some(thread),
+        })
+    }
+
+    /// ## Summary
+    ///
+    /// The messages of the driver. Iterating ends once the driver thread has exited.
+    ///
+    pub fn messages(&self) -> &Receiver<Result<LidarDriverMessage, LidarDriverError>> {
+        &self.messages
+    }
+
+    /// ## Summary
+    ///
+    /// Block on the packets and errors of the driver. Other messages are skipped.
+    ///
+    /// ## Remarks
+    ///
+    /// Iterating ends on `LidarDriverMessage::Shutdown` or once the driver thread has
+    /// exited. Shares the messages with `messages` and `scans`.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use neato_xv11::LidarDriver;
+    ///
+    /// let driver = LidarDriver::spawn("/dev/serial0").unwrap();
+    ///
+    /// for packet in driver.packets().take(100).flatten() {
+    ///     println!("{} RPM", packet.speed);
+    /// }
+    /// ```
+    pub fn packets(&self) -> impl Iterator<Item = Result<LidarPacket, LidarDriverError>> + '_ {
+        self.messages.iter()
+            .take_while(|message| !matches!(message, Ok(LidarDriverMessage::Shutdown)))
+            .filter_map(|message| match message {
+                Ok(LidarDriverMessage::Packet(packet)) => Some(Ok(packet)),
+                Ok(_) => None,
+                Err(err) => Some(Err(err)),
+            })
+    }
+
+    /// ## Summary
+    ///
+    /// Block on the scans of the driver, assembled from its packets, and on its errors.
+    ///
+    /// ## Remarks
+    ///
+    /// A scan is yielded once the next revolution starts, see `scan::ScanAssembler`.
+    /// Iterating ends like `packets`, without yielding the incomplete last scan.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use neato_xv11::LidarDriver;
+    ///
+    /// let driver = LidarDriver::spawn("/dev/serial0").unwrap();
+    ///
+    /// for scan in driver.scans().take(10).flatten() {
+    ///     println!("{} readings missing", scan.missing());
+    /// }
+    /// ```
+    pub fn scans(&self) -> impl Iterator<Item = Result<LidarScan, LidarDriverError>> + '_ {
+        self.scans_with(ScanAssembler::new())
+    }
+
+    /// ## Summary
+    ///
+    /// Block on the scans of the driver, assembled from its packets by `assembler`, and
+    /// on its errors. See `scans`.
+    ///
+    pub fn scans_with(&self, mut assembler: ScanAssembler) -> impl Iterator<Item = Result<LidarScan, LidarDriverError>> + '_ {
+        self.packets().filter_map(move |packet| match packet {
+            Ok(packet) => assembler.push(packet).map(Ok),
+            Err(err) => Some(Err(err)),
+        })
+    }
+
+    /// ## Summary
+    ///
+    /// A control sending commands to the driver, e.g. from another thread.
+    ///
+    pub fn control(&self) -> &DriverControl {
+        &self.control
+    }
+
+    /// ## Summary
+    ///
+    /// Pause reading. Data received while paused is discarded.
+    ///
+    pub fn pause(&self) -> Result<(), ControlError> {
+        self.control.pause()
+    }
+
+    /// ## Summary
+    ///
+    /// Resume reading after `pause`.
+    ///
+    pub fn resume(&self) -> Result<(), ControlError> {
+        self.control.resume()
+    }
+
+    /// ## Summary
+    ///
+    /// Stop the driver and wait until it has released the port. Messages sent before
+    /// stopping remain in `messages`.
+    ///
+    pub fn stop(&self) -> Result<(), ControlError> {
+        self.control.stop()
+    }
+
+    /// ## Summary
+    ///
+    /// Wait for the driver thread to exit, e.g. after `stop` or once the port is
+    /// disconnected. Returns an error if the thread panicked.
+    ///
+    pub fn join(mut self) -> thread::Result<()> {
+        match self.thread.take() {
+            Some(thread) => thread.join(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for LidarDriver {
+    fn drop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            // The driver may have stopped already.
+            let _ = self.control.stop();
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+
+use super::data::{Float, LidarScan, SCAN_SIZE};
+
+/// How long samples are kept when the spin speed of a scan is unknown.
+const DEFAULT_WINDOW: Duration = Duration::from_millis(200);
+
+/// ## Summary
+///
+/// A sample from an inertial measurement unit mounted on the robot.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImuSample {
+    // When the sample was measured.
+    pub timestamp: Instant,
+    // Rotation speed around the vertical axis in degrees per second, counterclockwise.
+    pub yaw_rate: Float,
+    // Roll angle in degrees.
+    pub roll: Float,
+    // Pitch angle in degrees.
+    pub pitch: Float,
+}
+
+/// ## Summary
+///
+/// Motion of the robot over a scan, summarized from IMU samples.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ImuSummary {
+    // Average yaw rate in degrees per second over the scan.
+    pub yaw_rate: Float,
+    // Largest roll or pitch, in absolute value and degrees, over the scan.
+    pub max_tilt: Float,
+    // Number of samples received during the scan.
+    pub samples: usize,
+}
+
+/// ## Summary
+///
+/// Collects IMU samples from a channel and summarizes them per scan.
+///
+/// ## Remarks
+///
+/// Samples older than the scan being summarized are discarded. Scans received
+/// while no sample covers them are left as is.
+///
+pub struct ImuInput {
+    // Receives the samples.
+    rx: Receiver<ImuSample>,
+    // Samples not older than the last scan.
+    samples: Vec<ImuSample>,
+}
+
+impl ImuInput {
+    /// ## Summary
+    ///
+    /// Read samples from the receiving half of a channel.
+    ///
+    pub fn new(rx: Receiver<ImuSample>) -> Self {
+        ImuInput {
+            rx,
+            samples: Vec::new(),
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Summarize the samples of the scan completed at `end`. Returns `None` if no sample
+    /// was received during the scan.
+    ///
+    pub fn summarize(&mut self, scan: &LidarScan, end: Instant) -> Option<ImuSummary> {
+        self.samples.extend(self.rx.try_iter());
+
+        let start = end.checked_sub(scan_duration(scan).unwrap_or(DEFAULT_WINDOW)).unwrap_or(end);
+
+        // Samples may arrive out of order.
+        self.samples.retain(|sample| sample.timestamp >= start);
+
+        let samples: Vec<&ImuSample> = self.samples.iter().filter(|sample| sample.timestamp <= end).collect();
+
+        if samples.is_empty() {
+            return None;
+        }
+
+        Some(ImuSummary {
+            yaw_rate: samples.iter().map(|sample| sample.yaw_rate).sum::<Float>() / samples.len() as Float,
+            max_tilt: samples.iter().map(|sample| sample.roll.abs().max(sample.pitch.abs())).fold(0.0, Float::max),
+            samples: samples.len(),
+        })
+    }
+}
+
+/// ## Summary
+///
+/// Time taken by the revolution of a scan, from its spin speed. Returns `None` if the
+/// speed is not positive.
+///
+pub fn scan_duration(scan: &LidarScan) -> Option<Duration> {
+    (scan.speed > 0.0).then(|| Duration::from_micros((60_000_000.0 / scan.speed) as u64))
+}
+
+/// ## Summary
+///
+/// Correct a scan for the rotation of the robot during the revolution, expressing
+/// every reading in the sensor frame at the end of the scan.
+///
+/// ## Parameters
+///
+/// scan: The scan, readings are moved to their corrected index.
+///
+/// yaw_rate: Rotation speed of the robot around the vertical axis in degrees per second, counterclockwise.
+///
+/// ## Remarks
+///
+/// The yaw rate is assumed constant over the revolution, and the revolution to start
+/// at index 0. Readings landing on the same index overwrite each other. Does nothing
+/// if the spin speed of the scan is unknown.
+///
+pub fn deskew(scan: &mut LidarScan, yaw_rate: Float) {
+    let Some(duration) = scan_duration(scan) else {
+        return;
+    };
+
+    let period = duration.as_secs_f64() as Float;
+    let readings = core::mem::replace(&mut scan.readings, (0..SCAN_SIZE).map(|_| None).collect());
+
+    for mut reading in readings.into_iter().flatten() {
+        // Rotation of the robot between the reading and the end of the scan.
+        let elapsed = period * (SCAN_SIZE - reading.index % SCAN_SIZE) as Float / SCAN_SIZE as Float;
+        let rotation = (yaw_rate * elapsed).round() as i64;
+        let index = (reading.index as i64 - rotation).rem_euclid(SCAN_SIZE as i64) as usize;
+
+        reading.index = index;
+        scan.readings[index] = Some(reading);
+    }
+}
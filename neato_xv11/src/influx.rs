@@ -0,0 +1,385 @@
+#[cfg(feature = "tls")]
+use std::convert::TryFrom;
+use std::fmt::Write as _;
+use std::io::{Error as IoError, ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+#[cfg(feature = "tls")]
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::mpsc::SendError;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "log")]
+use log::warn;
+#[cfg(feature = "tls")]
+use rustls::pki_types::ServerName;
+#[cfg(feature = "tls")]
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+
+use super::data::{LidarScan, SCAN_SIZE};
+use super::prelude::*;
+use super::scan::ScanAssembler;
+
+/// Longest wait for the database to accept a write.
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Where the points are written.
+enum Transport {
+    // Datagrams to a UDP listener.
+    Udp(UdpSocket),
+    // HTTP requests to a write endpoint.
+    Http {
+        host: String,
+        path: String,
+        token: Option<String>,
+        // Encrypts the requests when set.
+        #[cfg(feature = "tls")]
+        tls: Option<Arc<ClientConfig>>,
+    },
+}
+
+impl Transport {
+    /// Write a batch of lines.
+    fn write(&self, body: &str) -> Result<(), IoError> {
+        match self {
+            Transport::Udp(socket) => socket.send(body.as_bytes()).map(|_| ()),
+            Transport::Http {
+                host,
+                path,
+                token,
+                #[cfg(feature = "tls")]
+                tls,
+            } => {
+                let address = host.to_socket_addrs()?.next().ok_or_else(|| IoError::from(ErrorKind::AddrNotAvailable))?;
+                let stream = TcpStream::connect_timeout(&address, NETWORK_TIMEOUT)?;
+                stream.set_read_timeout(Some(NETWORK_TIMEOUT))?;
+                stream.set_write_timeout(Some(NETWORK_TIMEOUT))?;
+
+                let mut request = format!("POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n", path, host, body.len());
+
+                if let Some(token) = token {
+                    let _ = write!(request, "Authorization: Token {}\r\n", token);
+                }
+
+                request.push_str("\r\n");
+                request.push_str(body);
+
+                #[cfg(feature = "tls")]
+                if let Some(config) = tls {
+                    let name = ServerName::try_from(String::from(server_name(host))).map_err(|err| IoError::new(ErrorKind::InvalidInput, err))?;
+                    let connection = ClientConnection::new(config.clone(), name).map_err(IoError::other)?;
+                    return exchange(StreamOwned::new(connection, stream), &request);
+                }
+
+                exchange(stream, &request)
+            },
+        }
+    }
+}
+
+/// Send a request and check the status of the response.
+fn exchange<S: Read + Write>(mut stream: S, request: &str) -> Result<(), IoError> {
+    stream.write_all(request.as_bytes())?;
+
+    // Status line, e.g. "HTTP/1.1 204 No Content".
+    let mut response = [0; 12];
+    stream.read_exact(&mut response)?;
+
+    match response.get(9) {
+        Some(b'2') => Ok(()),
+        _ => Err(IoError::other(format!("InfluxDB rejected the write: {}", String::from_utf8_lossy(&response)))),
+    }
+}
+
+/// Host name of a `host:port` address, as checked against the server certificate.
+#[cfg(feature = "tls")]
+fn server_name(host: &str) -> &str {
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if !name.is_empty() && port.bytes().all(|byte| byte.is_ascii_digit()) => name,
+        _ => host,
+    };
+
+    name.trim_start_matches('[').trim_end_matches(']')
+}
+
+/// State of an `InfluxSink`.
+struct State {
+    assembler: ScanAssembler,
+    // Number of equal sectors summarized per scan, 0 to disable.
+    sectors: usize,
+    // Errors since the last point, by kind.
+    checksum_errors: u64,
+    resyncs: u64,
+    other_errors: u64,
+}
+
+/// ## Summary
+///
+/// A message sink that writes driver metrics to InfluxDB (or any database accepting
+/// the line protocol, such as Telegraf or VictoriaMetrics), one point per revolution.
+/// Every message is forwarded to the inner sink.
+///
+/// ## Remarks
+///
+/// Each revolution writes a point to the measurement (`lidar` by default) with the
+/// fields `rpm`, `missing`, `valid`, `quality_mean`, `checksum_errors`, `resyncs`,
+/// `other_errors` (errors since the previous point) and `dropped` (messages dropped
+/// by the inner sink so far). With `with_sectors`, a point per sector follows, tagged
+/// `sector`, with the fields `min_distance` and `valid`.
+///
+/// Points are written from the driver thread, with a 1 second timeout. Writing is
+/// best effort: failed writes are skipped. Over a shared network, use `https` (feature
+/// `tls`) so the token and the sector distances are not sent in clear.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::thread;
+/// # use std::sync::mpsc::channel;
+/// use neato_xv11::influx::InfluxSink;
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// let message_tx = InfluxSink::http(message_tx, "localhost:8086", "/api/v2/write?org=robot&bucket=telemetry&precision=ns")
+///     .with_token("secret")
+///     .with_tag("robot", "rover-1")
+///     .with_sectors(8);
+///
+/// thread::spawn(move || {
+///     neato_xv11::run("/dev/serial0", message_tx, command_rx);
+/// });
+/// ```
+pub struct InfluxSink<S> {
+    inner: S,
+    transport: Transport,
+    // Measurement and tags, escaped.
+    series: String,
+    state: Mutex<State>,
+}
+
+impl<S: MessageSink> InfluxSink<S> {
+    /// ## Summary
+    ///
+    /// Write points as UDP datagrams, e.g. to the UDP listener of InfluxDB 1.x or Telegraf.
+    ///
+    pub fn udp<A: ToSocketAddrs>(inner: S, address: A) -> Result<Self, IoError> {
+        let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+        socket.connect(address)?;
+
+        Ok(InfluxSink::new(inner, Transport::Udp(socket)))
+    }
+
+    /// ## Summary
+    ///
+    /// Write points with HTTP requests.
+    ///
+    /// ## Parameters
+    ///
+    /// inner: The sink messages are forwarded to.
+    ///
+    /// host: Host and port of the database, e.g. `localhost:8086`.
+    ///
+    /// path: Write endpoint with its query, e.g. `/write?db=robot` (InfluxDB 1.x) or
+    /// `/api/v2/write?org=robot&bucket=telemetry&precision=ns` (InfluxDB 2.x).
+    ///
+    pub fn http(inner: S, host: &str, path: &str) -> Self {
+        InfluxSink::new(
+            inner,
+            Transport::Http {
+                host: String::from(host),
+                path: String::from(path),
+                token: None,
+                #[cfg(feature = "tls")]
+                tls: None,
+            },
+        )
+    }
+
+    /// ## Summary
+    ///
+    /// Write points with HTTPS requests, checking the server certificate against the
+    /// Mozilla root certificates.
+    ///
+    /// ## Parameters
+    ///
+    /// inner: The sink messages are forwarded to.
+    ///
+    /// host: Host name and port of the database, e.g. `influx.example.com:8086`. The
+    /// certificate must be issued for the host name.
+    ///
+    /// path: Write endpoint with its query, as for `http`.
+    ///
+    #[cfg(feature = "tls")]
+    pub fn https(inner: S, host: &str, path: &str) -> Self {
+        let roots: RootCertStore = webpki_roots::TLS_SERVER_ROOTS.iter().cloned().collect();
+        let config = ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .expect("the ring provider supports the default protocol versions")
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+        InfluxSink::http(inner, host, path).with_tls_config(Arc::new(config))
+    }
+
+    /// ## Summary
+    ///
+    /// Encrypt HTTP writes with a custom TLS configuration, e.g. trusting a private
+    /// certificate authority or presenting a client certificate. Ignored over UDP.
+    ///
+    #[cfg(feature = "tls")]
+    pub fn with_tls_config(mut self, config: Arc<ClientConfig>) -> Self {
+        if let Transport::Http { tls, .. } = &mut self.transport {
+            *tls = Some(config);
+        }
+
+        self
+    }
+
+    /// Initialize a sink writing to a transport.
+    fn new(inner: S, transport: Transport) -> Self {
+        InfluxSink {
+            inner,
+            transport,
+            series: String::from("lidar"),
+            state: Mutex::new(State {
+                assembler: ScanAssembler::new(),
+                sectors: 0,
+                checksum_errors: 0,
+                resyncs: 0,
+                other_errors: 0,
+            }),
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Authenticate HTTP writes with an InfluxDB API token. Ignored over UDP.
+    ///
+    pub fn with_token(mut self, value: &str) -> Self {
+        if let Transport::Http { token, .. } = &mut self.transport {
+            *token = Some(String::from(value));
+        }
+
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Set the measurement the points are written to. Defaults to `lidar`. Tags added
+    /// before are discarded.
+    ///
+    pub fn with_measurement(mut self, measurement: &str) -> Self {
+        self.series = escape(measurement, &[',', ' ']);
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Add a tag to every point, e.g. the robot or port name.
+    ///
+    pub fn with_tag(mut self, key: &str, value: &str) -> Self {
+        let _ = write!(self.series, ",{}={}", escape(key, &[',', '=', ' ']), escape(value, &[',', '=', ' ']));
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Also write a point per sector of every scan, the scan split in `sectors` equal sectors.
+    ///
+    pub fn with_sectors(self, sectors: usize) -> Self {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).sectors = sectors;
+        self
+    }
+
+    /// Lines of the points of a scan.
+    fn lines(&self, state: &mut State, scan: &LidarScan) -> String {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_nanos());
+        let valid = scan.valid_readings().count();
+        let quality_mean = scan.quality_stats(0).map_or(0.0, |stats| stats.mean);
+
+        let mut lines = format!(
+            "{} rpm={},missing={}i,valid={}i,quality_mean={},checksum_errors={}i,resyncs={}i,other_errors={}i,dropped={}i {}\n",
+            self.series,
+            scan.speed,
+            scan.missing(),
+            valid,
+            quality_mean,
+            state.checksum_errors,
+            state.resyncs,
+            state.other_errors,
+            self.inner.dropped(),
+            timestamp,
+        );
+
+        for sector in 0..state.sectors {
+            let readings = (sector * SCAN_SIZE / state.sectors..(sector + 1) * SCAN_SIZE / state.sectors)
+                .filter_map(|index| scan.get(index))
+                .filter(|reading| reading.is_valid() && reading.distance > 0);
+            let (count, min_distance) = readings.fold((0, None), |(count, min), reading| (count + 1, Some(min.map_or(reading.distance, |min: i32| min.min(reading.distance)))));
+
+            let _ = write!(lines, "{},sector={} valid={}i", self.series, sector, count);
+
+            if let Some(min_distance) = min_distance {
+                let _ = write!(lines, ",min_distance={}i", min_distance);
+            }
+
+            let _ = writeln!(lines, " {}", timestamp);
+        }
+
+        state.checksum_errors = 0;
+        state.resyncs = 0;
+        state.other_errors = 0;
+        lines
+    }
+}
+
+/// Escape the characters the line protocol reserves in a name.
+fn escape(name: &str, reserved: &[char]) -> String {
+    let mut escaped = String::with_capacity(name.len());
+
+    for c in name.chars() {
+        if reserved.contains(&c) {
+            escaped.push('\\');
+        }
+
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+impl<S: MessageSink> MessageSink for InfluxSink<S> {
+    #[cfg_attr(not(feature = "log"), allow(unused_variables))]
+    fn send(&self, message: Result<LidarDriverMessage, LidarDriverError>) -> Result<(), SendError<Result<LidarDriverMessage, LidarDriverError>>> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        match &message {
+            Ok(LidarDriverMessage::Packet(packet)) => {
+                if let Some(scan) = state.assembler.push(packet.clone()) {
+                    let lines = self.lines(&mut state, &scan);
+
+                    if let Err(err) = self.transport.write(&lines) {
+                        #[cfg(feature = "log")]
+                        warn!("Unable to write to InfluxDB. {}", err);
+                    }
+                }
+            },
+            Err(LidarDriverError::Checksum(..)) => state.checksum_errors += 1,
+            Err(LidarDriverError::ResyncRequired) => state.resyncs += 1,
+            Err(_) => state.other_errors += 1,
+            Ok(_) => {},
+        }
+
+        drop(state);
+        self.inner.send(message)
+    }
+
+    fn dropped(&self) -> u64 {
+        self.inner.dropped()
+    }
+
+    fn backlog(&self) -> Option<Backlog> {
+        self.inner.backlog()
+    }
+}
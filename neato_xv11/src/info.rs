@@ -0,0 +1,80 @@
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+#[cfg(feature = "alloc")]
+use super::data::Float;
+use super::model::Model;
+#[cfg(feature = "alloc")]
+use super::protocol::LidarProtocol;
+
+/// ## Summary
+///
+/// A LIDAR the driver is able to detect.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum DetectedModel {
+    // LDROBOT LD06, LD19 or LD-02.
+    #[cfg(feature = "ldlidar")]
+    LdLidar,
+    // A Neato LIDAR.
+    Neato(Model),
+}
+
+impl DetectedModel {
+    /// ## Summary
+    ///
+    /// The protocol used to decode the packets of this LIDAR.
+    ///
+    #[cfg(feature = "alloc")]
+    pub fn protocol(self) -> Box<dyn LidarProtocol + Send> {
+        match self {
+            #[cfg(feature = "ldlidar")]
+            DetectedModel::LdLidar => Box::new(super::ldlidar::LdLidar),
+            DetectedModel::Neato(model) => Box::new(model),
+        }
+    }
+}
+
+/// ## Summary
+///
+/// The LIDAR found by protocol detection, sent in a `LidarDriverMessage::Info`.
+///
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct LidarInfo {
+    // The detected LIDAR.
+    pub model: DetectedModel,
+    // The baud rate the LIDAR is talking at.
+    pub baud_rate: u32,
+}
+
+/// ## Summary
+///
+/// Identification of a LIDAR unit, sent in a `LidarDriverMessage::DeviceInfo`.
+///
+/// ## Remarks
+///
+/// `firmware` and `serial` are only known when the LIDAR answered the info query.
+/// Otherwise the observed speed range and packet rate identify the unit.
+///
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct DeviceInfo {
+    // Firmware version reported by the LIDAR (e.g. "V2.6.15295").
+    pub firmware: Option<String>,
+    // Serial number reported by the LIDAR.
+    pub serial: Option<String>,
+    // Slowest observed spin speed (RPM).
+    pub min_speed: Option<Float>,
+    // Fastest observed spin speed (RPM).
+    pub max_speed: Option<Float>,
+    // Observed number of packets per second.
+    pub packet_rate: Option<Float>,
+}
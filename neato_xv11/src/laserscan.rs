@@ -0,0 +1,84 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+use super::data::{LidarScan, SCAN_SIZE};
+
+/// Closest distance the XV-11 measures, in meters.
+pub const RANGE_MIN: f32 = 0.06;
+/// Farthest distance the XV-11 measures, in meters.
+pub const RANGE_MAX: f32 = 5.0;
+
+/// ## Summary
+///
+/// A scan shaped like a ROS `sensor_msgs/LaserScan`, without the header.
+///
+/// ## Remarks
+///
+/// Follows REP-103: ranges in meters, counterclockwise from the x axis (index 0), one
+/// beam per degree. Missing and invalid readings are `NaN`, intensities are the reading
+/// qualities. The timing is derived from the spin speed, and is 0 while it is unknown.
+///
+/// ## Example
+///
+/// ```no_run
+/// # fn publish(scan: &neato_xv11::data::LidarScan) {
+/// use neato_xv11::laserscan::LaserScan;
+///
+/// let message = LaserScan::from(scan);
+/// println!("{} beams over {:.3} s", message.ranges.len(), message.scan_time);
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct LaserScan {
+    // Angle of the first beam (radians).
+    pub angle_min: f32,
+    // Angle of the last beam (radians).
+    pub angle_max: f32,
+    // Angle between two beams (radians).
+    pub angle_increment: f32,
+    // Time between two beams (seconds).
+    pub time_increment: f32,
+    // Time of a revolution (seconds).
+    pub scan_time: f32,
+    // Closest valid range (meters).
+    pub range_min: f32,
+    // Farthest valid range (meters).
+    pub range_max: f32,
+    // Range of each beam (meters), `NaN` when missing.
+    pub ranges: Vec<f32>,
+    // Quality of each beam.
+    pub intensities: Vec<f32>,
+}
+
+impl From<&LidarScan> for LaserScan {
+    fn from(scan: &LidarScan) -> Self {
+        let mut ranges = vec![f32::NAN; SCAN_SIZE];
+        let mut intensities = vec![0.0; SCAN_SIZE];
+
+        for reading in scan.valid_readings() {
+            // Beam `i` is `i` degrees counterclockwise, the sensor indexes clockwise.
+            let beam = (SCAN_SIZE - reading.index % SCAN_SIZE) % SCAN_SIZE;
+            ranges[beam] = reading.distance as f32 / 1000.0;
+            intensities[beam] = reading.quality as f32;
+        }
+
+        let scan_time = if scan.speed > 0.0 { 60.0 / scan.speed as f32 } else { 0.0 };
+        let angle_increment = (1.0f32).to_radians();
+
+        LaserScan {
+            angle_min: 0.0,
+            angle_max: angle_increment * (SCAN_SIZE - 1) as f32,
+            angle_increment,
+            time_increment: scan_time / SCAN_SIZE as f32,
+            scan_time,
+            range_min: RANGE_MIN,
+            range_max: RANGE_MAX,
+            ranges,
+            intensities,
+        }
+    }
+}
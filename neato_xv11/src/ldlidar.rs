@@ -0,0 +1,127 @@
+#[cfg(feature = "log")]
+use log::error;
+
+use super::data::{Float, LidarPacket, LidarReading, Readings};
+use super::error::{LidarDriverError, LidarReadingError};
+use super::protocol::LidarProtocol;
+
+/// Size of an encoded packet in bytes.
+const FRAME_SIZE: usize = 47;
+
+/// First byte of every packet.
+const FRAME_HEADER: u8 = 0x54;
+
+/// Second byte of every packet: packet type 1, 12 points.
+const FRAME_VER_LEN: u8 = 0x2C;
+
+/// Number of readings in a packet.
+const POINTS: usize = 12;
+
+/// ## Summary
+///
+/// Calculate the CRC-8 (polynomial 0x4D) of a packet.
+///
+pub(crate) fn calc_crc(data: &[u8]) -> u8 {
+    data.iter().fold(0, |crc, &byte| {
+        (0..8).fold(crc ^ byte, |crc, _| {
+            if crc & 0x80 > 0 {
+                (crc << 1) ^ 0x4D
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+/// ## Summary
+///
+/// The protocol of the LDROBOT LD06, LD19 and LD-02 (Delta-2D class) LIDARs.
+///
+/// ## Remarks
+///
+/// 47 byte packet format:
+/// [0x54, 0x2C, 2-byte speed (deg/s), 2-byte start angle, [2-byte distance, 1-byte intensity] * 12,
+/// 2-byte end angle, 2-byte timestamp, 1-byte CRC-8]
+/// All multi-byte values are little endian. Angles are in hundredths of a degree.
+///
+/// The reading index is the angle of the reading rounded to the nearest degree (0-359).
+/// A distance of 0 (no return) is reported as `LidarReadingError::InvalidDataError(0)`.
+/// The LIDAR runs at 230400 baud.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::sync::mpsc::channel;
+/// use neato_xv11::LidarDriverBuilder;
+/// use neato_xv11::ldlidar::LdLidar;
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// let handle = LidarDriverBuilder::new("/dev/ttyUSB0")
+///     .protocol(LdLidar)
+///     .baud_rate(230400)
+///     .spawn(message_tx, command_rx)
+///     .unwrap();
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LdLidar;
+
+impl LidarProtocol for LdLidar {
+    fn header_size(&self) -> usize {
+        2
+    }
+
+    fn accepts(&self, position: usize, byte: u8) -> bool {
+        match position {
+            0 => byte == FRAME_HEADER,
+            _ => byte == FRAME_VER_LEN,
+        }
+    }
+
+    fn frame_size(&self) -> usize {
+        FRAME_SIZE
+    }
+
+    fn decode(&self, frame: &[u8]) -> Result<LidarPacket, LidarDriverError> {
+        let word = |i: usize| (frame[i + 1] as u32) << 8 | frame[i] as u32;
+
+        let start_angle = word(4);
+        let mut end_angle = word(42);
+
+        if calc_crc(&frame[..FRAME_SIZE - 1]) != frame[FRAME_SIZE - 1] {
+            #[cfg(feature = "log")]
+            error!("A checksum error occured. The data is corrupted");
+
+            return Err(LidarDriverError::Checksum(start_angle as usize / 100, None));
+        }
+
+        if end_angle < start_angle {
+            // The packet crosses 0 degrees.
+            end_angle += 36000;
+        }
+
+        let step = (end_angle - start_angle) / (POINTS as u32 - 1);
+
+        let readings: Readings = (0..POINTS).map(|i| {
+            let byte_index = 6 + 3 * i;
+            let distance = word(byte_index) as i32;
+            let quality = frame[byte_index + 2] as i32;
+            let angle = start_angle + step * i as u32;
+            let index = ((angle + 50) / 100 % 360) as usize;
+
+            let error = if distance == 0 {
+                Some(LidarReadingError::InvalidDataError(0))
+            } else {
+                None
+            };
+
+            LidarReading::new(index, distance, quality, error)
+        }).collect();
+
+        // Degrees per second to revolutions per minute.
+        let speed = word(2) as Float / 6.0;
+
+        Ok(LidarPacket::new(readings, speed))
+    }
+}
@@ -1,13 +1,152 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "async")]
+mod async_driver;
+pub mod background;
+#[cfg(feature = "std")]
+mod baud;
+#[cfg(feature = "std")]
+pub mod bench;
+#[cfg(feature = "std")]
+mod builder;
+pub mod calibration;
+#[cfg(feature = "zstd")]
+pub mod capture;
+#[cfg(feature = "std")]
+pub mod clock;
+#[cfg(all(feature = "std", feature = "serde"))]
+pub mod config;
+#[cfg(feature = "std")]
+pub mod console;
+#[cfg(feature = "std")]
+pub mod control;
+pub mod convention;
+#[cfg(feature = "std")]
 mod driver;
+#[cfg(all(test, feature = "std"))]
 mod test;
 pub mod data;
+#[cfg(feature = "std")]
+pub mod device_info;
+#[cfg(feature = "std")]
+pub mod discovery;
+#[cfg(feature = "embassy")]
+pub mod embassy;
+#[cfg(feature = "embedded")]
+pub mod embedded;
 pub mod error;
+#[cfg(feature = "std")]
+pub mod error_policy;
+#[cfg(feature = "std")]
+pub mod export;
+#[cfg(feature = "std")]
+pub mod fault;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(all(feature = "std", unix))]
+mod fd;
+pub mod filter;
+pub mod fixed;
+#[cfg(feature = "std")]
+pub mod geometry;
+#[cfg(feature = "std")]
+mod handle;
+pub mod info;
+#[cfg(feature = "std")]
+pub mod imu;
+#[cfg(feature = "influx")]
+pub mod influx;
+#[cfg(feature = "laserscan")]
+pub mod laserscan;
+#[cfg(feature = "ldlidar")]
+pub mod ldlidar;
+#[cfg(feature = "std")]
+pub mod mapping;
+#[cfg(feature = "std")]
+pub mod matching;
 pub mod message;
+pub mod model;
+pub mod motor;
+pub mod mounting;
+#[cfg(feature = "std")]
+pub mod noise;
+#[cfg(all(feature = "std", windows))]
+mod overlapped;
+pub mod parser;
+#[cfg(feature = "std")]
+pub mod pcap;
+pub mod protocol;
+pub mod quirks;
+pub mod rpm;
+pub mod scan;
+#[cfg(feature = "std")]
+mod port;
+#[cfg(feature = "std")]
+pub mod multi;
+#[cfg(feature = "net")]
+pub mod net;
+#[cfg(feature = "std")]
+pub mod queue;
+#[cfg(feature = "std")]
+pub mod rate_limit;
+#[cfg(feature = "std")]
+pub mod reconnect;
+#[cfg(feature = "std")]
+pub mod recording;
+#[cfg(feature = "std")]
+mod ring;
+#[cfg(feature = "std")]
+mod sched;
+#[cfg(feature = "signal")]
+pub mod signal;
+#[cfg(feature = "rosbag")]
+pub mod rosbag;
+#[cfg(feature = "sim")]
+pub mod sim;
+#[cfg(feature = "std")]
+pub mod sink;
+#[cfg(feature = "std")]
+pub mod slam;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod stats;
+pub mod status;
+#[cfg(feature = "std")]
+pub mod summary;
+#[cfg(feature = "std")]
+pub mod sync;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "std")]
+pub mod tracking;
 
 pub mod prelude {
-    pub use crate::data::{LidarReading, LidarPacket};
+    #[cfg(feature = "std")]
+    pub use crate::control::CommandSource;
+    pub use crate::data::{Float, LidarReading, LidarPacket, LidarScan};
     pub use crate::error::{LidarDriverError, LidarReadingError};
     pub use crate::message::{LidarDriverCommand, LidarDriverMessage};
+    pub use crate::model::Model;
+    pub use crate::parser::Parser;
+    #[cfg(feature = "std")]
+    pub use crate::queue::{bounded, OverflowPolicy};
+    #[cfg(feature = "std")]
+    pub use crate::sink::MessageSink;
+    pub use crate::status::{Backlog, DriverStatus};
 }
 
-pub use driver::*;
\ No newline at end of file
+#[cfg(feature = "async")]
+pub use async_driver::*;
+#[cfg(feature = "std")]
+pub use baud::*;
+#[cfg(feature = "std")]
+pub use builder::*;
+#[cfg(feature = "std")]
+pub use driver::*;
+#[cfg(all(feature = "std", unix))]
+pub use fd::run_fd;
+#[cfg(feature = "std")]
+pub use handle::*;
@@ -0,0 +1,289 @@
+use std::fs::File;
+use std::io::{BufWriter, Error as IoError, Write};
+use std::path::Path;
+
+use super::data::{Float, LidarScan, Point2D};
+use super::slam::{Pose, ScanConsumer, StampedScan};
+
+/// Log odds added to a cell a reading ended in.
+const LOG_ODDS_HIT: Float = 0.85;
+/// Log odds added to a cell a reading passed through.
+const LOG_ODDS_MISS: Float = -0.4;
+/// Bound of the log odds of a cell, so the map can still change.
+const LOG_ODDS_LIMIT: Float = 5.0;
+/// Probability above which a cell is occupied in exported maps.
+const OCCUPIED_THRESHOLD: Float = 0.65;
+/// Probability below which a cell is free in exported maps.
+const FREE_THRESHOLD: Float = 0.196;
+
+/// ## Summary
+///
+/// An occupancy grid map built from pose-stamped scans.
+///
+/// ## Remarks
+///
+/// Each cell holds the log odds of being occupied. Cells along a reading are marked
+/// free and the cell it ends in occupied. The grid also implements `ScanConsumer`,
+/// inserting every scan that has a pose.
+///
+/// ## Example
+///
+/// ```no_run
+/// # fn build(scans: Vec<neato_xv11::slam::StampedScan>) -> std::io::Result<()> {
+/// use neato_xv11::mapping::OccupancyGrid;
+///
+/// // 10 m x 10 m at 5 cm per cell, centered on the starting pose.
+/// let mut grid = OccupancyGrid::new(10_000.0, 10_000.0, 50.0);
+///
+/// for stamped in &scans {
+///     if let Some(pose) = &stamped.pose {
+///         grid.insert(&stamped.scan, pose);
+///     }
+/// }
+///
+/// // Writes map.pgm and map.yaml for ROS map_server.
+/// grid.save("map")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct OccupancyGrid {
+    // Size of a cell in millimeters.
+    resolution: Float,
+    // Number of columns.
+    width: usize,
+    // Number of rows.
+    height: usize,
+    // Position of the corner of cell (0, 0) in the map frame, in millimeters.
+    origin: Point2D,
+    // Log odds of each cell, row by row from the lowest y.
+    log_odds: Vec<Float>,
+}
+
+impl OccupancyGrid {
+    /// ## Summary
+    ///
+    /// Initialize an unknown grid centered on the map origin.
+    ///
+    /// ## Parameters
+    ///
+    /// width: Extent of the map along x in millimeters.
+    ///
+    /// height: Extent of the map along y in millimeters.
+    ///
+    /// resolution: Size of a cell in millimeters.
+    ///
+    pub fn new(width: Float, height: Float, resolution: Float) -> Self {
+        let resolution = resolution.max(1.0);
+        let columns = (width / resolution).ceil().max(1.0) as usize;
+        let rows = (height / resolution).ceil().max(1.0) as usize;
+
+        OccupancyGrid {
+            resolution,
+            width: columns,
+            height: rows,
+            origin: Point2D {
+                x: -(columns as Float) * resolution / 2.0,
+                y: -(rows as Float) * resolution / 2.0,
+            },
+            log_odds: vec![0.0; columns * rows],
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Number of columns and rows.
+    ///
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// ## Summary
+    ///
+    /// Size of a cell in millimeters.
+    ///
+    pub fn resolution(&self) -> Float {
+        self.resolution
+    }
+
+    /// ## Summary
+    ///
+    /// Probability that the cell containing a point (map frame, millimeters) is
+    /// occupied. 0.5 when unknown, `None` outside the grid.
+    ///
+    pub fn probability(&self, point: &Point2D) -> Option<Float> {
+        let (column, row) = self.cell(point)?;
+        let log_odds = self.log_odds[row * self.width + column];
+        Some(1.0 - 1.0 / (1.0 + log_odds.exp()))
+    }
+
+    /// ## Summary
+    ///
+    /// Insert a scan taken at a pose.
+    ///
+    pub fn insert(&mut self, scan: &LidarScan, pose: &Pose) {
+        let (sin, cos) = pose.theta.to_radians().sin_cos();
+        let sensor = Point2D { x: pose.x, y: pose.y };
+
+        let Some(start) = self.cell(&sensor) else {
+            return;
+        };
+
+        for reading in scan.valid_readings().filter(|r| r.distance > 0) {
+            let local = reading.to_point();
+            let hit = Point2D {
+                x: pose.x + local.x * cos - local.y * sin,
+                y: pose.y + local.x * sin + local.y * cos,
+            };
+
+            let end = self.unclamped_cell(&hit);
+            let mut previous = None;
+
+            for (column, row) in line(start, end) {
+                if column < 0 || row < 0 || column as usize >= self.width || row as usize >= self.height {
+                    break;
+                }
+
+                if let Some(index) = previous {
+                    self.update(index, LOG_ODDS_MISS);
+                }
+
+                previous = Some(row as usize * self.width + column as usize);
+            }
+
+            // The last cell reached is only occupied if the reading ends inside the grid.
+            if let (Some(index), Some(_)) = (previous, self.cell(&hit)) {
+                self.update(index, LOG_ODDS_HIT);
+            }
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Save the map in the ROS `map_server` format: `<path>.pgm` and `<path>.yaml`.
+    ///
+    /// ## Remarks
+    ///
+    /// Occupied cells are black, free cells white and unknown cells gray. The YAML file
+    /// uses meters, as ROS does.
+    ///
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), IoError> {
+        let image = path.as_ref().with_extension("pgm");
+        let mut pgm = BufWriter::new(File::create(&image)?);
+        self.write_pgm(&mut pgm)?;
+        pgm.flush()?;
+
+        let image_name = image.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        let mut yaml = BufWriter::new(File::create(path.as_ref().with_extension("yaml"))?);
+        self.write_yaml(&mut yaml, &image_name)?;
+        yaml.flush()
+    }
+
+    /// ## Summary
+    ///
+    /// Write the map as a binary PGM image, top row first.
+    ///
+    pub fn write_pgm<W: Write>(&self, writer: &mut W) -> Result<(), IoError> {
+        write!(writer, "P5\n{} {}\n255\n", self.width, self.height)?;
+
+        for row in (0..self.height).rev() {
+            let pixels: Vec<u8> = self.log_odds[row * self.width..(row + 1) * self.width]
+                .iter()
+                .map(|&log_odds| {
+                    let probability = 1.0 - 1.0 / (1.0 + log_odds.exp());
+
+                    if log_odds == 0.0 {
+                        205
+                    } else if probability > OCCUPIED_THRESHOLD {
+                        0
+                    } else if probability < FREE_THRESHOLD {
+                        254
+                    } else {
+                        205
+                    }
+                })
+                .collect();
+
+            writer.write_all(&pixels)?;
+        }
+
+        Ok(())
+    }
+
+    /// ## Summary
+    ///
+    /// Write the `map_server` metadata referencing an image file.
+    ///
+    pub fn write_yaml<W: Write>(&self, writer: &mut W, image: &str) -> Result<(), IoError> {
+        writeln!(writer, "image: {}", image)?;
+        writeln!(writer, "resolution: {}", self.resolution / 1000.0)?;
+        writeln!(writer, "origin: [{}, {}, 0.0]", self.origin.x / 1000.0, self.origin.y / 1000.0)?;
+        writeln!(writer, "negate: 0")?;
+        writeln!(writer, "occupied_thresh: {}", OCCUPIED_THRESHOLD)?;
+        writeln!(writer, "free_thresh: {}", FREE_THRESHOLD)
+    }
+
+    /// Add log odds to a cell.
+    fn update(&mut self, index: usize, delta: Float) {
+        let log_odds = &mut self.log_odds[index];
+        *log_odds = (*log_odds + delta).clamp(-LOG_ODDS_LIMIT, LOG_ODDS_LIMIT);
+    }
+
+    /// Cell containing a point, even outside the grid.
+    fn unclamped_cell(&self, point: &Point2D) -> (i64, i64) {
+        (
+            ((point.x - self.origin.x) / self.resolution).floor() as i64,
+            ((point.y - self.origin.y) / self.resolution).floor() as i64,
+        )
+    }
+
+    /// Cell containing a point, if inside the grid.
+    fn cell(&self, point: &Point2D) -> Option<(usize, usize)> {
+        let (column, row) = self.unclamped_cell(point);
+        let is_inside = column >= 0 && row >= 0 && (column as usize) < self.width && (row as usize) < self.height;
+        is_inside.then_some((column as usize, row as usize))
+    }
+}
+
+impl ScanConsumer for OccupancyGrid {
+    fn on_scan(&mut self, scan: &StampedScan) {
+        if let Some(pose) = &scan.pose {
+            self.insert(&scan.scan, pose);
+        }
+    }
+}
+
+/// Cells crossed by a segment (Bresenham), both ends included.
+fn line(start: (usize, usize), end: (i64, i64)) -> impl Iterator<Item = (i64, i64)> {
+    let (mut x, mut y) = (start.0 as i64, start.1 as i64);
+    let (dx, dy) = ((end.0 - x).abs(), -(end.1 - y).abs());
+    let (step_x, step_y) = ((end.0 - x).signum(), (end.1 - y).signum());
+    let mut error = dx + dy;
+    let mut done = false;
+
+    core::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+
+        let cell = (x, y);
+
+        if cell == end {
+            done = true;
+        } else {
+            let doubled = 2 * error;
+
+            if doubled >= dy {
+                error += dy;
+                x += step_x;
+            }
+
+            if doubled <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+
+        Some(cell)
+    })
+}
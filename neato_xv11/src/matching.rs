@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+
+use super::data::{Float, LidarScan, Point2D};
+
+/// Default size in millimeters of a cell of the correlation grid.
+const DEFAULT_RESOLUTION: Float = 50.0;
+/// Default step in degrees between the rotations tried.
+const DEFAULT_ANGULAR_STEP: Float = 1.0;
+
+/// ## Summary
+///
+/// A rigid transform in the LIDAR frame. A point `p` maps to `rotate(p, dtheta) + (dx, dy)`.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Transform {
+    // Translation along the x axis in millimeters.
+    pub dx: Float,
+    // Translation along the y axis in millimeters.
+    pub dy: Float,
+    // Rotation in degrees, counterclockwise.
+    pub dtheta: Float,
+}
+
+impl Transform {
+    /// ## Summary
+    ///
+    /// Apply the transform to a point.
+    ///
+    pub fn apply(&self, point: &Point2D) -> Point2D {
+        let (sin, cos) = self.dtheta.to_radians().sin_cos();
+
+        Point2D {
+            x: point.x * cos - point.y * sin + self.dx,
+            y: point.x * sin + point.y * cos + self.dy,
+        }
+    }
+}
+
+/// ## Summary
+///
+/// The best transform found by a matcher.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScanMatch {
+    // Transform aligning the scan with the reference.
+    pub transform: Transform,
+    // Score from 0 (no overlap) to 1 (every reading falls on a reference reading).
+    pub score: Float,
+}
+
+/// A transform being evaluated.
+#[derive(Clone, Copy)]
+struct Candidate {
+    // Transform evaluated.
+    transform: Transform,
+    // Score of the transform.
+    score: Float,
+    // Number of search steps away from the identity.
+    steps: i32,
+}
+
+/// ## Summary
+///
+/// A correlative (grid-based) scan matcher, which exhaustively searches a window of
+/// translations and rotations for the best overlap between two scans.
+///
+/// ## Remarks
+///
+/// The reference scan is rasterized into a grid whose cells are blurred into their
+/// neighbours, so a few centimeters of noise still score. The search is robust to
+/// the LIDAR's noise and to poor initial guesses, which makes it a good coarse
+/// alignment. Its precision is bounded by the grid resolution and angular step.
+///
+/// ## Example
+///
+/// ```no_run
+/// # fn odometry(previous: &neato_xv11::data::LidarScan, current: &neato_xv11::data::LidarScan) {
+/// use neato_xv11::matching::CorrelativeMatcher;
+///
+/// // Search 300 mm and 15 degrees around the previous pose.
+/// let matcher = CorrelativeMatcher::new(300.0, 15.0);
+///
+/// if let Some(found) = matcher.match_scans(previous, current) {
+///     println!("Moved by {:?} (score {})", found.transform, found.score);
+/// }
+/// # }
+/// ```
+pub struct CorrelativeMatcher {
+    // Largest translation in millimeters searched along each axis.
+    linear_window: Float,
+    // Largest rotation in degrees searched in each direction.
+    angular_window: Float,
+    // Size in millimeters of a grid cell, also the translation step.
+    resolution: Float,
+    // Step in degrees between the rotations tried.
+    angular_step: Float,
+}
+
+impl CorrelativeMatcher {
+    /// ## Summary
+    ///
+    /// Initialize a new matcher.
+    ///
+    /// ## Parameters
+    ///
+    /// linear_window: Largest translation in millimeters searched along each axis.
+    ///
+    /// angular_window: Largest rotation in degrees searched in each direction.
+    ///
+    pub fn new(linear_window: Float, angular_window: Float) -> Self {
+        CorrelativeMatcher {
+            linear_window: linear_window.abs(),
+            angular_window: angular_window.abs(),
+            resolution: DEFAULT_RESOLUTION,
+            angular_step: DEFAULT_ANGULAR_STEP,
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Set the size in millimeters of a grid cell, also the translation step. Defaults to 50 mm.
+    ///
+    pub fn set_resolution(&mut self, resolution: Float) {
+        self.resolution = resolution.max(1.0);
+    }
+
+    /// ## Summary
+    ///
+    /// Set the step in degrees between the rotations tried. Defaults to 1 degree.
+    ///
+    pub fn set_angular_step(&mut self, step: Float) {
+        self.angular_step = step.max(0.01);
+    }
+
+    /// ## Summary
+    ///
+    /// Find the transform that best aligns `scan` with `reference`. Returns `None` if
+    /// either scan has no valid reading.
+    ///
+    pub fn match_scans(&self, reference: &LidarScan, scan: &LidarScan) -> Option<ScanMatch> {
+        let grid = self.rasterize(&reference.to_point_cloud());
+        let points = scan.to_point_cloud();
+
+        if grid.is_empty() || points.is_empty() {
+            return None;
+        }
+
+        let linear_steps = (self.linear_window / self.resolution).round() as i32;
+        let angular_steps = (self.angular_window / self.angular_step).round() as i32;
+        let mut best: Option<Candidate> = None;
+
+        for angular in -angular_steps..=angular_steps {
+            let dtheta = angular as Float * self.angular_step;
+            let rotation = Transform { dtheta, ..Transform::default() };
+            let rotated: Vec<Point2D> = points.iter().map(|point| rotation.apply(point)).collect();
+
+            for x in -linear_steps..=linear_steps {
+                for y in -linear_steps..=linear_steps {
+                    let (dx, dy) = (x as Float * self.resolution, y as Float * self.resolution);
+                    let total: Float = rotated
+                        .iter()
+                        .map(|point| grid.get(&self.cell(point.x + dx, point.y + dy)).copied().unwrap_or(0.0))
+                        .sum();
+                    let score = total / rotated.len() as Float;
+
+                    // Prefer the smallest transform among equal scores.
+                    let is_better = best.is_none_or(|best| {
+                        score > best.score || (score == best.score && x.abs() + y.abs() + angular.abs() < best.steps)
+                    });
+
+                    if is_better {
+                        best = Some(Candidate {
+                            transform: Transform { dx, dy, dtheta },
+                            score,
+                            steps: x.abs() + y.abs() + angular.abs(),
+                        });
+                    }
+                }
+            }
+        }
+
+        best.map(|best| ScanMatch {
+            transform: best.transform,
+            score: best.score,
+        })
+    }
+
+    /// Cell of a coordinate.
+    fn cell(&self, x: Float, y: Float) -> (i32, i32) {
+        ((x / self.resolution).round() as i32, (y / self.resolution).round() as i32)
+    }
+
+    /// Rasterize points into a likelihood grid, blurring each cell into its neighbours.
+    fn rasterize(&self, points: &[Point2D]) -> HashMap<(i32, i32), Float> {
+        let mut grid = HashMap::new();
+
+        for point in points {
+            let (x, y) = self.cell(point.x, point.y);
+
+            for (dx, dy) in [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)] {
+                let likelihood = grid.entry((x + dx, y + dy)).or_insert(0.0);
+                *likelihood = Float::max(*likelihood, 0.5);
+            }
+
+            grid.insert((x, y), 1.0);
+        }
+
+        grid
+    }
+}
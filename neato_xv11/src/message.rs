@@ -1,25 +1,67 @@
-use std::fmt::Display;
+use core::fmt::Display;
+#[cfg(feature = "std")]
+use std::sync::mpsc::Sender;
+#[cfg(feature = "std")]
+use std::time::Instant;
 
-use super::data::LidarPacket;
+use super::data::{Float, LidarPacket, LidarScan};
+use super::error::LidarDriverError;
+#[cfg(feature = "std")]
+use super::error::ErrorClass;
+#[cfg(feature = "alloc")]
+use super::info::DeviceInfo;
+use super::info::LidarInfo;
+use super::status::{DriverStats, DriverStatus, ErrorSummary};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
 
 /// ## Summary
 /// 
 /// Messages sent to the LIDAR driver.
 /// 
 pub enum LidarDriverCommand {
-    // Pause LIDAR reading.
+    // Reply with the driver status on the given channel once every previous command is processed.
+    // Used by `control::DriverControl` to wait for its commands.
+    #[cfg(feature = "std")]
+    Acknowledge(Sender<DriverStatus>),
+    // Request a `LidarDriverMessage::Echo` reply, sent behind the messages already queued.
+    #[cfg(feature = "std")]
+    Echo,
+    // Return the instant of a `LidarDriverMessage::Echo` once received, to report the round trip
+    // in `DriverStatus::echo_latency`.
+    #[cfg(feature = "std")]
+    EchoReceived(Instant),
+    // Start (true) or stop (false) sending `LidarDriverMessage::Hexdump` messages.
+    Hexdump(bool),
+    // Pause LIDAR reading. The data received while paused is discarded.
     Pause,
-    // Run LIDAR.
+    // Request a `LidarDriverMessage::Stats` reply. The statistics are counted anew after each reply.
+    QueryStats,
+    // Request a `LidarDriverMessage::Status` reply.
+    QueryStatus,
+    // Run LIDAR. After a pause, the stale data is flushed and the parser resyncs.
     Run,
+    // Set the spin speed the LIDAR should run at (RPM), reported in `DriverStatus::target_rpm`.
+    SetTargetRpm(Float),
     // Stop LIDAR.
     Stop,
 }
 
 impl Display for LidarDriverCommand {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match *self {
+            #[cfg(feature = "std")]
+            LidarDriverCommand::Acknowledge(_) => write!(f, "Acknowledge"),
+            #[cfg(feature = "std")]
+            LidarDriverCommand::Echo => write!(f, "Echo"),
+            #[cfg(feature = "std")]
+            LidarDriverCommand::EchoReceived(_) => write!(f, "EchoReceived"),
+            LidarDriverCommand::Hexdump(enabled) => write!(f, "Hexdump({})", enabled),
             LidarDriverCommand::Pause => write!(f, "Pause"),
+            LidarDriverCommand::QueryStats => write!(f, "QueryStats"),
+            LidarDriverCommand::QueryStatus => write!(f, "QueryStatus"),
             LidarDriverCommand::Run => write!(f, "Run"),
+            LidarDriverCommand::SetTargetRpm(rpm) => write!(f, "SetTargetRpm({})", rpm),
             LidarDriverCommand::Stop => write!(f, "Stop"),
         }
     }
@@ -30,9 +72,184 @@ impl Display for LidarDriverCommand {
 /// Messages received from the LIDAR driver.
 /// 
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum LidarDriverMessage {
+    // A persistent change from the learned background, sent by `background::ChangeDetectionSink`.
+    // The sector is the index of the sector, the magnitude the mean deviation in millimeters.
+    ChangeDetected { sector: usize, magnitude: Float },
+    // Identification of the LIDAR unit. Sent once, when enabled in `LidarDriverBuilder`.
+    #[cfg(feature = "alloc")]
+    DeviceInfo(DeviceInfo),
+    // Reply to `LidarDriverCommand::Echo`. The associated value is when it was sent, its
+    // `elapsed()` on receipt is how long messages take to reach the calling program.
+    #[cfg(feature = "std")]
+    Echo(Instant),
+    // Errors counted over a window, sent by `summary::ErrorSummarySink`.
+    ErrorSummary(ErrorSummary),
+    // The raw bytes of a frame, sent at most once per second after `LidarDriverCommand::Hexdump(true)`.
+    #[cfg(feature = "alloc")]
+    Hexdump(Hexdump),
+    // The LIDAR found by protocol detection. Sent once, before any packet.
+    Info(LidarInfo),
     // A LIDAR packet (4 readings).
     Packet(LidarPacket),
+    // The port was disconnected and is reopened after the delay, see `reconnect::ReconnectPolicy`.
+    // The attempt counts from 1 after each disconnection.
+    #[cfg(feature = "std")]
+    Reconnecting { attempt: u32, delay: core::time::Duration },
+    // The port was reopened after the given number of attempts.
+    #[cfg(feature = "std")]
+    Reconnected { attempts: u32 },
+    // The driver runs again after a pause. Every packet that follows was read after
+    // resuming. Sent when enabled in `LidarDriverBuilder::report_resume`.
+    #[cfg(feature = "std")]
+    Resumed,
+    // A full revolution, sent once the next one starts by `scan::ScanSink` or when
+    // enabled in `LidarDriverBuilder::assemble_scans`.
+    Scan(LidarScan),
     // The LIDAR is shutting down.
     Shutdown,
-}
\ No newline at end of file
+    // The parser found the packet boundaries, the associated value is how long it searched.
+    // Sent when enabled in `sync::SyncConfig`.
+    #[cfg(feature = "std")]
+    Synced { after: core::time::Duration },
+    // The parser is searching for the packet boundaries, after starting, resuming, reconnecting
+    // or losing sync. Sent when enabled in `sync::SyncConfig`.
+    #[cfg(feature = "std")]
+    Syncing,
+    // Link quality statistics, sent in response to `LidarDriverCommand::QueryStats`.
+    Stats(DriverStats),
+    // Driver status, sent in response to `LidarDriverCommand::QueryStatus`.
+    Status(DriverStatus),
+    // A condition worth attention that does not stop the driver.
+    Warning(Warning),
+}
+
+/// ## Summary
+///
+/// Conditions worth attention that do not stop the driver.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Warning {
+    // An error downgraded by `error_policy::ErrorPolicy`. The packet index is set for checksum errors.
+    #[cfg(feature = "std")]
+    Downgraded { class: ErrorClass, packet_index: Option<usize> },
+    // The spin speed left the band of `rpm::RpmMonitor`. The associated value is the speed (RPM).
+    RpmOutOfRange { rpm: Float },
+    // The spin speed returned to the band of `rpm::RpmMonitor`. The associated value is the speed (RPM).
+    RpmRecovered { rpm: Float },
+    // Messages of a kind were discarded by `rate_limit::ThrottledSink` since its last report.
+    Throttled { kind: MessageKind, dropped: u64 },
+}
+
+/// ## Summary
+/// 
+/// The kind of a message received from the LIDAR driver, errors included.
+/// 
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum MessageKind {
+    // `LidarDriverMessage::ChangeDetected`.
+    ChangeDetected,
+    // `LidarDriverMessage::DeviceInfo`.
+    DeviceInfo,
+    // `LidarDriverMessage::Echo`.
+    Echo,
+    // Any `LidarDriverError`.
+    Error,
+    // `LidarDriverMessage::ErrorSummary`.
+    ErrorSummary,
+    // `LidarDriverMessage::Hexdump`.
+    Hexdump,
+    // `LidarDriverMessage::Info`.
+    Info,
+    // `LidarDriverMessage::Packet`.
+    Packet,
+    // `LidarDriverMessage::Reconnecting`.
+    Reconnecting,
+    // `LidarDriverMessage::Reconnected`.
+    Reconnected,
+    // `LidarDriverMessage::Resumed`.
+    Resumed,
+    // `LidarDriverMessage::Scan`.
+    Scan,
+    // `LidarDriverMessage::Shutdown`.
+    Shutdown,
+    // `LidarDriverMessage::Stats`.
+    Stats,
+    // `LidarDriverMessage::Synced`.
+    Synced,
+    // `LidarDriverMessage::Syncing`.
+    Syncing,
+    // `LidarDriverMessage::Status`.
+    Status,
+    // `LidarDriverMessage::Warning`.
+    Warning,
+}
+
+impl MessageKind {
+    /// ## Summary
+    /// 
+    /// The kind of a message.
+    /// 
+    pub fn of(message: &Result<LidarDriverMessage, LidarDriverError>) -> Self {
+        match message {
+            Ok(LidarDriverMessage::ChangeDetected { .. }) => MessageKind::ChangeDetected,
+            #[cfg(feature = "alloc")]
+            Ok(LidarDriverMessage::DeviceInfo(_)) => MessageKind::DeviceInfo,
+            #[cfg(feature = "std")]
+            Ok(LidarDriverMessage::Echo(_)) => MessageKind::Echo,
+            Ok(LidarDriverMessage::ErrorSummary(_)) => MessageKind::ErrorSummary,
+            #[cfg(feature = "alloc")]
+            Ok(LidarDriverMessage::Hexdump(_)) => MessageKind::Hexdump,
+            Ok(LidarDriverMessage::Info(_)) => MessageKind::Info,
+            Ok(LidarDriverMessage::Packet(_)) => MessageKind::Packet,
+            #[cfg(feature = "std")]
+            Ok(LidarDriverMessage::Reconnecting { .. }) => MessageKind::Reconnecting,
+            #[cfg(feature = "std")]
+            Ok(LidarDriverMessage::Reconnected { .. }) => MessageKind::Reconnected,
+            #[cfg(feature = "std")]
+            Ok(LidarDriverMessage::Resumed) => MessageKind::Resumed,
+            Ok(LidarDriverMessage::Scan(_)) => MessageKind::Scan,
+            Ok(LidarDriverMessage::Shutdown) => MessageKind::Shutdown,
+            Ok(LidarDriverMessage::Stats(_)) => MessageKind::Stats,
+            #[cfg(feature = "std")]
+            Ok(LidarDriverMessage::Synced { .. }) => MessageKind::Synced,
+            #[cfg(feature = "std")]
+            Ok(LidarDriverMessage::Syncing) => MessageKind::Syncing,
+            Ok(LidarDriverMessage::Status(_)) => MessageKind::Status,
+            Ok(LidarDriverMessage::Warning(_)) => MessageKind::Warning,
+            Err(_) => MessageKind::Error,
+        }
+    }
+}
+
+/// ## Summary
+/// 
+/// The raw bytes of a frame, for diagnosing framing issues without a logic analyzer.
+/// 
+/// ## Remarks
+/// 
+/// Displayed as the offset of the first byte in the stream followed by the bytes in hexadecimal:
+/// `000000b0  fa a0 4b 48 ...`.
+/// 
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hexdump {
+    // Number of bytes parsed before the frame since the driver started.
+    pub offset: u64,
+    // The frame, as received.
+    pub bytes: Vec<u8>,
+}
+
+#[cfg(feature = "alloc")]
+impl Display for Hexdump {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:08x} ", self.offset)?;
+
+        for byte in &self.bytes {
+            write!(f, " {:02x}", byte)?;
+        }
+
+        Ok(())
+    }
+}
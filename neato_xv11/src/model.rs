@@ -0,0 +1,140 @@
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+use core::cell::Cell;
+
+use super::data::{Float, LidarPacket};
+use super::error::LidarDriverError;
+use super::fixed::Rpm;
+use super::parser::parse_packet;
+use super::protocol::LidarProtocol;
+
+/// The XV-11 spins at about 300 RPM. A Botvac speed decoded as an XV-11 speed reads above this.
+pub(crate) const BOTVAC_MIN_SPEED: Float = 400.0;
+
+/// ## Summary
+///
+/// The LIDAR model, which selects how packet fields are interpreted.
+///
+/// ## Remarks
+///
+/// Every model uses the same 22 byte packet framing and checksum.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum Model {
+    // Botvac D-series LDS. Speed is reported in hundredths of RPM. Bit 14 of the distance
+    // is not used, a weak signal is reported as a valid distance with a quality of 0.
+    Botvac,
+    // Neato XV-11 (Piccolo LDS). Speed is reported in Q10.6 RPM. Bit 15 of the distance
+    // flags invalid data, bit 14 flags a signal strength warning.
+    #[default]
+    Xv11,
+}
+
+impl Model {
+    /// ## Summary
+    ///
+    /// Convert the raw speed field of a packet into a Q10.6 speed.
+    ///
+    pub(crate) fn speed(self, raw: u16) -> Rpm {
+        match self {
+            Model::Botvac => Rpm::from_raw((((raw as u32) << Rpm::FRACTIONAL_BITS) / 100) as u16),
+            Model::Xv11 => Rpm::from_raw(raw),
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Whether bit 14 of the distance is a signal strength warning.
+    ///
+    pub(crate) fn has_strength_flag(self) -> bool {
+        matches!(self, Model::Xv11)
+    }
+}
+
+/// ## Summary
+///
+/// Decodes the packets of either Neato model, telling them apart by the speed of the
+/// first valid packet.
+///
+/// ## Remarks
+///
+/// Both models share the same framing, so the model is decided once the parser is
+/// synced, without reopening the port. Use it when the model is unknown and the source
+/// cannot be probed, e.g. a recording. The decision holds until `reset`.
+///
+/// ## Example
+///
+/// ```
+/// use neato_xv11::model::AutoModel;
+/// use neato_xv11::prelude::*;
+///
+/// let parser = Parser::with_protocol(AutoModel::new());
+/// assert_eq!(parser.protocol().model(), None);
+/// ```
+#[derive(Debug, Default)]
+pub struct AutoModel {
+    // The model, once decided.
+    model: Cell<Option<Model>>,
+}
+
+impl AutoModel {
+    /// ## Summary
+    ///
+    /// Initialize a protocol with the model undecided.
+    ///
+    pub fn new() -> Self {
+        AutoModel::default()
+    }
+
+    /// ## Summary
+    ///
+    /// The model, once a valid packet was decoded.
+    ///
+    pub fn model(&self) -> Option<Model> {
+        self.model.get()
+    }
+
+    /// ## Summary
+    ///
+    /// Decide the model again from the next valid packet, e.g. after the LIDAR was swapped.
+    ///
+    pub fn reset(&self) {
+        self.model.set(None);
+    }
+}
+
+impl LidarProtocol for AutoModel {
+    fn header_size(&self) -> usize {
+        Model::Xv11.header_size()
+    }
+
+    fn accepts(&self, position: usize, byte: u8) -> bool {
+        Model::Xv11.accepts(position, byte)
+    }
+
+    fn frame_size(&self) -> usize {
+        Model::Xv11.frame_size()
+    }
+
+    fn decode(&self, frame: &[u8]) -> Result<LidarPacket, LidarDriverError> {
+        if let Some(model) = self.model.get() {
+            return parse_packet(frame, model);
+        }
+
+        let packet = parse_packet(frame, Model::Xv11)?;
+
+        if packet.speed <= BOTVAC_MIN_SPEED {
+            self.model.set(Some(Model::Xv11));
+            return Ok(packet);
+        }
+
+        self.model.set(Some(Model::Botvac));
+        parse_packet(frame, Model::Botvac)
+    }
+
+    fn frame_index(&self, frame: &[u8]) -> Option<(usize, usize)> {
+        Model::Xv11.frame_index(frame)
+    }
+}
@@ -0,0 +1,351 @@
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+
+#[cfg(feature = "embedded")]
+use embedded_hal::PwmPin;
+
+use super::data::Float;
+
+/// Nominal spin speed of the XV-11 (RPM).
+pub const DEFAULT_TARGET_RPM: Float = 300.0;
+
+/// ## Summary
+///
+/// Drives the LIDAR motor, e.g. through a PWM pin and a transistor.
+///
+/// ## Remarks
+///
+/// The XV-11 motor is not regulated by the LIDAR, its speed drifts with the supply
+/// voltage and the temperature. `MotorRegulator` drives a controller from the speed
+/// reported in the packets.
+///
+pub trait MotorController {
+    /// ## Summary
+    ///
+    /// Set the duty cycle, from 0 (stopped) to 1 (full speed).
+    ///
+    fn set_duty(&mut self, duty: Float);
+}
+
+#[cfg(feature = "alloc")]
+impl<M: MotorController + ?Sized> MotorController for Box<M> {
+    fn set_duty(&mut self, duty: Float) {
+        (**self).set_duty(duty);
+    }
+}
+
+/// ## Summary
+///
+/// A motor controller calling a closure with the duty cycle. See `from_fn`.
+///
+pub struct FnMotor<F>(F);
+
+impl<F: FnMut(Float)> MotorController for FnMotor<F> {
+    fn set_duty(&mut self, duty: Float) {
+        (self.0)(duty);
+    }
+}
+
+/// ## Summary
+///
+/// A motor controller calling a closure with the duty cycle, from 0 to 1.
+///
+/// ## Example
+///
+/// ```
+/// use neato_xv11::motor::{self, MotorController};
+///
+/// let mut motor = motor::from_fn(|duty| println!("Duty cycle {:.2}", duty));
+/// motor.set_duty(0.5);
+/// ```
+pub fn from_fn<F: FnMut(Float)>(f: F) -> FnMotor<F> {
+    FnMotor(f)
+}
+
+/// ## Summary
+///
+/// A motor driven by an `embedded_hal::PwmPin` (`embedded` feature).
+///
+#[cfg(feature = "embedded")]
+pub struct PwmMotor<P>(P);
+
+#[cfg(feature = "embedded")]
+impl<P: PwmPin<Duty = u16>> PwmMotor<P> {
+    /// ## Summary
+    ///
+    /// Drive the motor through an enabled PWM pin.
+    ///
+    pub fn new(pin: P) -> Self {
+        PwmMotor(pin)
+    }
+
+    /// ## Summary
+    ///
+    /// Release the PWM pin.
+    ///
+    pub fn into_inner(self) -> P {
+        self.0
+    }
+}
+
+#[cfg(feature = "embedded")]
+impl<P: PwmPin<Duty = u16>> MotorController for PwmMotor<P> {
+    fn set_duty(&mut self, duty: Float) {
+        let max = self.0.get_max_duty();
+        self.0.set_duty((max as Float * duty.clamp(0.0, 1.0)) as u16);
+    }
+}
+
+/// ## Summary
+///
+/// A PID controller with a clamped output.
+///
+/// ## Remarks
+///
+/// The integral stops accumulating while the output is saturated, so the controller
+/// does not overshoot after the motor was stalled or the target was far away.
+///
+#[derive(Clone, Debug)]
+pub struct PidController {
+    // Proportional gain.
+    kp: Float,
+    // Integral gain.
+    ki: Float,
+    // Derivative gain.
+    kd: Float,
+    // Lowest output.
+    min: Float,
+    // Highest output.
+    max: Float,
+    // Accumulated error over time.
+    integral: Float,
+    // Error of the previous update, `None` after a reset.
+    previous_error: Option<Float>,
+}
+
+impl PidController {
+    /// ## Summary
+    ///
+    /// Initialize a controller with an output between -1 and 1.
+    ///
+    /// ## Parameters
+    ///
+    /// kp: Proportional gain.
+    ///
+    /// ki: Integral gain, per second.
+    ///
+    /// kd: Derivative gain, in seconds.
+    ///
+    pub fn new(kp: Float, ki: Float, kd: Float) -> Self {
+        PidController {
+            kp,
+            ki,
+            kd,
+            min: -1.0,
+            max: 1.0,
+            integral: 0.0,
+            previous_error: None,
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Clamp the output between `min` and `max`.
+    ///
+    pub fn with_limits(mut self, min: Float, max: Float) -> Self {
+        self.min = min;
+        self.max = max;
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Compute the output for an error.
+    ///
+    /// ## Parameters
+    ///
+    /// error: Target minus measured value.
+    ///
+    /// dt: Time since the previous update (s).
+    ///
+    pub fn update(&mut self, error: Float, dt: Float) -> Float {
+        let derivative = match self.previous_error {
+            Some(previous) if dt > 0.0 => (error - previous) / dt,
+            _ => 0.0,
+        };
+        self.previous_error = Some(error);
+
+        let integral = self.integral + error * dt;
+        let output = self.kp * error + self.ki * integral + self.kd * derivative;
+
+        if output > self.max {
+            self.max
+        } else if output < self.min {
+            self.min
+        } else {
+            self.integral = integral;
+            output
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Forget the accumulated error, e.g. after the motor was stopped.
+    ///
+    pub fn reset(&mut self) {
+        self.integral = 0.0;
+        self.previous_error = None;
+    }
+}
+
+impl Default for PidController {
+    /// Gains suited to an XV-11 motor on 3.3-5 V, in duty cycle per RPM.
+    fn default() -> Self {
+        PidController::new(0.001, 0.002, 0.0)
+    }
+}
+
+/// ## Summary
+///
+/// Regulates the spin speed by adjusting the duty cycle of the motor from the speed
+/// reported by the LIDAR.
+///
+/// ## Remarks
+///
+/// The LIDAR only reports its speed once spinning, the motor starts at the initial duty
+/// cycle. Attach a regulator to the driver with `LidarDriverBuilder::motor`, the target
+/// then follows `LidarDriverCommand::SetTargetRpm`.
+///
+/// ## Example
+///
+/// ```
+/// use neato_xv11::motor::{self, MotorRegulator, PidController};
+///
+/// let mut regulator = MotorRegulator::new(motor::from_fn(|_duty| {}), PidController::default());
+/// regulator.start();
+///
+/// // Too slow, the duty cycle increases.
+/// let duty = regulator.update(250.0, 0.2);
+/// assert!(duty > 0.5);
+/// ```
+pub struct MotorRegulator<M> {
+    // The motor driven.
+    motor: M,
+    // Computes the duty cycle from the speed error.
+    pid: PidController,
+    // Spin speed to regulate to (RPM).
+    target_rpm: Float,
+    // Duty cycle applied when starting (0-1).
+    initial_duty: Float,
+    // Duty cycle last applied (0-1).
+    duty: Float,
+}
+
+impl<M: MotorController> MotorRegulator<M> {
+    /// ## Summary
+    ///
+    /// Initialize a regulator targeting 300 RPM, starting the motor at 50% duty cycle.
+    ///
+    pub fn new(motor: M, pid: PidController) -> Self {
+        MotorRegulator {
+            motor,
+            pid,
+            target_rpm: DEFAULT_TARGET_RPM,
+            initial_duty: 0.5,
+            duty: 0.0,
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Set the duty cycle the motor is started at, from 0 to 1.
+    ///
+    pub fn with_initial_duty(mut self, duty: Float) -> Self {
+        self.initial_duty = duty;
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Spin speed the motor is regulated to (RPM).
+    ///
+    pub fn target_rpm(&self) -> Float {
+        self.target_rpm
+    }
+
+    /// ## Summary
+    ///
+    /// Set the spin speed the motor is regulated to (RPM).
+    ///
+    pub fn set_target_rpm(&mut self, rpm: Float) {
+        self.target_rpm = rpm;
+    }
+
+    /// ## Summary
+    ///
+    /// Duty cycle last applied to the motor, from 0 to 1.
+    ///
+    pub fn duty(&self) -> Float {
+        self.duty
+    }
+
+    /// ## Summary
+    ///
+    /// Start the motor at the initial duty cycle.
+    ///
+    pub fn start(&mut self) {
+        self.pid.reset();
+        self.apply(self.initial_duty);
+    }
+
+    /// ## Summary
+    ///
+    /// Stop the motor.
+    ///
+    pub fn stop(&mut self) {
+        self.pid.reset();
+        self.apply(0.0);
+    }
+
+    /// ## Summary
+    ///
+    /// Adjust the duty cycle from a speed reading. Returns the duty cycle applied.
+    ///
+    /// ## Parameters
+    ///
+    /// rpm: Spin speed reported by the LIDAR.
+    ///
+    /// dt: Time since the previous reading (s).
+    ///
+    /// ## Remarks
+    ///
+    /// The controller output is added to the initial duty cycle, so the integral only
+    /// has to make up for the difference to the nominal speed.
+    ///
+    pub fn update(&mut self, rpm: Float, dt: Float) -> Float {
+        let correction = self.pid.update(self.target_rpm - rpm, dt);
+        self.apply((self.initial_duty + correction).clamp(0.0, 1.0));
+        self.duty
+    }
+
+    /// The regulator driving a boxed motor, as held by the driver.
+    #[cfg(feature = "std")]
+    pub(crate) fn boxed(self) -> MotorRegulator<Box<dyn MotorController + Send>>
+    where
+        M: Send + 'static,
+    {
+        MotorRegulator {
+            motor: Box::new(self.motor),
+            pid: self.pid,
+            target_rpm: self.target_rpm,
+            initial_duty: self.initial_duty,
+            duty: self.duty,
+        }
+    }
+
+    /// Apply a duty cycle to the motor.
+    fn apply(&mut self, duty: Float) {
+        self.duty = duty;
+        self.motor.set_duty(duty);
+    }
+}
@@ -0,0 +1,162 @@
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+use super::data::{Angle, Float};
+#[cfg(feature = "std")]
+use super::data::{LidarReading, LidarScan, Point2D, SCAN_SIZE};
+#[cfg(feature = "std")]
+use super::geometry::Line;
+
+/// ## Summary
+///
+/// How the LIDAR is mounted on the robot.
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct MountingConfig {
+    // Sensor angle (degrees) of the robot's forward axis. A reading at sensor angle `a`
+    // is at `a - angle_offset` in the robot frame.
+    pub angle_offset: Angle,
+    // Position of the sensor along the robot's forward axis, in millimeters from the base origin.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub x: Float,
+    // Position of the sensor along the robot's left axis, in millimeters from the base origin.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub y: Float,
+}
+
+impl MountingConfig {
+    /// ## Summary
+    ///
+    /// Estimate `angle_offset` from a scan of a flat target (e.g. a wall) placed
+    /// perpendicular to the robot's forward axis, and store it.
+    ///
+    /// ## Parameters
+    ///
+    /// scan: A scan taken with the target straight ahead of the robot.
+    ///
+    /// window: Half width (degrees) of the sector around the closest reading used to fit the target.
+    ///
+    /// ## Remarks
+    ///
+    /// A line is fitted to the readings of the sector, the direction of its normal is
+    /// the forward axis. Returns the estimated offset, or `None` (leaving the
+    /// configuration unchanged) if fewer than 10 valid readings are in the sector.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # fn calibrate(scan: &neato_xv11::data::LidarScan) {
+    /// use neato_xv11::mounting::MountingConfig;
+    ///
+    /// let mut mounting = MountingConfig::default();
+    ///
+    /// match mounting.calibrate_forward(scan, 30) {
+    ///     Some(offset) => println!("Forward axis at sensor angle {:.1}", offset.degrees()),
+    ///     None => println!("Target not found"),
+    /// }
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn calibrate_forward(&mut self, scan: &LidarScan, window: usize) -> Option<Angle> {
+        let offset = estimate_forward_angle(scan, window)?;
+        self.angle_offset = offset;
+        Some(offset)
+    }
+
+    /// ## Summary
+    ///
+    /// Express a point of the LIDAR frame in the robot base frame.
+    ///
+    #[cfg(feature = "std")]
+    pub fn to_base(&self, point: &Point2D) -> Point2D {
+        let (sin, cos) = (-self.angle_offset).radians().sin_cos();
+
+        Point2D {
+            x: point.x * cos - point.y * sin + self.x,
+            y: point.x * sin + point.y * cos + self.y,
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Express a scan in the robot base frame. Readings are moved to the degree they
+    /// are seen at from the base origin, with their distance from the base origin.
+    ///
+    /// ## Remarks
+    ///
+    /// Readings without a usable distance are only rotated. Readings landing on the same
+    /// degree overwrite each other, the scan of an offset sensor has gaps and overlaps
+    /// in the base frame.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # fn convert(scan: &neato_xv11::data::LidarScan) {
+    /// use neato_xv11::data::Angle;
+    /// use neato_xv11::mounting::MountingConfig;
+    ///
+    /// // LIDAR 120 mm ahead of the wheel axis, turned 90 degrees.
+    /// let mounting = MountingConfig { angle_offset: Angle::from_degrees(90.0), x: 120.0, y: 0.0 };
+    /// let in_base = mounting.to_base_scan(scan);
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn to_base_scan(&self, scan: &LidarScan) -> LidarScan {
+        let mut base = scan.clone();
+        base.readings = (0..SCAN_SIZE).map(|_| None).collect();
+
+        for reading in scan.readings.iter().flatten() {
+            let mut reading = reading.clone();
+
+            if reading.is_valid() && reading.distance > 0 {
+                let point = self.to_base(&reading.to_point());
+                reading.index = point.angle().index();
+                reading.distance = point.x.hypot(point.y).round() as i32;
+            } else {
+                reading.index = (reading.angle() - self.angle_offset).index();
+            }
+
+            let index = reading.index;
+            base.readings[index] = Some(reading);
+        }
+
+        base
+    }
+}
+
+/// Smallest number of readings a line is fitted to.
+#[cfg(feature = "std")]
+const MIN_FIT_READINGS: usize = 10;
+
+/// ## Summary
+///
+/// Find the direction (-180 to 180 degrees) of the normal of the flat target closest to the sensor.
+///
+#[cfg(feature = "std")]
+pub(crate) fn estimate_forward_angle(scan: &LidarScan, window: usize) -> Option<Angle> {
+    let closest = scan.valid_readings().filter(|r| r.distance > 0).min_by_key(|r| r.distance)?.index;
+
+    let points: Vec<Point2D> = (0..=2 * window)
+        .map(|i| (closest + SCAN_SIZE * 2 + i - window) % SCAN_SIZE)
+        .filter_map(|index| scan.get(index).filter(|r| r.is_valid() && r.distance > 0))
+        .map(LidarReading::to_point)
+        .collect();
+
+    if points.len() < MIN_FIT_READINGS {
+        return None;
+    }
+
+    // Normal of the fitted line.
+    let line = Line::fit(&points)?;
+    let (mean_x, mean_y) = (line.centroid.x, line.centroid.y);
+    let (mut normal_x, mut normal_y) = (-line.direction.sin(), line.direction.cos());
+
+    // Point the normal from the sensor towards the target.
+    if normal_x * mean_x + normal_y * mean_y < 0.0 {
+        normal_x = -normal_x;
+        normal_y = -normal_y;
+    }
+
+    Some(Point2D { x: normal_x, y: normal_y }.angle())
+}
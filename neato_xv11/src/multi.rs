@@ -0,0 +1,159 @@
+use std::ffi::OsStr;
+use std::io::Error as IoError;
+use std::sync::mpsc::{SendError, Sender};
+use std::thread::{self, JoinHandle};
+
+use super::builder::PortConfig;
+use super::control::DriverControl;
+use super::driver::run_with_config;
+use super::error::ControlError;
+use super::prelude::*;
+
+/// ## Summary
+///
+/// A message from one of the drivers started by `run_many`.
+///
+#[derive(Debug)]
+pub struct SourceMessage {
+    // Position of the port in the list given to `run_many`.
+    pub source: usize,
+    // The message sent by the driver of that port.
+    pub message: Result<LidarDriverMessage, LidarDriverError>,
+}
+
+/// Tags the messages of a driver with its source.
+struct SourceSink {
+    source: usize,
+    tx: Sender<SourceMessage>,
+}
+
+impl MessageSink for SourceSink {
+    fn send(&self, message: Result<LidarDriverMessage, LidarDriverError>) -> Result<(), SendError<Result<LidarDriverMessage, LidarDriverError>>> {
+        self.tx.send(SourceMessage { source: self.source, message }).map_err(|err| SendError(err.0.message))
+    }
+}
+
+/// ## Summary
+///
+/// The drivers started by `run_many`.
+///
+pub struct MultiDriver {
+    // Controls each driver, in the order of the ports.
+    controls: Vec<DriverControl>,
+    // The driver threads.
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl MultiDriver {
+    /// ## Summary
+    ///
+    /// Number of drivers.
+    ///
+    pub fn len(&self) -> usize {
+        self.controls.len()
+    }
+
+    /// ## Summary
+    ///
+    /// Whether no driver was started.
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.controls.is_empty()
+    }
+
+    /// ## Summary
+    ///
+    /// Controls the driver of a single source.
+    ///
+    pub fn control(&self, source: usize) -> Option<&DriverControl> {
+        self.controls.get(source)
+    }
+
+    /// ## Summary
+    ///
+    /// The status of every driver, in the order of the ports.
+    ///
+    pub fn query_status(&self) -> Vec<Result<DriverStatus, ControlError>> {
+        self.controls.iter().map(DriverControl::query_status).collect()
+    }
+
+    /// ## Summary
+    ///
+    /// Stop every driver and wait for their threads to exit.
+    ///
+    pub fn stop(self) {
+        for control in &self.controls {
+            let _ = control.stop();
+        }
+
+        for thread in self.threads {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// ## Summary
+///
+/// Start one driver per port, each on its own thread, sending every message to a
+/// single channel tagged with the position of its port.
+///
+/// ## Parameters
+///
+/// ports: The port names and the settings applied to each.
+///
+/// tx: Receives the messages of every driver.
+///
+/// ## Remarks
+///
+/// For robots or test rigs with several LIDARs. Returns an error if a thread could not
+/// be spawned, after stopping the drivers already started. Each driver shuts down on
+/// its own on errors; its `LidarDriverMessage::Shutdown` tells which one.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::sync::mpsc::channel;
+/// use neato_xv11::PortConfig;
+/// use neato_xv11::multi::run_many;
+///
+/// let (message_tx, message_rx) = channel();
+///
+/// let ports = [("/dev/ttyUSB0", PortConfig::default()), ("/dev/ttyUSB1", PortConfig::default())];
+/// let drivers = run_many(&ports, message_tx).unwrap();
+///
+/// for tagged in message_rx.iter().take(1000) {
+///     println!("LIDAR {}: {:?}", tagged.source, tagged.message);
+/// }
+///
+/// drivers.stop();
+/// ```
+pub fn run_many<T: AsRef<OsStr>>(ports: &[(T, PortConfig)], tx: Sender<SourceMessage>) -> Result<MultiDriver, IoError> {
+    let mut drivers = MultiDriver {
+        controls: Vec::with_capacity(ports.len()),
+        threads: Vec::with_capacity(ports.len()),
+    };
+
+    for (source, (port_name, config)) in ports.iter().enumerate() {
+        let port_name = port_name.as_ref().to_os_string();
+        let config = config.clone();
+        let sink = SourceSink { source, tx: tx.clone() };
+        let (control, command_rx) = DriverControl::channel();
+
+        let thread = thread::Builder::new()
+            .name(format!("lidar-{}", source))
+            .spawn(move || run_with_config(&port_name, &config, sink, command_rx));
+
+        match thread {
+            Ok(thread) => {
+                drivers.controls.push(control);
+                drivers.threads.push(thread);
+            },
+            Err(err) => {
+                drivers.stop();
+                return Err(err);
+            },
+        }
+    }
+
+    Ok(drivers)
+}
@@ -0,0 +1,714 @@
+#[cfg(feature = "tls")]
+use std::convert::TryFrom;
+use std::io::{BufRead, BufReader, Error as IoError, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, SendError, SyncSender, TryRecvError, TrySendError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "log")]
+use log::{info, warn};
+#[cfg(feature = "tls")]
+use rustls::pki_types::pem::PemObject;
+#[cfg(feature = "tls")]
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+#[cfg(feature = "tls")]
+use rustls::{ClientConfig, ClientConnection, ServerConfig, ServerConnection, StreamOwned};
+
+use super::control::CommandSource;
+use super::data::LidarScan;
+use super::driver::send_message;
+use super::prelude::*;
+use super::scan::ScanAssembler;
+
+/// How often the server checks for new clients and for being dropped.
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Longest time a client may block a write before it is disconnected.
+const WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Number of scans queued for a client before it is considered behind and disconnected.
+const CLIENT_QUEUE_SIZE: usize = 8;
+
+/// Read timeout of `run_from_tcp`. Bounds how long a command waits.
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Longest time a client may take to set up TLS and send its token.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Longest token line accepted from a client.
+const MAX_TOKEN_LINE: usize = 256;
+
+/// ## Summary
+///
+/// Access control of a `ScanServer`: the bearer token clients must send, and the TLS
+/// configuration encrypting the stream (`tls` feature).
+///
+/// ## Remarks
+///
+/// With a token, a client must send `Bearer <token>` on its own line within 1 second
+/// of connecting (after the TLS handshake, if any), or it is disconnected without
+/// receiving any scan. Without TLS, the token is sent in clear: use both on shared
+/// networks.
+///
+#[derive(Clone, Default)]
+pub struct ScanServerConfig {
+    // Token clients must send before receiving scans.
+    token: Option<String>,
+    // Encrypts the stream when set.
+    #[cfg(feature = "tls")]
+    tls: Option<Arc<ServerConfig>>,
+}
+
+impl ScanServerConfig {
+    /// ## Summary
+    ///
+    /// Require clients to send `Bearer <token>` before receiving scans.
+    ///
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.token = Some(String::from(token));
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Encrypt the stream with a certificate chain and its private key.
+    ///
+    /// ## Parameters
+    ///
+    /// cert_chain: PEM encoded certificates, the server certificate first.
+    ///
+    /// private_key: PEM encoded private key of the server certificate.
+    ///
+    /// ## Remarks
+    ///
+    /// Returns an `ErrorKind::InvalidInput` error if a certificate or the key cannot
+    /// be parsed, or the key does not match the certificate.
+    ///
+    #[cfg(feature = "tls")]
+    pub fn with_tls(self, cert_chain: &[u8], private_key: &[u8]) -> Result<Self, IoError> {
+        let certs = CertificateDer::pem_slice_iter(cert_chain)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| IoError::new(ErrorKind::InvalidInput, err))?;
+        let key = PrivateKeyDer::from_pem_slice(private_key).map_err(|err| IoError::new(ErrorKind::InvalidInput, err))?;
+
+        let config = ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .expect("the ring provider supports the default protocol versions")
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .map_err(|err| IoError::new(ErrorKind::InvalidInput, err))?;
+
+        Ok(self.with_tls_config(Arc::new(config)))
+    }
+
+    /// ## Summary
+    ///
+    /// Encrypt the stream with a custom TLS configuration, e.g. requiring client
+    /// certificates.
+    ///
+    #[cfg(feature = "tls")]
+    pub fn with_tls_config(mut self, config: Arc<ServerConfig>) -> Self {
+        self.tls = Some(config);
+        self
+    }
+}
+
+/// ## Summary
+///
+/// A TCP server streaming scans to every connected client, e.g. from the computer
+/// attached to the LIDAR to a laptop visualizing them.
+///
+/// ## Remarks
+///
+/// Each scan is sent as a JSON document on its own line (JSON Lines), through the
+/// serde representation of `LidarScan`. `run_from_tcp` reads them back as driver
+/// messages, and any language with a JSON parser can read them too.
+///
+/// Clients are accepted on a background thread, stopped when the server is dropped.
+/// Each client has its own writer thread and a queue of 8 scans, so broadcasting
+/// never waits on the network. A client that does not keep up (its queue is full or
+/// a write blocks for more than 100 ms) or disconnects is dropped.
+///
+/// Servers started with `bind` are not authenticated nor encrypted: anyone reaching
+/// the port receives the scans. Bind them to the loopback interface, or use
+/// `bind_with` and a `ScanServerConfig` requiring a token and TLS.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::thread;
+/// # use std::sync::mpsc::channel;
+/// use neato_xv11::net::{ScanServer, ScanServerSink};
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// // Local clients only, the stream is not authenticated.
+/// let server = ScanServer::bind("127.0.0.1:5555").unwrap();
+/// let message_tx = ScanServerSink::new(message_tx, server);
+///
+/// thread::spawn(move || {
+///     neato_xv11::run("/dev/serial0", message_tx, command_rx);
+/// });
+/// ```
+pub struct ScanServer {
+    // Connected clients.
+    clients: Arc<Mutex<Vec<Client>>>,
+    local_addr: SocketAddr,
+    // Stops the accept thread.
+    is_stopped: Arc<AtomicBool>,
+}
+
+impl ScanServer {
+    /// ## Summary
+    ///
+    /// Listen on `addr` and start accepting clients, without authentication nor
+    /// encryption.
+    ///
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> Result<Self, IoError> {
+        ScanServer::bind_with(addr, ScanServerConfig::default())
+    }
+
+    /// ## Summary
+    ///
+    /// Listen on `addr` and start accepting the clients allowed by `config`.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use neato_xv11::net::{ScanServer, ScanServerConfig};
+    ///
+    /// let config = ScanServerConfig::default().with_token("secret");
+    /// # #[cfg(feature = "tls")]
+    /// let config = config.with_tls(&std::fs::read("cert.pem").unwrap(), &std::fs::read("key.pem").unwrap()).unwrap();
+    ///
+    /// let server = ScanServer::bind_with("0.0.0.0:5555", config).unwrap();
+    /// ```
+    pub fn bind_with<A: ToSocketAddrs>(addr: A, config: ScanServerConfig) -> Result<Self, IoError> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+
+        let local_addr = listener.local_addr()?;
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        let is_stopped = Arc::new(AtomicBool::new(false));
+
+        let config = Arc::new(config);
+        let accepted = clients.clone();
+        let stopped = is_stopped.clone();
+
+        thread::Builder::new().name("neato-xv11-server".into()).spawn(move || accept_loop(listener, &config, &accepted, &stopped))?;
+
+        Ok(ScanServer { clients, local_addr, is_stopped })
+    }
+
+    /// ## Summary
+    ///
+    /// Address the server listens on, e.g. to find the port chosen for port 0.
+    ///
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// ## Summary
+    ///
+    /// Number of connected clients.
+    ///
+    pub fn clients(&self) -> usize {
+        self.clients.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// ## Summary
+    ///
+    /// Queue a scan for every connected client.
+    ///
+    /// ## Remarks
+    ///
+    /// Does not wait for the scan to be written. Clients whose queue is full are
+    /// disconnected.
+    ///
+    pub fn broadcast(&self, scan: &LidarScan) -> Result<(), IoError> {
+        let mut line = serde_json::to_vec(scan)?;
+        line.push(b'\n');
+        let line: Arc<[u8]> = line.into();
+
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+
+        clients.retain(|client| match client.tx.try_send(line.clone()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                #[cfg(feature = "log")]
+                warn!("Disconnecting scan client {}, it fell behind", client.addr);
+
+                false
+            },
+            // The writer thread stopped on a write error.
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+
+        Ok(())
+    }
+}
+
+impl Drop for ScanServer {
+    fn drop(&mut self) {
+        self.is_stopped.store(true, Ordering::Release);
+        // Stops the writer threads once their queue is written.
+        self.clients.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
+
+/// A connected client, written to by its own thread.
+struct Client {
+    // Queues the lines for the writer thread.
+    tx: SyncSender<Arc<[u8]>>,
+    #[cfg_attr(not(feature = "log"), allow(dead_code))]
+    addr: SocketAddr,
+}
+
+/// Accept clients until the server is dropped.
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+fn accept_loop(listener: TcpListener, config: &Arc<ScanServerConfig>, clients: &Arc<Mutex<Vec<Client>>>, is_stopped: &Arc<AtomicBool>) {
+    while !is_stopped.load(Ordering::Acquire) {
+        let (client, addr) = match listener.accept() {
+            Ok((client, addr)) => (client, addr),
+            Err(err) if err.kind() == ErrorKind::WouldBlock || err.kind() == ErrorKind::Interrupted => {
+                thread::sleep(ACCEPT_POLL_INTERVAL);
+                continue;
+            },
+            Err(err) => {
+                #[cfg(feature = "log")]
+                warn!("Unable to accept a scan client. {}", err);
+
+                thread::sleep(ACCEPT_POLL_INTERVAL);
+                continue;
+            },
+        };
+
+        let (config, clients, is_stopped) = (config.clone(), clients.clone(), is_stopped.clone());
+        let spawned = thread::Builder::new().name("neato-xv11-client".into()).spawn(move || serve_client(client, addr, &config, &clients, &is_stopped));
+
+        if let Err(err) = spawned {
+            #[cfg(feature = "log")]
+            warn!("Unable to start the writer of scan client {}. {}", addr, err);
+        }
+    }
+}
+
+/// Set up a client, then write the scans queued for it until it is dropped or a write fails.
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+fn serve_client(client: TcpStream, addr: SocketAddr, config: &ScanServerConfig, clients: &Mutex<Vec<Client>>, is_stopped: &AtomicBool) {
+    let mut client = match open_client(client, config) {
+        Ok(client) => client,
+        Err(err) => {
+            #[cfg(feature = "log")]
+            warn!("Rejecting scan client {}. {}", addr, err);
+
+            return;
+        },
+    };
+
+    let (tx, rx) = sync_channel(CLIENT_QUEUE_SIZE);
+
+    {
+        let mut clients = clients.lock().unwrap_or_else(|e| e.into_inner());
+
+        // The server was dropped during the handshake, nothing would stop the writer.
+        if is_stopped.load(Ordering::Acquire) {
+            return;
+        }
+
+        clients.push(Client { tx, addr });
+    }
+
+    #[cfg(feature = "log")]
+    info!("Scan client {} connected", addr);
+
+    for line in rx.iter() {
+        if let Err(err) = client.write_all(&line).and_then(|_| client.flush()) {
+            #[cfg(feature = "log")]
+            warn!("Disconnecting scan client {}. {}", addr, err);
+
+            return;
+        }
+    }
+}
+
+/// Set up the connection of a client: the TLS handshake, then the token check.
+fn open_client(client: TcpStream, config: &ScanServerConfig) -> Result<Box<dyn Write + Send>, IoError> {
+    client.set_nonblocking(false)?;
+    client.set_nodelay(true)?;
+    client.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    client.set_write_timeout(Some(HANDSHAKE_TIMEOUT))?;
+
+    let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+
+    #[cfg(feature = "tls")]
+    if let Some(tls) = &config.tls {
+        let connection = ServerConnection::new(tls.clone()).map_err(IoError::other)?;
+        let mut client = StreamOwned::new(connection, client);
+
+        while client.conn.is_handshaking() {
+            check_deadline(deadline)?;
+            client.conn.complete_io(&mut client.sock)?;
+        }
+
+        authenticate(&mut client, config.token.as_deref(), deadline)?;
+        client.sock.set_write_timeout(Some(WRITE_TIMEOUT))?;
+
+        return Ok(Box::new(client));
+    }
+
+    let mut client = client;
+    authenticate(&mut client, config.token.as_deref(), deadline)?;
+    client.set_write_timeout(Some(WRITE_TIMEOUT))?;
+
+    Ok(Box::new(client))
+}
+
+/// Read the `Bearer <token>` line of a client, if the server requires a token.
+fn authenticate<R: Read>(client: &mut R, token: Option<&str>, deadline: Instant) -> Result<(), IoError> {
+    let token = match token {
+        Some(token) => token,
+        None => return Ok(()),
+    };
+
+    let mut line = Vec::new();
+    let mut byte = [0; 1];
+
+    // Byte by byte, so no scan request or TLS record is read past the line.
+    loop {
+        check_deadline(deadline)?;
+
+        if client.read(&mut byte)? == 0 {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+
+        if byte[0] == b'\n' {
+            break;
+        }
+
+        if line.len() == MAX_TOKEN_LINE {
+            return Err(IoError::new(ErrorKind::InvalidData, "Token line too long"));
+        }
+
+        line.push(byte[0]);
+    }
+
+    let line = line.strip_suffix(b"\r").unwrap_or(&line);
+    let expected = format!("Bearer {}", token);
+
+    // Compared in constant time, so the time taken does not tell how much of the token matched.
+    let is_valid = line.len() == expected.len() && line.iter().zip(expected.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0;
+
+    if !is_valid {
+        return Err(IoError::new(ErrorKind::PermissionDenied, "Invalid token"));
+    }
+
+    Ok(())
+}
+
+/// Fail with `ErrorKind::TimedOut` once `deadline` has passed.
+fn check_deadline(deadline: Instant) -> Result<(), IoError> {
+    if Instant::now() >= deadline {
+        return Err(IoError::new(ErrorKind::TimedOut, "Handshake timed out"));
+    }
+
+    Ok(())
+}
+
+/// ## Summary
+///
+/// A message sink that streams scans through a `ScanServer`. Every message is
+/// forwarded to the inner sink.
+///
+/// ## Remarks
+///
+/// The `LidarDriverMessage::Scan` messages are streamed as they are. Until one is
+/// received (e.g. the driver was not built with `LidarDriverBuilder::scans`), scans
+/// are assembled from the packets instead. Streaming is best effort: a scan that
+/// cannot be serialized is skipped.
+///
+pub struct ScanServerSink<S> {
+    inner: S,
+    server: ScanServer,
+    // Assembles the scans while the driver does not send any.
+    assembler: Mutex<ScanAssembler>,
+    // Whether the driver sends scans, so packets are no longer assembled.
+    has_scans: AtomicBool,
+}
+
+impl<S: MessageSink> ScanServerSink<S> {
+    /// ## Summary
+    ///
+    /// Wrap a sink with a server and a default scan assembler.
+    ///
+    pub fn new(inner: S, server: ScanServer) -> Self {
+        ScanServerSink::with_assembler(inner, server, ScanAssembler::new())
+    }
+
+    /// ## Summary
+    ///
+    /// Wrap a sink with a server and a configured scan assembler.
+    ///
+    pub fn with_assembler(inner: S, server: ScanServer, assembler: ScanAssembler) -> Self {
+        ScanServerSink { inner, server, assembler: Mutex::new(assembler), has_scans: AtomicBool::new(false) }
+    }
+
+    /// ## Summary
+    ///
+    /// The server the scans are streamed through.
+    ///
+    pub fn server(&self) -> &ScanServer {
+        &self.server
+    }
+}
+
+impl<S: MessageSink> MessageSink for ScanServerSink<S> {
+    #[cfg_attr(not(feature = "log"), allow(unused_variables))]
+    fn send(&self, message: Result<LidarDriverMessage, LidarDriverError>) -> Result<(), SendError<Result<LidarDriverMessage, LidarDriverError>>> {
+        let assembled = if let Ok(LidarDriverMessage::Scan(_)) = &message {
+            self.has_scans.store(true, Ordering::Relaxed);
+            None
+        } else if self.has_scans.load(Ordering::Relaxed) {
+            None
+        } else {
+            let mut assembler = self.assembler.lock().unwrap_or_else(|e| e.into_inner());
+
+            match &message {
+                Ok(LidarDriverMessage::Packet(packet)) => assembler.push(packet.clone()),
+                _ => assembler.poll(),
+            }
+        };
+
+        let scan = match &message {
+            Ok(LidarDriverMessage::Scan(scan)) => Some(scan),
+            _ => assembled.as_ref(),
+        };
+
+        if let Some(scan) = scan {
+            if let Err(err) = self.server.broadcast(scan) {
+                #[cfg(feature = "log")]
+                warn!("Unable to stream scan {}. {}", scan.revolution, err);
+            }
+        }
+
+        self.inner.send(message)
+    }
+
+    fn dropped(&self) -> u64 {
+        self.inner.dropped()
+    }
+
+    fn backlog(&self) -> Option<Backlog> {
+        self.inner.backlog()
+    }
+}
+
+/// ## Summary
+///
+/// How `run_from_tcp_with` connects to a `ScanServer`: the bearer token to send, and
+/// the TLS configuration checking the server certificate (`tls` feature).
+///
+#[derive(Clone, Default)]
+pub struct ScanClientConfig {
+    // Token sent to the server after connecting.
+    token: Option<String>,
+    // Encrypts the stream when set, with the name the certificate must be issued for.
+    #[cfg(feature = "tls")]
+    tls: Option<(Arc<ClientConfig>, ServerName<'static>)>,
+}
+
+impl ScanClientConfig {
+    /// ## Summary
+    ///
+    /// Send `Bearer <token>` to the server after connecting.
+    ///
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.token = Some(String::from(token));
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Encrypt the stream, checking the server certificate against the roots of `config`.
+    ///
+    /// ## Parameters
+    ///
+    /// config: The TLS configuration, e.g. trusting the certificate of the server.
+    ///
+    /// server_name: Host name or IP address the certificate must be issued for.
+    ///
+    /// ## Remarks
+    ///
+    /// Returns an `ErrorKind::InvalidInput` error if `server_name` is not a valid name.
+    ///
+    #[cfg(feature = "tls")]
+    pub fn with_tls_config(mut self, config: Arc<ClientConfig>, server_name: &str) -> Result<Self, IoError> {
+        let name = ServerName::try_from(String::from(server_name)).map_err(|err| IoError::new(ErrorKind::InvalidInput, err))?;
+        self.tls = Some((config, name));
+        Ok(self)
+    }
+}
+
+/// ## Summary
+///
+/// Receive the scans streamed by a `ScanServer` and send them as
+/// `LidarDriverMessage::Scan`, until stopped or disconnected. Runs on the calling thread.
+///
+/// ## Parameters
+///
+/// addr: Address of the server.
+///
+/// tx: Sends the received scans or error encountered.
+///
+/// rx: Receives commands from the calling program. `Pause` drops the scans received
+/// until `Run`, `Stop` stops.
+///
+/// ## Remarks
+///
+/// Failing to connect is reported as `LidarDriverError::OpenSerialPort`, the server
+/// closing the connection as `LidarDriverError::DeviceDisconnected`, and an invalid
+/// line as `LidarDriverError::SerialRead`. `LidarDriverMessage::Shutdown` is sent last.
+///
+/// Connects without a token nor TLS, see `run_from_tcp_with`.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::thread;
+/// # use std::sync::mpsc::channel;
+/// use neato_xv11::prelude::*;
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// thread::spawn(move || {
+///     neato_xv11::net::run_from_tcp("raspberrypi.local:5555", message_tx, command_rx);
+/// });
+///
+/// for message in message_rx.iter() {
+///     if let Ok(LidarDriverMessage::Scan(scan)) = message {
+///         println!("Revolution {}: {} readings missing", scan.revolution, scan.missing());
+///     }
+/// }
+/// ```
+pub fn run_from_tcp<A: ToSocketAddrs, S: MessageSink, C: CommandSource>(addr: A, tx: S, rx: C) {
+    run_from_tcp_with(addr, &ScanClientConfig::default(), tx, rx);
+}
+
+/// ## Summary
+///
+/// Receive the scans streamed by a `ScanServer` started with `ScanServer::bind_with`,
+/// sending the token and setting up TLS as configured. Runs on the calling thread.
+///
+/// ## Parameters
+///
+/// addr: Address of the server.
+///
+/// config: The token and TLS configuration.
+///
+/// tx: Sends the received scans or error encountered.
+///
+/// rx: Receives commands from the calling program, as for `run_from_tcp`.
+///
+/// ## Remarks
+///
+/// A failed TLS handshake is reported as `LidarDriverError::OpenSerialPort`. A server
+/// rejecting the token closes the connection, reported as
+/// `LidarDriverError::DeviceDisconnected`.
+///
+pub fn run_from_tcp_with<A: ToSocketAddrs, S: MessageSink, C: CommandSource>(addr: A, config: &ScanClientConfig, tx: S, mut rx: C) {
+    let stream = match connect(addr, config) {
+        Ok(stream) => stream,
+        Err(err) => {
+            let _ = send_message(&tx, Err(LidarDriverError::OpenSerialPort(err.into())));
+            let _ = send_message(&tx, Ok(LidarDriverMessage::Shutdown));
+            return;
+        },
+    };
+
+    let mut reader = BufReader::new(stream);
+    let mut line = Vec::new();
+    let mut is_paused = false;
+
+    'driver: loop {
+        loop {
+            match rx.try_recv() {
+                Ok(LidarDriverCommand::Pause) => is_paused = true,
+                Ok(LidarDriverCommand::Run) => is_paused = false,
+                Ok(LidarDriverCommand::Stop) | Err(TryRecvError::Disconnected) => break 'driver,
+                // The other commands do not apply to a remote stream.
+                Ok(_) => continue,
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+
+        // A timed out read keeps the bytes of the partial line in `line`.
+        let result = match reader.read_until(b'\n', &mut line) {
+            Ok(0) => Err(LidarDriverError::DeviceDisconnected(ErrorKind::UnexpectedEof.into())),
+            Ok(_) if !line.ends_with(b"\n") => Err(LidarDriverError::DeviceDisconnected(ErrorKind::UnexpectedEof.into())),
+            Ok(_) => serde_json::from_slice::<LidarScan>(&line)
+                .map(LidarDriverMessage::Scan)
+                .map_err(|err| LidarDriverError::SerialRead(IoError::new(ErrorKind::InvalidData, err))),
+            Err(err) if err.kind() == ErrorKind::WouldBlock || err.kind() == ErrorKind::TimedOut || err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => Err(LidarDriverError::DeviceDisconnected(err)),
+        };
+
+        line.clear();
+
+        if let Err(LidarDriverError::DeviceDisconnected(_)) = result {
+            let _ = send_message(&tx, result);
+            break;
+        }
+
+        if !is_paused && send_message(&tx, result).is_err() {
+            // The calling program is gone.
+            break;
+        }
+    }
+
+    let _ = send_message(&tx, Ok(LidarDriverMessage::Shutdown));
+}
+
+/// Connect to a server: the TLS handshake, then the token.
+fn connect<A: ToSocketAddrs>(addr: A, config: &ScanClientConfig) -> Result<Box<dyn Read>, IoError> {
+    let stream = TcpStream::connect(addr)?;
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    stream.set_write_timeout(Some(HANDSHAKE_TIMEOUT))?;
+
+    let token = config.token.as_ref().map(|token| format!("Bearer {}\n", token));
+
+    #[cfg(feature = "tls")]
+    if let Some((tls, name)) = &config.tls {
+        let connection = ClientConnection::new(tls.clone(), name.clone()).map_err(IoError::other)?;
+        let mut stream = StreamOwned::new(connection, stream);
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+
+        while stream.conn.is_handshaking() {
+            check_deadline(deadline)?;
+            stream.conn.complete_io(&mut stream.sock)?;
+        }
+
+        if let Some(token) = &token {
+            stream.write_all(token.as_bytes())?;
+            stream.flush()?;
+        }
+
+        stream.sock.set_read_timeout(Some(READ_TIMEOUT))?;
+
+        return Ok(Box::new(stream));
+    }
+
+    let mut stream = stream;
+
+    if let Some(token) = &token {
+        stream.write_all(token.as_bytes())?;
+    }
+
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+    Ok(Box::new(stream))
+}
@@ -0,0 +1,214 @@
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+use super::data::{Float, LidarPacket, LidarReading, LidarScan, SCAN_SIZE};
+
+/// ## Summary
+///
+/// Distance variance measured for a range of distances and qualities.
+///
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct NoiseCell {
+    // Distance band. Covers `[distance_band * NoiseModel::distance_band_width, ...)`.
+    pub distance_band: usize,
+    // Quality band. Covers `[quality_band * NoiseModel::quality_band_width, ...)`.
+    pub quality_band: usize,
+    // Distance variance in square millimeters.
+    pub variance: Float,
+    // Number of degrees of freedom the variance was estimated from.
+    pub samples: usize,
+}
+
+/// ## Summary
+///
+/// Distance noise of a sensor as a function of range and quality, estimated from a
+/// stationary capture by `NoiseEstimator`.
+///
+/// ## Remarks
+///
+/// Serializable with the `serde` feature, so a model estimated once per unit can be
+/// stored and loaded by filters and SLAM front-ends.
+///
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct NoiseModel {
+    // Width of a distance band in millimeters.
+    pub distance_band_width: u32,
+    // Width of a quality band.
+    pub quality_band_width: u32,
+    // Measured bands. Bands without data are absent.
+    pub cells: Vec<NoiseCell>,
+}
+
+impl NoiseModel {
+    /// ## Summary
+    ///
+    /// Distance variance (square millimeters) of a reading.
+    ///
+    /// ## Remarks
+    ///
+    /// Uses the band of the reading, or the nearest measured band: the closest distance
+    /// band first, then the closest quality band. Returns `None` if the model is empty.
+    ///
+    pub fn variance(&self, distance: i32, quality: i32) -> Option<Float> {
+        let distance_band = distance.max(0) as usize / self.distance_band_width.max(1) as usize;
+        let quality_band = quality.max(0) as usize / self.quality_band_width.max(1) as usize;
+
+        self.cells.iter()
+            .min_by_key(|cell| (cell.distance_band.abs_diff(distance_band), cell.quality_band.abs_diff(quality_band)))
+            .map(|cell| cell.variance)
+    }
+
+    /// ## Summary
+    ///
+    /// Distance standard deviation (millimeters) of a reading. See `variance`.
+    ///
+    pub fn std_dev(&self, distance: i32, quality: i32) -> Option<Float> {
+        self.variance(distance, quality).map(Float::sqrt)
+    }
+
+    /// ## Summary
+    ///
+    /// Set `LidarReading::std_dev` on every valid reading of a scan, e.g. as the
+    /// measurement covariance of an EKF or graph-SLAM front-end.
+    ///
+    pub fn annotate(&self, scan: &mut LidarScan) {
+        scan.readings.iter_mut().flatten().for_each(|reading| self.annotate_reading(reading));
+    }
+
+    /// ## Summary
+    ///
+    /// Set `LidarReading::std_dev` on every valid reading of a packet.
+    ///
+    pub fn annotate_packet(&self, packet: &mut LidarPacket) {
+        packet.readings.iter_mut().for_each(|reading| self.annotate_reading(reading));
+    }
+
+    fn annotate_reading(&self, reading: &mut LidarReading) {
+        reading.std_dev = if reading.is_valid() {
+            self.std_dev(reading.distance, reading.quality)
+        } else {
+            None
+        };
+    }
+}
+
+/// Running statistics of the readings of one angle (Welford's algorithm).
+#[derive(Clone, Copy, Default)]
+struct AngleStats {
+    count: usize,
+    mean: Float,
+    m2: Float,
+    quality_sum: Float,
+}
+
+/// ## Summary
+///
+/// Estimates a `NoiseModel` from scans of a static scene taken without moving the sensor.
+///
+/// ## Remarks
+///
+/// The readings of each angle measure the same point, so their spread is the sensor
+/// noise at that point's distance and quality. The per-angle variances are pooled
+/// into distance and quality bands.
+///
+/// ## Example
+///
+/// ```no_run
+/// # fn estimate(scans: &[neato_xv11::data::LidarScan]) {
+/// use neato_xv11::noise::NoiseEstimator;
+///
+/// let mut estimator = NoiseEstimator::new(500, 50);
+///
+/// for scan in scans {
+///     estimator.push(scan);
+/// }
+///
+/// let model = estimator.build();
+/// println!("{:?} mm at 2 m", model.std_dev(2000, 100));
+/// # }
+/// ```
+pub struct NoiseEstimator {
+    distance_band_width: u32,
+    quality_band_width: u32,
+    angles: Vec<AngleStats>,
+}
+
+impl NoiseEstimator {
+    /// ## Summary
+    ///
+    /// Initialize a new estimator.
+    ///
+    /// ## Parameters
+    ///
+    /// distance_band_width: Width of a distance band in millimeters.
+    ///
+    /// quality_band_width: Width of a quality band.
+    ///
+    pub fn new(distance_band_width: u32, quality_band_width: u32) -> Self {
+        NoiseEstimator {
+            distance_band_width: distance_band_width.max(1),
+            quality_band_width: quality_band_width.max(1),
+            angles: vec![AngleStats::default(); SCAN_SIZE],
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Add the valid readings of a scan.
+    ///
+    pub fn push(&mut self, scan: &LidarScan) {
+        for reading in scan.valid_readings() {
+            let stats = &mut self.angles[reading.index % SCAN_SIZE];
+            let distance = reading.distance as Float;
+
+            stats.count += 1;
+            let delta = distance - stats.mean;
+            stats.mean += delta / stats.count as Float;
+            stats.m2 += delta * (distance - stats.mean);
+            stats.quality_sum += reading.quality as Float;
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Pool the variances of every angle measured at least twice into bands.
+    ///
+    pub fn build(&self) -> NoiseModel {
+        let mut cells: Vec<NoiseCell> = Vec::new();
+
+        for stats in self.angles.iter().filter(|stats| stats.count >= 2) {
+            let quality = stats.quality_sum / stats.count as Float;
+            let distance_band = stats.mean as usize / self.distance_band_width as usize;
+            let quality_band = quality as usize / self.quality_band_width as usize;
+            let samples = stats.count - 1;
+
+            let position = cells.iter().position(|cell| cell.distance_band == distance_band && cell.quality_band == quality_band);
+
+            match position {
+                Some(position) => {
+                    // Pooled variance: sum of squares over the total degrees of freedom.
+                    let cell = &mut cells[position];
+                    let m2 = cell.variance * cell.samples as Float + stats.m2;
+                    cell.samples += samples;
+                    cell.variance = m2 / cell.samples as Float;
+                },
+                None => cells.push(NoiseCell {
+                    distance_band,
+                    quality_band,
+                    variance: stats.m2 / samples as Float,
+                    samples,
+                }),
+            }
+        }
+
+        cells.sort_by_key(|cell| (cell.distance_band, cell.quality_band));
+
+        NoiseModel {
+            distance_band_width: self.distance_band_width,
+            quality_band_width: self.quality_band_width,
+            cells,
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use std::ffi::OsStr;
+use std::io::{Error as IoError, ErrorKind, Read, Write};
+use std::os::windows::ffi::OsStrExt;
+use std::ptr;
+use std::time::Duration;
+
+use serial::{CharSize, FlowControl, Parity, PortSettings, StopBits};
+use windows_sys::Win32::Devices::Communication::{
+    GetCommState, PurgeComm, SetCommState, SetCommTimeouts, COMMTIMEOUTS, DCB, EVENPARITY, NOPARITY, ODDPARITY, ONESTOPBIT, PURGE_RXCLEAR,
+    TWOSTOPBITS,
+};
+use windows_sys::Win32::Foundation::{
+    CloseHandle, ERROR_IO_PENDING, ERROR_OPERATION_ABORTED, FALSE, GENERIC_READ, GENERIC_WRITE, HANDLE, INVALID_HANDLE_VALUE, TRUE,
+    WAIT_OBJECT_0, WAIT_TIMEOUT,
+};
+use windows_sys::Win32::Storage::FileSystem::{CreateFileW, ReadFile, WriteFile, FILE_FLAG_OVERLAPPED, OPEN_EXISTING};
+use windows_sys::Win32::System::IO::{CancelIoEx, GetOverlappedResult, OVERLAPPED};
+use windows_sys::Win32::System::Threading::{CreateEventW, WaitForSingleObject};
+
+/// `DCB` flags: `fBinary`, `fParity`, `fOutxCtsFlow`, `fOutX`, `fInX` and `fRtsControl`.
+const DCB_BINARY: u32 = 1 << 0;
+const DCB_PARITY: u32 = 1 << 1;
+const DCB_OUTX_CTS_FLOW: u32 = 1 << 2;
+const DCB_OUTX: u32 = 1 << 8;
+const DCB_INX: u32 = 1 << 9;
+const DCB_RTS_CONTROL_MASK: u32 = 0b11 << 12;
+const DCB_RTS_CONTROL_ENABLE: u32 = 0b01 << 12;
+const DCB_RTS_CONTROL_HANDSHAKE: u32 = 0b10 << 12;
+
+/// ## Summary
+///
+/// A COM port opened for overlapped I/O.
+///
+/// ## Remarks
+///
+/// The blocking `serial` backend waits in `ReadFile` for the whole read timeout and
+/// cannot be interrupted. Here a read is started asynchronously and waited on for at
+/// most the read timeout, then cancelled, so the reader thread always gets back to its
+/// stop flag in time, even if the driver reports no timeout to `ReadFile`.
+///
+pub(crate) struct OverlappedPort {
+    handle: HANDLE,
+    // Signaled when the pending read or write completes.
+    event: HANDLE,
+    // How long a read waits for data.
+    timeout: Duration,
+}
+
+// Safety: the handles are owned by the port and only used through `&mut self`.
+unsafe impl Send for OverlappedPort {}
+
+impl OverlappedPort {
+    /// ## Summary
+    ///
+    /// Open `\\.\<port_name>` for overlapped I/O.
+    ///
+    /// ## Remarks
+    ///
+    /// A missing device and a denied access are reported as `serial::ErrorKind::NoDevice`,
+    /// like the `serial` backend, so `port::open_error` tells them apart.
+    ///
+    pub(crate) fn open(port_name: &OsStr) -> Result<Self, serial::Error> {
+        let mut path: Vec<u16> = OsStr::new(r"\\.\").encode_wide().chain(port_name.encode_wide()).collect();
+        path.push(0);
+
+        // Safety: path is a NUL-terminated wide string.
+        let handle = unsafe {
+            CreateFileW(path.as_ptr(), GENERIC_READ | GENERIC_WRITE, 0, ptr::null(), OPEN_EXISTING, FILE_FLAG_OVERLAPPED, 0)
+        };
+
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(serial_error(IoError::last_os_error()));
+        }
+
+        // Safety: manual reset event, unnamed and without security attributes.
+        let event = unsafe { CreateEventW(ptr::null(), TRUE, FALSE, ptr::null()) };
+
+        if event == 0 {
+            let err = IoError::last_os_error();
+
+            // Safety: handle was opened above and is not used afterwards.
+            unsafe { CloseHandle(handle) };
+
+            return Err(serial_error(err));
+        }
+
+        Ok(OverlappedPort { handle, event, timeout: Duration::from_secs(1) })
+    }
+
+    /// ## Summary
+    ///
+    /// Set how long a read waits for data before failing with `ErrorKind::TimedOut`.
+    ///
+    pub(crate) fn set_timeout(&mut self, timeout: Duration) -> Result<(), serial::Error> {
+        let milliseconds = timeout.as_millis().min(u32::MAX as u128 - 1) as u32;
+        // Reads return as soon as a byte is received, or after the timeout.
+        let timeouts = COMMTIMEOUTS {
+            ReadIntervalTimeout: u32::MAX,
+            ReadTotalTimeoutMultiplier: u32::MAX,
+            ReadTotalTimeoutConstant: milliseconds,
+            WriteTotalTimeoutMultiplier: 0,
+            WriteTotalTimeoutConstant: milliseconds,
+        };
+
+        // Safety: handle is a valid COM port handle.
+        if unsafe { SetCommTimeouts(self.handle, &timeouts) } == 0 {
+            return Err(serial_error(IoError::last_os_error()));
+        }
+
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    /// ## Summary
+    ///
+    /// Apply the baud rate, framing and flow control, then discard the bytes received so far.
+    ///
+    pub(crate) fn configure(&mut self, settings: &PortSettings) -> Result<(), serial::Error> {
+        // Safety: DCB is plain data, it is fully initialized by GetCommState.
+        let mut dcb: DCB = unsafe { std::mem::zeroed() };
+        dcb.DCBlength = std::mem::size_of::<DCB>() as u32;
+
+        // Safety: handle is a valid COM port handle and dcb a valid DCB.
+        if unsafe { GetCommState(self.handle, &mut dcb) } == 0 {
+            return Err(serial_error(IoError::last_os_error()));
+        }
+
+        dcb.BaudRate = settings.baud_rate.speed() as u32;
+        dcb.ByteSize = match settings.char_size {
+            CharSize::Bits5 => 5,
+            CharSize::Bits6 => 6,
+            CharSize::Bits7 => 7,
+            CharSize::Bits8 => 8,
+        };
+        dcb.Parity = match settings.parity {
+            Parity::ParityNone => NOPARITY,
+            Parity::ParityOdd => ODDPARITY,
+            Parity::ParityEven => EVENPARITY,
+        };
+        dcb.StopBits = match settings.stop_bits {
+            StopBits::Stop1 => ONESTOPBIT,
+            StopBits::Stop2 => TWOSTOPBITS,
+        };
+
+        dcb._bitfield &= !(DCB_PARITY | DCB_OUTX_CTS_FLOW | DCB_OUTX | DCB_INX | DCB_RTS_CONTROL_MASK);
+        dcb._bitfield |= DCB_BINARY;
+
+        if settings.parity != Parity::ParityNone {
+            dcb._bitfield |= DCB_PARITY;
+        }
+
+        dcb._bitfield |= match settings.flow_control {
+            FlowControl::FlowNone => DCB_RTS_CONTROL_ENABLE,
+            FlowControl::FlowSoftware => DCB_RTS_CONTROL_ENABLE | DCB_OUTX | DCB_INX,
+            FlowControl::FlowHardware => DCB_RTS_CONTROL_HANDSHAKE | DCB_OUTX_CTS_FLOW,
+        };
+
+        // Safety: handle is a valid COM port handle and dcb a valid DCB.
+        if unsafe { SetCommState(self.handle, &mut dcb) } == 0 {
+            return Err(serial_error(IoError::last_os_error()));
+        }
+
+        // Safety: handle is a valid COM port handle.
+        if unsafe { PurgeComm(self.handle, PURGE_RXCLEAR) } == 0 {
+            return Err(serial_error(IoError::last_os_error()));
+        }
+
+        Ok(())
+    }
+
+    /// Wait for the pending operation for at most `timeout`, cancelling it if it is still
+    /// pending, and return the number of bytes transferred.
+    fn complete(&mut self, overlapped: &OVERLAPPED, timeout: Option<Duration>) -> Result<u32, IoError> {
+        let milliseconds = timeout.map_or(u32::MAX, |timeout| timeout.as_millis().min(u32::MAX as u128 - 1) as u32);
+
+        // Safety: event is a valid event handle.
+        match unsafe { WaitForSingleObject(self.event, milliseconds) } {
+            WAIT_OBJECT_0 => {},
+            WAIT_TIMEOUT => {
+                // Safety: overlapped belongs to the operation pending on handle.
+                unsafe { CancelIoEx(self.handle, overlapped) };
+            },
+            _ => return Err(IoError::last_os_error()),
+        }
+
+        let mut count = 0;
+
+        // Wait for the cancellation too, the buffer must outlive the operation.
+        // Safety: overlapped belongs to the operation pending on handle.
+        if unsafe { GetOverlappedResult(self.handle, overlapped, &mut count, TRUE) } == 0 {
+            let err = IoError::last_os_error();
+
+            return match err.raw_os_error() {
+                Some(code) if code as u32 == ERROR_OPERATION_ABORTED => Ok(0),
+                _ => Err(err),
+            };
+        }
+
+        Ok(count)
+    }
+
+    /// A new `OVERLAPPED` signaling the port event.
+    fn overlapped(&self) -> OVERLAPPED {
+        // Safety: OVERLAPPED is plain data, zero is its initial state.
+        let mut overlapped: OVERLAPPED = unsafe { std::mem::zeroed() };
+        overlapped.hEvent = self.event;
+        overlapped
+    }
+}
+
+impl Read for OverlappedPort {
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, IoError> {
+        let mut overlapped = self.overlapped();
+        let length = buffer.len().min(u32::MAX as usize) as u32;
+
+        // Safety: buffer and overlapped outlive the operation, `complete` waits for it.
+        let started = unsafe { ReadFile(self.handle, buffer.as_mut_ptr(), length, ptr::null_mut(), &mut overlapped) };
+
+        if started == 0 {
+            let err = IoError::last_os_error();
+
+            if err.raw_os_error() != Some(ERROR_IO_PENDING as i32) {
+                return Err(err);
+            }
+        }
+
+        match self.complete(&overlapped, Some(self.timeout))? {
+            0 => Err(IoError::new(ErrorKind::TimedOut, "Operation timed out")),
+            count => Ok(count as usize),
+        }
+    }
+}
+
+impl Write for OverlappedPort {
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, IoError> {
+        let mut overlapped = self.overlapped();
+        let length = buffer.len().min(u32::MAX as usize) as u32;
+
+        // Safety: buffer and overlapped outlive the operation, `complete` waits for it.
+        let started = unsafe { WriteFile(self.handle, buffer.as_ptr(), length, ptr::null_mut(), &mut overlapped) };
+
+        if started == 0 {
+            let err = IoError::last_os_error();
+
+            if err.raw_os_error() != Some(ERROR_IO_PENDING as i32) {
+                return Err(err);
+            }
+        }
+
+        // Bounded by the write timeout of the port.
+        match self.complete(&overlapped, None)? {
+            0 if !buffer.is_empty() => Err(IoError::new(ErrorKind::TimedOut, "Operation timed out")),
+            count => Ok(count as usize),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), IoError> {
+        Ok(())
+    }
+}
+
+impl Drop for OverlappedPort {
+    fn drop(&mut self) {
+        // Safety: both handles are owned by the port and no operation is pending.
+        unsafe {
+            CloseHandle(self.handle);
+            CloseHandle(self.event);
+        }
+    }
+}
+
+/// Convert an error opening or configuring the port to the errors of the `serial` crate.
+fn serial_error(err: IoError) -> serial::Error {
+    let kind = match err.kind() {
+        ErrorKind::NotFound | ErrorKind::PermissionDenied => serial::ErrorKind::NoDevice,
+        kind => serial::ErrorKind::Io(kind),
+    };
+
+    serial::Error::new(kind, err.to_string())
+}
@@ -0,0 +1,717 @@
+#[cfg(feature = "log")]
+use log::error;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+use super::calibration::DistanceCorrection;
+use super::data::LidarPacket;
+use super::error::{ChecksumDetails, LidarDriverError, LidarReadingError};
+use super::fixed::{FixedPacket, FixedReading};
+use super::model::Model;
+use super::protocol::{LidarProtocol, MAX_FRAME_SIZE};
+use super::quirks::Quirks;
+
+/// Size of an encoded LIDAR packet in bytes.
+pub(crate) const PACKET_SIZE: usize = 22;
+
+/// Number of packets in a revolution, indexed 0xA0 to 0xF9.
+pub(crate) const PACKETS_PER_REVOLUTION: usize = 90;
+
+/// First byte of every LIDAR packet.
+pub(crate) const PACKET_HEADER: u8 = 0xFA;
+
+/// ## Summary
+/// 
+/// Check whether a byte is a valid packet index (0xA0 to 0xF9).
+/// 
+pub(crate) fn is_valid_index(byte: u8) -> bool {
+    (0xA0..=0xF9).contains(&byte)
+}
+
+/// ## Summary
+/// 
+/// Calculate the checksum using the first 20 bytes of the packet.
+/// 
+/// ## Remarks
+/// 
+/// The slice must be 20 bytes in size.
+/// 
+pub(crate) fn calc_checksum(data : &[u8]) -> u32 {
+    let mut chk32 : u32 = 0;
+
+    for i in 0..10 {
+        // Group the data by word, little-endian
+        let lsb = data[2 * i] as u32;
+        let msb = data[2 * i + 1] as u32;
+        let val = (msb << 8) | lsb;
+        // compute the checksum on 32 bits
+        chk32 = (chk32 << 1) + val;
+    }
+
+    // Wrap around to fit into 15 bits
+    let mut check_sum = (chk32 & 0x7FFF) + (chk32 >> 15);
+    // Truncate to 15 bits
+    check_sum &= 0x7FFF;
+    // Return the checksum
+    check_sum
+}
+
+/// ## Summary
+/// 
+/// Parse encoded LIDAR packet.
+/// 
+/// ## Remarks
+/// 
+/// The slice must be 22 bytes in size.
+/// 
+pub(crate) fn parse_packet(buffer: &[u8], model: Model) -> Result<LidarPacket, LidarDriverError> {
+    parse_packet_fixed(buffer, model).map(LidarPacket::from)
+}
+
+/// ## Summary
+/// 
+/// Parse encoded LIDAR packet into the fixed-point representation.
+/// No floating point operations are performed.
+/// 
+/// ## Remarks
+/// 
+/// The slice must be 22 bytes in size.
+/// 
+pub(crate) fn parse_packet_fixed(buffer: &[u8], model: Model) -> Result<FixedPacket, LidarDriverError> {
+    // Packet index | Range = [0,89].
+    let index = buffer[1] as u16;
+    let index = index - 0xA0;
+
+    // Lidar Speed, scaled according to the model.
+    let msb = buffer[3] as u16;
+    let lsb = buffer[2] as u16;
+    let speed = model.speed((msb << 8) | lsb);
+
+    // Verify the packet's integrity.
+    let msb = buffer[21] as u32;
+    let lsb = buffer[20] as u32;
+
+    // Generate the expected checksum.
+    let expected_checksum = (msb << 8) | lsb;
+    let calc_checksum = calc_checksum(&buffer[0..20]);
+
+    if calc_checksum != expected_checksum {
+        #[cfg(feature = "log")]
+        error!("A checksum error occured. The data is corrupted");
+
+        // Checksum error occured. The data is corrupted.
+        let mut packet = [0; 22];
+        packet.copy_from_slice(&buffer[..22]);
+
+        let details = ChecksumDetails {
+            packet,
+            received: expected_checksum as u16,
+            calculated: calc_checksum as u16,
+        };
+
+        return Err(LidarDriverError::Checksum(index as usize, Some(details)));
+    }
+
+    let readings = [1, 2, 3, 4].map(|i| {
+        let byte_index = 4 * i;
+        let reading_index = 4 * index + i as u16 - 1;
+
+        // The first 2 bytes are two flags + distance.
+        let msb = buffer[byte_index + 1] as u16;
+        let lsb = buffer[byte_index] as u16;
+        let distance = (msb << 8) | lsb;
+
+        // The next 2 bytes are the reliability (higher # = more reliable reading).
+        let msb = buffer[byte_index + 3] as u16;
+        let lsb = buffer[byte_index + 2] as u16;
+        let quality = (msb << 8) | lsb;
+
+        if distance & 0x8000 > 0 {
+            // Invalid data flag triggered. LSB contains error code.
+            let error_code = (distance & 0x00FF) as i32;
+            FixedReading::new(reading_index, distance, quality, Some(LidarReadingError::InvalidDataError(error_code)))
+        } else if !model.has_strength_flag() {
+            // Bit 14 is unused. Remove it before recording.
+            FixedReading::new(reading_index, distance & 0x3FFF, quality, None)
+        } else if distance & 0x4000 > 0 {
+            // Signal strength warning flag triggered. Remove flag before recording.
+            let distance = distance & 0x3FFF;
+            FixedReading::new(reading_index, distance, quality, Some(LidarReadingError::SignalStrengthWarning))
+        } else {
+            // No flag triggered. Write distance to readings.
+            FixedReading::new(reading_index, distance, quality, None)
+        }
+    });
+    
+    Ok(FixedPacket::new(readings, speed))
+}
+
+/// Outcome of pushing a byte into the parser.
+enum Step {
+    // The byte was consumed, no frame is complete yet.
+    Pending,
+    // The buffer holds a complete frame.
+    Frame,
+    // The byte was not where a packet should have started. A resync is required.
+    LostSync,
+}
+
+/// ## Summary
+/// 
+/// How the parser decides that a frame is the start of the packet stream.
+/// 
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SyncStrategy {
+    // The first complete frame after a header and a valid index, even if its checksum is
+    // wrong. A payload byte that looks like a header is reported as a checksum error.
+    #[default]
+    Header,
+    // The first complete frame whose checksum is valid. Frames failing their checksum
+    // before sync are discarded without being reported, and the search restarts at the
+    // next header inside them.
+    Checksum,
+}
+
+/// Number of complete frames `Parser::feed` can hold before the oldest is overwritten.
+pub(crate) const FRAME_QUEUE_SIZE: usize = 8;
+
+/// ## Summary
+/// 
+/// A byte-oriented packet parser. Bytes are pushed one at a time, which makes it
+/// suitable for non-blocking serial ports and targets without `std`.
+/// 
+/// ## Remarks
+/// 
+/// The parser synchronizes on its own by discarding bytes until a packet header
+/// followed by a valid index is found. Other LIDARs are supported through
+/// `Parser::with_protocol`, the Neato protocol is used by default.
+/// 
+/// Chunks of arbitrary size (e.g. half-complete DMA transfers) can be passed to
+/// `feed`, which only copies bytes and never decodes or allocates, so it can be
+/// called from an interrupt handler. The queued frames are decoded later with `pop`.
+/// 
+/// Programs that only decode recorded or network-delivered bytes can depend on the
+/// parser alone, without the serial port driver:
+/// `neato_xv11 = { version = "0.3", default-features = false, features = ["parser"] }`.
+/// On targets without an allocator, disable every feature instead
+/// (`default-features = false`): the parser is then `no_std` and allocation-free, and `push_fixed` decodes into a
+/// `fixed::FixedPacket` holding four readings without any floating point operation.
+/// 
+/// ## Example
+/// 
+/// ```
+/// use neato_xv11::prelude::*;
+/// use neato_xv11::scan::ScanAssembler;
+/// 
+/// # let bytes: &[u8] = &[];
+/// let mut parser = Parser::new();
+/// let mut assembler = ScanAssembler::new();
+/// 
+/// for &byte in bytes {
+///     if let Some(Ok(packet)) = parser.push(byte) {
+///         if let Some(scan) = assembler.push(packet) {
+///             println!("{} readings missing", scan.missing());
+///         }
+///     }
+/// }
+/// ```
+pub struct Parser<P: LidarProtocol = Model> {
+    // Packet currently being assembled.
+    buffer: [u8; MAX_FRAME_SIZE],
+    // Number of bytes in the buffer.
+    len: usize,
+    // Whether the last bytes received were a complete frame.
+    is_synced: bool,
+    // Complete frames queued by `feed`.
+    frames: [[u8; MAX_FRAME_SIZE]; FRAME_QUEUE_SIZE],
+    // Index of the oldest queued frame.
+    head: usize,
+    // Number of queued frames.
+    queued: usize,
+    // Number of frames overwritten before being popped.
+    overruns: usize,
+    // Framing and decoding of the packets.
+    protocol: P,
+    // Firmware deviations to compensate for.
+    quirks: Quirks,
+    // How the first frame is validated.
+    sync_strategy: SyncStrategy,
+    // Correction applied to every valid distance.
+    correction: Option<DistanceCorrection>,
+    // Sequence number of the next packet.
+    sequence: u64,
+    // Index of the last packet decoded since the last reset, to count the missing packets.
+    last_index: Option<usize>,
+}
+
+impl Parser {
+    /// ## Summary
+    /// 
+    /// Initialize a new parser for the Neato XV-11.
+    /// 
+    pub fn new() -> Self {
+        Parser::with_model(Model::Xv11)
+    }
+
+    /// ## Summary
+    /// 
+    /// Initialize a new parser for the given LIDAR model.
+    /// 
+    pub fn with_model(model: Model) -> Self {
+        Parser::with_protocol(model)
+    }
+
+    /// ## Summary
+    /// 
+    /// Push a single byte into the parser, decoding into the fixed-point representation.
+    /// 
+    /// ## Remarks
+    /// 
+    /// Same as `push`, without any floating point operations.
+    /// 
+    pub fn push_fixed(&mut self, byte: u8) -> Option<Result<FixedPacket, LidarDriverError>> {
+        let was_synced = self.is_synced;
+
+        match self.push_byte(byte) {
+            Step::Pending => None,
+            Step::Frame => {
+                let result = parse_packet_fixed(&self.buffer, self.protocol);
+
+                if self.rejects_sync(was_synced, &result) {
+                    return None;
+                }
+
+                Some(result)
+            },
+            Step::LostSync => Some(Err(LidarDriverError::ResyncRequired)),
+        }
+    }
+
+    /// ## Summary
+    /// 
+    /// Decode the oldest frame queued by `feed` into the fixed-point representation.
+    /// 
+    pub fn pop_fixed(&mut self) -> Option<Result<FixedPacket, LidarDriverError>> {
+        let model = self.protocol;
+        self.pop_frame().map(|frame| parse_packet_fixed(&frame, model))
+    }
+
+    /// ## Summary
+    /// 
+    /// The LIDAR model the parser decodes packets for.
+    /// 
+    pub fn model(&self) -> Model {
+        self.protocol
+    }
+}
+
+impl<P: LidarProtocol> Parser<P> {
+    /// ## Summary
+    /// 
+    /// Initialize a new parser for the given protocol.
+    /// 
+    /// ## Remarks
+    /// 
+    /// Panics if the protocol's frame size exceeds `MAX_FRAME_SIZE`.
+    /// 
+    pub fn with_protocol(protocol: P) -> Self {
+        assert!(protocol.frame_size() <= MAX_FRAME_SIZE, "frame size exceeds MAX_FRAME_SIZE");
+
+        Parser {
+            buffer: [0; MAX_FRAME_SIZE],
+            len: 0,
+            is_synced: false,
+            frames: [[0; MAX_FRAME_SIZE]; FRAME_QUEUE_SIZE],
+            head: 0,
+            queued: 0,
+            overruns: 0,
+            protocol,
+            quirks: Quirks::default(),
+            sync_strategy: SyncStrategy::default(),
+            correction: None,
+            sequence: 0,
+            last_index: None,
+        }
+    }
+
+    /// ## Summary
+    /// 
+    /// Compensate for the deviations of a firmware. See `Firmware::quirks`.
+    /// 
+    /// ## Remarks
+    /// 
+    /// Quirks are applied by `push` and `pop`. `rescan_on_checksum_error` only applies
+    /// to `push`, since `feed` does not verify checksums.
+    /// 
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    /// ## Summary
+    /// 
+    /// Set how the first frame is validated after starting or losing sync.
+    /// Defaults to `SyncStrategy::Header`.
+    /// 
+    /// ## Remarks
+    /// 
+    /// Applies to `push` and `push_fixed`, since `feed` does not verify checksums.
+    /// 
+    pub fn set_sync_strategy(&mut self, strategy: SyncStrategy) {
+        self.sync_strategy = strategy;
+    }
+
+    /// ## Summary
+    /// 
+    /// Whether the last bytes pushed were a complete frame, i.e. the parser found the
+    /// packet boundaries.
+    /// 
+    pub fn is_synced(&self) -> bool {
+        self.is_synced
+    }
+
+    /// ## Summary
+    /// 
+    /// Correct every valid distance, e.g. for a unit that consistently reads 3% short.
+    /// 
+    /// ## Remarks
+    /// 
+    /// Applied by `push` and `pop`, before any filter or scan calibration table.
+    /// 
+    pub fn set_distance_correction(&mut self, correction: DistanceCorrection) {
+        self.correction = Some(correction);
+    }
+
+    /// ## Summary
+    /// 
+    /// The firmware deviations the parser compensates for.
+    /// 
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    /// ## Summary
+    /// 
+    /// The protocol the parser decodes packets with.
+    /// 
+    pub fn protocol(&self) -> &P {
+        &self.protocol
+    }
+
+    /// ## Summary
+    /// 
+    /// Push a single byte into the parser.
+    /// 
+    /// ## Remarks
+    /// 
+    /// Returns the decoded packet, or a checksum error, once a complete frame
+    /// has been received. Returns `LidarDriverError::ResyncRequired` the first time
+    /// a byte is discarded after a complete packet. Returns `None` otherwise.
+    /// 
+    /// Packets are numbered in `LidarPacket::sequence` and the packets missing from the
+    /// index sequence since the previous one are counted in `LidarPacket::missed`.
+    /// 
+    pub fn push(&mut self, byte: u8) -> Option<Result<LidarPacket, LidarDriverError>> {
+        let was_synced = self.is_synced;
+
+        match self.push_byte(byte) {
+            Step::Pending => None,
+            Step::Frame => {
+                let frame = &self.buffer[..self.protocol.frame_size()];
+                let index = self.protocol.frame_index(frame);
+                let result = self.decode(frame);
+
+                if self.rejects_sync(was_synced, &result) {
+                    return None;
+                }
+
+                let result = result.map(|packet| self.number(packet, index));
+
+                if self.quirks.rescan_on_checksum_error && matches!(result, Err(LidarDriverError::Checksum(..))) {
+                    self.rescan();
+                }
+
+                Some(result)
+            },
+            Step::LostSync => Some(Err(LidarDriverError::ResyncRequired)),
+        }
+    }
+
+    /// ## Summary
+    /// 
+    /// Decode a chunk of bytes, e.g. received from a socket, an async reader or a DMA
+    /// buffer. Yields what `push` returns for each byte: packets, checksum errors and
+    /// resyncs.
+    /// 
+    /// ## Remarks
+    /// 
+    /// The parser owns no I/O and keeps a partial packet across chunks. Bytes are only
+    /// decoded as the iterator advances, those left when it is dropped are available from
+    /// `Packets::remaining` and must be passed again.
+    /// 
+    /// ## Example
+    /// 
+    /// ```
+    /// use neato_xv11::prelude::*;
+    /// 
+    /// # let chunks: Vec<Vec<u8>> = Vec::new();
+    /// let mut parser = Parser::new();
+    /// 
+    /// for chunk in &chunks {
+    ///     for result in parser.packets(chunk) {
+    ///         match result {
+    ///             Ok(packet) => println!("{} RPM", packet.speed),
+    ///             Err(err) => println!("{}", err),
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn packets<'a>(&'a mut self, bytes: &'a [u8]) -> Packets<'a, P> {
+        Packets { parser: self, bytes }
+    }
+
+    /// ## Summary
+    /// 
+    /// Feed a chunk of bytes into the parser and queue every completed frame.
+    /// 
+    /// ## Remarks
+    /// 
+    /// Returns the number of complete frames produced by this chunk. Frames are
+    /// retrieved with `pop`. If more than 8 frames are queued, the oldest is
+    /// overwritten and counted in `overruns`.
+    /// 
+    pub fn feed(&mut self, bytes: &[u8]) -> usize {
+        let mut produced = 0;
+
+        for &byte in bytes {
+            if !matches!(self.push_byte(byte), Step::Frame) {
+                continue;
+            }
+
+            if self.queued == FRAME_QUEUE_SIZE {
+                // Queue full, overwrite the oldest frame.
+                self.head = (self.head + 1) % FRAME_QUEUE_SIZE;
+                self.queued -= 1;
+                self.overruns += 1;
+            }
+
+            let tail = (self.head + self.queued) % FRAME_QUEUE_SIZE;
+            self.frames[tail] = self.buffer;
+            self.queued += 1;
+            produced += 1;
+        }
+
+        produced
+    }
+
+    /// ## Summary
+    /// 
+    /// Decode the oldest frame queued by `feed`.
+    /// 
+    pub fn pop(&mut self) -> Option<Result<LidarPacket, LidarDriverError>> {
+        let size = self.protocol.frame_size();
+        let frame = self.pop_frame()?;
+        let index = self.protocol.frame_index(&frame[..size]);
+
+        Some(self.decode(&frame[..size]).map(|packet| self.number(packet, index)))
+    }
+
+    /// ## Summary
+    /// 
+    /// Number of frames queued by `feed` and not yet popped.
+    /// 
+    pub fn queued(&self) -> usize {
+        self.queued
+    }
+
+    /// ## Summary
+    /// 
+    /// Number of frames overwritten because `pop` was not called often enough.
+    /// 
+    pub fn overruns(&self) -> usize {
+        self.overruns
+    }
+
+    /// ## Summary
+    /// 
+    /// Discard any partially received packet and every queued frame.
+    /// 
+    /// ## Remarks
+    /// 
+    /// The packets are still numbered from the last sequence number, but the first
+    /// packet after a reset never counts missing packets.
+    /// 
+    pub fn reset(&mut self) {
+        self.len = 0;
+        self.is_synced = false;
+        self.head = 0;
+        self.queued = 0;
+        self.last_index = None;
+    }
+
+    /// Remove the oldest frame queued by `feed`.
+    fn pop_frame(&mut self) -> Option<[u8; MAX_FRAME_SIZE]> {
+        if self.queued == 0 {
+            return None;
+        }
+
+        let frame = self.frames[self.head];
+        self.head = (self.head + 1) % FRAME_QUEUE_SIZE;
+        self.queued -= 1;
+
+        Some(frame)
+    }
+
+    /// Decode a frame and apply the quirks.
+    fn decode(&self, frame: &[u8]) -> Result<LidarPacket, LidarDriverError> {
+        let mut packet = self.protocol.decode(frame)?;
+
+        if self.quirks.zero_distance_invalid {
+            for reading in packet.readings.iter_mut().filter(|r| r.distance == 0 && r.error.is_none()) {
+                reading.error = Some(LidarReadingError::InvalidDataError(0));
+            }
+        }
+
+        if let Some(correction) = &self.correction {
+            for reading in packet.readings.iter_mut().filter(|r| r.is_valid()) {
+                reading.distance = correction.apply(reading.distance);
+            }
+        }
+
+        Ok(packet)
+    }
+
+    /// Set the sequence number of a decoded packet and count the packets missing before it.
+    fn number(&mut self, mut packet: LidarPacket, index: Option<(usize, usize)>) -> LidarPacket {
+        packet.sequence = self.sequence;
+        self.sequence += 1;
+
+        if let Some((index, count)) = index {
+            if let Some(last) = self.last_index {
+                // Packets between the last one and this one, wrapping at the end of the revolution.
+                packet.missed = (index + count - last - 1) % count;
+            }
+
+            self.last_index = Some(index);
+        }
+
+        packet
+    }
+
+    /// Whether the frame completed while searching for sync is rejected by the sync
+    /// strategy. A rejected frame is rescanned for the next header.
+    fn rejects_sync<T>(&mut self, was_synced: bool, result: &Result<T, LidarDriverError>) -> bool {
+        let is_rejected = !was_synced
+            && self.sync_strategy == SyncStrategy::Checksum
+            && matches!(result, Err(LidarDriverError::Checksum(..)));
+
+        if is_rejected {
+            self.is_synced = false;
+            self.rescan();
+        }
+
+        is_rejected
+    }
+
+    /// Restart the frame at the first header found inside the rejected frame in the buffer.
+    fn rescan(&mut self) {
+        let size = self.protocol.frame_size();
+        let header_size = self.protocol.header_size();
+
+        let start = (1..size).find(|&start| {
+            (start..size.min(start + header_size)).all(|i| self.protocol.accepts(i - start, self.buffer[i]))
+        });
+
+        if let Some(start) = start {
+            self.buffer.copy_within(start..size, 0);
+            self.len = size - start;
+        }
+    }
+
+    /// Add a byte to the packet being assembled.
+    fn push_byte(&mut self, byte: u8) -> Step {
+        if self.len < self.protocol.header_size() {
+            if self.protocol.accepts(self.len, byte) {
+                self.buffer[self.len] = byte;
+                self.len += 1;
+                return Step::Pending;
+            }
+
+            if self.len > 0 && self.protocol.accepts(0, byte) {
+                // Not a packet, but the byte may start the next one.
+                self.buffer[0] = byte;
+                self.len = 1;
+                return Step::Pending;
+            }
+
+            // Not a packet, search for the next header.
+            self.len = 0;
+            return self.lose_sync();
+        }
+
+        self.buffer[self.len] = byte;
+        self.len += 1;
+
+        if self.len < self.protocol.frame_size() {
+            return Step::Pending;
+        }
+
+        self.len = 0;
+        self.is_synced = true;
+        Step::Frame
+    }
+
+    /// Record that a byte was discarded. Returns `Step::LostSync` if the parser was in sync.
+    fn lose_sync(&mut self) -> Step {
+        if self.is_synced {
+            self.is_synced = false;
+            Step::LostSync
+        } else {
+            Step::Pending
+        }
+    }
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Parser::new()
+    }
+}
+
+/// ## Summary
+/// 
+/// The results decoded from a chunk of bytes. See `Parser::packets`.
+/// 
+pub struct Packets<'a, P: LidarProtocol> {
+    // The parser decoding the bytes.
+    parser: &'a mut Parser<P>,
+    // Bytes not pushed into the parser yet.
+    bytes: &'a [u8],
+}
+
+impl<P: LidarProtocol> Packets<'_, P> {
+    /// ## Summary
+    /// 
+    /// The bytes not decoded yet.
+    /// 
+    pub fn remaining(&self) -> &[u8] {
+        self.bytes
+    }
+}
+
+impl<P: LidarProtocol> Iterator for Packets<'_, P> {
+    type Item = Result<LidarPacket, LidarDriverError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((&byte, rest)) = self.bytes.split_first() {
+            self.bytes = rest;
+
+            if let Some(result) = self.parser.push(byte) {
+                return Some(result);
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,97 @@
+use std::io::{Error as IoError, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// ## Summary
+///
+/// Link type of the captures, `LINKTYPE_USER0`. Each frame is a raw LIDAR packet
+/// (22 bytes for the XV-11).
+///
+/// ## Remarks
+///
+/// In Wireshark, map `DLT_USER0` to a dissector for the packets under
+/// Preferences > Protocols > DLT_USER, or filter on the raw bytes with
+/// `tshark -Y "frame[1] == 0xa0"`.
+///
+pub const LINK_TYPE: u32 = 147;
+
+/// Largest frame length stored.
+const SNAPLEN: u32 = 65535;
+
+/// ## Summary
+///
+/// Writes LIDAR packets as a pcap capture, one timestamped frame per packet.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::fs::File;
+/// use std::io::BufWriter;
+/// use std::time::SystemTime;
+/// use neato_xv11::pcap::PcapWriter;
+///
+/// let file = BufWriter::new(File::create("lidar.pcap").unwrap());
+/// let mut pcap = PcapWriter::new(file).unwrap();
+///
+/// # let packet = [0u8; 22];
+/// pcap.write_frame(&packet, SystemTime::now()).unwrap();
+/// ```
+pub struct PcapWriter<W> {
+    // Receives the capture.
+    writer: W,
+}
+
+impl<W: Write> PcapWriter<W> {
+    /// ## Summary
+    ///
+    /// Start a capture, writing the pcap file header.
+    ///
+    pub fn new(mut writer: W) -> Result<Self, IoError> {
+        let mut header = Vec::with_capacity(24);
+        header.extend_from_slice(&0xA1B2_C3D4u32.to_le_bytes());
+        // Version 2.4.
+        header.extend_from_slice(&2u16.to_le_bytes());
+        header.extend_from_slice(&4u16.to_le_bytes());
+        // Timezone offset and timestamp accuracy, unused.
+        header.extend_from_slice(&0i32.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&SNAPLEN.to_le_bytes());
+        header.extend_from_slice(&LINK_TYPE.to_le_bytes());
+
+        writer.write_all(&header)?;
+        Ok(PcapWriter { writer })
+    }
+
+    /// ## Summary
+    ///
+    /// Write a frame captured at `timestamp`. Frames longer than 65535 bytes are truncated.
+    ///
+    pub fn write_frame(&mut self, frame: &[u8], timestamp: SystemTime) -> Result<(), IoError> {
+        let since_epoch = timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let captured = frame.len().min(SNAPLEN as usize);
+
+        let mut record = Vec::with_capacity(16 + captured);
+        record.extend_from_slice(&(since_epoch.as_secs() as u32).to_le_bytes());
+        record.extend_from_slice(&since_epoch.subsec_micros().to_le_bytes());
+        record.extend_from_slice(&(captured as u32).to_le_bytes());
+        record.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        record.extend_from_slice(&frame[..captured]);
+
+        self.writer.write_all(&record)
+    }
+
+    /// ## Summary
+    ///
+    /// Flush the capture.
+    ///
+    pub fn flush(&mut self) -> Result<(), IoError> {
+        self.writer.flush()
+    }
+
+    /// ## Summary
+    ///
+    /// Take the writer back.
+    ///
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
@@ -0,0 +1,260 @@
+use std::ffi::{OsStr, OsString};
+use std::fs::OpenOptions;
+use std::io::{Error as IoError, ErrorKind, Read};
+use std::time::{Duration, Instant};
+
+use serial::Error as SerialError;
+
+use super::error::LidarDriverError;
+
+#[cfg(windows)]
+pub(crate) use super::overlapped::OverlappedPort as SystemPort;
+#[cfg(not(windows))]
+pub(crate) use serial::SystemPort;
+
+/// ## Summary
+///
+/// Open the serial port, without configuring it.
+///
+/// ## Remarks
+///
+/// On Windows the port is opened for overlapped I/O, see `overlapped::OverlappedPort`.
+///
+#[cfg(windows)]
+pub(crate) fn open(port_name: &OsStr) -> Result<SystemPort, SerialError> {
+    SystemPort::open(port_name)
+}
+
+#[cfg(not(windows))]
+pub(crate) fn open(port_name: &OsStr) -> Result<SystemPort, SerialError> {
+    serial::open(port_name)
+}
+
+/// ## Summary
+///
+/// Normalize a port name before it is passed to the serial backend.
+///
+/// ## Remarks
+///
+/// On Windows the backend always opens `\\.\<name>`, which is required for COM10 and
+/// above. A name that already carries the `\\.\` prefix is stripped so it is not doubled.
+///
+#[cfg(windows)]
+pub(crate) fn normalize_port_name(port_name: &OsStr) -> OsString {
+    let name = port_name.to_string_lossy();
+
+    match name.strip_prefix(r"\\.\") {
+        Some(stripped) => OsString::from(stripped),
+        None => port_name.to_os_string(),
+    }
+}
+
+#[cfg(not(windows))]
+pub(crate) fn normalize_port_name(port_name: &OsStr) -> OsString {
+    port_name.to_os_string()
+}
+
+/// ## Summary
+///
+/// Convert an error returned when opening the serial port into a driver error.
+///
+/// ## Remarks
+///
+/// The serial backends report a missing device and a denied access with the same
+/// error kind. The device is probed again with the standard library, which keeps
+/// the distinction, to report `PortNotFound`, `PermissionDenied` or `PortBusy`.
+///
+pub(crate) fn open_error(port_name: &OsStr, err: SerialError) -> LidarDriverError {
+    if err.kind() != serial::ErrorKind::NoDevice {
+        return LidarDriverError::OpenSerialPort(err);
+    }
+
+    match probe(port_name) {
+        Some(ErrorKind::NotFound) => LidarDriverError::PortNotFound(err),
+        Some(ErrorKind::PermissionDenied) => LidarDriverError::PermissionDenied(err),
+        Some(ErrorKind::ResourceBusy) => LidarDriverError::PortBusy,
+        _ => LidarDriverError::OpenSerialPort(err),
+    }
+}
+
+/// ## Summary
+///
+/// Whether opening the port may succeed later: the device does not exist yet
+/// (e.g. the USB adapter is still enumerating) or is busy.
+///
+pub(crate) fn is_retryable(err: &LidarDriverError) -> bool {
+    match err {
+        LidarDriverError::PortBusy | LidarDriverError::PortNotFound(_) => true,
+        // EBUSY is reported as `NoDevice` by the serial backends.
+        LidarDriverError::OpenSerialPort(err) => err.kind() == serial::ErrorKind::NoDevice,
+        _ => false,
+    }
+}
+
+/// ## Summary
+///
+/// Whether a read error means the device is gone (e.g. the USB adapter was unplugged),
+/// as opposed to a transient failure that the next read may recover from.
+///
+pub(crate) fn is_disconnected(err: &IoError) -> bool {
+    if matches!(err.kind(), ErrorKind::BrokenPipe | ErrorKind::NotConnected) {
+        return true;
+    }
+
+    match err.raw_os_error() {
+        #[cfg(unix)]
+        Some(code) => code == libc::ENXIO || code == libc::ENODEV || code == libc::EPIPE,
+        // ERROR_DEVICE_NOT_CONNECTED and ERROR_NO_SUCH_DEVICE.
+        #[cfg(windows)]
+        Some(code) => code == 1167 || code == 433,
+        _ => false,
+    }
+}
+
+/// Open the device with the standard library and return the error kind, if any.
+#[cfg(unix)]
+fn probe(port_name: &OsStr) -> Option<ErrorKind> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        // Never become the controlling terminal or wait for carrier detect.
+        .custom_flags(libc::O_NOCTTY | libc::O_NONBLOCK)
+        .open(port_name)
+        .err()
+        .map(|e| e.kind())
+}
+
+#[cfg(windows)]
+fn probe(port_name: &OsStr) -> Option<ErrorKind> {
+    let mut path = OsString::from(r"\\.\");
+    path.push(port_name);
+
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .err()
+        .map(|e| e.kind())
+}
+
+/// ## Summary
+///
+/// Lock the port so no other process can read from it while it is open.
+///
+/// ## Remarks
+///
+/// Sets `TIOCEXCL`, which makes further opens fail with `EBUSY` (except for root),
+/// and takes an exclusive `flock`, which also stops other instances running as root.
+/// Both are released when the port is closed. Returns `ErrorKind::WouldBlock` if
+/// another process holds the lock.
+///
+#[cfg(unix)]
+pub(crate) fn lock_exclusive<P: std::os::unix::io::AsRawFd>(port: &P) -> Result<(), IoError> {
+    let fd = port.as_raw_fd();
+
+    // Safety: fd is a valid descriptor for the duration of the calls.
+    unsafe {
+        if libc::flock(fd, libc::LOCK_EX | libc::LOCK_NB) != 0 {
+            return Err(IoError::last_os_error());
+        }
+
+        if libc::ioctl(fd, libc::TIOCEXCL) != 0 {
+            return Err(IoError::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+/// Windows always opens serial ports exclusively.
+#[cfg(not(unix))]
+pub(crate) fn lock_exclusive<P>(_port: &P) -> Result<(), IoError> {
+    Ok(())
+}
+
+/// ## Summary
+///
+/// Read and discard bytes until the port stays silent for `gap`, or `timeout` elapses.
+///
+/// ## Remarks
+///
+/// Reads must time out (see `SerialPort::set_timeout`), at most a read timeout
+/// is added to `gap` and `timeout`.
+///
+pub(crate) fn wait_quiet<R: Read>(port: &mut R, gap: Duration, timeout: Duration) -> Result<(), IoError> {
+    let mut chunk = [0; 256];
+    let start = Instant::now();
+    let mut last_byte = start;
+
+    while last_byte.elapsed() < gap && start.elapsed() < timeout {
+        match port.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(_) => last_byte = Instant::now(),
+            Err(err) if matches!(err.kind(), ErrorKind::TimedOut | ErrorKind::Interrupted) => {},
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(())
+}
+
+/// Flag of `serial_struct::flags` that lowers the USB-serial latency timer.
+#[cfg(target_os = "linux")]
+const ASYNC_LOW_LATENCY: libc::c_int = 1 << 13;
+
+/// `struct serial_struct` from `linux/serial.h`.
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct SerialStruct {
+    kind: libc::c_int,
+    line: libc::c_int,
+    port: libc::c_uint,
+    irq: libc::c_int,
+    flags: libc::c_int,
+    xmit_fifo_size: libc::c_int,
+    custom_divisor: libc::c_int,
+    baud_base: libc::c_int,
+    close_delay: libc::c_ushort,
+    io_type: libc::c_char,
+    reserved_char: [libc::c_char; 1],
+    hub6: libc::c_int,
+    closing_wait: libc::c_ushort,
+    closing_wait2: libc::c_ushort,
+    iomem_base: *mut libc::c_uchar,
+    iomem_reg_shift: libc::c_ushort,
+    port_high: libc::c_uint,
+    iomap_base: libc::c_ulong,
+}
+
+/// ## Summary
+///
+/// Set `ASYNC_LOW_LATENCY` on the port so USB-serial adapters forward data immediately.
+///
+#[cfg(target_os = "linux")]
+pub(crate) fn set_low_latency<P: std::os::unix::io::AsRawFd>(port: &P) -> Result<(), IoError> {
+    let fd = port.as_raw_fd();
+    // Safety: SerialStruct is plain data, it is fully initialized by TIOCGSERIAL.
+    let mut serial: SerialStruct = unsafe { std::mem::zeroed() };
+
+    // Safety: fd is a valid descriptor and serial matches the layout expected by the kernel.
+    unsafe {
+        if libc::ioctl(fd, libc::TIOCGSERIAL, &mut serial) != 0 {
+            return Err(IoError::last_os_error());
+        }
+
+        serial.flags |= ASYNC_LOW_LATENCY;
+
+        if libc::ioctl(fd, libc::TIOCSSERIAL, &serial) != 0 {
+            return Err(IoError::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn set_low_latency<P>(_port: &P) -> Result<(), IoError> {
+    Err(IoError::new(ErrorKind::Unsupported, "low latency is only supported on Linux"))
+}
@@ -0,0 +1,114 @@
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+
+use super::data::LidarPacket;
+use super::error::LidarDriverError;
+use super::model::Model;
+use super::parser::{is_valid_index, parse_packet, PACKETS_PER_REVOLUTION, PACKET_HEADER, PACKET_SIZE};
+
+/// Largest frame a protocol may use, in bytes.
+pub const MAX_FRAME_SIZE: usize = 64;
+
+/// ## Summary
+///
+/// The framing and decoding of a spinning LIDAR's serial protocol.
+///
+/// ## Remarks
+///
+/// `Parser` and the driver only deal with fixed size frames that start with a
+/// header. Implementing this trait is enough to read another LIDAR with the same
+/// driver loop, channels and filters.
+///
+/// ## Example
+///
+/// ```
+/// use neato_xv11::prelude::*;
+/// use neato_xv11::protocol::LidarProtocol;
+///
+/// let mut parser = Parser::with_protocol(Model::Xv11);
+/// assert_eq!(parser.protocol().frame_size(), 22);
+/// ```
+pub trait LidarProtocol {
+    /// ## Summary
+    ///
+    /// Number of bytes at the start of a frame checked by `accepts`.
+    ///
+    fn header_size(&self) -> usize;
+
+    /// ## Summary
+    ///
+    /// Whether a byte is valid at the given position of the header.
+    /// Bytes are discarded until a complete header is accepted.
+    ///
+    fn accepts(&self, position: usize, byte: u8) -> bool;
+
+    /// ## Summary
+    ///
+    /// Total size of a frame in bytes, header included. At most `MAX_FRAME_SIZE`.
+    ///
+    fn frame_size(&self) -> usize;
+
+    /// ## Summary
+    ///
+    /// Decode a complete frame. The slice is `frame_size` bytes long.
+    ///
+    fn decode(&self, frame: &[u8]) -> Result<LidarPacket, LidarDriverError>;
+
+    /// ## Summary
+    ///
+    /// Position of a complete frame in the revolution and number of frames per revolution,
+    /// used to count the missing packets. `None` if the protocol has no packet index.
+    ///
+    fn frame_index(&self, _frame: &[u8]) -> Option<(usize, usize)> {
+        None
+    }
+}
+
+/// The Neato protocol: a 0xFA header, an index byte (0xA0 to 0xF9) and 20 bytes of data.
+impl LidarProtocol for Model {
+    fn header_size(&self) -> usize {
+        2
+    }
+
+    fn accepts(&self, position: usize, byte: u8) -> bool {
+        match position {
+            0 => byte == PACKET_HEADER,
+            _ => is_valid_index(byte),
+        }
+    }
+
+    fn frame_size(&self) -> usize {
+        PACKET_SIZE
+    }
+
+    fn decode(&self, frame: &[u8]) -> Result<LidarPacket, LidarDriverError> {
+        parse_packet(frame, *self)
+    }
+
+    fn frame_index(&self, frame: &[u8]) -> Option<(usize, usize)> {
+        Some(((frame[1] - 0xA0) as usize, PACKETS_PER_REVOLUTION))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<P: LidarProtocol + ?Sized> LidarProtocol for Box<P> {
+    fn header_size(&self) -> usize {
+        (**self).header_size()
+    }
+
+    fn accepts(&self, position: usize, byte: u8) -> bool {
+        (**self).accepts(position, byte)
+    }
+
+    fn frame_size(&self) -> usize {
+        (**self).frame_size()
+    }
+
+    fn decode(&self, frame: &[u8]) -> Result<LidarPacket, LidarDriverError> {
+        (**self).decode(frame)
+    }
+
+    fn frame_index(&self, frame: &[u8]) -> Option<(usize, usize)> {
+        (**self).frame_index(frame)
+    }
+}
@@ -0,0 +1,309 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{RecvError, RecvTimeoutError, SendError, TryRecvError};
+use std::time::{Duration, Instant};
+
+/// ## Summary
+///
+/// Behaviour of a bounded queue when a message is sent while it is full.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OverflowPolicy {
+    // Block the sender until the receiver makes room.
+    Block,
+    // Discard the oldest queued message to make room for the new one.
+    DropOldest,
+}
+
+/// Queue state shared between the sender and receiver halves.
+struct State<T> {
+    // Queued messages, oldest first.
+    items: VecDeque<T>,
+    // Number of live senders.
+    senders: usize,
+    // Whether the receiver is still alive.
+    receiver_alive: bool,
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    // Signalled when a message is queued or the last sender is dropped.
+    not_empty: Condvar,
+    // Signalled when a message is dequeued or the receiver is dropped.
+    not_full: Condvar,
+    capacity: usize,
+    policy: OverflowPolicy,
+    // Number of messages discarded by the overflow policy.
+    dropped: AtomicU64,
+    // Time senders spent waiting for room, in nanoseconds.
+    blocked_nanos: AtomicU64,
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        // A panic while holding the lock cannot leave the queue inconsistent.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// ## Summary
+///
+/// Create a bounded message queue.
+///
+/// ## Parameters
+///
+/// capacity: Maximum number of queued messages. Must be greater than zero.
+///
+/// policy: What to do when a message is sent to a full queue.
+///
+/// ## Example
+///
+/// ```
+/// use neato_xv11::queue::{bounded, OverflowPolicy};
+///
+/// let (tx, rx) = bounded(2, OverflowPolicy::DropOldest);
+///
+/// for i in 0..3 {
+///     tx.send(i).unwrap();
+/// }
+///
+/// assert_eq!(1, tx.dropped());
+/// assert_eq!(Ok(1), rx.try_recv());
+/// ```
+pub fn bounded<T>(capacity: usize, policy: OverflowPolicy) -> (BoundedSender<T>, BoundedReceiver<T>) {
+    assert!(capacity > 0, "queue capacity must be greater than zero");
+
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            items: VecDeque::with_capacity(capacity),
+            senders: 1,
+            receiver_alive: true,
+        }),
+        not_empty: Condvar::new(),
+        not_full: Condvar::new(),
+        capacity,
+        policy,
+        dropped: AtomicU64::new(0),
+        blocked_nanos: AtomicU64::new(0),
+    });
+
+    (BoundedSender { shared: shared.clone() }, BoundedReceiver { shared })
+}
+
+/// ## Summary
+///
+/// The sending half of a bounded queue.
+///
+pub struct BoundedSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> BoundedSender<T> {
+    /// ## Summary
+    ///
+    /// Send a message, applying the overflow policy if the queue is full.
+    ///
+    /// ## Remarks
+    ///
+    /// Returns the message back if the receiver has been dropped.
+    ///
+    pub fn send(&self, item: T) -> Result<(), SendError<T>> {
+        let mut state = self.shared.lock();
+
+        loop {
+            if !state.receiver_alive {
+                return Err(SendError(item));
+            }
+
+            if state.items.len() < self.shared.capacity {
+                break;
+            }
+
+            match self.shared.policy {
+                OverflowPolicy::Block => {
+                    let start = Instant::now();
+                    state = self.shared.not_full.wait(state).unwrap_or_else(|e| e.into_inner());
+                    self.shared.blocked_nanos.fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+                },
+                OverflowPolicy::DropOldest => {
+                    state.items.pop_front();
+                    self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+                    break;
+                },
+            }
+        }
+
+        state.items.push_back(item);
+        self.shared.not_empty.notify_one();
+        Ok(())
+    }
+
+    /// ## Summary
+    ///
+    /// Number of messages discarded by the overflow policy so far.
+    ///
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+
+    /// ## Summary
+    ///
+    /// Number of messages currently queued.
+    ///
+    pub fn len(&self) -> usize {
+        self.shared.lock().items.len()
+    }
+
+    /// ## Summary
+    ///
+    /// Whether no messages are currently queued.
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// ## Summary
+    ///
+    /// Maximum number of queued messages.
+    ///
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity
+    }
+
+    /// ## Summary
+    ///
+    /// Total time senders spent waiting for room with `OverflowPolicy::Block`.
+    ///
+    pub fn blocked_time(&self) -> Duration {
+        Duration::from_nanos(self.shared.blocked_nanos.load(Ordering::Relaxed))
+    }
+}
+
+impl<T> Clone for BoundedSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.lock().senders += 1;
+        BoundedSender { shared: self.shared.clone() }
+    }
+}
+
+impl<T> Drop for BoundedSender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.senders -= 1;
+
+        if state.senders == 0 {
+            // Wake the receiver so it can observe the disconnect.
+            self.shared.not_empty.notify_all();
+        }
+    }
+}
+
+/// ## Summary
+///
+/// The receiving half of a bounded queue.
+///
+pub struct BoundedReceiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> BoundedReceiver<T> {
+    /// ## Summary
+    ///
+    /// Block until a message is available.
+    ///
+    pub fn recv(&self) -> Result<T, RecvError> {
+        let mut state = self.shared.lock();
+
+        loop {
+            if let Some(item) = state.items.pop_front() {
+                self.shared.not_full.notify_one();
+                return Ok(item);
+            }
+
+            if state.senders == 0 {
+                return Err(RecvError);
+            }
+
+            state = self.shared.not_empty.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Receive a message if one is available, without blocking.
+    ///
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut state = self.shared.lock();
+
+        match state.items.pop_front() {
+            Some(item) => {
+                self.shared.not_full.notify_one();
+                Ok(item)
+            },
+            None if state.senders == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Block until a message is available or the timeout elapses.
+    ///
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.shared.lock();
+
+        loop {
+            if let Some(item) = state.items.pop_front() {
+                self.shared.not_full.notify_one();
+                return Ok(item);
+            }
+
+            if state.senders == 0 {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(RecvTimeoutError::Timeout);
+            }
+
+            state = self.shared.not_empty.wait_timeout(state, deadline - now)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Number of messages currently queued.
+    ///
+    pub fn len(&self) -> usize {
+        self.shared.lock().items.len()
+    }
+
+    /// ## Summary
+    ///
+    /// Whether no messages are currently queued.
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// ## Summary
+    ///
+    /// Number of messages discarded by the overflow policy so far.
+    ///
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl<T> Drop for BoundedReceiver<T> {
+    fn drop(&mut self) {
+        self.shared.lock().receiver_alive = false;
+        // Wake any blocked sender so it can observe the disconnect.
+        self.shared.not_full.notify_all();
+    }
+}
@@ -0,0 +1,63 @@
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+/// ## Summary
+///
+/// Deviations of a LIDAR firmware from the documented protocol.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Quirks {
+    // The firmware emits stray 0xFA bytes, so a frame may start inside the previous payload.
+    // After a checksum error the parser looks for a header inside the rejected frame
+    // instead of discarding it whole and resynchronizing on the following bytes.
+    pub rescan_on_checksum_error: bool,
+    // The firmware reports missing returns as a distance of 0 without the invalid data flag.
+    // Such readings are marked `LidarReadingError::InvalidDataError(0)`.
+    pub zero_distance_invalid: bool,
+}
+
+/// ## Summary
+///
+/// Known firmware versions of the Neato XV-11 LDS.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum Firmware {
+    // Firmware 2.4.
+    V2_4,
+    // Firmware 2.6, the most common version.
+    V2_6,
+}
+
+impl Firmware {
+    /// ## Summary
+    ///
+    /// The quirks of the firmware.
+    ///
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Firmware::V2_4 => Quirks {
+                rescan_on_checksum_error: true,
+                zero_distance_invalid: true,
+            },
+            Firmware::V2_6 => Quirks::default(),
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Find the firmware from the version reported by the LIDAR (e.g. "V2.6.15295").
+    ///
+    pub fn from_version(version: &str) -> Option<Self> {
+        let version = version.trim().trim_start_matches(['V', 'v']);
+
+        if version.starts_with("2.4") {
+            Some(Firmware::V2_4)
+        } else if version.starts_with("2.6") {
+            Some(Firmware::V2_6)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,496 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::SendError;
+use std::time::{Duration, Instant};
+
+use super::clock::{Clock, SystemClock};
+use super::data::{MAX_READINGS, SCAN_SIZE};
+use super::message::{MessageKind, Warning};
+use super::prelude::*;
+
+/// ## Summary
+///
+/// How packets arriving faster than the configured rate are combined.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RateStrategy {
+    // Emit the most recent packet, discarding the ones received in between.
+    LatestWins,
+    // Emit the average of every valid reading received since the last emission.
+    Averaged,
+}
+
+/// Readings held back for one starting degree.
+struct Pending {
+    // Most recent packet received.
+    latest: LidarPacket,
+    // Sum of valid distances per reading.
+    distance_sum: [i64; MAX_READINGS],
+    // Sum of valid qualities per reading.
+    quality_sum: [i64; MAX_READINGS],
+    // Number of valid values per reading.
+    count: [i64; MAX_READINGS],
+    // Sum of reported speeds.
+    speed_sum: Float,
+    // Number of packets accumulated.
+    packets: usize,
+}
+
+impl Pending {
+    fn new(packet: LidarPacket) -> Self {
+        let mut pending = Pending {
+            latest: packet,
+            distance_sum: [0; MAX_READINGS],
+            quality_sum: [0; MAX_READINGS],
+            count: [0; MAX_READINGS],
+            speed_sum: 0.0,
+            packets: 0,
+        };
+        pending.accumulate();
+        pending
+    }
+
+    fn add(&mut self, packet: LidarPacket) {
+        self.latest = packet;
+        self.accumulate();
+    }
+
+    /// Add the latest packet to the running sums.
+    fn accumulate(&mut self) {
+        for (i, reading) in self.latest.readings.iter().enumerate() {
+            if reading.error.is_none() {
+                self.distance_sum[i] += reading.distance as i64;
+                self.quality_sum[i] += reading.quality as i64;
+                self.count[i] += 1;
+            }
+        }
+        self.speed_sum += self.latest.speed;
+        self.packets += 1;
+    }
+
+    fn average(self) -> LidarPacket {
+        let speed = self.speed_sum / self.packets as Float;
+        let mut readings = self.latest.readings;
+
+        for (i, reading) in readings.iter_mut().enumerate() {
+            if self.count[i] > 0 {
+                reading.distance = (self.distance_sum[i] / self.count[i]) as i32;
+                reading.quality = (self.quality_sum[i] / self.count[i]) as i32;
+                reading.error = None;
+            }
+        }
+
+        let mut packet = LidarPacket::new(readings, speed);
+        packet.sequence = self.latest.sequence;
+        packet
+    }
+}
+
+#[derive(Default)]
+struct Slot {
+    // When a packet starting at this degree was last emitted.
+    last_emit: Option<Instant>,
+    // Packets held back since the last emission.
+    pending: Option<Pending>,
+}
+
+/// ## Summary
+///
+/// Caps how often each packet index is emitted, which caps the number of
+/// revolutions per second regardless of the LIDAR's RPM.
+///
+/// ## Remarks
+///
+/// Packets are keyed by the index of their first reading (0-359), so packets of any
+/// size and alignment covering different angles never share a slot, and readings are
+/// averaged by position within packets starting at the same degree.
+///
+pub struct RateLimiter {
+    // Minimum time between two emissions of packets starting at the same degree.
+    interval: Duration,
+    // How held-back packets are combined.
+    strategy: RateStrategy,
+    // State per starting degree.
+    slots: Vec<Slot>,
+    // Source of the time packets are received at.
+    clock: Arc<dyn Clock>,
+}
+
+impl RateLimiter {
+    /// ## Summary
+    ///
+    /// Initialize a new rate limiter.
+    ///
+    /// ## Parameters
+    ///
+    /// max_rate: Maximum number of revolutions emitted per second.
+    ///
+    /// strategy: How packets arriving faster than `max_rate` are combined.
+    ///
+    pub fn new(max_rate: f64, strategy: RateStrategy) -> Self {
+        assert!(max_rate > 0.0, "max_rate must be greater than zero");
+
+        RateLimiter {
+            interval: Duration::from_secs_f64(1.0 / max_rate),
+            strategy,
+            slots: (0..SCAN_SIZE).map(|_| Slot::default()).collect(),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Read the time from the given clock, e.g. a `clock::VirtualClock` in simulations.
+    ///
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Offer a packet to the limiter. Returns the packet to emit, if any.
+    ///
+    pub fn push(&mut self, packet: LidarPacket) -> Option<LidarPacket> {
+        let now = self.clock.now();
+        self.push_at(packet, now)
+    }
+
+    pub(crate) fn push_at(&mut self, packet: LidarPacket, now: Instant) -> Option<LidarPacket> {
+        let index = match packet.readings.first() {
+            Some(reading) => reading.index % SCAN_SIZE,
+            // Nothing to rate limit.
+            None => return Some(packet),
+        };
+        let slot = &mut self.slots[index];
+
+        match slot.pending.as_mut() {
+            Some(pending) => pending.add(packet),
+            None => slot.pending = Some(Pending::new(packet)),
+        }
+
+        let is_due = match slot.last_emit {
+            Some(last_emit) => now.duration_since(last_emit) >= self.interval,
+            None => true,
+        };
+
+        if !is_due {
+            // Hold the packet back until the interval elapses.
+            return None;
+        }
+
+        slot.last_emit = Some(now);
+        slot.pending.take().map(|pending| {
+            match self.strategy {
+                RateStrategy::LatestWins => pending.latest,
+                RateStrategy::Averaged => pending.average(),
+            }
+        })
+    }
+}
+
+/// ## Summary
+///
+/// A message sink that rate limits packets before forwarding them to an inner sink.
+/// Errors and other messages are forwarded immediately.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::thread;
+/// # use std::sync::mpsc::channel;
+/// use neato_xv11::rate_limit::{RateLimitedSink, RateStrategy};
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// // Forward at most 5 revolutions per second, averaging the rest.
+/// let message_tx = RateLimitedSink::new(message_tx, 5.0, RateStrategy::Averaged);
+///
+/// thread::spawn(move || {
+///     neato_xv11::run("/dev/serial0", message_tx, command_rx);
+/// });
+/// ```
+pub struct RateLimitedSink<S> {
+    inner: S,
+    limiter: Mutex<RateLimiter>,
+}
+
+impl<S: MessageSink> RateLimitedSink<S> {
+    /// ## Summary
+    ///
+    /// Wrap a sink with a rate limiter.
+    ///
+    /// ## Parameters
+    ///
+    /// inner: The sink to forward messages to.
+    ///
+    /// max_rate: Maximum number of revolutions forwarded per second.
+    ///
+    /// strategy: How packets arriving faster than `max_rate` are combined.
+    ///
+    pub fn new(inner: S, max_rate: f64, strategy: RateStrategy) -> Self {
+        RateLimitedSink {
+            inner,
+            limiter: Mutex::new(RateLimiter::new(max_rate, strategy)),
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Read the time from the given clock, e.g. a `clock::VirtualClock` in simulations.
+    ///
+    pub fn with_clock<C: Clock + 'static>(self, clock: C) -> Self {
+        let limiter = self.limiter.into_inner().unwrap_or_else(|e| e.into_inner());
+
+        RateLimitedSink {
+            inner: self.inner,
+            limiter: Mutex::new(limiter.with_clock(clock)),
+        }
+    }
+}
+
+impl<S: MessageSink> MessageSink for RateLimitedSink<S> {
+    fn send(&self, message: Result<LidarDriverMessage, LidarDriverError>) -> Result<(), SendError<Result<LidarDriverMessage, LidarDriverError>>> {
+        match message {
+            Ok(LidarDriverMessage::Packet(packet)) => {
+                let packet = self.limiter.lock().unwrap_or_else(|e| e.into_inner()).push(packet);
+
+                match packet {
+                    Some(packet) => self.inner.send(Ok(LidarDriverMessage::Packet(packet))),
+                    None => Ok(()),
+                }
+            },
+            message => self.inner.send(message),
+        }
+    }
+
+    fn dropped(&self) -> u64 {
+        self.inner.dropped()
+    }
+
+    fn backlog(&self) -> Option<Backlog> {
+        self.inner.backlog()
+    }
+}
+
+/// ## Summary
+///
+/// A token bucket: allows `burst` events at once, refilled at `rate` events per second.
+///
+#[derive(Clone, Debug)]
+pub struct TokenBucket {
+    // Tokens added per second.
+    rate: f64,
+    // Maximum number of tokens.
+    burst: f64,
+    // Tokens available.
+    tokens: f64,
+    // When the tokens were last refilled.
+    last: Option<Instant>,
+}
+
+impl TokenBucket {
+    /// ## Summary
+    ///
+    /// Initialize a full bucket.
+    ///
+    /// ## Parameters
+    ///
+    /// rate: Number of events allowed per second in the long run.
+    ///
+    /// burst: Number of events allowed at once.
+    ///
+    pub fn new(rate: f64, burst: u32) -> Self {
+        assert!(rate >= 0.0, "rate must not be negative");
+        let burst = f64::from(burst.max(1));
+
+        TokenBucket {
+            rate,
+            burst,
+            tokens: burst,
+            last: None,
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Take a token at `now`. Returns false when the bucket is empty.
+    ///
+    pub fn try_take(&mut self, now: Instant) -> bool {
+        if let Some(last) = self.last {
+            let elapsed = now.saturating_duration_since(last).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+        }
+
+        self.last = Some(now);
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// State of a `ThrottledSink`.
+struct Throttle {
+    // Bucket and number of messages discarded since the last report, per kind.
+    limits: HashMap<MessageKind, (TokenBucket, u64)>,
+    // Number of messages discarded since the sink was created.
+    dropped: u64,
+    // When the discarded messages were last reported.
+    last_report: Option<Instant>,
+}
+
+/// ## Summary
+///
+/// A message sink that limits how often each kind of message is forwarded, so a burst
+/// of warnings or errors (e.g. checksum errors during a cable fault) cannot flood the
+/// channels, logs or network sinks downstream.
+///
+/// ## Remarks
+///
+/// Each limited kind has its own token bucket, kinds without a limit are forwarded
+/// unchanged. Messages over the limit are discarded and counted in `dropped`. At most
+/// once per report interval (1 second by default), a `Warning::Throttled` is forwarded
+/// per kind with the number of messages discarded since the previous report. Reports
+/// are sent when a message arrives, so they follow the driver's traffic.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::thread;
+/// # use std::sync::mpsc::channel;
+/// use neato_xv11::message::MessageKind;
+/// use neato_xv11::rate_limit::ThrottledSink;
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// // At most 10 errors per second, with bursts of 20.
+/// let message_tx = ThrottledSink::new(message_tx).with_limit(MessageKind::Error, 10.0, 20);
+///
+/// thread::spawn(move || {
+///     neato_xv11::run("/dev/serial0", message_tx, command_rx);
+/// });
+/// ```
+pub struct ThrottledSink<S> {
+    inner: S,
+    // Minimum time between two reports.
+    report_interval: Duration,
+    // Source of the time messages are received at.
+    clock: Arc<dyn Clock>,
+    state: Mutex<Throttle>,
+}
+
+impl<S: MessageSink> ThrottledSink<S> {
+    /// ## Summary
+    ///
+    /// Wrap a sink, without limits.
+    ///
+    pub fn new(inner: S) -> Self {
+        ThrottledSink {
+            inner,
+            report_interval: Duration::from_secs(1),
+            clock: Arc::new(SystemClock),
+            state: Mutex::new(Throttle {
+                limits: HashMap::new(),
+                dropped: 0,
+                last_report: None,
+            }),
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Limit a kind of message.
+    ///
+    /// ## Parameters
+    ///
+    /// kind: The kind of message.
+    ///
+    /// rate: Number of messages forwarded per second in the long run.
+    ///
+    /// burst: Number of messages forwarded at once.
+    ///
+    pub fn with_limit(self, kind: MessageKind, rate: f64, burst: u32) -> Self {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).limits.insert(kind, (TokenBucket::new(rate, burst), 0));
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Set the minimum time between two reports of discarded messages.
+    ///
+    pub fn with_report_interval(mut self, interval: Duration) -> Self {
+        self.report_interval = interval;
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Read the time from the given clock, e.g. a `clock::VirtualClock` in simulations.
+    ///
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+}
+
+impl<S: MessageSink> MessageSink for ThrottledSink<S> {
+    fn send(&self, message: Result<LidarDriverMessage, LidarDriverError>) -> Result<(), SendError<Result<LidarDriverMessage, LidarDriverError>>> {
+        let now = self.clock.now();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        let is_allowed = match state.limits.get_mut(&MessageKind::of(&message)) {
+            Some((bucket, dropped)) => {
+                let is_allowed = bucket.try_take(now);
+
+                if !is_allowed {
+                    *dropped += 1;
+                }
+
+                is_allowed
+            },
+            None => true,
+        };
+
+        if !is_allowed {
+            state.dropped += 1;
+        }
+
+        let is_due = state.last_report.is_none_or(|last| now.saturating_duration_since(last) >= self.report_interval);
+        let mut reports = Vec::new();
+
+        if is_due {
+            state.last_report = Some(now);
+
+            for (kind, (_, dropped)) in state.limits.iter_mut().filter(|(_, (_, dropped))| *dropped > 0) {
+                reports.push(Warning::Throttled { kind: *kind, dropped: *dropped });
+                *dropped = 0;
+            }
+        }
+
+        drop(state);
+
+        if is_allowed {
+            self.inner.send(message)?;
+        }
+
+        for report in reports {
+            self.inner.send(Ok(LidarDriverMessage::Warning(report)))?;
+        }
+
+        Ok(())
+    }
+
+    fn dropped(&self) -> u64 {
+        self.inner.dropped() + self.state.lock().unwrap_or_else(|e| e.into_inner()).dropped
+    }
+
+    fn backlog(&self) -> Option<Backlog> {
+        self.inner.backlog()
+    }
+}
@@ -0,0 +1,146 @@
+use std::io::Read;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::error::LidarDriverError;
+
+/// ## Summary
+///
+/// When the driver reopens a disconnected port, e.g. after a USB adapter glitch.
+///
+/// ## Remarks
+///
+/// The first attempt waits `initial_delay`, every further one `multiplier` times longer,
+/// up to `max_delay`. Each delay is randomly shortened or lengthened by up to `jitter`
+/// (a fraction of the delay), so several drivers do not retry in lockstep. The delays
+/// start over once the port is reopened.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::sync::mpsc::channel;
+/// use std::time::Duration;
+/// use neato_xv11::reconnect::ReconnectPolicy;
+/// use neato_xv11::LidarDriverBuilder;
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// let policy = ReconnectPolicy { max_retries: Some(20), max_delay: Duration::from_secs(5), ..ReconnectPolicy::default() };
+///
+/// let handle = LidarDriverBuilder::new("/dev/ttyUSB0")
+///     .reconnect(policy)
+///     .spawn(message_tx, command_rx)
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReconnectPolicy {
+    // Attempts after each disconnection before giving up, `None` to retry forever.
+    pub max_retries: Option<u32>,
+    // Delay before the first attempt.
+    pub initial_delay: Duration,
+    // Longest delay between two attempts.
+    pub max_delay: Duration,
+    // Factor applied to the delay after each failed attempt.
+    pub multiplier: f64,
+    // Largest random change of a delay, as a fraction of it (0 to 1).
+    pub jitter: f64,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        ReconnectPolicy {
+            max_retries: None,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// ## Summary
+    ///
+    /// Delay before an attempt, without jitter.
+    ///
+    /// ## Parameters
+    ///
+    /// attempt: Number of the attempt, starting at 1.
+    ///
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = self.multiplier.max(1.0).powi(attempt.saturating_sub(1) as i32);
+        let delay = self.initial_delay.as_secs_f64() * factor;
+
+        Duration::from_secs_f64(delay.min(self.max_delay.as_secs_f64()))
+    }
+}
+
+/// ## Summary
+///
+/// The delays of the attempts following a disconnection.
+///
+pub(crate) struct Backoff {
+    policy: ReconnectPolicy,
+    // Number of attempts since the last disconnection.
+    attempts: u32,
+    // State of the xorshift random generator.
+    state: u64,
+}
+
+impl Backoff {
+    pub(crate) fn new(policy: ReconnectPolicy) -> Self {
+        let seed = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64);
+
+        Backoff {
+            policy,
+            attempts: 0,
+            // The generator must not start at 0.
+            state: seed | 1,
+        }
+    }
+
+    /// Number of attempts since the last disconnection.
+    pub(crate) fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Delay before the next attempt, `None` once the retries are exhausted.
+    pub(crate) fn next_delay(&mut self) -> Option<Duration> {
+        if self.policy.max_retries.is_some_and(|max_retries| self.attempts >= max_retries) {
+            return None;
+        }
+
+        self.attempts += 1;
+        let delay = self.policy.delay(self.attempts).as_secs_f64();
+        // Uniform between -1 and 1.
+        let random = (self.next() >> 11) as f64 / (1u64 << 52) as f64 - 1.0;
+        let jitter = self.policy.jitter.clamp(0.0, 1.0) * random;
+
+        Some(Duration::from_secs_f64(delay * (1.0 + jitter)))
+    }
+
+    /// Start over after the port was reopened.
+    pub(crate) fn reset(&mut self) {
+        self.attempts = 0;
+    }
+
+    /// Next pseudo-random number.
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+}
+
+/// Reopens a port.
+pub(crate) type Reopen = Box<dyn FnMut() -> Result<Box<dyn Read + Send>, LidarDriverError> + Send>;
+
+/// ## Summary
+///
+/// How the reader thread reopens its port after a disconnection.
+///
+pub(crate) struct Reconnect {
+    pub(crate) policy: ReconnectPolicy,
+    pub(crate) reopen: Reopen,
+}
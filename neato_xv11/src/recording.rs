@@ -0,0 +1,305 @@
+use std::convert::{TryFrom, TryInto};
+use std::io::{Error as IoError, ErrorKind, Read, Write};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use super::clock::Clock;
+
+/// Identifies a log file.
+const MAGIC: [u8; 4] = *b"XVLG";
+/// Version of the format written.
+pub const FORMAT_VERSION: u16 = 1;
+/// Size of the header written: magic, version and header size.
+const HEADER_SIZE: u16 = 8;
+/// Size of a record header: timestamp and length.
+const RECORD_HEADER_SIZE: usize = 12;
+
+/// ## Summary
+///
+/// Writes the bytes read from a LIDAR as a timestamped log, so the capture can be
+/// replayed with its original timing by a `LogReader`.
+///
+/// ## Remarks
+///
+/// All integers are little-endian. The file starts with a header:
+///
+/// | Field       | Size | Value                                  |
+/// |-------------|------|----------------------------------------|
+/// | magic       | 4    | `XVLG`                                 |
+/// | version     | 2    | `FORMAT_VERSION`                       |
+/// | header size | 2    | Size of the header, including magic    |
+///
+/// followed by one record per read: the timestamp in nanoseconds since the Unix epoch
+/// (8 bytes), the length of the data (4 bytes) and the data. Later versions only
+/// append fields to the header, so readers skip the ones they do not know.
+///
+/// Use `LidarDriverBuilder::log_tap` to record from the driver.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::fs::File;
+/// use std::io::BufWriter;
+/// use std::time::SystemTime;
+/// use neato_xv11::recording::LogWriter;
+///
+/// let file = BufWriter::new(File::create("capture.xvlog").unwrap());
+/// let mut log = LogWriter::new(file).unwrap();
+///
+/// # let chunk = [0u8; 64];
+/// log.write_record(&chunk, SystemTime::now()).unwrap();
+/// ```
+pub struct LogWriter<W> {
+    // Receives the log.
+    writer: W,
+}
+
+impl<W: Write> LogWriter<W> {
+    /// ## Summary
+    ///
+    /// Start a log, writing its header.
+    ///
+    pub fn new(mut writer: W) -> Result<Self, IoError> {
+        let mut header = Vec::with_capacity(HEADER_SIZE as usize);
+        header.extend_from_slice(&MAGIC);
+        header.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        header.extend_from_slice(&HEADER_SIZE.to_le_bytes());
+
+        writer.write_all(&header)?;
+        Ok(LogWriter { writer })
+    }
+
+    /// ## Summary
+    ///
+    /// Write the bytes received at `timestamp`.
+    ///
+    pub fn write_record(&mut self, data: &[u8], timestamp: SystemTime) -> Result<(), IoError> {
+        let since_epoch = timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let length = u32::try_from(data.len()).map_err(|_| IoError::new(ErrorKind::InvalidInput, "record larger than 4 GiB"))?;
+
+        let mut record = Vec::with_capacity(RECORD_HEADER_SIZE + data.len());
+        record.extend_from_slice(&(since_epoch.as_nanos() as u64).to_le_bytes());
+        record.extend_from_slice(&length.to_le_bytes());
+        record.extend_from_slice(data);
+
+        self.writer.write_all(&record)
+    }
+
+    /// ## Summary
+    ///
+    /// Flush the log.
+    ///
+    pub fn flush(&mut self) -> Result<(), IoError> {
+        self.writer.flush()
+    }
+
+    /// ## Summary
+    ///
+    /// Take the writer back.
+    ///
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// ## Summary
+///
+/// Bytes of a log received at once.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogRecord {
+    // When the bytes were received.
+    pub timestamp: SystemTime,
+    // The bytes.
+    pub data: Vec<u8>,
+}
+
+/// ## Summary
+///
+/// Reads a log written by `LogWriter`, record by record or as a byte stream to replay
+/// through the driver.
+///
+/// ## Remarks
+///
+/// Logs of any version up to `FORMAT_VERSION` are read. A truncated last record, e.g.
+/// when the recording process was killed, is ignored.
+///
+/// As a byte stream, the log is read as fast as possible, or with the original timing
+/// between records after `real_time`.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::sync::mpsc::channel;
+/// use std::fs::File;
+/// use std::io::BufReader;
+/// use neato_xv11::clock::SystemClock;
+/// use neato_xv11::recording::LogReader;
+/// use neato_xv11::LidarDriverBuilder;
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// let log = LogReader::open(BufReader::new(File::open("capture.xvlog").unwrap())).unwrap();
+///
+/// LidarDriverBuilder::new("capture.xvlog")
+///     .replay(log.real_time(SystemClock), message_tx, command_rx);
+/// ```
+pub struct LogReader<R> {
+    reader: R,
+    // Version of the log.
+    version: u16,
+    // Sleeps between records when replaying in real time.
+    clock: Option<Arc<dyn Clock>>,
+    // Timestamp of the first record and when it was read, to pace the next ones.
+    origin: Option<(SystemTime, Instant)>,
+    // Record being read as a byte stream, and the number of bytes already read.
+    pending: Option<(LogRecord, usize)>,
+}
+
+impl<R: Read> LogReader<R> {
+    /// ## Summary
+    ///
+    /// Open a log, reading its header.
+    ///
+    /// ## Remarks
+    ///
+    /// Fails with `ErrorKind::InvalidData` if the source is not a log or was written by
+    /// a newer version of the format.
+    ///
+    pub fn open(mut reader: R) -> Result<Self, IoError> {
+        let mut header = [0; HEADER_SIZE as usize];
+        reader.read_exact(&mut header)?;
+
+        if header[..4] != MAGIC {
+            return Err(IoError::new(ErrorKind::InvalidData, "not a LIDAR log"));
+        }
+
+        let version = u16::from_le_bytes([header[4], header[5]]);
+        let header_size = u16::from_le_bytes([header[6], header[7]]);
+
+        if version == 0 || version > FORMAT_VERSION {
+            return Err(IoError::new(ErrorKind::InvalidData, format!("unsupported log version {}", version)));
+        }
+
+        if header_size < HEADER_SIZE {
+            return Err(IoError::new(ErrorKind::InvalidData, "invalid log header size"));
+        }
+
+        // Skip the fields appended by later versions.
+        let extra = u64::from(header_size - HEADER_SIZE);
+        let skipped = std::io::copy(&mut (&mut reader).take(extra), &mut std::io::sink())?;
+
+        if skipped < extra {
+            return Err(IoError::from(ErrorKind::UnexpectedEof));
+        }
+
+        Ok(LogReader { reader, version, clock: None, origin: None, pending: None })
+    }
+
+    /// ## Summary
+    ///
+    /// Version of the format of the log.
+    ///
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// ## Summary
+    ///
+    /// Replay the byte stream with the original timing between records, sleeping on
+    /// `clock`.
+    ///
+    pub fn real_time<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Read the next record, `None` at the end of the log.
+    ///
+    pub fn next_record(&mut self) -> Result<Option<LogRecord>, IoError> {
+        let mut header = [0; RECORD_HEADER_SIZE];
+
+        if !read_full(&mut self.reader, &mut header)? {
+            return Ok(None);
+        }
+
+        let nanos = u64::from_le_bytes(header[..8].try_into().unwrap());
+        let length = u32::from_le_bytes(header[8..].try_into().unwrap());
+        let mut data = vec![0; length as usize];
+
+        if !read_full(&mut self.reader, &mut data)? {
+            return Ok(None);
+        }
+
+        Ok(Some(LogRecord { timestamp: UNIX_EPOCH + Duration::from_nanos(nanos), data }))
+    }
+
+    /// Wait until the record is due, when replaying in real time.
+    fn pace(&mut self, record: &LogRecord) {
+        let clock = match &self.clock {
+            Some(clock) => clock,
+            None => return,
+        };
+
+        match self.origin {
+            Some((timestamp, instant)) => {
+                let offset = record.timestamp.duration_since(timestamp).unwrap_or_default();
+                let due = instant + offset;
+                let now = clock.now();
+
+                if due > now {
+                    clock.sleep(due - now);
+                }
+            },
+            None => self.origin = Some((record.timestamp, clock.now())),
+        }
+    }
+}
+
+impl<R: Read> Iterator for LogReader<R> {
+    type Item = Result<LogRecord, IoError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+impl<R: Read> Read for LogReader<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, IoError> {
+        loop {
+            if let Some((record, position)) = &mut self.pending {
+                let count = buffer.len().min(record.data.len() - *position);
+                buffer[..count].copy_from_slice(&record.data[*position..*position + count]);
+                *position += count;
+
+                if *position == record.data.len() {
+                    self.pending = None;
+                }
+
+                if count > 0 || buffer.is_empty() {
+                    return Ok(count);
+                }
+            }
+
+            match self.next_record()? {
+                Some(record) => {
+                    self.pace(&record);
+                    self.pending = Some((record, 0));
+                },
+                None => return Ok(0),
+            }
+        }
+    }
+}
+
+/// Fill `buffer`, false if the source ends first.
+fn read_full<R: Read>(reader: &mut R, buffer: &mut [u8]) -> Result<bool, IoError> {
+    match reader.read_exact(buffer) {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => Ok(false),
+        Err(err) => Err(err),
+    }
+}
@@ -0,0 +1,166 @@
+use std::cell::UnsafeCell;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::thread::Thread;
+
+/// Buffer shared between the producer and the consumer.
+struct Shared {
+    // Storage. The length is a power of two.
+    buffer: Box<[UnsafeCell<u8>]>,
+    // Position of the next byte to read. Only written by the consumer.
+    head: AtomicUsize,
+    // Position of the next byte to write. Only written by the producer.
+    tail: AtomicUsize,
+    // Number of bytes discarded because the buffer was full.
+    overruns: AtomicU64,
+    // Set when the producer is dropped.
+    closed: AtomicBool,
+}
+
+// The producer only writes to the free region and the consumer only reads from the
+// filled region. Ownership of a region is handed over by the release stores to
+// `head` and `tail`, so a byte is never accessed by both sides at the same time.
+unsafe impl Sync for Shared {}
+
+impl Shared {
+    fn mask(&self) -> usize {
+        self.buffer.len() - 1
+    }
+}
+
+/// ## Summary
+///
+/// Create a lock-free single-producer single-consumer byte ring buffer.
+///
+/// ## Parameters
+///
+/// capacity: Size of the buffer in bytes. Rounded up to a power of two.
+///
+/// consumer: The thread to unpark whenever bytes are written.
+///
+pub(crate) fn channel(capacity: usize, consumer: Thread) -> (Producer, Consumer) {
+    let capacity = capacity.max(1).next_power_of_two();
+
+    let shared = Arc::new(Shared {
+        buffer: (0..capacity).map(|_| UnsafeCell::new(0)).collect(),
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+        overruns: AtomicU64::new(0),
+        closed: AtomicBool::new(false),
+    });
+
+    (Producer { shared: shared.clone(), consumer }, Consumer { shared })
+}
+
+/// ## Summary
+///
+/// The writing half of a ring buffer.
+///
+pub(crate) struct Producer {
+    shared: Arc<Shared>,
+    // Thread waiting for data.
+    consumer: Thread,
+}
+
+impl Producer {
+    /// ## Summary
+    ///
+    /// Write bytes to the buffer and wake the consumer.
+    ///
+    /// ## Remarks
+    ///
+    /// Bytes that do not fit are discarded and counted as overruns.
+    ///
+    pub(crate) fn push(&self, data: &[u8]) -> usize {
+        let shared = &self.shared;
+        let head = shared.head.load(Ordering::Acquire);
+        let tail = shared.tail.load(Ordering::Relaxed);
+
+        let free = shared.buffer.len() - tail.wrapping_sub(head);
+        let count = free.min(data.len());
+
+        for (i, &byte) in data[..count].iter().enumerate() {
+            // Safety: the region between tail and head + len is owned by the producer.
+            unsafe { *shared.buffer[tail.wrapping_add(i) & shared.mask()].get() = byte; }
+        }
+
+        shared.tail.store(tail.wrapping_add(count), Ordering::Release);
+
+        if count < data.len() {
+            shared.overruns.fetch_add((data.len() - count) as u64, Ordering::Relaxed);
+        }
+
+        self.consumer.unpark();
+        count
+    }
+
+    /// ## Summary
+    ///
+    /// Wake the consumer without writing, e.g. when the producer has something else to report.
+    ///
+    pub(crate) fn wake(&self) {
+        self.consumer.unpark();
+    }
+}
+
+impl Drop for Producer {
+    fn drop(&mut self) {
+        self.shared.closed.store(true, Ordering::Release);
+        self.consumer.unpark();
+    }
+}
+
+/// ## Summary
+///
+/// The reading half of a ring buffer.
+///
+pub(crate) struct Consumer {
+    shared: Arc<Shared>,
+}
+
+impl Consumer {
+    /// ## Summary
+    ///
+    /// Read as many bytes as are available, up to the size of `out`.
+    ///
+    pub(crate) fn pop(&self, out: &mut [u8]) -> usize {
+        let shared = &self.shared;
+        let tail = shared.tail.load(Ordering::Acquire);
+        let head = shared.head.load(Ordering::Relaxed);
+
+        let count = tail.wrapping_sub(head).min(out.len());
+
+        for (i, byte) in out[..count].iter_mut().enumerate() {
+            // Safety: the region between head and tail is owned by the consumer.
+            *byte = unsafe { *shared.buffer[head.wrapping_add(i) & shared.mask()].get() };
+        }
+
+        shared.head.store(head.wrapping_add(count), Ordering::Release);
+        count
+    }
+
+    /// ## Summary
+    ///
+    /// Discard every buffered byte.
+    ///
+    pub(crate) fn clear(&self) {
+        let tail = self.shared.tail.load(Ordering::Acquire);
+        self.shared.head.store(tail, Ordering::Release);
+    }
+
+    /// ## Summary
+    ///
+    /// Number of bytes discarded because the buffer was full.
+    ///
+    pub(crate) fn overruns(&self) -> u64 {
+        self.shared.overruns.load(Ordering::Relaxed)
+    }
+
+    /// ## Summary
+    ///
+    /// Whether the producer has been dropped.
+    ///
+    pub(crate) fn is_closed(&self) -> bool {
+        self.shared.closed.load(Ordering::Acquire)
+    }
+}
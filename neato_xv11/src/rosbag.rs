@@ -0,0 +1,258 @@
+use std::fs;
+use std::io::Error as IoError;
+use std::path::{Path, PathBuf};
+
+use rusqlite::{params, Connection};
+
+use super::data::LidarScan;
+use super::laserscan::LaserScan;
+
+/// Type of the recorded messages.
+const MESSAGE_TYPE: &str = "sensor_msgs/msg/LaserScan";
+
+/// Tables of the rosbag2 `sqlite3` storage, as written by ROS 2 Humble.
+const SCHEMA: &str = "
+    CREATE TABLE schema(schema_version INTEGER PRIMARY KEY, ros_distro TEXT NOT NULL);
+    CREATE TABLE metadata(id INTEGER PRIMARY KEY, metadata_version INTEGER NOT NULL, metadata TEXT NOT NULL);
+    CREATE TABLE topics(id INTEGER PRIMARY KEY, name TEXT NOT NULL, type TEXT NOT NULL, serialization_format TEXT NOT NULL, offered_qos_profiles TEXT NOT NULL);
+    CREATE TABLE messages(id INTEGER PRIMARY KEY, topic_id INTEGER NOT NULL, timestamp INTEGER NOT NULL, data BLOB NOT NULL);
+    CREATE INDEX timestamp_idx ON messages (timestamp ASC);
+    INSERT INTO schema VALUES (3, 'humble');
+";
+
+/// ## Summary
+///
+/// Records scans to a rosbag2 bag as `sensor_msgs/LaserScan` messages, so recordings
+/// open directly in Foxglove, rviz or `ros2 bag play`.
+///
+/// ## Remarks
+///
+/// A bag is a directory holding a single SQLite database and the `metadata.yaml`
+/// written by `finish`. Scans are converted with `laserscan::LaserScan`.
+///
+/// ## Example
+///
+/// ```no_run
+/// # fn record(scans: impl Iterator<Item = (neato_xv11::data::LidarScan, u64)>) {
+/// use neato_xv11::rosbag::BagWriter;
+///
+/// let mut bag = BagWriter::create("lidar_bag", "/scan", "laser").unwrap();
+///
+/// // Nanoseconds since the Unix epoch, e.g. from `clock::ClockMapper`.
+/// for (scan, stamp) in scans {
+///     bag.write(&scan, stamp).unwrap();
+/// }
+///
+/// bag.finish().unwrap();
+/// # }
+/// ```
+pub struct BagWriter {
+    // The bag database.
+    connection: Connection,
+    // Directory of the bag.
+    directory: PathBuf,
+    // Name of the database file, relative to the directory.
+    file_name: String,
+    // Name of the recorded topic.
+    topic: String,
+    // Frame id of the messages.
+    frame_id: String,
+    // Timestamps of the first and last messages, in nanoseconds since the Unix epoch.
+    span: Option<(u64, u64)>,
+    // Number of messages written.
+    count: u64,
+}
+
+impl BagWriter {
+    /// ## Summary
+    ///
+    /// Create a bag in a new directory.
+    ///
+    /// ## Parameters
+    ///
+    /// directory: Directory of the bag. Must not exist.
+    ///
+    /// topic: Topic the scans are recorded on, e.g. `/scan`.
+    ///
+    /// frame_id: Frame id of the messages, e.g. `laser`.
+    ///
+    pub fn create<P: AsRef<Path>>(directory: P, topic: &str, frame_id: &str) -> Result<Self, IoError> {
+        let directory = directory.as_ref().to_path_buf();
+        let name = directory.file_name().and_then(|name| name.to_str()).unwrap_or("bag");
+        let file_name = format!("{}_0.db3", name);
+
+        fs::create_dir(&directory)?;
+
+        let connection = Connection::open(directory.join(&file_name)).map_err(IoError::other)?;
+        connection.execute_batch(SCHEMA).map_err(IoError::other)?;
+        connection
+            .execute(
+                "INSERT INTO topics (id, name, type, serialization_format, offered_qos_profiles) VALUES (1, ?1, ?2, 'cdr', '')",
+                params![topic, MESSAGE_TYPE],
+            )
+            .map_err(IoError::other)?;
+
+        Ok(BagWriter {
+            connection,
+            directory,
+            file_name,
+            topic: String::from(topic),
+            frame_id: String::from(frame_id),
+            span: None,
+            count: 0,
+        })
+    }
+
+    /// ## Summary
+    ///
+    /// Record a scan.
+    ///
+    /// ## Parameters
+    ///
+    /// scan: The scan.
+    ///
+    /// stamp: When the scan completed, in nanoseconds since the Unix epoch.
+    ///
+    pub fn write(&mut self, scan: &LidarScan, stamp: u64) -> Result<(), IoError> {
+        let data = encode_laser_scan(scan, stamp, &self.frame_id);
+
+        self.connection
+            .execute("INSERT INTO messages (topic_id, timestamp, data) VALUES (1, ?1, ?2)", params![stamp as i64, data])
+            .map_err(IoError::other)?;
+
+        self.span = Some(match self.span {
+            Some((first, last)) => (first.min(stamp), last.max(stamp)),
+            None => (stamp, stamp),
+        });
+        self.count += 1;
+
+        Ok(())
+    }
+
+    /// ## Summary
+    ///
+    /// Number of scans recorded.
+    ///
+    pub fn len(&self) -> u64 {
+        self.count
+    }
+
+    /// ## Summary
+    ///
+    /// Whether no scan was recorded.
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// ## Summary
+    ///
+    /// Write `metadata.yaml` and close the bag.
+    ///
+    pub fn finish(self) -> Result<(), IoError> {
+        let (start, end) = self.span.unwrap_or_default();
+        let metadata = format!(
+            "rosbag2_bagfile_information:
+  version: 5
+  storage_identifier: sqlite3
+  duration:
+    nanoseconds: {duration}
+  starting_time:
+    nanoseconds_since_epoch: {start}
+  message_count: {count}
+  topics_with_message_count:
+    - topic_metadata:
+        name: {topic}
+        type: {message_type}
+        serialization_format: cdr
+        offered_qos_profiles: \"\"
+      message_count: {count}
+  compression_format: \"\"
+  compression_mode: \"\"
+  relative_file_paths:
+    - {file}
+  files:
+    - path: {file}
+      starting_time:
+        nanoseconds_since_epoch: {start}
+      duration:
+        nanoseconds: {duration}
+      message_count: {count}
+",
+            duration = end - start,
+            start = start,
+            count = self.count,
+            topic = self.topic,
+            message_type = MESSAGE_TYPE,
+            file = self.file_name,
+        );
+
+        self.connection.close().map_err(|(_, err)| IoError::other(err))?;
+        fs::write(self.directory.join("metadata.yaml"), metadata)
+    }
+}
+
+/// Little endian CDR serializer.
+struct Cdr(Vec<u8>);
+
+impl Cdr {
+    /// Start a message with the encapsulation header.
+    fn new() -> Self {
+        Cdr(vec![0x00, 0x01, 0x00, 0x00])
+    }
+
+    /// Pad to a multiple of `size`, relative to the end of the encapsulation header.
+    fn align(&mut self, size: usize) {
+        while !(self.0.len() - 4).is_multiple_of(size) {
+            self.0.push(0);
+        }
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.align(4);
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.align(4);
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32 + 1);
+        self.0.extend_from_slice(value.as_bytes());
+        self.0.push(0);
+    }
+
+    fn f32_sequence(&mut self, values: &[f32]) {
+        self.u32(values.len() as u32);
+
+        for &value in values {
+            self.f32(value);
+        }
+    }
+}
+
+/// ## Summary
+///
+/// Serialize a scan as a CDR encoded `sensor_msgs/LaserScan`.
+///
+fn encode_laser_scan(scan: &LidarScan, stamp: u64, frame_id: &str) -> Vec<u8> {
+    let scan = LaserScan::from(scan);
+
+    let mut cdr = Cdr::new();
+    // std_msgs/Header
+    cdr.u32((stamp / 1_000_000_000) as u32);
+    cdr.u32((stamp % 1_000_000_000) as u32);
+    cdr.string(frame_id);
+    cdr.f32(scan.angle_min);
+    cdr.f32(scan.angle_max);
+    cdr.f32(scan.angle_increment);
+    cdr.f32(scan.time_increment);
+    cdr.f32(scan.scan_time);
+    cdr.f32(scan.range_min);
+    cdr.f32(scan.range_max);
+    cdr.f32_sequence(&scan.ranges);
+    cdr.f32_sequence(&scan.intensities);
+    cdr.0
+}
@@ -0,0 +1,174 @@
+#[cfg(feature = "std")]
+use std::sync::Mutex;
+#[cfg(feature = "std")]
+use std::sync::mpsc::SendError;
+
+use super::data::Float;
+use super::message::Warning;
+#[cfg(feature = "std")]
+use super::prelude::*;
+
+/// ## Summary
+///
+/// Watches the spin speed and reports when it leaves or returns to a band.
+///
+/// ## Remarks
+///
+/// The speed is out of range below `min` or above `max`. It only counts as recovered
+/// once it is back inside the band by at least the hysteresis, so a speed hovering
+/// around a limit does not flap between warnings.
+///
+/// ## Example
+///
+/// ```
+/// use neato_xv11::message::Warning;
+/// use neato_xv11::rpm::RpmMonitor;
+///
+/// // 240-300 RPM, recovered 5 RPM inside the band.
+/// let mut monitor = RpmMonitor::new(240.0, 300.0, 5.0);
+///
+/// assert_eq!(monitor.push(250.0), None);
+/// assert_eq!(monitor.push(238.0), Some(Warning::RpmOutOfRange { rpm: 238.0 }));
+/// assert_eq!(monitor.push(242.0), None);
+/// assert_eq!(monitor.push(246.0), Some(Warning::RpmRecovered { rpm: 246.0 }));
+/// ```
+#[derive(Clone, Debug)]
+pub struct RpmMonitor {
+    // Lowest speed in range (RPM).
+    min: Float,
+    // Highest speed in range (RPM).
+    max: Float,
+    // How far inside the band the speed must return to recover (RPM).
+    hysteresis: Float,
+    // A `Warning::RpmOutOfRange` was reported and the speed has not recovered yet.
+    is_out_of_range: bool,
+}
+
+impl RpmMonitor {
+    /// ## Summary
+    ///
+    /// Initialize a new monitor.
+    ///
+    /// ## Parameters
+    ///
+    /// min: Lowest speed in range (RPM).
+    ///
+    /// max: Highest speed in range (RPM).
+    ///
+    /// hysteresis: How far inside the band the speed must return to recover (RPM).
+    ///
+    pub fn new(min: Float, max: Float, hysteresis: Float) -> Self {
+        RpmMonitor {
+            min,
+            max,
+            hysteresis,
+            is_out_of_range: false,
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Whether the speed is out of range and has not recovered yet.
+    ///
+    pub fn is_out_of_range(&self) -> bool {
+        self.is_out_of_range
+    }
+
+    /// ## Summary
+    ///
+    /// Add a speed reading. Returns a warning when the speed leaves the band or recovers.
+    ///
+    pub fn push(&mut self, rpm: Float) -> Option<Warning> {
+        if self.is_out_of_range {
+            if rpm >= self.min + self.hysteresis && rpm <= self.max - self.hysteresis {
+                self.is_out_of_range = false;
+                return Some(Warning::RpmRecovered { rpm });
+            }
+        } else if rpm < self.min || rpm > self.max {
+            self.is_out_of_range = true;
+            return Some(Warning::RpmOutOfRange { rpm });
+        }
+
+        None
+    }
+
+    /// ## Summary
+    ///
+    /// Forget that the speed was out of range.
+    ///
+    pub fn reset(&mut self) {
+        self.is_out_of_range = false;
+    }
+}
+
+/// ## Summary
+///
+/// A sink that sends a `LidarDriverMessage::Warning` whenever the speed of the
+/// packets leaves or returns to a band, before forwarding every message.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::thread;
+/// # use std::sync::mpsc::channel;
+/// use neato_xv11::message::Warning;
+/// use neato_xv11::prelude::*;
+/// use neato_xv11::rpm::{RpmMonitor, RpmMonitorSink};
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// let message_tx = RpmMonitorSink::new(message_tx, RpmMonitor::new(240.0, 300.0, 5.0));
+///
+/// thread::spawn(move || {
+///     neato_xv11::run("/dev/serial0", message_tx, command_rx);
+/// });
+///
+/// for message in message_rx.iter() {
+///     if let Ok(LidarDriverMessage::Warning(Warning::RpmOutOfRange { rpm })) = message {
+///         println!("Motor speed out of range: {} RPM", rpm);
+///     }
+/// }
+/// ```
+#[cfg(feature = "std")]
+pub struct RpmMonitorSink<S> {
+    inner: S,
+    monitor: Mutex<RpmMonitor>,
+}
+
+#[cfg(feature = "std")]
+impl<S: MessageSink> RpmMonitorSink<S> {
+    /// ## Summary
+    ///
+    /// Wrap a sink with a speed monitor.
+    ///
+    pub fn new(inner: S, monitor: RpmMonitor) -> Self {
+        RpmMonitorSink {
+            inner,
+            monitor: Mutex::new(monitor),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: MessageSink> MessageSink for RpmMonitorSink<S> {
+    fn send(&self, message: Result<LidarDriverMessage, LidarDriverError>) -> Result<(), SendError<Result<LidarDriverMessage, LidarDriverError>>> {
+        if let Ok(LidarDriverMessage::Packet(packet)) = &message {
+            let warning = self.monitor.lock().unwrap_or_else(|e| e.into_inner()).push(packet.speed);
+
+            if let Some(warning) = warning {
+                self.inner.send(Ok(LidarDriverMessage::Warning(warning)))?;
+            }
+        }
+
+        self.inner.send(message)
+    }
+
+    fn dropped(&self) -> u64 {
+        self.inner.dropped()
+    }
+
+    fn backlog(&self) -> Option<Backlog> {
+        self.inner.backlog()
+    }
+}
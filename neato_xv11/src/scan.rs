@@ -0,0 +1,429 @@
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+#[cfg(feature = "std")]
+use std::sync::Mutex;
+#[cfg(feature = "std")]
+use std::sync::mpsc::SendError;
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+
+use super::calibration::CalibrationTable;
+#[cfg(feature = "std")]
+use super::calibration::Calibration;
+use super::data::{Float, LidarPacket, LidarScan, SCAN_SIZE};
+#[cfg(feature = "alloc")]
+use super::filter::ScanFilter;
+#[cfg(feature = "std")]
+use super::prelude::*;
+
+/// ## Summary
+///
+/// When `ScanAssembler` emits a scan, trading completeness for latency.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ScanPolicy {
+    // Emit every scan when the LIDAR wraps around index 0, whatever its gaps. Suits displays.
+    #[default]
+    Wraparound,
+    // Emit a scan on wraparound only if every slot holds a reading, discard the others.
+    // Suits mapping, which would rather skip a scan than integrate a partial one.
+    CompleteOnly,
+    // Emit a scan on wraparound, or once its first packet is older than the duration,
+    // with the slots of the packets not received yet left as `None`. Bounds the latency
+    // when packets stop arriving, e.g. on a cable fault. Requires `push` and `poll`.
+    #[cfg(feature = "std")]
+    Timeout(Duration),
+}
+
+/// ## Summary
+///
+/// Assembles packets into full 360 degree scans.
+///
+/// ## Remarks
+///
+/// By default, a scan is complete when a packet's first index is lower than the
+/// previous packet's, i.e. the LIDAR wrapped around index 0. Slots of missing or
+/// corrupted packets are left as `None`. The first scan is usually partial, since
+/// reading rarely starts at index 0. See `ScanPolicy` for the other policies.
+///
+/// Scans are numbered in `LidarScan::revolution` and carry the frame id of the
+/// assembler, so scans from several LIDARs can be told apart downstream.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::thread;
+/// # use std::sync::mpsc::channel;
+/// use neato_xv11::prelude::*;
+/// use neato_xv11::scan::ScanAssembler;
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// thread::spawn(move || {
+///     neato_xv11::run("/dev/serial0", message_tx, command_rx);
+/// });
+///
+/// let mut assembler = ScanAssembler::new();
+/// assembler.set_frame_id("laser_front");
+///
+/// for message in message_rx.iter() {
+///     if let Ok(LidarDriverMessage::Packet(packet)) = message {
+///         if let Some(scan) = assembler.push(packet) {
+///             println!("{} readings missing", scan.missing());
+///         }
+///     }
+/// }
+/// ```
+pub struct ScanAssembler {
+    // Scan being assembled.
+    scan: LidarScan,
+    // First index of the last packet pushed.
+    last_index: Option<usize>,
+    // Sum of the packet speeds of the scan.
+    speed_sum: Float,
+    // Number of packets in the scan.
+    packets: usize,
+    // Per-angle distance corrections applied to completed scans.
+    calibration: Option<CalibrationTable>,
+    // Corrections of the installed unit and transform to the robot frame.
+    #[cfg(feature = "std")]
+    robot_calibration: Option<Calibration>,
+    // Filter applied to completed scans.
+    #[cfg(feature = "alloc")]
+    filter: Option<Box<dyn ScanFilter + Send>>,
+    // Frame id given to completed scans.
+    #[cfg(feature = "alloc")]
+    frame_id: String,
+    // When scans are emitted.
+    policy: ScanPolicy,
+    // When the first packet of the scan was pushed.
+    #[cfg(feature = "std")]
+    started: Option<Instant>,
+    // Number of scans completed.
+    revolution: u64,
+    // Number of incomplete scans discarded by `ScanPolicy::CompleteOnly`.
+    discarded: u64,
+}
+
+impl ScanAssembler {
+    /// ## Summary
+    ///
+    /// Initialize a new scan assembler.
+    ///
+    pub fn new() -> Self {
+        ScanAssembler {
+            scan: LidarScan::empty(),
+            last_index: None,
+            speed_sum: 0.0,
+            packets: 0,
+            calibration: None,
+            #[cfg(feature = "std")]
+            robot_calibration: None,
+            #[cfg(feature = "alloc")]
+            filter: None,
+            #[cfg(feature = "alloc")]
+            frame_id: String::new(),
+            policy: ScanPolicy::default(),
+            #[cfg(feature = "std")]
+            started: None,
+            revolution: 0,
+            discarded: 0,
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Set when scans are emitted. Defaults to `ScanPolicy::Wraparound`.
+    ///
+    pub fn set_policy(&mut self, policy: ScanPolicy) {
+        self.policy = policy;
+    }
+
+    /// ## Summary
+    ///
+    /// Number of incomplete scans discarded by `ScanPolicy::CompleteOnly`. Discarded scans
+    /// still use a revolution number, so gaps show in `LidarScan::revolution`.
+    ///
+    pub fn discarded(&self) -> u64 {
+        self.discarded
+    }
+
+    /// ## Summary
+    ///
+    /// Correct the distances of every completed scan with a calibration table.
+    ///
+    pub fn set_calibration(&mut self, calibration: CalibrationTable) {
+        self.calibration = Some(calibration);
+    }
+
+    /// ## Summary
+    ///
+    /// Correct every completed scan for how the unit is installed and express it in the
+    /// robot frame, after the calibration table. See `calibration::Calibration`.
+    ///
+    /// ## Remarks
+    ///
+    /// `ScanPolicy::CompleteOnly` checks the scans before the transform, which leaves
+    /// gaps in the scans of a sensor away from the robot origin.
+    ///
+    #[cfg(feature = "std")]
+    pub fn set_robot_calibration(&mut self, calibration: Calibration) {
+        self.robot_calibration = Some(calibration);
+    }
+
+    /// ## Summary
+    ///
+    /// Filter every completed scan, e.g. with a `filter::FilterChain`, after the calibration.
+    ///
+    /// ## Remarks
+    ///
+    /// `ScanPolicy::CompleteOnly` checks the scans before filtering, so readings removed
+    /// by the filter do not discard them.
+    ///
+    #[cfg(feature = "alloc")]
+    pub fn set_filter<F: ScanFilter + Send + 'static>(&mut self, filter: F) {
+        self.filter = Some(Box::new(filter));
+    }
+
+    /// ## Summary
+    ///
+    /// Name the coordinate frame of the scans, e.g. `laser_front`, as expected by
+    /// transform systems such as ROS `tf`.
+    ///
+    #[cfg(feature = "alloc")]
+    pub fn set_frame_id(&mut self, frame_id: &str) {
+        self.frame_id = String::from(frame_id);
+    }
+
+    /// ## Summary
+    ///
+    /// Add a packet to the scan being assembled.
+    ///
+    /// ## Remarks
+    ///
+    /// Returns the previous scan when this packet starts a new revolution, or the scan
+    /// being assembled when it timed out.
+    ///
+    pub fn push(&mut self, packet: LidarPacket) -> Option<LidarScan> {
+        #[cfg(feature = "std")]
+        {
+            self.push_at(packet, Instant::now())
+        }
+
+        #[cfg(not(feature = "std"))]
+        {
+            self.push_packet(packet)
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn push_at(&mut self, packet: LidarPacket, now: Instant) -> Option<LidarScan> {
+        let timed_out = self.poll_at(now);
+        self.started.get_or_insert(now);
+        let scan = self.push_packet(packet);
+
+        if scan.is_some() {
+            self.started = Some(now);
+        }
+
+        timed_out.or(scan)
+    }
+
+    /// ## Summary
+    ///
+    /// Return the scan being assembled if it timed out under `ScanPolicy::Timeout`.
+    ///
+    /// ## Remarks
+    ///
+    /// Call periodically, e.g. whenever receiving messages times out, since a timed out
+    /// scan is otherwise only returned by the next `push`. Packets of the same
+    /// revolution received afterwards start a new, partial scan.
+    ///
+    #[cfg(feature = "std")]
+    pub fn poll(&mut self) -> Option<LidarScan> {
+        self.poll_at(Instant::now())
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn poll_at(&mut self, now: Instant) -> Option<LidarScan> {
+        match (self.policy, self.started) {
+            (ScanPolicy::Timeout(timeout), Some(started)) if now.saturating_duration_since(started) >= timeout => {
+                self.started = None;
+                self.finish()
+            },
+            _ => None,
+        }
+    }
+
+    /// Add a packet, returning the previous scan on wraparound.
+    fn push_packet(&mut self, packet: LidarPacket) -> Option<LidarScan> {
+        let first_index = packet.readings.first()?.index % SCAN_SIZE;
+
+        let scan = match self.last_index {
+            Some(last_index) if first_index < last_index => self.finish(),
+            _ => None,
+        };
+
+        self.last_index = Some(first_index);
+        self.speed_sum += packet.speed;
+        self.packets += 1;
+
+        for reading in packet.readings {
+            let index = reading.index % SCAN_SIZE;
+            self.scan.readings[index] = Some(reading);
+        }
+
+        scan
+    }
+
+    /// ## Summary
+    ///
+    /// Discard the scan being assembled. The revolution number keeps increasing.
+    ///
+    pub fn reset(&mut self) {
+        self.scan = LidarScan::empty();
+        self.last_index = None;
+        self.speed_sum = 0.0;
+        self.packets = 0;
+
+        #[cfg(feature = "std")]
+        {
+            self.started = None;
+        }
+    }
+
+    /// Take the scan being assembled and start a new one.
+    fn finish(&mut self) -> Option<LidarScan> {
+        if self.packets == 0 {
+            return None;
+        }
+
+        let mut scan = core::mem::replace(&mut self.scan, LidarScan::empty());
+        scan.speed = self.speed_sum / self.packets as Float;
+        scan.revolution = self.revolution;
+
+        #[cfg(feature = "alloc")]
+        scan.frame_id.clone_from(&self.frame_id);
+
+        if let Some(calibration) = &self.calibration {
+            calibration.apply(&mut scan);
+        }
+
+        self.speed_sum = 0.0;
+        self.packets = 0;
+        self.revolution += 1;
+
+        if self.policy == ScanPolicy::CompleteOnly && scan.missing() > 0 {
+            self.discarded += 1;
+            return None;
+        }
+
+        #[cfg(feature = "std")]
+        if let Some(calibration) = &self.robot_calibration {
+            calibration.apply(&mut scan);
+        }
+
+        #[cfg(feature = "alloc")]
+        if let Some(filter) = &mut self.filter {
+            filter.apply(&mut scan);
+        }
+
+        Some(scan)
+    }
+}
+
+impl Default for ScanAssembler {
+    fn default() -> Self {
+        ScanAssembler::new()
+    }
+}
+
+/// ## Summary
+///
+/// A message sink that assembles the packets into scans and sends a
+/// `LidarDriverMessage::Scan` once per revolution, before the packet starting the next
+/// one. Every message is forwarded to the inner sink.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::thread;
+/// # use std::sync::mpsc::channel;
+/// use neato_xv11::prelude::*;
+/// use neato_xv11::scan::ScanSink;
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// let message_tx = ScanSink::new(message_tx);
+///
+/// thread::spawn(move || {
+///     neato_xv11::run("/dev/serial0", message_tx, command_rx);
+/// });
+///
+/// for message in message_rx.iter() {
+///     if let Ok(LidarDriverMessage::Scan(scan)) = message {
+///         println!("Revolution {}: {} readings missing", scan.revolution, scan.missing());
+///     }
+/// }
+/// ```
+#[cfg(feature = "std")]
+pub struct ScanSink<S> {
+    inner: S,
+    assembler: Mutex<ScanAssembler>,
+}
+
+#[cfg(feature = "std")]
+impl<S: MessageSink> ScanSink<S> {
+    /// ## Summary
+    ///
+    /// Wrap a sink with a default scan assembler.
+    ///
+    pub fn new(inner: S) -> Self {
+        ScanSink::with_assembler(inner, ScanAssembler::new())
+    }
+
+    /// ## Summary
+    ///
+    /// Wrap a sink with a configured scan assembler, e.g. with a calibration table,
+    /// a frame id or a `ScanPolicy`.
+    ///
+    pub fn with_assembler(inner: S, assembler: ScanAssembler) -> Self {
+        ScanSink {
+            inner,
+            assembler: Mutex::new(assembler),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: MessageSink> MessageSink for ScanSink<S> {
+    fn send(&self, message: Result<LidarDriverMessage, LidarDriverError>) -> Result<(), SendError<Result<LidarDriverMessage, LidarDriverError>>> {
+        let scan = {
+            let mut assembler = self.assembler.lock().unwrap_or_else(|e| e.into_inner());
+
+            match &message {
+                Ok(LidarDriverMessage::Packet(packet)) => assembler.push(packet.clone()),
+                // Scans timed out under `ScanPolicy::Timeout` are sent on the next message.
+                _ => assembler.poll(),
+            }
+        };
+
+        if let Some(scan) = scan {
+            self.inner.send(Ok(LidarDriverMessage::Scan(scan)))?;
+        }
+
+        self.inner.send(message)
+    }
+
+    fn dropped(&self) -> u64 {
+        self.inner.dropped()
+    }
+
+    fn backlog(&self) -> Option<Backlog> {
+        self.inner.backlog()
+    }
+}
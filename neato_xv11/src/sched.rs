@@ -0,0 +1,96 @@
+use std::io::{Error as IoError, ErrorKind};
+
+#[cfg(feature = "log")]
+use log::warn;
+
+use super::builder::ThreadConfig;
+
+/// ## Summary
+///
+/// Apply the realtime priority and core affinity to the calling thread.
+///
+/// ## Remarks
+///
+/// Every setting is attempted. Returns the first error encountered.
+///
+pub(crate) fn apply(config: &ThreadConfig) -> Result<(), IoError> {
+    let mut result = Ok(());
+
+    if let Some(priority) = config.realtime_priority {
+        if let Err(err) = set_realtime_priority(priority) {
+            #[cfg(feature = "log")]
+            warn!("Unable to set realtime priority {}. {}", priority, err);
+
+            result = result.and(Err(err));
+        }
+    }
+
+    if let Some(cores) = &config.affinity {
+        if let Err(err) = set_affinity(cores) {
+            #[cfg(feature = "log")]
+            warn!("Unable to set core affinity {:?}. {}", cores, err);
+
+            result = result.and(Err(err));
+        }
+    }
+
+    result
+}
+
+/// ## Summary
+///
+/// Switch the calling thread to the `SCHED_FIFO` policy with the given priority.
+/// Usually requires root or `CAP_SYS_NICE`.
+///
+#[cfg(target_os = "linux")]
+fn set_realtime_priority(priority: i32) -> Result<(), IoError> {
+    let param = libc::sched_param { sched_priority: priority };
+
+    // Safety: param is a valid sched_param for the duration of the call.
+    let result = unsafe { libc::pthread_setschedparam(libc::pthread_self(), libc::SCHED_FIFO, &param) };
+
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(IoError::from_raw_os_error(result))
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_realtime_priority(_priority: i32) -> Result<(), IoError> {
+    Err(IoError::new(ErrorKind::Unsupported, "realtime priority is only supported on Linux"))
+}
+
+/// ## Summary
+///
+/// Pin the calling thread to the given cores.
+///
+#[cfg(target_os = "linux")]
+fn set_affinity(cores: &[usize]) -> Result<(), IoError> {
+    // Safety: cpu_set_t is plain data, all zeroes is an empty set.
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    let max_cores = 8 * std::mem::size_of::<libc::cpu_set_t>();
+
+    for &core in cores {
+        if core >= max_cores {
+            return Err(IoError::new(ErrorKind::InvalidInput, format!("core {} is out of range", core)));
+        }
+
+        // Safety: core is within the bounds of the set.
+        unsafe { libc::CPU_SET(core, &mut set) };
+    }
+
+    // Safety: set is a valid cpu_set_t of the given size.
+    let result = unsafe { libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) };
+
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(IoError::last_os_error())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_affinity(_cores: &[usize]) -> Result<(), IoError> {
+    Err(IoError::new(ErrorKind::Unsupported, "core affinity is only supported on Linux"))
+}
@@ -0,0 +1,70 @@
+use std::io::Error as IoError;
+use std::sync::mpsc::channel;
+
+use super::builder::LidarDriverBuilder;
+use super::control::StopToken;
+use super::prelude::*;
+
+/// ## Summary
+///
+/// Stop the drivers holding `token` on Ctrl-C, SIGINT or SIGTERM.
+///
+/// ## Remarks
+///
+/// A process has a single handler, returns an error if one is already installed.
+///
+pub fn stop_on_signal(token: StopToken) -> Result<(), IoError> {
+    ctrlc::set_handler(move || token.stop()).map_err(|err| match err {
+        ctrlc::Error::System(err) => err,
+        err => IoError::other(err),
+    })
+}
+
+/// ## Summary
+///
+/// Spawn the driver and pass every message to `handler` until the driver shuts down.
+/// Ctrl-C, SIGINT and SIGTERM stop the driver cleanly.
+///
+/// ## Remarks
+///
+/// Returns after `LidarDriverMessage::Shutdown`, once the serial port is closed, so
+/// simple command line programs never leave the port in an odd state when interrupted.
+/// Returns an error if the signal handler could not be installed or the driver thread
+/// could not be spawned.
+///
+/// ## Example
+///
+/// ```no_run
+/// use neato_xv11::LidarDriverBuilder;
+/// use neato_xv11::prelude::*;
+///
+/// neato_xv11::signal::run_until_signal(LidarDriverBuilder::new("/dev/ttyUSB0"), |message| {
+///     if let Ok(LidarDriverMessage::Packet(packet)) = message {
+///         println!("{:?}", packet);
+///     }
+/// }).unwrap();
+/// ```
+pub fn run_until_signal<F>(builder: LidarDriverBuilder, mut handler: F) -> Result<(), IoError>
+where
+    F: FnMut(Result<LidarDriverMessage, LidarDriverError>),
+{
+    let token = StopToken::new();
+    stop_on_signal(token.clone())?;
+
+    let (message_tx, message_rx) = channel();
+    // The driver stops when every command sender is dropped, keep this one until it has.
+    let (_command_tx, command_rx) = channel();
+    let driver = builder.stop_token(token).spawn(message_tx, command_rx)?;
+
+    for message in message_rx.iter() {
+        let is_shutdown = matches!(message, Ok(LidarDriverMessage::Shutdown));
+        handler(message);
+
+        if is_shutdown {
+            break;
+        }
+    }
+
+    let _ = driver.join();
+    Ok(())
+}
@@ -0,0 +1,334 @@
+use std::io::{Error as IoError, Read};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::clock::{Clock, SystemClock};
+use super::data::{Float, SCAN_SIZE};
+use super::testing::{corrupt_checksum, generate_packet, ReadingSpec, PACKETS_PER_REVOLUTION, PACKET_SIZE};
+
+/// Longest distance a packet can encode (14 bits), in millimeters.
+const MAX_DISTANCE: Float = 0x3FFF as Float;
+
+/// Error code of the readings with nothing in range.
+const NO_ECHO_CODE: u8 = 0x02;
+
+/// ## Summary
+///
+/// What the simulated LIDAR sees: the distance to the nearest obstacle in a direction.
+///
+/// ## Remarks
+///
+/// Angles follow `convention::Convention::Native`: degrees in the direction the LIDAR
+/// spins, 0 along the x axis and 90 along the y axis. Any `Fn(Float) -> Option<Float>`
+/// is an environment.
+///
+pub trait Environment: Send {
+    /// ## Summary
+    ///
+    /// Distance in millimeters to the nearest obstacle at `degrees` (0 to 360), `None`
+    /// if nothing is in range.
+    ///
+    fn distance(&self, degrees: Float) -> Option<Float>;
+}
+
+impl<F: Fn(Float) -> Option<Float> + Send> Environment for F {
+    fn distance(&self, degrees: Float) -> Option<Float> {
+        self(degrees)
+    }
+}
+
+/// ## Summary
+///
+/// A rectangular room without furniture, the LIDAR somewhere inside.
+///
+/// ## Remarks
+///
+/// The room spans 0 to `width` along the x axis and 0 to `depth` along the y axis,
+/// in millimeters.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Room {
+    // Size along the x axis (millimeters).
+    pub width: Float,
+    // Size along the y axis (millimeters).
+    pub depth: Float,
+    // Position of the LIDAR (millimeters).
+    pub x: Float,
+    pub y: Float,
+}
+
+impl Room {
+    /// ## Summary
+    ///
+    /// A room with the LIDAR in its center.
+    ///
+    pub fn new(width: Float, depth: Float) -> Self {
+        Room { width, depth, x: width / 2.0, y: depth / 2.0 }
+    }
+}
+
+impl Environment for Room {
+    fn distance(&self, degrees: Float) -> Option<Float> {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+
+        // Distance to the wall ahead along each axis, the nearest one is hit.
+        let along = |position: Float, size: Float, direction: Float| match direction {
+            d if d > Float::EPSILON => (size - position) / d,
+            d if d < -Float::EPSILON => -position / d,
+            _ => Float::INFINITY,
+        };
+
+        let distance = along(self.x, self.width, cos).min(along(self.y, self.depth, sin));
+
+        if distance.is_finite() && distance >= 0.0 {
+            Some(distance)
+        } else {
+            None
+        }
+    }
+}
+
+/// ## Summary
+///
+/// Settings of a `LidarSimulator`. Rates are probabilities from 0 to 1.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimConfig {
+    // Spin speed reported by the packets, and pace of `SimSource` (RPM).
+    pub rpm: Float,
+    // Signal strength of the readings.
+    pub quality: u16,
+    // Distances are perturbed by up to this many millimeters either way.
+    pub noise: Float,
+    // Obstacles further away are out of range (millimeters).
+    pub max_range: Float,
+    // Probability of a packet failing its checksum.
+    pub checksum_error_rate: f64,
+    // Probability of a reading having the Invalid Data Error flag set.
+    pub invalid_rate: f64,
+    // Probability of a reading having the Signal Strength Warning flag set.
+    pub weak_signal_rate: f64,
+    // Seed of the random generator. The same seed and environment reproduce the same packets.
+    pub seed: u64,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        SimConfig {
+            rpm: 300.0,
+            quality: 200,
+            noise: 0.0,
+            max_range: 6000.0,
+            checksum_error_rate: 0.0,
+            invalid_rate: 0.0,
+            weak_signal_rate: 0.0,
+            seed: 0,
+        }
+    }
+}
+
+/// ## Summary
+///
+/// Generates the packets an XV-11 would send in an environment, to develop and test
+/// without the hardware.
+///
+/// ## Remarks
+///
+/// Packets are valid 22-byte frames, one revolution after the other starting at index 0,
+/// except for the errors injected by the configuration. Iterate the simulator to feed
+/// the packets to a `parser::Parser`, or turn it into a `SimSource` to run the whole
+/// driver on it.
+///
+/// ## Example
+///
+/// ```
+/// use neato_xv11::parser::Parser;
+/// use neato_xv11::sim::{LidarSimulator, Room, SimConfig};
+///
+/// let simulator = LidarSimulator::new(Room::new(4000.0, 3000.0), SimConfig::default());
+/// let mut parser = Parser::new();
+///
+/// let packets: Vec<_> = simulator.take(90).flatten().filter_map(|byte| parser.push(byte)).collect();
+///
+/// // The wall 2 meters ahead.
+/// let packet = packets[0].as_ref().unwrap();
+/// assert_eq!(packet.readings[0].distance, 2000);
+/// assert_eq!(packets.len(), 90);
+/// ```
+pub struct LidarSimulator<E> {
+    environment: E,
+    config: SimConfig,
+    // State of the xorshift random generator.
+    state: u64,
+    // Index of the next packet.
+    index: u8,
+}
+
+impl<E: Environment> LidarSimulator<E> {
+    /// ## Summary
+    ///
+    /// Simulate a LIDAR in an environment.
+    ///
+    pub fn new(environment: E, config: SimConfig) -> Self {
+        // xorshift must not start at 0.
+        let state = config.seed ^ 0x9E37_79B9_7F4A_7C15;
+
+        LidarSimulator { environment, config, state: if state == 0 { 1 } else { state }, index: 0 }
+    }
+
+    /// ## Summary
+    ///
+    /// Generate the next packet.
+    ///
+    pub fn next_packet(&mut self) -> [u8; PACKET_SIZE] {
+        let first = 4 * self.index as usize;
+        let readings = [self.reading(first), self.reading(first + 1), self.reading(first + 2), self.reading(first + 3)];
+        let packet = generate_packet(self.index, self.config.rpm, readings);
+
+        self.index = (self.index + 1) % PACKETS_PER_REVOLUTION as u8;
+
+        if self.chance(self.config.checksum_error_rate) {
+            corrupt_checksum(packet)
+        } else {
+            packet
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// A source sending the packets at the pace of the configured spin speed, for
+    /// `run_from_source` or `LidarDriverBuilder::replay`.
+    ///
+    /// ## Remarks
+    ///
+    /// The source never ends, use `Read::take` to stop after a number of bytes. See
+    /// `SimSource::with_clock` to simulate without waiting.
+    ///
+    // `Float` is `f32` with the `f32` feature.
+    #[allow(clippy::unnecessary_cast)]
+    pub fn into_source(self) -> SimSource<E> {
+        let revolution = 60.0 / self.config.rpm.max(1.0) as f64;
+
+        SimSource {
+            simulator: self,
+            packet: [0; PACKET_SIZE],
+            // Nothing pending, the first read generates a packet.
+            position: PACKET_SIZE,
+            packet_time: Duration::from_secs_f64(revolution / PACKETS_PER_REVOLUTION as f64),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// The reading at a degree.
+    fn reading(&mut self, degree: usize) -> ReadingSpec {
+        if self.chance(self.config.invalid_rate) {
+            return ReadingSpec::Invalid(NO_ECHO_CODE);
+        }
+
+        let noise = self.config.noise * (2.0 * self.uniform() as Float - 1.0);
+        let distance = match self.environment.distance((degree % SCAN_SIZE) as Float) {
+            Some(distance) if distance <= self.config.max_range => (distance + noise).clamp(0.0, MAX_DISTANCE),
+            _ => return ReadingSpec::Invalid(NO_ECHO_CODE),
+        };
+
+        let distance = (distance + 0.5) as u16;
+
+        if self.chance(self.config.weak_signal_rate) {
+            ReadingSpec::Weak(distance, self.config.quality)
+        } else {
+            ReadingSpec::Valid(distance, self.config.quality)
+        }
+    }
+
+    /// Next pseudo-random number.
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// Pseudo-random number from 0 to 1.
+    fn uniform(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Draw an event of the given probability.
+    fn chance(&mut self, rate: f64) -> bool {
+        rate > 0.0 && self.uniform() < rate
+    }
+}
+
+impl<E: Environment> Iterator for LidarSimulator<E> {
+    type Item = [u8; PACKET_SIZE];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_packet())
+    }
+}
+
+/// ## Summary
+///
+/// A pseudo serial port reading the packets of a `LidarSimulator`, see
+/// `LidarSimulator::into_source`.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::thread;
+/// # use std::sync::mpsc::channel;
+/// use neato_xv11::sim::{LidarSimulator, Room, SimConfig};
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// let config = SimConfig { checksum_error_rate: 0.01, noise: 10.0, ..SimConfig::default() };
+/// let source = LidarSimulator::new(Room::new(5000.0, 4000.0), config).into_source();
+///
+/// thread::spawn(move || {
+///     neato_xv11::run_from_source(source, message_tx, command_rx);
+/// });
+/// ```
+pub struct SimSource<E> {
+    simulator: LidarSimulator<E>,
+    // The packet being read.
+    packet: [u8; PACKET_SIZE],
+    // Number of bytes of the packet already read.
+    position: usize,
+    // Time between two packets at the configured spin speed.
+    packet_time: Duration,
+    // Clock slept on between packets.
+    clock: Arc<dyn Clock>,
+}
+
+impl<E: Environment> SimSource<E> {
+    /// ## Summary
+    ///
+    /// Sleep on the given clock between packets, e.g. a `clock::VirtualClock` so the
+    /// simulation runs as fast as possible while the virtual time advances.
+    ///
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+}
+
+impl<E: Environment> Read for SimSource<E> {
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, IoError> {
+        if buffer.is_empty() {
+            return Ok(0);
+        }
+
+        if self.position == PACKET_SIZE {
+            self.clock.sleep(self.packet_time);
+            self.packet = self.simulator.next_packet();
+            self.position = 0;
+        }
+
+        let count = buffer.len().min(PACKET_SIZE - self.position);
+        buffer[..count].copy_from_slice(&self.packet[self.position..self.position + count]);
+        self.position += count;
+
+        Ok(count)
+    }
+}
@@ -0,0 +1,292 @@
+use std::ops::ControlFlow;
+use std::sync::Mutex;
+use std::sync::mpsc::{SendError, Sender};
+
+use super::control::StopToken;
+use super::error::LidarDriverError;
+use super::message::LidarDriverMessage;
+use super::queue::BoundedSender;
+use super::status::Backlog;
+
+/// ## Summary
+///
+/// A destination for messages produced by the LIDAR driver.
+///
+pub trait MessageSink {
+    /// ## Summary
+    ///
+    /// Deliver a message. Returns the message back if the receiving end is gone,
+    /// in which case the driver shuts down.
+    ///
+    #[allow(clippy::result_large_err)]
+    fn send(&self, message: Result<LidarDriverMessage, LidarDriverError>) -> Result<(), SendError<Result<LidarDriverMessage, LidarDriverError>>>;
+
+    /// ## Summary
+    ///
+    /// Number of messages the sink has discarded. Reported in `DriverStatus`.
+    ///
+    fn dropped(&self) -> u64 {
+        0
+    }
+
+    /// ## Summary
+    ///
+    /// Messages queued and waiting for the calling program, if the sink is a bounded
+    /// queue. Reported in `DriverStatus`.
+    ///
+    fn backlog(&self) -> Option<Backlog> {
+        None
+    }
+}
+
+impl<S: MessageSink + ?Sized> MessageSink for Box<S> {
+    fn send(&self, message: Result<LidarDriverMessage, LidarDriverError>) -> Result<(), SendError<Result<LidarDriverMessage, LidarDriverError>>> {
+        (**self).send(message)
+    }
+
+    fn dropped(&self) -> u64 {
+        (**self).dropped()
+    }
+
+    fn backlog(&self) -> Option<Backlog> {
+        (**self).backlog()
+    }
+}
+
+impl MessageSink for Sender<Result<LidarDriverMessage, LidarDriverError>> {
+    fn send(&self, message: Result<LidarDriverMessage, LidarDriverError>) -> Result<(), SendError<Result<LidarDriverMessage, LidarDriverError>>> {
+        Sender::send(self, message)
+    }
+}
+
+#[cfg(feature = "crossbeam")]
+impl MessageSink for crossbeam_channel::Sender<Result<LidarDriverMessage, LidarDriverError>> {
+    fn send(&self, message: Result<LidarDriverMessage, LidarDriverError>) -> Result<(), SendError<Result<LidarDriverMessage, LidarDriverError>>> {
+        crossbeam_channel::Sender::send(self, message).map_err(|err| SendError(err.0))
+    }
+}
+
+/// Waits for room in the channel. The driver runs on its own thread, outside of the runtime.
+#[cfg(feature = "async")]
+impl MessageSink for tokio::sync::mpsc::Sender<Result<LidarDriverMessage, LidarDriverError>> {
+    fn send(&self, message: Result<LidarDriverMessage, LidarDriverError>) -> Result<(), SendError<Result<LidarDriverMessage, LidarDriverError>>> {
+        self.blocking_send(message).map_err(|err| SendError(err.0))
+    }
+}
+
+#[cfg(feature = "async")]
+impl MessageSink for tokio::sync::mpsc::UnboundedSender<Result<LidarDriverMessage, LidarDriverError>> {
+    fn send(&self, message: Result<LidarDriverMessage, LidarDriverError>) -> Result<(), SendError<Result<LidarDriverMessage, LidarDriverError>>> {
+        tokio::sync::mpsc::UnboundedSender::send(self, message).map_err(|err| SendError(err.0))
+    }
+}
+
+impl MessageSink for BoundedSender<Result<LidarDriverMessage, LidarDriverError>> {
+    fn send(&self, message: Result<LidarDriverMessage, LidarDriverError>) -> Result<(), SendError<Result<LidarDriverMessage, LidarDriverError>>> {
+        BoundedSender::send(self, message)
+    }
+
+    fn dropped(&self) -> u64 {
+        BoundedSender::dropped(self)
+    }
+
+    fn backlog(&self) -> Option<Backlog> {
+        Some(Backlog {
+            queued: self.len(),
+            capacity: self.capacity(),
+            blocked_time: self.blocked_time(),
+        })
+    }
+}
+
+/// ## Summary
+///
+/// A consumer of driver messages, such as a file writer, a network publisher or an
+/// in-process handler. Attached to the driver with a `TeeSink`.
+///
+pub trait LidarSink {
+    /// ## Summary
+    ///
+    /// Consume a message.
+    ///
+    fn consume(&mut self, message: &LidarDriverMessage);
+}
+
+impl<F: FnMut(&LidarDriverMessage)> LidarSink for F {
+    fn consume(&mut self, message: &LidarDriverMessage) {
+        self(message)
+    }
+}
+
+/// ## Summary
+///
+/// A `LidarSink` handing every message to several sinks, in the order they were added.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::thread;
+/// # use std::sync::mpsc::channel;
+/// use neato_xv11::prelude::*;
+/// use neato_xv11::sink::{FanOut, TeeSink};
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// let sinks = FanOut::new()
+///     .with(|message: &LidarDriverMessage| println!("{:?}", message))
+///     .with(|message: &LidarDriverMessage| {
+///         if let LidarDriverMessage::Packet(packet) = message {
+///             // Publish the packet.
+///         }
+///     });
+///
+/// let message_tx = TeeSink::new(message_tx, sinks);
+///
+/// thread::spawn(move || {
+///     neato_xv11::run("/dev/serial0", message_tx, command_rx);
+/// });
+/// ```
+#[derive(Default)]
+pub struct FanOut {
+    sinks: Vec<Box<dyn LidarSink + Send>>,
+}
+
+impl FanOut {
+    /// ## Summary
+    ///
+    /// Initialize an empty fan-out.
+    ///
+    pub fn new() -> Self {
+        FanOut::default()
+    }
+
+    /// ## Summary
+    ///
+    /// Add a sink.
+    ///
+    pub fn with<L: LidarSink + Send + 'static>(mut self, sink: L) -> Self {
+        self.push(sink);
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Add a sink.
+    ///
+    pub fn push<L: LidarSink + Send + 'static>(&mut self, sink: L) {
+        self.sinks.push(Box::new(sink));
+    }
+
+    /// ## Summary
+    ///
+    /// Number of sinks.
+    ///
+    pub fn len(&self) -> usize {
+        self.sinks.len()
+    }
+
+    /// ## Summary
+    ///
+    /// Whether there is no sink.
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+}
+
+impl LidarSink for FanOut {
+    fn consume(&mut self, message: &LidarDriverMessage) {
+        for sink in &mut self.sinks {
+            sink.consume(message);
+        }
+    }
+}
+
+/// ## Summary
+///
+/// A message sink that hands every message to a `LidarSink` before forwarding it to
+/// an inner sink. Errors are only forwarded.
+///
+pub struct TeeSink<S, L> {
+    inner: S,
+    sink: Mutex<L>,
+}
+
+impl<S: MessageSink, L: LidarSink> TeeSink<S, L> {
+    /// ## Summary
+    ///
+    /// Attach a `LidarSink` to a sink.
+    ///
+    pub fn new(inner: S, sink: L) -> Self {
+        TeeSink {
+            inner,
+            sink: Mutex::new(sink),
+        }
+    }
+}
+
+impl<S: MessageSink, L: LidarSink> MessageSink for TeeSink<S, L> {
+    fn send(&self, message: Result<LidarDriverMessage, LidarDriverError>) -> Result<(), SendError<Result<LidarDriverMessage, LidarDriverError>>> {
+        if let Ok(message) = &message {
+            self.sink.lock().unwrap_or_else(|e| e.into_inner()).consume(message);
+        }
+
+        self.inner.send(message)
+    }
+
+    fn dropped(&self) -> u64 {
+        self.inner.dropped()
+    }
+
+    fn backlog(&self) -> Option<Backlog> {
+        self.inner.backlog()
+    }
+}
+
+/// ## Summary
+///
+/// A message sink calling a closure on the driver thread. The driver stops once the
+/// closure returns `ControlFlow::Break`, see `run_with_callback`.
+///
+pub(crate) struct CallbackSink<F> {
+    callback: Mutex<F>,
+    // Stops the driver once the callback breaks.
+    stop: StopToken,
+}
+
+impl<F> CallbackSink<F>
+where
+    F: FnMut(Result<LidarDriverMessage, LidarDriverError>) -> ControlFlow<()>,
+{
+    /// ## Summary
+    ///
+    /// Call `callback` with every message, until it breaks.
+    ///
+    pub(crate) fn new(callback: F, stop: StopToken) -> Self {
+        CallbackSink {
+            callback: Mutex::new(callback),
+            stop,
+        }
+    }
+}
+
+impl<F> MessageSink for CallbackSink<F>
+where
+    F: FnMut(Result<LidarDriverMessage, LidarDriverError>) -> ControlFlow<()>,
+{
+    fn send(&self, message: Result<LidarDriverMessage, LidarDriverError>) -> Result<(), SendError<Result<LidarDriverMessage, LidarDriverError>>> {
+        // The callback is not called again once it broke.
+        if self.stop.is_stopped() {
+            return Err(SendError(message));
+        }
+
+        let mut callback = self.callback.lock().unwrap_or_else(|e| e.into_inner());
+
+        if callback(message).is_break() {
+            self.stop.stop();
+        }
+
+        Ok(())
+    }
+}
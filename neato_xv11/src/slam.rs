@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+use std::io::Error as IoError;
+use std::sync::Mutex;
+use std::sync::mpsc::{channel, sync_channel, Receiver, SendError, Sender, SyncSender};
+use std::thread;
+use std::time::Instant;
+
+use super::data::{Float, LidarScan};
+use super::filter::ScanFilter;
+use super::imu::{deskew, ImuInput, ImuSample, ImuSummary};
+use super::matching::{CorrelativeMatcher, Transform};
+use super::mounting::MountingConfig;
+use super::prelude::*;
+use super::scan::ScanAssembler;
+
+/// Number of scans between two keyframes kept for loop closure.
+const KEYFRAME_INTERVAL: u64 = 10;
+/// Smallest number of scans between a scan and a keyframe it can close a loop with.
+const MIN_LOOP_SEPARATION: u64 = 50;
+/// Number of scans waiting for the loop closure worker before new ones are skipped.
+const LOOP_CLOSURE_QUEUE_SIZE: usize = 2;
+
+/// ## Summary
+///
+/// A robot pose in the map frame.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Pose {
+    // Position along the x axis in millimeters.
+    pub x: Float,
+    // Position along the y axis in millimeters.
+    pub y: Float,
+    // Heading in degrees, counterclockwise.
+    pub theta: Float,
+}
+
+/// ## Summary
+///
+/// An assembled scan stamped with when it completed and where the robot was.
+///
+#[derive(Clone, Debug)]
+pub struct StampedScan {
+    // Sequence number, starting at 0.
+    pub sequence: u64,
+    // When the scan completed.
+    pub timestamp: Instant,
+    // Pose reported by the pose provider (e.g. wheel odometry), if any.
+    pub pose: Option<Pose>,
+    // Motion of the robot during the scan, if an IMU is configured and sent samples.
+    pub imu: Option<ImuSummary>,
+    // Whether the platform was tilted beyond the configured threshold during the scan.
+    pub is_tilted: bool,
+    // The scan, after the configured filter.
+    pub scan: LidarScan,
+}
+
+/// ## Summary
+///
+/// A loop closure: a scan re-observing the place of an earlier keyframe.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LoopClosure {
+    // Sequence number of the earlier keyframe.
+    pub from: u64,
+    // Sequence number of the current scan.
+    pub to: u64,
+    // Transform aligning the current scan with the keyframe.
+    pub transform: Transform,
+    // Score of the match (0 to 1).
+    pub score: Float,
+}
+
+/// ## Summary
+///
+/// A SLAM front-end fed with the driver's assembled scans.
+///
+/// ## Remarks
+///
+/// Implement this trait to plug an external SLAM crate into the driver through a
+/// `ScanConsumerSink`, without converting packets to scans yourself.
+///
+pub trait ScanConsumer {
+    /// ## Summary
+    ///
+    /// Called for every assembled scan.
+    ///
+    fn on_scan(&mut self, scan: &StampedScan);
+
+    /// ## Summary
+    ///
+    /// Called when a scan matches an earlier keyframe. Does nothing by default.
+    ///
+    fn on_loop_closure(&mut self, _closure: &LoopClosure) {}
+}
+
+/// Provides the robot pose at a given time, e.g. from wheel odometry.
+type PoseProvider = Box<dyn FnMut(Instant) -> Option<Pose> + Send>;
+
+/// A scan to check for a loop closure: sequence number, pose and scan.
+type Keyframe = (u64, Pose, LidarScan);
+
+/// Loop closure detection settings and keyframes, owned by the worker thread.
+struct LoopClosureDetector {
+    // Matches scans against keyframes.
+    matcher: CorrelativeMatcher,
+    // Largest distance in millimeters between the poses of a scan and a keyframe,
+    // also the size of the keyframe grid cells.
+    radius: Float,
+    // Smallest match score accepted as a loop closure.
+    min_score: Float,
+    // Latest keyframe of each grid cell, so their number is bounded by the mapped area.
+    keyframes: HashMap<(i64, i64), Keyframe>,
+}
+
+impl LoopClosureDetector {
+    /// Grid cell of a pose.
+    fn cell(&self, pose: &Pose) -> (i64, i64) {
+        ((pose.x / self.radius).floor() as i64, (pose.y / self.radius).floor() as i64)
+    }
+
+    /// Look for a loop closure with an earlier keyframe, then keep the scan as a keyframe
+    /// if due, evicting the previous keyframe of its cell.
+    fn push(&mut self, (sequence, pose, scan): Keyframe) -> Option<LoopClosure> {
+        let (column, row) = self.cell(&pose);
+
+        // Keyframes within the radius are in the neighbouring cells.
+        let closure = (column - 1..=column + 1)
+            .flat_map(|column| (row - 1..=row + 1).map(move |row| (column, row)))
+            .filter_map(|cell| self.keyframes.get(&cell))
+            .filter(|(keyframe, _, _)| sequence >= keyframe + MIN_LOOP_SEPARATION)
+            .filter(|(_, keyframe, _)| (keyframe.x - pose.x).hypot(keyframe.y - pose.y) <= self.radius)
+            .filter_map(|(keyframe, _, reference)| {
+                self.matcher.match_scans(reference, &scan).map(|found| LoopClosure {
+                    from: *keyframe,
+                    to: sequence,
+                    transform: found.transform,
+                    score: found.score,
+                })
+            })
+            .filter(|closure| closure.score >= self.min_score)
+            .max_by(|a, b| a.score.total_cmp(&b.score));
+
+        if sequence.is_multiple_of(KEYFRAME_INTERVAL) {
+            self.keyframes.insert((column, row), (sequence, pose, scan));
+        }
+
+        closure
+    }
+}
+
+/// Queues of the loop closure worker thread.
+struct LoopClosureWorker {
+    // Sends the pose-stamped scans to check.
+    tx: SyncSender<Keyframe>,
+    // Receives the loop closures found.
+    rx: Receiver<LoopClosure>,
+}
+
+/// Check the scans for loop closures until the sink is dropped.
+fn detect_loop_closures(mut detector: LoopClosureDetector, rx: Receiver<Keyframe>, tx: Sender<LoopClosure>) {
+    for keyframe in rx.iter() {
+        if let Some(closure) = detector.push(keyframe) {
+            if tx.send(closure).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// IMU input and tilt threshold.
+struct Imu {
+    input: ImuInput,
+    // Largest roll or pitch in degrees before a scan is flagged as tilted.
+    max_tilt: Float,
+}
+
+/// State of a `ScanConsumerSink`.
+struct State<C> {
+    consumer: C,
+    assembler: ScanAssembler,
+    filter: Option<Box<dyn ScanFilter + Send>>,
+    pose: Option<PoseProvider>,
+    imu: Option<Imu>,
+    mounting: Option<MountingConfig>,
+    loop_closure: Option<LoopClosureWorker>,
+    sequence: u64,
+}
+
+/// ## Summary
+///
+/// A message sink that assembles scans and hands them to a `ScanConsumer`.
+/// Every message is forwarded to the inner sink.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::thread;
+/// # use std::sync::mpsc::channel;
+/// use neato_xv11::filter::KalmanFilter;
+/// use neato_xv11::slam::{ScanConsumer, ScanConsumerSink, StampedScan};
+///
+/// struct Mapper;
+///
+/// impl ScanConsumer for Mapper {
+///     fn on_scan(&mut self, scan: &StampedScan) {
+///         println!("Scan {} at {:?}", scan.sequence, scan.pose);
+///     }
+/// }
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// let message_tx = ScanConsumerSink::new(message_tx, Mapper)
+///     .with_filter(KalmanFilter::new(4.0, 100.0));
+///
+/// thread::spawn(move || {
+///     neato_xv11::run("/dev/serial0", message_tx, command_rx);
+/// });
+/// ```
+pub struct ScanConsumerSink<S, C> {
+    inner: S,
+    state: Mutex<State<C>>,
+}
+
+impl<S: MessageSink, C: ScanConsumer> ScanConsumerSink<S, C> {
+    /// ## Summary
+    ///
+    /// Wrap a sink with a scan consumer.
+    ///
+    pub fn new(inner: S, consumer: C) -> Self {
+        ScanConsumerSink {
+            inner,
+            state: Mutex::new(State {
+                consumer,
+                assembler: ScanAssembler::new(),
+                filter: None,
+                pose: None,
+                imu: None,
+                mounting: None,
+                loop_closure: None,
+                sequence: 0,
+            }),
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Filter scans before handing them to the consumer.
+    ///
+    pub fn with_filter<F: ScanFilter + Send + 'static>(self, filter: F) -> Self {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).filter = Some(Box::new(filter));
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Stamp scans with the pose returned by a provider (e.g. wheel odometry).
+    ///
+    pub fn with_pose<P: FnMut(Instant) -> Option<Pose> + Send + 'static>(self, provider: P) -> Self {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).pose = Some(Box::new(provider));
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Use IMU samples to deskew scans and flag scans taken while the platform was tilted.
+    ///
+    /// ## Parameters
+    ///
+    /// rx: Receives the IMU samples.
+    ///
+    /// max_tilt: Largest roll or pitch in degrees before a scan is flagged as tilted.
+    ///
+    /// ## Remarks
+    ///
+    /// The average yaw rate over a scan corrects the rotation of the robot during the
+    /// revolution, before the filter is applied. Handheld and rough terrain platforms
+    /// otherwise produce smeared maps.
+    ///
+    pub fn with_imu(self, rx: Receiver<ImuSample>, max_tilt: Float) -> Self {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).imu = Some(Imu {
+            input: ImuInput::new(rx),
+            max_tilt,
+        });
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Hand scans to the consumer expressed in the robot base frame rather than the LIDAR frame.
+    ///
+    /// ## Remarks
+    ///
+    /// The transform is applied after deskewing and before the filter.
+    ///
+    pub fn with_base_frame(self, mounting: MountingConfig) -> Self {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).mounting = Some(mounting);
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Detect loop closures between pose-stamped scans and earlier keyframes.
+    ///
+    /// ## Parameters
+    ///
+    /// matcher: Matches scans against keyframes.
+    ///
+    /// radius: Largest distance in millimeters between the poses of a scan and a keyframe.
+    ///
+    /// min_score: Smallest match score (0 to 1) accepted as a loop closure.
+    ///
+    /// ## Remarks
+    ///
+    /// Scans are matched on a worker thread, so the driver thread never waits for the
+    /// matcher. Scans arriving while the worker is busy are not checked, and loop
+    /// closures are handed to the consumer with the next message. One keyframe is kept
+    /// per `radius` sized cell of the map, the latest replacing the previous one, so
+    /// memory grows with the mapped area rather than with time.
+    ///
+    /// Returns an error if the worker thread cannot be spawned.
+    ///
+    pub fn with_loop_closure(self, matcher: CorrelativeMatcher, radius: Float, min_score: Float) -> Result<Self, IoError> {
+        let detector = LoopClosureDetector {
+            matcher,
+            radius: radius.max(1.0),
+            min_score,
+            keyframes: HashMap::new(),
+        };
+
+        let (scan_tx, scan_rx) = sync_channel(LOOP_CLOSURE_QUEUE_SIZE);
+        let (closure_tx, closure_rx) = channel();
+
+        thread::Builder::new()
+            .name("neato-xv11-loop-closure".into())
+            .spawn(move || detect_loop_closures(detector, scan_rx, closure_tx))?;
+
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).loop_closure = Some(LoopClosureWorker { tx: scan_tx, rx: closure_rx });
+        Ok(self)
+    }
+}
+
+impl<S: MessageSink, C: ScanConsumer> MessageSink for ScanConsumerSink<S, C> {
+    fn send(&self, message: Result<LidarDriverMessage, LidarDriverError>) -> Result<(), SendError<Result<LidarDriverMessage, LidarDriverError>>> {
+        let mut guard = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let state = &mut *guard;
+
+        if let Ok(LidarDriverMessage::Packet(packet)) = &message {
+            if let Some(mut scan) = state.assembler.push(packet.clone()) {
+                let timestamp = Instant::now();
+                let imu = state.imu.as_mut().and_then(|imu| imu.input.summarize(&scan, timestamp).map(|summary| (summary, imu.max_tilt)));
+
+                if let Some((summary, _)) = imu {
+                    deskew(&mut scan, summary.yaw_rate);
+                }
+
+                if let Some(mounting) = &state.mounting {
+                    scan = mounting.to_base_scan(&scan);
+                }
+
+                if let Some(filter) = &mut state.filter {
+                    filter.apply(&mut scan);
+                }
+
+                let stamped = StampedScan {
+                    sequence: state.sequence,
+                    timestamp,
+                    pose: state.pose.as_mut().and_then(|provider| provider(timestamp)),
+                    imu: imu.map(|(summary, _)| summary),
+                    is_tilted: imu.is_some_and(|(summary, max_tilt)| summary.max_tilt > max_tilt),
+                    scan,
+                };
+                state.sequence += 1;
+
+                state.consumer.on_scan(&stamped);
+
+                if let (Some(worker), Some(pose)) = (&state.loop_closure, stamped.pose) {
+                    // Skipped if the worker is busy.
+                    let _ = worker.tx.try_send((stamped.sequence, pose, stamped.scan));
+                }
+            }
+        }
+
+        if let Some(worker) = &state.loop_closure {
+            for closure in worker.rx.try_iter() {
+                state.consumer.on_loop_closure(&closure);
+            }
+        }
+
+        drop(guard);
+        self.inner.send(message)
+    }
+
+    fn dropped(&self) -> u64 {
+        self.inner.dropped()
+    }
+
+    fn backlog(&self) -> Option<Backlog> {
+        self.inner.backlog()
+    }
+}
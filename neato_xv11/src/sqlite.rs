@@ -0,0 +1,175 @@
+use std::io::Error as IoError;
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::mpsc::SendError;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "log")]
+use log::warn;
+use rusqlite::{params, Connection};
+
+use super::data::LidarScan;
+use super::prelude::*;
+use super::scan::ScanAssembler;
+
+/// Tables of the database.
+const SCHEMA: &str = "
+    PRAGMA journal_mode = WAL;
+    CREATE TABLE IF NOT EXISTS scans (
+        id INTEGER PRIMARY KEY,
+        timestamp INTEGER NOT NULL,
+        revolution INTEGER NOT NULL,
+        rpm REAL NOT NULL,
+        missing INTEGER NOT NULL,
+        distances BLOB NOT NULL,
+        qualities BLOB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS events (
+        id INTEGER PRIMARY KEY,
+        timestamp INTEGER NOT NULL,
+        kind TEXT NOT NULL,
+        message TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS scans_timestamp ON scans (timestamp);
+    CREATE INDEX IF NOT EXISTS events_timestamp ON events (timestamp);
+";
+
+/// State of a `SqliteSink`.
+struct State {
+    connection: Connection,
+    assembler: ScanAssembler,
+}
+
+/// ## Summary
+///
+/// A message sink that appends assembled scans and driver events to a SQLite
+/// database. Every message is forwarded to the inner sink.
+///
+/// ## Remarks
+///
+/// The database is opened in WAL mode, so other processes can query it while the
+/// driver writes. Two tables are filled, with timestamps in nanoseconds since the
+/// Unix epoch:
+///
+/// - `scans`: `timestamp`, `revolution`, `rpm`, `missing`, and `distances` and
+///   `qualities` as 360 little endian `i32`, indexed by degree (0 where missing).
+/// - `events`: `timestamp`, `kind` (`error`, `warning`, `change`, `info`,
+///   `device_info` or `shutdown`) and `message`.
+///
+/// Logging is best effort: a failed write is skipped and the message still forwarded.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::thread;
+/// # use std::sync::mpsc::channel;
+/// use neato_xv11::sqlite::SqliteSink;
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// let message_tx = SqliteSink::open(message_tx, "lidar.db").unwrap();
+///
+/// thread::spawn(move || {
+///     neato_xv11::run("/dev/serial0", message_tx, command_rx);
+/// });
+/// ```
+///
+/// ```sql
+/// SELECT datetime(timestamp / 1e9, 'unixepoch'), kind, message FROM events WHERE kind = 'error';
+/// ```
+pub struct SqliteSink<S> {
+    inner: S,
+    state: Mutex<State>,
+}
+
+impl<S: MessageSink> SqliteSink<S> {
+    /// ## Summary
+    ///
+    /// Open or create a database and wrap a sink with it.
+    ///
+    pub fn open<P: AsRef<Path>>(inner: S, path: P) -> Result<Self, IoError> {
+        let connection = Connection::open(path).map_err(IoError::other)?;
+        connection.execute_batch(SCHEMA).map_err(IoError::other)?;
+
+        Ok(SqliteSink {
+            inner,
+            state: Mutex::new(State {
+                connection,
+                assembler: ScanAssembler::new(),
+            }),
+        })
+    }
+}
+
+impl State {
+    /// Append the scan completed by a packet, or an event.
+    fn log(&mut self, message: &Result<LidarDriverMessage, LidarDriverError>) -> Result<(), rusqlite::Error> {
+        let event = match message {
+            Ok(LidarDriverMessage::Packet(packet)) => {
+                return match self.assembler.push(packet.clone()) {
+                    Some(scan) => self.insert_scan(&scan),
+                    None => Ok(()),
+                };
+            },
+            Err(err) => ("error", err.to_string()),
+            Ok(LidarDriverMessage::Warning(warning)) => ("warning", format!("{:?}", warning)),
+            Ok(LidarDriverMessage::ChangeDetected { sector, magnitude }) => ("change", format!("Sector {} changed by {} mm", sector, magnitude)),
+            Ok(LidarDriverMessage::Info(info)) => ("info", format!("{:?}", info)),
+            Ok(LidarDriverMessage::DeviceInfo(info)) => ("device_info", format!("{:?}", info)),
+            Ok(LidarDriverMessage::Shutdown) => ("shutdown", String::new()),
+            Ok(_) => return Ok(()),
+        };
+
+        self.connection
+            .execute("INSERT INTO events (timestamp, kind, message) VALUES (?1, ?2, ?3)", params![now(), event.0, event.1])
+            .map(|_| ())
+    }
+
+    /// Append a scan.
+    fn insert_scan(&mut self, scan: &LidarScan) -> Result<(), rusqlite::Error> {
+        let mut distances = Vec::with_capacity(scan.readings.len() * 4);
+        let mut qualities = Vec::with_capacity(scan.readings.len() * 4);
+
+        for slot in scan.readings.iter() {
+            let (distance, quality) = slot.as_ref().map_or((0, 0), |reading| (reading.distance, reading.quality));
+            distances.extend_from_slice(&distance.to_le_bytes());
+            qualities.extend_from_slice(&quality.to_le_bytes());
+        }
+
+        self.connection
+            .execute(
+                "INSERT INTO scans (timestamp, revolution, rpm, missing, distances, qualities) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![now(), scan.revolution as i64, scan.speed, scan.missing() as i64, distances, qualities],
+            )
+            .map(|_| ())
+    }
+}
+
+/// Nanoseconds since the Unix epoch.
+fn now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_nanos() as i64)
+}
+
+impl<S: MessageSink> MessageSink for SqliteSink<S> {
+    #[cfg_attr(not(feature = "log"), allow(unused_variables))]
+    fn send(&self, message: Result<LidarDriverMessage, LidarDriverError>) -> Result<(), SendError<Result<LidarDriverMessage, LidarDriverError>>> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Err(err) = state.log(&message) {
+            #[cfg(feature = "log")]
+            warn!("Unable to write to the SQLite log. {}", err);
+        }
+
+        drop(state);
+        self.inner.send(message)
+    }
+
+    fn dropped(&self) -> u64 {
+        self.inner.dropped()
+    }
+
+    fn backlog(&self) -> Option<Backlog> {
+        self.inner.backlog()
+    }
+}
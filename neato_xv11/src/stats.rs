@@ -0,0 +1,178 @@
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+use super::data::{Float, LidarScan, SCAN_SIZE};
+
+/// ## Summary
+///
+/// Distribution of the quality (signal strength) of the valid readings of a scan.
+///
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct QualityStats {
+    // Number of valid readings the statistics are computed over.
+    pub count: usize,
+    // Lowest quality.
+    pub min: i32,
+    // 10th percentile.
+    pub p10: i32,
+    // Median quality.
+    pub p50: i32,
+    // 90th percentile.
+    pub p90: i32,
+    // Highest quality.
+    pub max: i32,
+    // Mean quality.
+    pub mean: Float,
+    // Fraction (0-1) of the valid readings with a quality below the threshold.
+    pub below_threshold: Float,
+}
+
+impl LidarScan {
+    /// ## Summary
+    ///
+    /// Compute the quality distribution of the valid readings.
+    ///
+    /// ## Parameters
+    ///
+    /// threshold: Quality under which a reading counts in `below_threshold`.
+    ///
+    /// ## Remarks
+    ///
+    /// Returns `None` if the scan has no valid reading. Percentiles use the nearest rank.
+    ///
+    pub fn quality_stats(&self, threshold: i32) -> Option<QualityStats> {
+        let mut qualities = [0; SCAN_SIZE];
+        let mut count = 0;
+
+        for reading in self.valid_readings().take(SCAN_SIZE) {
+            qualities[count] = reading.quality;
+            count += 1;
+        }
+
+        if count == 0 {
+            return None;
+        }
+
+        let qualities = &mut qualities[..count];
+        qualities.sort_unstable();
+
+        let percentile = |p: usize| qualities[(p * (count - 1) + 50) / 100];
+        let sum: i64 = qualities.iter().map(|&q| q as i64).sum();
+        let below = qualities.iter().filter(|&&q| q < threshold).count();
+
+        Some(QualityStats {
+            count,
+            min: qualities[0],
+            p10: percentile(10),
+            p50: percentile(50),
+            p90: percentile(90),
+            max: qualities[count - 1],
+            mean: sum as Float / count as Float,
+            below_threshold: below as Float / count as Float,
+        })
+    }
+}
+
+/// ## Summary
+///
+/// Exponentially weighted estimate of the quality across scans, to follow slow changes
+/// such as a dirty lens or ambient IR.
+///
+/// ## Example
+///
+/// ```no_run
+/// # fn track(scans: &[neato_xv11::data::LidarScan]) {
+/// use neato_xv11::stats::RollingQuality;
+///
+/// let mut rolling = RollingQuality::new(0.05);
+///
+/// for scan in scans {
+///     if let Some(stats) = scan.quality_stats(20) {
+///         rolling.push(&stats);
+///     }
+/// }
+///
+/// println!("Median quality {:.1}, {:.0}% weak", rolling.median(), 100.0 * rolling.below_threshold());
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct RollingQuality {
+    // Weight of the newest scan (0-1].
+    alpha: Float,
+    // Number of scans pushed.
+    scans: u64,
+    // Rolling mean quality.
+    mean: Float,
+    // Rolling median quality.
+    median: Float,
+    // Rolling fraction of readings below the threshold.
+    below_threshold: Float,
+}
+
+impl RollingQuality {
+    /// ## Summary
+    ///
+    /// Initialize a new rolling estimate.
+    ///
+    /// ## Parameters
+    ///
+    /// alpha: Weight of the newest scan, in (0, 1]. Lower values follow changes more slowly.
+    ///
+    pub fn new(alpha: Float) -> Self {
+        RollingQuality {
+            alpha: alpha.clamp(Float::EPSILON, 1.0),
+            scans: 0,
+            mean: 0.0,
+            median: 0.0,
+            below_threshold: 0.0,
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Update the estimate with the statistics of a scan.
+    ///
+    pub fn push(&mut self, stats: &QualityStats) {
+        // The first scan initializes the estimate.
+        let alpha = if self.scans == 0 { 1.0 } else { self.alpha };
+
+        self.mean += alpha * (stats.mean - self.mean);
+        self.median += alpha * (stats.p50 as Float - self.median);
+        self.below_threshold += alpha * (stats.below_threshold - self.below_threshold);
+        self.scans += 1;
+    }
+
+    /// ## Summary
+    ///
+    /// Number of scans pushed.
+    ///
+    pub fn scans(&self) -> u64 {
+        self.scans
+    }
+
+    /// ## Summary
+    ///
+    /// Rolling mean quality.
+    ///
+    pub fn mean(&self) -> Float {
+        self.mean
+    }
+
+    /// ## Summary
+    ///
+    /// Rolling median quality.
+    ///
+    pub fn median(&self) -> Float {
+        self.median
+    }
+
+    /// ## Summary
+    ///
+    /// Rolling fraction (0-1) of the readings below the threshold.
+    ///
+    pub fn below_threshold(&self) -> Float {
+        self.below_threshold
+    }
+}
@@ -0,0 +1,179 @@
+use core::time::Duration;
+
+use super::data::Float;
+#[cfg(feature = "std")]
+use super::data::LidarPacket;
+#[cfg(feature = "std")]
+use super::error::{ErrorClass, LidarDriverError};
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+/// ## Summary
+///
+/// A snapshot of the driver state, sent in response to `LidarDriverCommand::QueryStatus`.
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct DriverStatus {
+    // Whether the driver is paused.
+    pub is_paused: bool,
+    // Number of messages discarded by the message sink (e.g. a drop-oldest queue).
+    pub dropped_messages: u64,
+    // Number of bytes read from the serial port but discarded because the parser fell behind.
+    pub overrun_bytes: u64,
+    // Mean time from reading the last bytes of a packet to sending it, since the previous status.
+    // `None` if no packet was sent.
+    pub send_latency: Option<Duration>,
+    // Longest time from reading the last bytes of a packet to sending it, since the previous status.
+    pub max_send_latency: Option<Duration>,
+    // Round trip of the last `LidarDriverMessage::Echo` through the calling program, see `LidarDriverCommand::Echo`.
+    pub echo_latency: Option<Duration>,
+    // Messages waiting for the calling program. `None` unless the sink is a bounded queue.
+    pub backlog: Option<Backlog>,
+    // Spin speed set with `LidarDriverCommand::SetTargetRpm`, if any (RPM).
+    pub target_rpm: Option<Float>,
+}
+
+/// ## Summary
+///
+/// The messages waiting in a bounded queue (see `queue::bounded`), to notice a calling
+/// program falling behind before messages are dropped or the driver blocks for too long.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Backlog {
+    // Number of messages queued.
+    pub queued: usize,
+    // Maximum number of queued messages.
+    pub capacity: usize,
+    // Total time the driver spent waiting for room with `OverflowPolicy::Block`.
+    pub blocked_time: Duration,
+}
+
+/// ## Summary
+///
+/// Errors counted over a window, sent by `summary::ErrorSummarySink` to monitor trends
+/// without handling every error.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct ErrorSummary {
+    // Time covered by the summary.
+    pub window: Duration,
+    // Number of `LidarDriverError::Checksum`.
+    pub checksum_errors: u64,
+    // Number of `LidarDriverError::ResyncRequired`.
+    pub resyncs: u64,
+    // Number of `LidarDriverError::ReadTimeout`, `LidarDriverError::NoData`, `LidarDriverError::SyncTimeout`
+    // and `LidarDriverError::MotorStalled`.
+    pub timeouts: u64,
+    // Number of the other errors.
+    pub other_errors: u64,
+    // Number of messages discarded by the message sink.
+    pub dropped_msgs: u64,
+}
+
+#[cfg(feature = "std")]
+impl ErrorSummary {
+    /// Count an error by kind. Returns whether it is a checksum error, resync or timeout.
+    pub(crate) fn count(&mut self, error: &LidarDriverError) -> bool {
+        match ErrorClass::of(error) {
+            Some(ErrorClass::Checksum) => self.checksum_errors += 1,
+            Some(ErrorClass::Resync) => self.resyncs += 1,
+            Some(ErrorClass::Timeout) => self.timeouts += 1,
+            None => {
+                self.other_errors += 1;
+                return false;
+            },
+        }
+
+        true
+    }
+}
+
+/// ## Summary
+///
+/// Link quality since the previous statistics, sent in response to
+/// `LidarDriverCommand::QueryStats`.
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct DriverStats {
+    // Time covered by the statistics.
+    pub window: Duration,
+    // Number of valid packets.
+    pub packets: u64,
+    // Valid packets per second over the window.
+    pub packets_per_second: Float,
+    // Number of `LidarDriverError::Checksum`.
+    pub checksum_errors: u64,
+    // Fraction (0-1) of the frames failing their checksum.
+    pub checksum_error_rate: Float,
+    // Number of `LidarDriverError::ResyncRequired`.
+    pub resyncs: u64,
+    // Mean spin speed reported by the packets (RPM). `None` without packets.
+    pub mean_rpm: Option<Float>,
+    // Lowest spin speed reported by a packet (RPM).
+    pub min_rpm: Option<Float>,
+    // Highest spin speed reported by a packet (RPM).
+    pub max_rpm: Option<Float>,
+    // Fraction (0-1) of the readings of the valid packets flagged invalid.
+    pub invalid_reading_ratio: Float,
+}
+
+/// Counts the packets and errors parsed for `DriverStats`.
+#[cfg(feature = "std")]
+#[derive(Default)]
+pub(crate) struct StatsAccumulator {
+    packets: u64,
+    // Errors counted by kind, like `summary::ErrorSummarySink`.
+    errors: ErrorSummary,
+    // Readings of the valid packets, and how many of them are invalid.
+    readings: u64,
+    invalid_readings: u64,
+    // Sum, lowest and highest of the packet speeds.
+    speed_sum: Float,
+    min_rpm: Option<Float>,
+    max_rpm: Option<Float>,
+}
+
+#[cfg(feature = "std")]
+impl StatsAccumulator {
+    /// Count a result of the parser.
+    pub(crate) fn push(&mut self, result: &Result<LidarPacket, LidarDriverError>) {
+        match result {
+            Ok(packet) => {
+                self.packets += 1;
+                self.readings += packet.readings.len() as u64;
+                self.invalid_readings += packet.readings.iter().filter(|reading| !reading.is_valid()).count() as u64;
+                self.speed_sum += packet.speed;
+                self.min_rpm = Some(self.min_rpm.map_or(packet.speed, |min| min.min(packet.speed)));
+                self.max_rpm = Some(self.max_rpm.map_or(packet.speed, |max| max.max(packet.speed)));
+            },
+            Err(err) => {
+                self.errors.count(err);
+            },
+        }
+    }
+
+    /// The statistics over the window, counting anew.
+    pub(crate) fn take(&mut self, window: Duration) -> DriverStats {
+        let stats = core::mem::take(self);
+        let ratio = |count: u64, total: u64| if total == 0 { 0.0 } else { count as Float / total as Float };
+        let seconds = window.as_secs_f64() as Float;
+
+        DriverStats {
+            window,
+            packets: stats.packets,
+            packets_per_second: if seconds > 0.0 { stats.packets as Float / seconds } else { 0.0 },
+            checksum_errors: stats.errors.checksum_errors,
+            checksum_error_rate: ratio(stats.errors.checksum_errors, stats.packets + stats.errors.checksum_errors),
+            resyncs: stats.errors.resyncs,
+            mean_rpm: if stats.packets == 0 { None } else { Some(stats.speed_sum / stats.packets as Float) },
+            min_rpm: stats.min_rpm,
+            max_rpm: stats.max_rpm,
+            invalid_reading_ratio: ratio(stats.invalid_readings, stats.readings),
+        }
+    }
+}
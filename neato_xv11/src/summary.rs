@@ -0,0 +1,148 @@
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::SendError;
+use std::time::{Duration, Instant};
+
+use super::clock::{Clock, SystemClock};
+use super::prelude::*;
+use super::status::ErrorSummary;
+
+/// State of an `ErrorSummarySink`.
+struct Window {
+    // When the window started, `None` before the first message.
+    start: Option<Instant>,
+    // Errors counted since the window started.
+    summary: ErrorSummary,
+    // Messages discarded by the inner sink when the window started.
+    dropped: u64,
+}
+
+/// ## Summary
+///
+/// A message sink that sends a `LidarDriverMessage::ErrorSummary` at the end of every
+/// window, counting the errors by kind.
+///
+/// ## Remarks
+///
+/// Summaries are sent when a message arrives after the window elapsed, so `window`
+/// may be a little longer than configured. A window without errors is summarized too,
+/// a supervisor can tell a quiet driver from a stopped one. With `suppress_errors`,
+/// checksum errors, resyncs and timeouts are only counted, not forwarded; the other
+/// errors are always forwarded.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::thread;
+/// # use std::time::Duration;
+/// # use std::sync::mpsc::channel;
+/// use neato_xv11::prelude::*;
+/// use neato_xv11::summary::ErrorSummarySink;
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// let message_tx = ErrorSummarySink::new(message_tx, Duration::from_secs(10)).suppress_errors();
+///
+/// thread::spawn(move || {
+///     neato_xv11::run("/dev/serial0", message_tx, command_rx);
+/// });
+///
+/// for message in message_rx.iter() {
+///     if let Ok(LidarDriverMessage::ErrorSummary(summary)) = message {
+///         println!("{} checksum errors in {:?}", summary.checksum_errors, summary.window);
+///     }
+/// }
+/// ```
+pub struct ErrorSummarySink<S> {
+    inner: S,
+    // Time covered by a summary.
+    window: Duration,
+    // Whether the counted errors are forwarded.
+    forward_errors: bool,
+    // Source of the time messages are received at.
+    clock: Arc<dyn Clock>,
+    state: Mutex<Window>,
+}
+
+impl<S: MessageSink> ErrorSummarySink<S> {
+    /// ## Summary
+    ///
+    /// Wrap a sink, summarizing errors every `window`.
+    ///
+    pub fn new(inner: S, window: Duration) -> Self {
+        ErrorSummarySink {
+            inner,
+            window,
+            forward_errors: true,
+            clock: Arc::new(SystemClock),
+            state: Mutex::new(Window {
+                start: None,
+                summary: ErrorSummary::default(),
+                dropped: 0,
+            }),
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Only count checksum errors, resyncs and timeouts instead of forwarding them.
+    ///
+    pub fn suppress_errors(mut self) -> Self {
+        self.forward_errors = false;
+        self
+    }
+
+    /// ## Summary
+    ///
+    /// Read the time from the given clock, e.g. a `clock::VirtualClock` in simulations.
+    ///
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+}
+
+impl<S: MessageSink> MessageSink for ErrorSummarySink<S> {
+    fn send(&self, message: Result<LidarDriverMessage, LidarDriverError>) -> Result<(), SendError<Result<LidarDriverMessage, LidarDriverError>>> {
+        let now = self.clock.now();
+        let dropped = self.inner.dropped();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let start = *state.start.get_or_insert(now);
+
+        let summary = if now.saturating_duration_since(start) >= self.window {
+            let mut summary = std::mem::take(&mut state.summary);
+            summary.window = now.saturating_duration_since(start);
+            summary.dropped_msgs = dropped.saturating_sub(state.dropped);
+            state.start = Some(now);
+            state.dropped = dropped;
+            Some(summary)
+        } else {
+            None
+        };
+
+        let is_counted = match &message {
+            Err(err) => state.summary.count(err),
+            Ok(_) => false,
+        };
+
+        drop(state);
+
+        if let Some(summary) = summary {
+            self.inner.send(Ok(LidarDriverMessage::ErrorSummary(summary)))?;
+        }
+
+        if is_counted && !self.forward_errors {
+            return Ok(());
+        }
+
+        self.inner.send(message)
+    }
+
+    fn dropped(&self) -> u64 {
+        self.inner.dropped()
+    }
+
+    fn backlog(&self) -> Option<Backlog> {
+        self.inner.backlog()
+    }
+}
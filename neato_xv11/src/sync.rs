@@ -0,0 +1,118 @@
+use std::time::{Duration, Instant};
+
+use super::error::LidarDriverError;
+use super::message::LidarDriverMessage;
+use super::parser::SyncStrategy;
+
+/// ## Summary
+///
+/// How the driver finds the packet boundaries in the byte stream, and what it reports
+/// while searching for them.
+///
+/// ## Remarks
+///
+/// The parser searches for the boundaries when the driver starts, resumes or reconnects,
+/// and after losing them (`LidarDriverError::ResyncRequired`). Without a timeout, a LIDAR
+/// whose motor is off keeps the driver searching without any feedback.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::sync::mpsc::channel;
+/// use std::time::Duration;
+/// use neato_xv11::parser::SyncStrategy;
+/// use neato_xv11::sync::SyncConfig;
+/// use neato_xv11::LidarDriverBuilder;
+///
+/// let (message_tx, message_rx) = channel();
+/// let (command_tx, command_rx) = channel();
+///
+/// let sync = SyncConfig {
+///     strategy: SyncStrategy::Checksum,
+///     timeout: Some(Duration::from_secs(2)),
+///     report: true,
+/// };
+///
+/// let handle = LidarDriverBuilder::new("/dev/ttyUSB0")
+///     .sync(sync)
+///     .spawn(message_tx, command_rx)
+///     .unwrap();
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SyncConfig {
+    // How the parser validates the first frame.
+    pub strategy: SyncStrategy,
+    // Report `LidarDriverError::SyncTimeout` when the parser searches for this long, then
+    // again after every further timeout. `None` to search without reporting.
+    pub timeout: Option<Duration>,
+    // Send `LidarDriverMessage::Syncing` and `LidarDriverMessage::Synced`.
+    pub report: bool,
+}
+
+/// ## Summary
+///
+/// Tracks the search for the packet boundaries on the parser thread.
+///
+pub(crate) struct SyncMonitor {
+    config: SyncConfig,
+    // When the search started, `None` while synced.
+    since: Option<Instant>,
+    // When the timeout was last reported, or the search started.
+    last_report: Instant,
+}
+
+impl SyncMonitor {
+    pub(crate) fn new(config: SyncConfig, now: Instant) -> Self {
+        SyncMonitor { config, since: None, last_report: now }
+    }
+
+    /// ## Summary
+    ///
+    /// Record that the parser (re)starts searching. Returns the message to send, if enabled.
+    ///
+    /// ## Remarks
+    ///
+    /// The time spent paused or reconnecting does not count, the search starts over.
+    ///
+    pub(crate) fn start(&mut self, now: Instant) -> Option<LidarDriverMessage> {
+        let was_syncing = self.since.replace(now).is_some();
+        self.last_report = now;
+
+        if self.config.report && !was_syncing {
+            Some(LidarDriverMessage::Syncing)
+        } else {
+            None
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Record that the parser decoded a packet. Returns the message to send, if enabled.
+    ///
+    pub(crate) fn synced(&mut self, now: Instant) -> Option<LidarDriverMessage> {
+        let since = self.since.take()?;
+
+        if self.config.report {
+            Some(LidarDriverMessage::Synced { after: now.saturating_duration_since(since) })
+        } else {
+            None
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// The timeout error, if the search has lasted a timeout since it started or was
+    /// last reported.
+    ///
+    pub(crate) fn poll(&mut self, now: Instant) -> Option<LidarDriverError> {
+        let (timeout, since) = (self.config.timeout?, self.since?);
+
+        if now.saturating_duration_since(self.last_report) < timeout {
+            return None;
+        }
+
+        // Report again after another timeout.
+        self.last_report = now;
+        Some(LidarDriverError::SyncTimeout { since: now.saturating_duration_since(since) })
+    }
+}
@@ -0,0 +1,302 @@
+use super::data::Float;
+use super::error::{ChecksumDetails, LidarDriverError, LidarReadingError};
+use super::parser::calc_checksum;
+
+/// Size of an XV-11 packet in bytes.
+pub const PACKET_SIZE: usize = 22;
+
+/// Number of packets in a revolution.
+pub const PACKETS_PER_REVOLUTION: usize = 90;
+
+/// ## Summary
+///
+/// The known-good decoding of a golden packet.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GoldenDecoding {
+    // Index of the first reading (4 times the packet index).
+    pub first_index: usize,
+    // Decoded speed in RPM (XV-11).
+    pub speed: Float,
+    // Decoded distance, quality and error of each reading.
+    pub readings: [(i32, i32, Option<LidarReadingError>); 4],
+}
+
+/// ## Summary
+///
+/// A packet and the result of decoding it as an XV-11 packet.
+///
+#[derive(Debug, PartialEq)]
+pub struct GoldenPacket {
+    // Raw bytes as received from the LIDAR.
+    pub bytes: [u8; PACKET_SIZE],
+    // The decoding, or the error the packet is rejected with.
+    pub expected: Result<GoldenDecoding, LidarDriverError>,
+}
+
+/// Readings of the captured packet.
+const CAPTURED_READINGS: [(i32, i32, Option<LidarReadingError>); 4] = [(228, 1505, None), (226, 1588, None), (224, 1573, None), (223, 1668, None)];
+
+/// Speed of the captured packet in RPM.
+const CAPTURED_SPEED: Float = 18915.0 / 64.0;
+
+/// ## Summary
+///
+/// Packets of an XV-11 and their decoding, valid and invalid.
+///
+/// ## Remarks
+///
+/// The first two packets were captured from an XV-11, the second one corrupted on the
+/// wire. The others are edge cases derived from the captured packet, with their
+/// checksum recomputed: the first and last packets of a revolution, the invalid data
+/// and signal strength flags, the largest distance and quality, and a stopped motor.
+///
+pub const GOLDEN_PACKETS: &[GoldenPacket] = &[
+    // Captured.
+    GoldenPacket {
+        bytes: [0xFA, 0xB1, 0xE3, 0x49, 0xE4, 0x00, 0xE1, 0x05, 0xE2, 0x00, 0x34,
+                0x06, 0xE0, 0x00, 0x25, 0x06, 0xDF, 0x00, 0x84, 0x06, 0xF6, 0x6B],
+        expected: Ok(GoldenDecoding { first_index: 68, speed: CAPTURED_SPEED, readings: CAPTURED_READINGS }),
+    },
+    // Captured with a corrupted checksum.
+    GoldenPacket {
+        bytes: CORRUPTED_BYTES,
+        expected: Err(LidarDriverError::Checksum(17, Some(ChecksumDetails {
+            packet: CORRUPTED_BYTES,
+            received: 0xCEA6,
+            calculated: 0x6BF6,
+        }))),
+    },
+    // First packet of a revolution.
+    GoldenPacket {
+        bytes: [0xFA, 0xA0, 0xE3, 0x49, 0xE4, 0x00, 0xE1, 0x05, 0xE2, 0x00, 0x34,
+                0x06, 0xE0, 0x00, 0x25, 0x06, 0xDF, 0x00, 0x84, 0x06, 0xB2, 0x6B],
+        expected: Ok(GoldenDecoding { first_index: 0, speed: CAPTURED_SPEED, readings: CAPTURED_READINGS }),
+    },
+    // Last packet of a revolution.
+    GoldenPacket {
+        bytes: [0xFA, 0xF9, 0xE3, 0x49, 0xE4, 0x00, 0xE1, 0x05, 0xE2, 0x00, 0x34,
+                0x06, 0xE0, 0x00, 0x25, 0x06, 0xDF, 0x00, 0x84, 0x06, 0x16, 0x6D],
+        expected: Ok(GoldenDecoding { first_index: 356, speed: CAPTURED_SPEED, readings: CAPTURED_READINGS }),
+    },
+    // Invalid data (error codes 0x21 and 0x35) and a signal strength warning.
+    GoldenPacket {
+        bytes: [0xFA, 0xB1, 0xE3, 0x49, 0x21, 0x80, 0x00, 0x00, 0xE2, 0x40, 0x34,
+                0x05, 0x35, 0x80, 0x00, 0x00, 0xDF, 0x00, 0x84, 0x06, 0xDE, 0x64],
+        expected: Ok(GoldenDecoding {
+            first_index: 68,
+            speed: CAPTURED_SPEED,
+            readings: [
+                (0x8021, 0, Some(LidarReadingError::InvalidDataError(0x21))),
+                (226, 1332, Some(LidarReadingError::SignalStrengthWarning)),
+                (0x8035, 0, Some(LidarReadingError::InvalidDataError(0x35))),
+                (223, 1668, None),
+            ],
+        }),
+    },
+    // Largest distance and quality, then a zero distance.
+    GoldenPacket {
+        bytes: [0xFA, 0xA0, 0xE3, 0x49, 0xFF, 0x3F, 0xFF, 0xFF, 0x00, 0x00, 0x00,
+                0x00, 0xE0, 0x00, 0x25, 0x06, 0xDF, 0x00, 0x84, 0x06, 0xED, 0x01],
+        expected: Ok(GoldenDecoding {
+            first_index: 0,
+            speed: CAPTURED_SPEED,
+            readings: [(0x3FFF, 0xFFFF, None), (0, 0, None), (224, 1573, None), (223, 1668, None)],
+        }),
+    },
+    // Motor stopped.
+    GoldenPacket {
+        bytes: [0xFA, 0xB1, 0x00, 0x00, 0xE4, 0x00, 0xE1, 0x05, 0xE2, 0x00, 0x34,
+                0x06, 0xE0, 0x00, 0x25, 0x06, 0xDF, 0x00, 0x84, 0x06, 0x63, 0x08],
+        expected: Ok(GoldenDecoding { first_index: 68, speed: 0.0, readings: CAPTURED_READINGS }),
+    },
+];
+
+/// The captured packet with a corrupted checksum.
+const CORRUPTED_BYTES: [u8; PACKET_SIZE] = [0xFA, 0xB1, 0xE3, 0x49, 0xE4, 0x00, 0xE1, 0x05, 0xE2, 0x00, 0x34,
+                                            0x06, 0xE0, 0x00, 0x25, 0x06, 0xDF, 0x00, 0x84, 0x06, 0xA6, 0xCE];
+
+/// ## Summary
+///
+/// A reading to encode in a generated packet.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReadingSpec {
+    // The Invalid Data Error flag is set. The associated value is the error code.
+    Invalid(u8),
+    // A valid distance (millimeters, 14 bits) and quality.
+    Valid(u16, u16),
+    // A distance (millimeters, 14 bits) and quality with the Signal Strength Warning flag set.
+    Weak(u16, u16),
+}
+
+impl ReadingSpec {
+    /// Encode the distance and quality words.
+    fn encode(self) -> (u16, u16) {
+        match self {
+            ReadingSpec::Invalid(code) => (0x8000 | code as u16, 0),
+            ReadingSpec::Valid(distance, quality) => (distance & 0x3FFF, quality),
+            ReadingSpec::Weak(distance, quality) => (0x4000 | (distance & 0x3FFF), quality),
+        }
+    }
+}
+
+/// ## Summary
+///
+/// Generate an XV-11 packet with a valid checksum.
+///
+/// ## Parameters
+///
+/// index: Packet index (0 to 89). The first reading is at degree `4 * index`.
+///
+/// speed: Speed in RPM.
+///
+/// readings: The four readings.
+///
+/// ## Example
+///
+/// ```
+/// use neato_xv11::parser::Parser;
+/// use neato_xv11::testing::{generate_packet, ReadingSpec};
+///
+/// let bytes = generate_packet(10, 300.0, [ReadingSpec::Valid(1000, 50); 4]);
+///
+/// let mut parser = Parser::new();
+/// let packet = bytes.iter().find_map(|&byte| parser.push(byte)).unwrap().unwrap();
+/// assert_eq!(packet.readings[0].index, 40);
+/// ```
+pub fn generate_packet(index: u8, speed: Float, readings: [ReadingSpec; 4]) -> [u8; PACKET_SIZE] {
+    let mut packet = [0; PACKET_SIZE];
+    let speed = (speed * 64.0 + 0.5) as u16;
+
+    packet[0] = 0xFA;
+    packet[1] = 0xA0 + index % PACKETS_PER_REVOLUTION as u8;
+    packet[2..4].copy_from_slice(&speed.to_le_bytes());
+
+    for (i, reading) in readings.iter().enumerate() {
+        let (distance, quality) = reading.encode();
+        packet[4 + 4 * i..6 + 4 * i].copy_from_slice(&distance.to_le_bytes());
+        packet[6 + 4 * i..8 + 4 * i].copy_from_slice(&quality.to_le_bytes());
+    }
+
+    let checksum = calc_checksum(&packet[..20]) as u16;
+    packet[20..].copy_from_slice(&checksum.to_le_bytes());
+    packet
+}
+
+/// ## Summary
+///
+/// Generate the 90 packets of a revolution, starting at index 0.
+///
+/// ## Parameters
+///
+/// speed: Speed in RPM.
+///
+/// reading: Returns the reading at a degree (0 to 359).
+///
+pub fn generate_revolution<F: FnMut(usize) -> ReadingSpec>(speed: Float, mut reading: F) -> impl Iterator<Item = [u8; PACKET_SIZE]> {
+    (0..PACKETS_PER_REVOLUTION as u8).map(move |index| {
+        let first = 4 * index as usize;
+        let readings = [reading(first), reading(first + 1), reading(first + 2), reading(first + 3)];
+        generate_packet(index, speed, readings)
+    })
+}
+
+/// ## Summary
+///
+/// Copy of a packet whose checksum no longer matches.
+///
+pub fn corrupt_checksum(mut packet: [u8; PACKET_SIZE]) -> [u8; PACKET_SIZE] {
+    packet[20] ^= 0xFF;
+    packet
+}
+
+/// ## Summary
+///
+/// proptest strategies producing packets and byte streams (`proptest` feature).
+///
+/// ## Example
+///
+/// ```
+/// use neato_xv11::parser::Parser;
+/// use neato_xv11::testing::strategies::byte_stream;
+/// use proptest::test_runner::TestRunner;
+///
+/// // Usually written with the `proptest!` macro in a test.
+/// TestRunner::default()
+///     .run(&byte_stream(), |bytes| {
+///         let mut parser = Parser::new();
+///
+///         for packet in bytes.iter().filter_map(|&byte| parser.push(byte)).flatten() {
+///             assert!(packet.readings.iter().all(|reading| reading.index < 360));
+///         }
+///
+///         Ok(())
+///     })
+///     .unwrap();
+/// ```
+#[cfg(feature = "proptest")]
+pub mod strategies {
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    use super::{generate_packet, ReadingSpec, PACKETS_PER_REVOLUTION, PACKET_SIZE};
+    use crate::data::Float;
+
+    /// Largest raw speed generated (600 RPM).
+    const MAX_RAW_SPEED: u16 = 600 * 64;
+
+    /// ## Summary
+    ///
+    /// Any reading: valid, weak or invalid.
+    ///
+    pub fn reading_spec() -> impl Strategy<Value = ReadingSpec> {
+        prop_oneof![
+            any::<u8>().prop_map(ReadingSpec::Invalid),
+            (0..0x4000u16, any::<u16>()).prop_map(|(distance, quality)| ReadingSpec::Valid(distance, quality)),
+            (0..0x4000u16, any::<u16>()).prop_map(|(distance, quality)| ReadingSpec::Weak(distance, quality)),
+        ]
+    }
+
+    /// ## Summary
+    ///
+    /// A packet with a valid checksum.
+    ///
+    pub fn valid_packet() -> impl Strategy<Value = [u8; PACKET_SIZE]> {
+        (0..PACKETS_PER_REVOLUTION as u8, 0..=MAX_RAW_SPEED, [reading_spec(), reading_spec(), reading_spec(), reading_spec()])
+            .prop_map(|(index, speed, readings)| generate_packet(index, speed as Float / 64.0, readings))
+    }
+
+    /// ## Summary
+    ///
+    /// A valid packet with one bit flipped, which usually breaks its checksum or header.
+    ///
+    pub fn bit_flipped_packet() -> impl Strategy<Value = [u8; PACKET_SIZE]> {
+        (valid_packet(), 0..PACKET_SIZE * 8).prop_map(|(mut packet, bit)| {
+            packet[bit / 8] ^= 1 << (bit % 8);
+            packet
+        })
+    }
+
+    /// ## Summary
+    ///
+    /// Random bytes.
+    ///
+    pub fn garbage() -> impl Strategy<Value = Vec<u8>> {
+        vec(any::<u8>(), 0..2 * PACKET_SIZE)
+    }
+
+    /// ## Summary
+    ///
+    /// Valid packets, bit-flipped packets and garbage, interleaved.
+    ///
+    pub fn byte_stream() -> impl Strategy<Value = Vec<u8>> {
+        let chunk = prop_oneof![
+            valid_packet().prop_map(|packet| packet.to_vec()),
+            bit_flipped_packet().prop_map(|packet| packet.to_vec()),
+            garbage(),
+        ];
+
+        vec(chunk, 0..16).prop_map(|chunks| chunks.concat())
+    }
+}
@@ -0,0 +1,259 @@
+use std::collections::VecDeque;
+
+use super::data::{Float, LidarScan, Point2D};
+
+/// Default largest gap in millimeters between neighbouring readings of a blob.
+const DEFAULT_CLUSTER_GAP: Float = 150.0;
+/// Default smallest number of readings in a blob.
+const DEFAULT_MIN_READINGS: usize = 3;
+/// Default number of scans a blob can go unseen before it is dropped.
+const DEFAULT_MAX_MISSED: u32 = 5;
+/// Default number of positions kept in the history of a blob.
+const DEFAULT_HISTORY: usize = 50;
+
+/// ## Summary
+///
+/// A foreground cluster tracked across scans.
+///
+#[derive(Clone, Debug)]
+pub struct Blob {
+    // Identifier, stable for the lifetime of the blob.
+    pub id: u32,
+    // Centroid in the LIDAR frame.
+    pub position: Point2D,
+    // Number of readings in the cluster of the last scan the blob was seen in.
+    pub readings: usize,
+    // Past centroids, oldest first, including the current position.
+    pub history: VecDeque<Point2D>,
+    // Number of consecutive scans the blob was not seen in.
+    pub missed: u32,
+}
+
+/// ## Summary
+///
+/// Tracks foreground clusters across scans and assigns them stable identifiers.
+///
+/// ## Remarks
+///
+/// Scans are usually the foreground of a `background::BackgroundModel`. Neighbouring
+/// valid readings closer than the cluster gap form a cluster. Each cluster is matched
+/// to the nearest blob of the previous scan within the maximum distance, otherwise it
+/// becomes a new blob. Blobs unseen for more than `max_missed` scans are dropped.
+///
+/// ## Example
+///
+/// ```no_run
+/// # fn count(foregrounds: Vec<neato_xv11::data::LidarScan>) {
+/// use neato_xv11::tracking::BlobTracker;
+///
+/// // A person moves less than 300 mm between revolutions.
+/// let mut tracker = BlobTracker::new(300.0);
+///
+/// for foreground in &foregrounds {
+///     tracker.push(foreground);
+/// }
+///
+/// println!("{} blobs seen", tracker.total());
+/// # }
+/// ```
+pub struct BlobTracker {
+    // Largest distance in millimeters a blob moves between scans.
+    max_distance: Float,
+    // Largest gap in millimeters between neighbouring readings of a cluster.
+    cluster_gap: Float,
+    // Smallest number of readings in a cluster.
+    min_readings: usize,
+    // Number of scans a blob can go unseen before it is dropped.
+    max_missed: u32,
+    // Number of positions kept in the history of a blob.
+    history: usize,
+    // Blobs being tracked.
+    blobs: Vec<Blob>,
+    // Identifier of the next new blob.
+    next_id: u32,
+}
+
+impl BlobTracker {
+    /// ## Summary
+    ///
+    /// Initialize a new tracker.
+    ///
+    /// ## Parameters
+    ///
+    /// max_distance: Largest distance in millimeters a blob moves between scans.
+    ///
+    pub fn new(max_distance: Float) -> Self {
+        BlobTracker {
+            max_distance,
+            cluster_gap: DEFAULT_CLUSTER_GAP,
+            min_readings: DEFAULT_MIN_READINGS,
+            max_missed: DEFAULT_MAX_MISSED,
+            history: DEFAULT_HISTORY,
+            blobs: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    /// ## Summary
+    ///
+    /// Set the largest gap in millimeters between neighbouring readings of a cluster.
+    /// Defaults to 150 mm.
+    ///
+    pub fn set_cluster_gap(&mut self, gap: Float) {
+        self.cluster_gap = gap;
+    }
+
+    /// ## Summary
+    ///
+    /// Set the smallest number of readings in a cluster. Defaults to 3.
+    ///
+    pub fn set_min_readings(&mut self, min_readings: usize) {
+        self.min_readings = min_readings.max(1);
+    }
+
+    /// ## Summary
+    ///
+    /// Set the number of scans a blob can go unseen before it is dropped. Defaults to 5.
+    ///
+    pub fn set_max_missed(&mut self, max_missed: u32) {
+        self.max_missed = max_missed;
+    }
+
+    /// ## Summary
+    ///
+    /// Set the number of positions kept in the history of a blob. Defaults to 50.
+    ///
+    pub fn set_history(&mut self, history: usize) {
+        self.history = history.max(1);
+    }
+
+    /// ## Summary
+    ///
+    /// The blobs being tracked.
+    ///
+    pub fn blobs(&self) -> &[Blob] {
+        &self.blobs
+    }
+
+    /// ## Summary
+    ///
+    /// Number of blobs seen since the tracker was created.
+    ///
+    pub fn total(&self) -> u32 {
+        self.next_id
+    }
+
+    /// ## Summary
+    ///
+    /// Cluster a scan and update the blobs. Returns the blobs being tracked.
+    ///
+    pub fn push(&mut self, scan: &LidarScan) -> &[Blob] {
+        let mut clusters = cluster(scan, self.cluster_gap, self.min_readings);
+
+        for blob in &mut self.blobs {
+            blob.missed += 1;
+        }
+
+        // Greedily match the closest cluster and blob pairs first.
+        let mut pairs: Vec<(Float, usize, usize)> = Vec::new();
+
+        for (cluster_index, (centroid, _)) in clusters.iter().enumerate() {
+            for (blob_index, blob) in self.blobs.iter().enumerate() {
+                let distance = blob.position.distance(centroid);
+
+                if distance <= self.max_distance {
+                    pairs.push((distance, cluster_index, blob_index));
+                }
+            }
+        }
+
+        pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut matched = vec![false; clusters.len()];
+
+        for (_, cluster_index, blob_index) in pairs {
+            let blob = &mut self.blobs[blob_index];
+
+            if matched[cluster_index] || blob.missed == 0 {
+                continue;
+            }
+
+            let (centroid, readings) = clusters[cluster_index];
+            matched[cluster_index] = true;
+            blob.missed = 0;
+            blob.position = centroid;
+            blob.readings = readings;
+            blob.history.push_back(centroid);
+
+            if blob.history.len() > self.history {
+                blob.history.pop_front();
+            }
+        }
+
+        let max_missed = self.max_missed;
+        self.blobs.retain(|blob| blob.missed <= max_missed);
+
+        let mut index = 0;
+        clusters.retain(|_| {
+            index += 1;
+            !matched[index - 1]
+        });
+
+        for (centroid, readings) in clusters {
+            self.blobs.push(Blob {
+                id: self.next_id,
+                position: centroid,
+                readings,
+                history: VecDeque::from([centroid]),
+                missed: 0,
+            });
+            self.next_id = self.next_id.wrapping_add(1);
+        }
+
+        &self.blobs
+    }
+}
+
+/// Group neighbouring valid readings into clusters. Returns the centroid and number of
+/// readings of each cluster.
+fn cluster(scan: &LidarScan, gap: Float, min_readings: usize) -> Vec<(Point2D, usize)> {
+    let mut groups: Vec<Vec<Point2D>> = Vec::new();
+    let mut last: Option<(usize, Point2D)> = None;
+
+    for reading in scan.valid_readings() {
+        let point = reading.to_point();
+
+        match (groups.last_mut(), last) {
+            (Some(group), Some((index, previous))) if reading.index == index + 1 && point.distance(&previous) <= gap => {
+                group.push(point);
+            },
+            _ => groups.push(vec![point]),
+        }
+
+        last = Some((reading.index, point));
+    }
+
+    // Join the clusters on both sides of index 0.
+    if groups.len() > 1 {
+        let first = scan.valid_readings().next();
+        let last = scan.valid_readings().last();
+
+        if let (Some(first), Some(last)) = (first, last) {
+            if first.index == 0 && last.index == scan.readings.len() - 1 && first.to_point().distance(&last.to_point()) <= gap {
+                let head = groups.remove(0);
+                groups.last_mut().unwrap().extend(head);
+            }
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter(|group| group.len() >= min_readings)
+        .map(|group| {
+            let count = group.len() as Float;
+            let x = group.iter().map(|point| point.x).sum::<Float>() / count;
+            let y = group.iter().map(|point| point.y).sum::<Float>() / count;
+            (Point2D { x, y }, group.len())
+        })
+        .collect()
+}